@@ -0,0 +1,61 @@
+//! Runs the configured ROSpec for a few seconds and prints every tag it reads.
+//!
+//! ```text
+//! cargo run --example basic_inventory -- config.json 10
+//! ```
+//!
+//! Arguments: the client configuration (default - config.json) and the number of
+//! seconds to read for (default - 10).
+
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use llrp_lib::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+
+  let args: Vec<String> = env::args().skip(1).collect();
+  let config_path = args.first().cloned().unwrap_or_else(|| "config.json".to_string());
+  let duration = Duration::from_secs(args.get(1).map(|secs| secs.parse()).transpose()?.unwrap_or(10));
+
+  let mut client = LlrpClient::initialize(&config_path).await?;
+
+  // Subscribe before starting, so no report sent right after the ROSpec starts is missed.
+  let mut ro_report_rx = client.subscribe_ro_reports();
+  client.start_inventory().await?;
+
+  let deadline = tokio::time::sleep(duration);
+  tokio::pin!(deadline);
+
+  loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          if let LlrpResponseData::TagReport(tag_reports) = report.decode()? {
+            for tag in tag_reports {
+              println!(
+                "{}  antenna {}  {}",
+                tag,
+                tag.antenna_id.map_or("-".to_string(), |antenna_id| antenna_id.to_string()),
+                tag.peak_rssi.map_or("-".to_string(), |peak_rssi| format!("{} dBm", peak_rssi))
+              );
+            }
+          }
+        }
+        Err(RecvError::Lagged(skipped)) => eprintln!("Skipped {} reports", skipped),
+        Err(RecvError::Closed) => break
+      },
+
+      _ = &mut deadline => break
+    }
+  }
+
+  client.stop_inventory().await?;
+  client.send_close_connection().await?;
+
+  Ok(())
+}
\ No newline at end of file
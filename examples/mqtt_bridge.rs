@@ -0,0 +1,85 @@
+//! Publishes every tag a reader reports to an MQTT broker, as one JSON message per
+//! tag on `<prefix>/<reader_id>/tags`, until Ctrl-C.
+//!
+//! ```text
+//! cargo run --example mqtt_bridge -- config.json localhost:1883 llrp
+//! ```
+//!
+//! Arguments: the client configuration (default - config.json), the broker address
+//! (default - localhost:1883) and the topic prefix (default - llrp).
+
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use llrp_lib::client::LlrpClient;
+use llrp_lib::llrp::LlrpResponseData;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+
+  let args: Vec<String> = env::args().skip(1).collect();
+  let config_path = args.first().cloned().unwrap_or_else(|| "config.json".to_string());
+  let broker = args.get(1).cloned().unwrap_or_else(|| "localhost:1883".to_string());
+  let prefix = args.get(2).cloned().unwrap_or_else(|| "llrp".to_string());
+
+  let (broker_host, broker_port) = broker.rsplit_once(':').ok_or("Broker must be of the form <host>:<port>")?;
+
+  let mut client = LlrpClient::initialize(&config_path).await?;
+  let reader_id = client.config().reader_id();
+  let topic = format!("{}/{}/tags", prefix, reader_id);
+
+  let mut mqtt_options = MqttOptions::new(format!("llrp-bridge-{}", reader_id), broker_host, broker_port.parse()?);
+  mqtt_options.set_keep_alive(Duration::from_secs(30));
+
+  // The event loop carries the published messages to the broker and must be polled
+  // for as long as the bridge runs; it reconnects on its own after errors.
+  let (mqtt, mut event_loop) = AsyncClient::new(mqtt_options, 256);
+  tokio::spawn(async move {
+    loop {
+      if let Err(e) = event_loop.poll().await {
+        eprintln!("MQTT: {}", e);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+      }
+    }
+  });
+
+  let mut ro_report_rx = client.subscribe_ro_reports();
+  client.start_inventory().await?;
+
+  loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          let Ok(LlrpResponseData::TagReport(tag_reports)) = report.decode() else {
+            continue;
+          };
+          for tag in tag_reports {
+            let message = json!({
+              "reader_id"    : reader_id,
+              "epc"          : tag.to_string(),
+              "antenna_id"   : tag.antenna_id,
+              "peak_rssi"    : tag.peak_rssi,
+              "received_utc" : tag.received_at.utc_us
+            });
+            mqtt.publish(&topic, QoS::AtLeastOnce, false, message.to_string()).await?;
+          }
+        }
+        Err(RecvError::Lagged(skipped)) => eprintln!("Skipped {} reports", skipped),
+        Err(RecvError::Closed) => break
+      },
+
+      _ = tokio::signal::ctrl_c() => break
+    }
+  }
+
+  client.stop_inventory().await?;
+  client.send_close_connection().await?;
+  mqtt.disconnect().await?;
+
+  Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,120 @@
+//! Reads from several readers at once and prints their tags as one stream, each
+//! line prefixed with the reader that saw the tag, until Ctrl-C.
+//!
+//! ```text
+//! cargo run --example multi_reader -- dock-1.json dock-2.json dock-3.json
+//! ```
+//!
+//! Arguments: one client configuration per reader. Give each a distinct `reader_id`
+//! to tell readers apart by name rather than address.
+
+use std::env;
+use std::error::Error;
+use futures::future::join_all;
+use tokio::sync::{mpsc, watch};
+use tokio::sync::broadcast::error::RecvError;
+
+use llrp_lib::client::LlrpClient;
+use llrp_lib::llrp::LlrpResponseData;
+
+/// A tag read, as passed from the reader tasks to the printer.
+struct TagRead {
+  reader_id  : String,
+  epc        : String,
+  antenna_id : Option<u16>
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+
+  let config_paths: Vec<String> = env::args().skip(1).collect();
+  if config_paths.is_empty() {
+    return Err("Usage: multi_reader <config> [<config> ...]".into());
+  }
+
+  let (read_tx, mut read_rx) = mpsc::channel::<TagRead>(1024);
+  let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+  // The readers run concurrently on this task rather than spawned, as client futures
+  // are not all `Send`.
+  let readers = join_all(config_paths.into_iter().map(|config_path| {
+    run_reader(config_path, read_tx.clone(), shutdown_rx.clone())
+  }));
+
+  // The printer stops once every reader has dropped its sender.
+  drop(read_tx);
+
+  let printer = async {
+    loop {
+      tokio::select! {
+
+        read = read_rx.recv() => match read {
+          Some(read) => println!(
+            "{:<16} {}  antenna {}",
+            read.reader_id,
+            read.epc,
+            read.antenna_id.map_or("-".to_string(), |antenna_id| antenna_id.to_string())
+          ),
+          None => break
+        },
+
+        _ = tokio::signal::ctrl_c() => {
+          let _ = shutdown_tx.send(true);
+        }
+      }
+    }
+  };
+
+  let (results, ()) = tokio::join!(readers, printer);
+
+  for e in results.into_iter().filter_map(Result::err) {
+    eprintln!("{}", e);
+  }
+
+  Ok(())
+}
+
+/// Connects to one reader and forwards its tags until shutdown. Errors are returned
+/// prefixed with the configuration or reader they came from.
+async fn run_reader(
+  config_path  : String,
+  read_tx      : mpsc::Sender<TagRead>,
+  mut shutdown : watch::Receiver<bool>
+) -> Result<(), String> {
+
+  let mut client = LlrpClient::initialize(&config_path).await
+    .map_err(|e| format!("{}: {}", config_path, e))?;
+
+  let reader_id = client.config().reader_id();
+  let mut ro_report_rx = client.subscribe_ro_reports();
+
+  client.start_inventory().await.map_err(|e| format!("{}: {}", reader_id, e))?;
+
+  loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          let Ok(LlrpResponseData::TagReport(tag_reports)) = report.decode() else {
+            continue;
+          };
+          for tag in tag_reports {
+            let read = TagRead { reader_id: reader_id.clone(), epc: tag.to_string(), antenna_id: tag.antenna_id };
+            if read_tx.send(read).await.is_err() {
+              break;
+            }
+          }
+        }
+        Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => return Err(format!("{}: connection closed", reader_id))
+      },
+
+      _ = shutdown.changed() => break
+    }
+  }
+
+  client.stop_inventory().await.map_err(|e| format!("{}: {}", reader_id, e))?;
+  client.send_close_connection().await.map_err(|e| format!("{}: {}", reader_id, e))?;
+
+  Ok(())
+}
\ No newline at end of file
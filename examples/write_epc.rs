@@ -0,0 +1,101 @@
+//! Writes a new 96-bit EPC to the next tag the reader singulates, then prints the
+//! outcome the reader reports with that tag. Keep a single tag in the field.
+//!
+//! ```text
+//! cargo run --example write_epc -- config.json 3008 33B2 DDD9 0140 0000 0001
+//! ```
+//!
+//! Arguments: the client configuration, then the new EPC as 24 hex digits, spaces
+//! optional. An access password may follow as `--password <hex>`.
+
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use llrp_lib::client::LlrpClient;
+use llrp_lib::llrp::LlrpResponseData;
+
+/// The EPC memory bank holds the StoredCRC and StoredPC ahead of the EPC.
+const EPC_MEMORY_BANK  : u8 = 1;
+const EPC_WORD_POINTER : u16 = 2;
+
+/// How long to wait for a tag to be written.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+
+  let mut args: Vec<String> = env::args().skip(1).collect();
+
+  let access_password = match args.iter().position(|arg| arg == "--password") {
+    Some(index) => {
+      let password = args.get(index + 1).ok_or("--password requires a value")?;
+      let password = u32::from_str_radix(password, 16)?;
+      args.drain(index..index + 2);
+      password
+    }
+    None => 0
+  };
+
+  let config_path = args.first().ok_or("Usage: write_epc <config> <epc hex> [--password <hex>]")?.clone();
+  let epc = parse_epc(&args[1..].concat())?;
+
+  let mut client = LlrpClient::initialize(&config_path).await?;
+  let mut ro_report_rx = client.subscribe_ro_reports();
+
+  // The write is queued as an AccessSpec, carried out once the inventory singulates a tag.
+  client.write_tag_memory(EPC_MEMORY_BANK, EPC_WORD_POINTER, &epc, access_password).await?;
+  client.start_inventory().await?;
+
+  let outcome = tokio::time::timeout(WRITE_TIMEOUT, async {
+    loop {
+      match ro_report_rx.recv().await {
+        Ok(report) => {
+          let LlrpResponseData::TagReport(tag_reports) = report.decode()? else {
+            continue;
+          };
+          for tag in tag_reports {
+            if let Some(result) = tag.write_results.first() {
+              return Ok::<_, Box<dyn Error>>(format!(
+                "Tag {} (as read): {} words written, {}",
+                tag,
+                result.num_words_written,
+                if result.succeeded() { "succeeded".to_string() } else { format!("failed with result {}", result.result) }
+              ));
+            }
+          }
+        }
+        Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => return Err("Connection closed before the write was reported".into())
+      }
+    }
+  }).await;
+
+  client.stop_inventory().await?;
+  client.send_close_connection().await?;
+
+  match outcome {
+    Ok(outcome) => println!("{}", outcome?),
+    Err(_) => println!("No tag was written within {} seconds", WRITE_TIMEOUT.as_secs())
+  }
+
+  Ok(())
+}
+
+/// Parses 24 hex digits into the six words of a 96-bit EPC.
+fn parse_epc(
+  hex: &str
+) -> Result<Vec<u16>, Box<dyn Error>> {
+
+  if hex.len() != 24 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(format!("EPC {:?} must be 24 hex digits", hex).into());
+  }
+
+  let words = (0..hex.len())
+    .step_by(4)
+    .map(|index| u16::from_str_radix(&hex[index..index + 4], 16))
+    .collect::<Result<Vec<u16>, _>>()?;
+
+  Ok(words)
+}
\ No newline at end of file
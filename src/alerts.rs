@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use chrono::Utc;
+use log::warn;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::clock::SharedClock;
+use crate::config::AlertRules;
+use crate::log_context;
+
+const RECENT_ALERTS_CAPACITY : usize = 64;
+const RECONNECT_WINDOW       : Duration = Duration::from_secs(3600);
+const WATCHDOG_INTERVAL      : Duration = Duration::from_secs(1);
+
+/// A reader health alert raised by one of the configured `AlertRules`.
+///
+/// Fields:
+/// - `timestamp_ms`: UTC time the alert was raised, in milliseconds since the Unix epoch.
+/// - `reader_id`: Identifier of the reader the alert concerns.
+/// - `kind`: Which rule fired, with the observed value and the configured limit.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+  pub timestamp_ms : i64,
+  pub reader_id    : String,
+  pub kind         : AlertKind
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "alert", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AlertKind {
+  NoTagReads    { silent_secs: u64, limit_secs: u64 },
+  ReconnectRate { reconnects_last_hour: u32, limit: u32 },
+  KeepaliveRtt  { rtt_ms: u64, limit_ms: u64 },
+  ClockDrift    { drift_ms: i64, limit_ms: u64 },
+  Temperature   { temperature_c: f64, limit_c: f64 }
+}
+
+struct AlertState {
+  spec_active      : bool,
+  last_read        : Instant,
+  no_reads_alerted : bool,
+  too_hot_alerted  : bool,
+  reconnects       : VecDeque<Instant>,
+  recent           : VecDeque<Alert>
+}
+
+/// Evaluates the alert rules of a single reader against the activity observed
+/// by its client, and publishes raised alerts to subscribers.
+#[derive(Clone)]
+pub struct AlertMonitor {
+  reader_id : String,
+  rules     : AlertRules,
+  clock     : SharedClock,
+  state     : Arc<Mutex<AlertState>>,
+  alert_tx  : broadcast::Sender<Alert>
+}
+
+impl AlertMonitor {
+
+  pub fn new(
+    reader_id : String,
+    rules     : AlertRules,
+    clock     : SharedClock
+  ) -> Self {
+
+    let (alert_tx, _) = broadcast::channel(RECENT_ALERTS_CAPACITY);
+
+    AlertMonitor {
+      reader_id,
+      rules,
+      state: Arc::new(Mutex::new(AlertState {
+        spec_active      : false,
+        last_read        : clock.now(),
+        no_reads_alerted : false,
+        too_hot_alerted  : false,
+        reconnects       : VecDeque::new(),
+        recent           : VecDeque::with_capacity(RECENT_ALERTS_CAPACITY)
+      })),
+      clock,
+      alert_tx
+    }
+  }
+
+  pub fn subscribe(
+    &self
+  ) -> broadcast::Receiver<Alert> {
+    self.alert_tx.subscribe()
+  }
+
+  /// Returns the most recently raised alerts, oldest first.
+  pub fn recent_alerts(
+    &self
+  ) -> Vec<Alert> {
+    self.state.lock().unwrap().recent.iter().cloned().collect()
+  }
+
+  /// Marks whether an ROSpec is currently expected to produce tag reports.
+  pub fn set_spec_active(
+    &self,
+    active: bool
+  ) {
+
+    let mut state = self.state.lock().unwrap();
+
+    if active && !state.spec_active {
+      state.last_read = self.clock.now();
+      state.no_reads_alerted = false;
+    }
+
+    state.spec_active = active;
+  }
+
+  pub fn record_tag_report(
+    &self
+  ) {
+
+    let mut state = self.state.lock().unwrap();
+    state.last_read = self.clock.now();
+    state.no_reads_alerted = false;
+  }
+
+  pub fn record_reconnect(
+    &self
+  ) {
+
+    let Some(limit) = self.rules.max_reconnects_per_hour else {
+      return;
+    };
+
+    let mut state = self.state.lock().unwrap();
+    let now = self.clock.now();
+
+    state.reconnects.push_back(now);
+    while state.reconnects.front().is_some_and(|at| now.duration_since(*at) > RECONNECT_WINDOW) {
+      state.reconnects.pop_front();
+    }
+
+    let reconnects_last_hour = state.reconnects.len() as u32;
+    if reconnects_last_hour > limit {
+      self.raise(&mut state, AlertKind::ReconnectRate { reconnects_last_hour, limit });
+    }
+  }
+
+  pub fn record_keepalive_rtt(
+    &self,
+    rtt: Duration
+  ) {
+
+    let Some(limit_ms) = self.rules.max_keepalive_rtt_ms else {
+      return;
+    };
+
+    let rtt_ms = rtt.as_millis() as u64;
+    if rtt_ms > limit_ms {
+      let mut state = self.state.lock().unwrap();
+      self.raise(&mut state, AlertKind::KeepaliveRtt { rtt_ms, limit_ms });
+    }
+  }
+
+  /// Checks the offset of the reader clock from the host clock, positive when the
+  /// reader is ahead.
+  pub fn record_clock_drift(
+    &self,
+    drift_ms: i64
+  ) {
+
+    let Some(limit_ms) = self.rules.max_clock_drift_ms else {
+      return;
+    };
+
+    if drift_ms.unsigned_abs() > limit_ms {
+      let mut state = self.state.lock().unwrap();
+      self.raise(&mut state, AlertKind::ClockDrift { drift_ms, limit_ms });
+    }
+  }
+
+  /// Raises a `Temperature` alert once per overheating episode, which ends when
+  /// the reader reports a temperature within the limit again.
+  pub fn record_temperature(
+    &self,
+    temperature_c: f64
+  ) {
+
+    let Some(limit_c) = self.rules.max_temperature_c else {
+      return;
+    };
+
+    let mut state = self.state.lock().unwrap();
+
+    if temperature_c <= limit_c {
+      state.too_hot_alerted = false;
+    } else if !state.too_hot_alerted {
+      state.too_hot_alerted = true;
+      self.raise(&mut state, AlertKind::Temperature { temperature_c, limit_c });
+    }
+  }
+
+  /// Raises a `NoTagReads` alert once per silent period when an ROSpec is active
+  /// and no tag report arrived within the configured window.
+  pub fn check_tag_reads(
+    &self
+  ) {
+
+    let Some(limit_secs) = self.rules.no_tag_reads_secs else {
+      return;
+    };
+
+    let mut state = self.state.lock().unwrap();
+    let silent_secs = self.clock.elapsed(state.last_read).as_secs();
+
+    if state.spec_active && !state.no_reads_alerted && silent_secs >= limit_secs {
+      state.no_reads_alerted = true;
+      self.raise(&mut state, AlertKind::NoTagReads { silent_secs, limit_secs });
+    }
+  }
+
+  /// Starts a task that periodically evaluates the time-based rules. The task
+  /// ends once every handle to this monitor has been dropped.
+  pub fn spawn_watchdog(
+    &self
+  ) {
+
+    if self.rules.no_tag_reads_secs.is_none() {
+      return;
+    }
+
+    let monitor = WeakAlertMonitor {
+      reader_id : self.reader_id.clone(),
+      rules     : self.rules.clone(),
+      clock     : self.clock.clone(),
+      state     : Arc::downgrade(&self.state),
+      alert_tx  : self.alert_tx.clone()
+    };
+
+    tokio::spawn(log_context::scope(self.reader_id.clone(), async move {
+      loop {
+        monitor.clock.sleep(WATCHDOG_INTERVAL).await;
+        match monitor.upgrade() {
+          Some(monitor) => monitor.check_tag_reads(),
+          None => break
+        }
+      }
+    }));
+  }
+
+  fn raise(
+    &self,
+    state : &mut AlertState,
+    kind  : AlertKind
+  ) {
+
+    warn!("Alert raised: {:?}", kind);
+
+    let alert = Alert {
+      timestamp_ms : Utc::now().timestamp_millis(),
+      reader_id    : self.reader_id.clone(),
+      kind
+    };
+
+    if state.recent.len() == RECENT_ALERTS_CAPACITY {
+      state.recent.pop_front();
+    }
+    state.recent.push_back(alert.clone());
+
+    let _ = self.alert_tx.send(alert);
+  }
+}
+
+struct WeakAlertMonitor {
+  reader_id : String,
+  rules     : AlertRules,
+  clock     : SharedClock,
+  state     : Weak<Mutex<AlertState>>,
+  alert_tx  : broadcast::Sender<Alert>
+}
+
+impl WeakAlertMonitor {
+
+  fn upgrade(
+    &self
+  ) -> Option<AlertMonitor> {
+    self.state.upgrade().map(|state| AlertMonitor {
+      reader_id : self.reader_id.clone(),
+      rules     : self.rules.clone(),
+      clock     : self.clock.clone(),
+      state,
+      alert_tx  : self.alert_tx.clone()
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::clock::TokioClock;
+
+  fn monitor(
+    rules: AlertRules
+  ) -> AlertMonitor {
+    AlertMonitor::new("test-reader".to_string(), rules, Arc::new(TokioClock))
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn no_tag_reads_alert_is_raised_once_per_silent_period() {
+
+    let alerts = monitor(AlertRules { no_tag_reads_secs: Some(10), ..AlertRules::default() });
+    alerts.set_spec_active(true);
+
+    tokio::time::advance(Duration::from_secs(9)).await;
+    alerts.check_tag_reads();
+    assert!(alerts.recent_alerts().is_empty());
+
+    tokio::time::advance(Duration::from_secs(2)).await;
+    alerts.check_tag_reads();
+    alerts.check_tag_reads();
+    assert_eq!(alerts.recent_alerts().len(), 1);
+
+    alerts.record_tag_report();
+    tokio::time::advance(Duration::from_secs(10)).await;
+    alerts.check_tag_reads();
+    assert_eq!(alerts.recent_alerts().len(), 2);
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn no_tag_reads_alert_is_not_raised_without_an_active_spec() {
+
+    let alerts = monitor(AlertRules { no_tag_reads_secs: Some(10), ..AlertRules::default() });
+
+    tokio::time::advance(Duration::from_secs(60)).await;
+    alerts.check_tag_reads();
+
+    assert!(alerts.recent_alerts().is_empty());
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn reconnects_older_than_an_hour_are_not_counted() {
+
+    let alerts = monitor(AlertRules { max_reconnects_per_hour: Some(2), ..AlertRules::default() });
+
+    alerts.record_reconnect();
+    alerts.record_reconnect();
+    tokio::time::advance(RECONNECT_WINDOW + Duration::from_secs(1)).await;
+    alerts.record_reconnect();
+    assert!(alerts.recent_alerts().is_empty());
+
+    alerts.record_reconnect();
+    alerts.record_reconnect();
+    assert_eq!(alerts.recent_alerts().len(), 1);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+use std::fmt::Write;
+use std::io::{self, Error, ErrorKind};
+
+use crate::llrp::{get_message_type_str, LlrpHeader, LlrpParameterType, LLRP_HEADER_LENGTH};
+
+/// Width of a fixed-size field, or an array of u16 whose length is given by an
+/// earlier field of the same parameter.
+#[derive(Clone, Copy)]
+enum Field {
+  U8     (&'static str),
+  U16    (&'static str),
+  U32    (&'static str),
+  U16Array { name: &'static str, count_field: &'static str }
+}
+
+/// Fixed fields preceding the sub-parameters of the TLV parameters used when
+/// configuring readers. Parameters not listed here are dumped as raw bytes.
+fn parameter_fields(
+  param_type: LlrpParameterType
+) -> Option<&'static [Field]> {
+
+  use Field::*;
+
+  let fields: &'static [Field] = match param_type {
+    LlrpParameterType::ROSpec                   => &[U32("ROSpecID"), U8("Priority"), U8("CurrentState")],
+    LlrpParameterType::ROBoundarySpec           => &[],
+    LlrpParameterType::ROSpecStartTrigger       => &[U8("ROSpecStartTriggerType")],
+    LlrpParameterType::PeriodicTriggerValue     => &[U32("Offset"), U32("Period")],
+    LlrpParameterType::GPITriggerValue          => &[U16("GPIPortNum"), U8("GPIEvent"), U32("Timeout")],
+    LlrpParameterType::ROSpecStopTrigger        => &[U8("ROSpecStopTriggerType"), U32("DurationTriggerValue")],
+    LlrpParameterType::AISpec                   => &[U16("AntennaCount"), U16Array { name: "AntennaID", count_field: "AntennaCount" }],
+    LlrpParameterType::AISpecStopTrigger        => &[U8("AISpecStopTriggerType"), U32("DurationTrigger")],
+    LlrpParameterType::LoopSpec                 => &[U32("LoopCount")],
+    LlrpParameterType::InventoryParameterSpec   => &[U16("InventoryParameterSpecID"), U8("ProtocolID")],
+    LlrpParameterType::ROReportSpec             => &[U8("ROReportTrigger"), U16("N")],
+    LlrpParameterType::TagReportContentSelector => &[U16("EnableFlags")],
+    LlrpParameterType::C1G2EPCMemorySelector    => &[U8("EnableFlags")],
+    LlrpParameterType::AntennaConfiguration     => &[U16("AntennaID")],
+    LlrpParameterType::C1G2InventoryCommand     => &[U8("TagInventoryStateAware")],
+    LlrpParameterType::C1G2Filter               => &[U8("T")],
+    LlrpParameterType::C1G2SingulationControl   => &[U8("Session"), U16("TagPopulation"), U32("TagTransitTime")],
+    LlrpParameterType::C1G2TagInventoryStateAwareFilterAction => &[U8("Target"), U8("Action")],
+    LlrpParameterType::C1G2TagInventoryStateUnawareFilterAction => &[U8("Action")],
+    LlrpParameterType::C1G2TagInventoryStateAwareSingulationAction => &[U8("I/S")],
+    _ => return None
+  };
+
+  Some(fields)
+}
+
+fn hex(
+  bytes: &[u8]
+) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<String>>().join(" ")
+}
+
+/// Collects annotated lines of the form `offset  bytes  description`.
+struct Annotation {
+  out: String
+}
+
+impl Annotation {
+
+  fn line(
+    &mut self,
+    offset      : usize,
+    bytes       : &[u8],
+    depth       : usize,
+    description : String
+  ) {
+    let _ = writeln!(self.out, "{:04x}  {:<24} {}{}", offset, hex(bytes), "  ".repeat(depth), description);
+  }
+
+  /// Annotates the TLV parameters in `buf`, which starts at `base` in the message.
+  fn parameters(
+    &mut self,
+    buf   : &[u8],
+    base  : usize,
+    depth : usize
+  ) -> io::Result<()> {
+
+    let mut pos = 0;
+
+    while pos < buf.len() {
+
+      if buf[pos] & 0x80 != 0 {
+        self.line(base + pos, &buf[pos..], depth, "TV parameter (not annotated)".to_string());
+        return Ok(());
+      }
+
+      if buf.len() - pos < 4 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Truncated parameter header at offset {}", base + pos)));
+      }
+
+      let type_value = u16::from_be_bytes([buf[pos], buf[pos + 1]]) & 0x03FF;
+      let length = u16::from_be_bytes([buf[pos + 2], buf[pos + 3]]) as usize;
+
+      if length < 4 || pos + length > buf.len() {
+        return Err(Error::new(ErrorKind::InvalidData, format!(
+          "Parameter type {} at offset {} has invalid length {}",
+          type_value, base + pos, length
+        )));
+      }
+
+      let param_type = LlrpParameterType::from_value(type_value);
+      let name = param_type.map(|param_type| format!("{:?}", param_type)).unwrap_or_else(|| "Unknown".to_string());
+
+      self.line(base + pos, &buf[pos..pos + 4], depth, format!("{} (type {}, length {})", name, type_value, length));
+
+      let body = &buf[pos + 4..pos + length];
+      let body_base = base + pos + 4;
+
+      match param_type.and_then(parameter_fields) {
+        Some(fields) => {
+          let consumed = self.fields(fields, body, body_base, depth + 1)?;
+          self.parameters(&body[consumed..], body_base + consumed, depth + 1)?;
+        }
+        None if !body.is_empty() => self.line(body_base, body, depth + 1, "(raw)".to_string()),
+        None => {}
+      }
+
+      pos += length;
+    }
+
+    Ok(())
+  }
+
+  /// Annotates the fixed fields at the start of a parameter body and returns the
+  /// number of bytes they occupy.
+  fn fields(
+    &mut self,
+    fields : &[Field],
+    body   : &[u8],
+    base   : usize,
+    depth  : usize
+  ) -> io::Result<usize> {
+
+    let mut pos = 0;
+    let mut values: Vec<(&str, u64)> = Vec::new();
+
+    for field in fields {
+
+      let (name, width, count) = match *field {
+        Field::U8(name) => (name, 1, 1),
+        Field::U16(name) => (name, 2, 1),
+        Field::U32(name) => (name, 4, 1),
+        Field::U16Array { name, count_field } => {
+          let count = values.iter().find(|(field_name, _)| *field_name == count_field).map(|(_, value)| *value).unwrap_or(0);
+          (name, 2, count as usize)
+        }
+      };
+
+      for index in 0..count {
+
+        if pos + width > body.len() {
+          return Err(Error::new(ErrorKind::InvalidData, format!("Field {} truncated at offset {}", name, base + pos)));
+        }
+
+        let bytes = &body[pos..pos + width];
+        let value = bytes.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64);
+
+        let label = if matches!(field, Field::U16Array { .. }) { format!("{}[{}]", name, index) } else { name.to_string() };
+        self.line(base + pos, bytes, depth, format!("{} = {}", label, value));
+
+        values.push((name, value));
+        pos += width;
+      }
+    }
+
+    Ok(pos)
+  }
+}
+
+/// Renders an encoded LLRP message as an annotated hex dump, one field or
+/// parameter header per line, with nested parameters indented.
+pub fn annotate_message(
+  bytes: &[u8]
+) -> io::Result<String> {
+
+  let header = LlrpHeader::decode(bytes)?;
+
+  if bytes.len() < header.message_length as usize {
+    return Err(Error::new(ErrorKind::InvalidData, "Message shorter than its header length"));
+  }
+
+  let mut annotation = Annotation { out: String::new() };
+
+  annotation.line(0, &bytes[0..2], 0, format!(
+    "Version = {}, MessageType = {} ({})",
+    header.version, header.message_type_value, get_message_type_str(header.message_type_value)
+  ));
+  annotation.line(2, &bytes[2..6], 0, format!("MessageLength = {}", header.message_length));
+  annotation.line(6, &bytes[6..10], 0, format!("MessageID = {}", header.message_id));
+
+  annotation.parameters(&bytes[LLRP_HEADER_LENGTH..header.message_length as usize], LLRP_HEADER_LENGTH, 0)?;
+
+  Ok(annotation.out)
+}
\ No newline at end of file
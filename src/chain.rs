@@ -0,0 +1,282 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use log::{info, warn};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::client::LlrpClient;
+use crate::llrp::{LlrpResponse, LlrpResponseData};
+use crate::log_context;
+use crate::params::ROSpecEventType;
+
+/// Runs the ROSpecs of the configured `SpecChainConfig` back to back, starting
+/// each one when the reader reports the end of the previous one.
+pub struct SpecChain {
+  task           : JoinHandle<()>,
+  client         : Arc<Mutex<LlrpClient>>,
+  repeat         : Arc<AtomicBool>,
+  current_rospec : Arc<AtomicU32>
+}
+
+impl SpecChain {
+
+  /// Starts the first ROSpec of the chain. Returns `None` when the configuration
+  /// has no valid `spec_chain` section.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub async fn start(
+    client: Arc<Mutex<LlrpClient>>
+  ) -> Option<Self> {
+
+    let (reader_id, chain, reader_event_rx) = {
+      let client = client.lock().await;
+      (client.config().reader_id(), client.config().spec_chain.clone()?, client.subscribe_reader_events())
+    };
+
+    if chain.rospec_ids.is_empty() || chain.rospec_ids.contains(&0) {
+      log_context::sync_scope(reader_id, || {
+        warn!("Ignoring spec chain with invalid ROSpec IDs {:?}", chain.rospec_ids);
+      });
+      return None;
+    }
+
+    let repeat = Arc::new(AtomicBool::new(chain.repeat));
+    let current_rospec = Arc::new(AtomicU32::new(0));
+
+    let task = tokio::spawn(log_context::scope(
+      reader_id,
+      run_chain(client.clone(), chain.rospec_ids, repeat.clone(), current_rospec.clone(), reader_event_rx)
+    ));
+
+    Some(SpecChain { task, client, repeat, current_rospec })
+  }
+
+  /// Changes whether the chain starts over after its last ROSpec. Turning repeat
+  /// off lets the running pass finish and then ends the chain.
+  pub fn set_repeat(
+    &self,
+    repeat: bool
+  ) {
+    self.repeat.store(repeat, Ordering::SeqCst);
+  }
+
+  /// The ROSpec the chain is currently running, or `None` once it has ended.
+  pub fn current_rospec(
+    &self
+  ) -> Option<u32> {
+    Some(self.current_rospec.load(Ordering::SeqCst)).filter(|rospec_id| *rospec_id != 0)
+  }
+
+  pub fn is_finished(
+    &self
+  ) -> bool {
+    self.task.is_finished()
+  }
+
+  /// Stops sequencing without touching the reader; the running ROSpec continues
+  /// until its own stop trigger fires.
+  pub fn stop(
+    self
+  ) {
+    self.task.abort();
+  }
+
+  /// Stops sequencing and sends STOP_ROSPEC for the running ROSpec.
+  pub async fn abort(
+    self
+  ) {
+
+    self.task.abort();
+
+    let Some(rospec_id) = self.current_rospec() else {
+      return;
+    };
+
+    let mut client = self.client.lock().await;
+    let reader_id = client.config().reader_id();
+
+    log_context::scope(reader_id, async {
+      info!("Aborting spec chain, stopping ROSpec {}", rospec_id);
+      if let Err(e) = client.send_stop_rospec_with_id(rospec_id).await {
+        warn!("Failed to stop ROSpec {}: {}", rospec_id, e);
+      }
+    }).await
+  }
+}
+
+async fn run_chain(
+  client              : Arc<Mutex<LlrpClient>>,
+  rospec_ids          : Vec<u32>,
+  repeat              : Arc<AtomicBool>,
+  current_rospec      : Arc<AtomicU32>,
+  mut reader_event_rx : broadcast::Receiver<LlrpResponse>
+) {
+
+  info!("Starting spec chain {:?}", rospec_ids);
+
+  let mut index = 0;
+
+  loop {
+
+    let rospec_id = rospec_ids[index];
+    current_rospec.store(rospec_id, Ordering::SeqCst);
+
+    info!("Starting ROSpec {} ({} of {})", rospec_id, index + 1, rospec_ids.len());
+
+    if let Err(e) = client.lock().await.send_start_rospec_with_id(rospec_id).await {
+      warn!("Failed to start ROSpec {}, ending spec chain: {}", rospec_id, e);
+      break;
+    }
+
+    if !wait_for_rospec_end(&mut reader_event_rx, rospec_id).await {
+      warn!("Lost track of ROSpec {}, ending spec chain", rospec_id);
+      break;
+    }
+
+    index += 1;
+
+    if index == rospec_ids.len() {
+      if !repeat.load(Ordering::SeqCst) {
+        info!("Spec chain finished");
+        break;
+      }
+      index = 0;
+    }
+  }
+
+  current_rospec.store(0, Ordering::SeqCst);
+}
+
+/// Waits for an ROSpecEvent reporting that `rospec_id` ended or was preempted.
+/// Returns false if the event stream closed first, or if events were missed, as
+/// the end may have been among them and the chain would otherwise wait forever.
+async fn wait_for_rospec_end(
+  reader_event_rx : &mut broadcast::Receiver<LlrpResponse>,
+  rospec_id       : u32
+) -> bool {
+
+  loop {
+    match reader_event_rx.recv().await {
+      Ok(event) => {
+
+        let Ok(LlrpResponseData::ReaderEvent(event_data)) = event.decode() else {
+          continue;
+        };
+
+        let Some(rospec_event) = event_data.rospec_event else {
+          continue;
+        };
+
+        if rospec_event.rospec_id != rospec_id {
+          continue;
+        }
+
+        match rospec_event.event_type {
+          ROSpecEventType::End => {
+            info!("ROSpec {} ended", rospec_id);
+            return true;
+          }
+          ROSpecEventType::Preempted => {
+            info!("ROSpec {} preempted by ROSpec {}", rospec_id, rospec_event.preempting_rospec_id);
+            return true;
+          }
+          _ => continue
+        }
+      }
+      Err(broadcast::error::RecvError::Lagged(skipped)) => {
+        warn!("Missed {} reader events while waiting for ROSpec {} to end", skipped, rospec_id);
+        return false;
+      }
+      Err(broadcast::error::RecvError::Closed) => {
+        warn!("Reader event stream closed while waiting for ROSpec {} to end", rospec_id);
+        return false;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use std::time::Duration;
+  use bytes::{BufMut, BytesMut};
+  use tokio::sync::oneshot;
+
+  use super::*;
+  use crate::config::SpecChainConfig;
+  use crate::llrp::{LlrpMessage, LlrpMessageType, LlrpParameterType};
+  use crate::scripted_reader::{status_response, test_config, ScriptedConnection, ScriptedReader};
+
+  fn rospec_end_event(
+    rospec_id: u32
+  ) -> LlrpMessage {
+
+    let mut payload = BytesMut::new();
+    payload.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+    payload.put_u16(17);
+    payload.put_u16(LlrpParameterType::ROSpecEvent.value());
+    payload.put_u16(13);
+    payload.put_u8(1); // End
+    payload.put_u32(rospec_id);
+    payload.put_u32(0);
+
+    LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, payload.to_vec())
+  }
+
+  /// Answers the next START_ROSPEC, checking it starts `rospec_id`.
+  async fn accept_start(
+    connection : &mut ScriptedConnection,
+    rospec_id  : u32
+  ) -> tokio::io::Result<()> {
+
+    let request = connection.expect(LlrpMessageType::StartROSpec).await?;
+    assert_eq!(request.payload[..4], rospec_id.to_be_bytes());
+    connection.send(&[status_response(LlrpMessageType::StartROSpecResponse, request.message_id)]).await
+  }
+
+  async fn wait_until(
+    condition: impl Fn() -> bool
+  ) {
+    tokio::time::timeout(Duration::from_secs(2), async {
+      while !condition() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+      }
+    }).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn rospec_ends_advance_the_chain_until_repeat_is_turned_off() {
+
+    let (last_end_tx, last_end_rx) = oneshot::channel::<()>();
+    let (done_tx, done_rx) = oneshot::channel::<()>();
+
+    let reader = ScriptedReader::start(|mut connection| async move {
+      accept_start(&mut connection, 11).await?;
+      connection.send(&[rospec_end_event(11)]).await?;
+      accept_start(&mut connection, 12).await?;
+      let _ = last_end_rx.await;
+      connection.send(&[rospec_end_event(12)]).await?;
+      let _ = done_rx.await;
+      Ok(())
+    }).await;
+
+    let mut config = test_config(&reader.host, 5000);
+    config.spec_chain = Some(SpecChainConfig { rospec_ids: vec![11, 12], repeat: true });
+    let client = Arc::new(Mutex::new(LlrpClient::initialize_with_config(config).await.unwrap()));
+
+    let chain = SpecChain::start(client).await.unwrap();
+    wait_until(|| chain.current_rospec() == Some(12)).await;
+
+    chain.set_repeat(false);
+    last_end_tx.send(()).unwrap();
+
+    // The reader stays connected, so a restart from ROSpec 11 would go unanswered
+    // for the whole response timeout.
+    wait_until(|| chain.is_finished()).await;
+    assert_eq!(chain.current_rospec(), None);
+
+    done_tx.send(()).unwrap();
+
+    reader.finish().await;
+  }
+}
\ No newline at end of file
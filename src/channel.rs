@@ -0,0 +1,158 @@
+//! The broadcast channels the receive loop delivers reports and events through,
+//! with the configured capacity and overflow strategy.
+
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::clock::Clock;
+use crate::config::{ChannelConfig, OverflowStrategy};
+use crate::stats::ChannelCounters;
+
+/// How often the `block` strategy checks whether a slow subscriber made room.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A broadcast channel to the subscribers of one kind of message. A message is
+/// held until every subscriber has received it, so the fullness of the channel is
+/// that of its slowest subscriber.
+#[derive(Clone)]
+pub struct SubscriberChannel<T> {
+  name          : &'static str,
+  tx            : broadcast::Sender<T>,
+  capacity      : usize,
+  overflow      : OverflowStrategy,
+  block_timeout : Duration,
+  counters      : ChannelCounters
+}
+
+impl<T: Clone> SubscriberChannel<T> {
+
+  pub fn new(
+    name     : &'static str,
+    config   : &ChannelConfig,
+    counters : ChannelCounters
+  ) -> Self {
+
+    // Tokio rounds the capacity up to a power of two, and evicts only beyond that.
+    let capacity = config.capacity.max(1).next_power_of_two();
+
+    SubscriberChannel {
+      name,
+      tx            : broadcast::channel(capacity).0,
+      capacity,
+      overflow      : config.overflow,
+      block_timeout : Duration::from_millis(config.block_timeout),
+      counters
+    }
+  }
+
+  pub fn subscribe(
+    &self
+  ) -> broadcast::Receiver<T> {
+    self.tx.subscribe()
+  }
+
+  /// Delivers `value` to the current subscribers, if any, applying the overflow
+  /// strategy when the slowest of them is `capacity` messages behind.
+  pub async fn send(
+    &self,
+    value : T,
+    clock : &dyn Clock
+  ) {
+
+    if self.tx.len() >= self.capacity {
+      match self.overflow {
+
+        OverflowStrategy::DropOldest => self.counters.record_dropped(self.name),
+
+        OverflowStrategy::DropNewest => {
+          self.counters.record_dropped(self.name);
+          return;
+        }
+
+        OverflowStrategy::Block => {
+          let started = clock.now();
+          while self.tx.len() >= self.capacity && clock.elapsed(started) < self.block_timeout {
+            clock.sleep(BLOCK_POLL_INTERVAL).await;
+          }
+          self.counters.record_blocked(self.name, clock.elapsed(started));
+          if self.tx.len() >= self.capacity {
+            self.counters.record_dropped(self.name);
+          }
+        }
+      }
+    }
+
+    let _ = self.tx.send(value);
+  }
+
+  /// Replaces the channel with an empty one. Current subscribers receive the
+  /// messages already sent and then see the channel closed, once every clone
+  /// of the old channel is gone.
+  pub fn renew(
+    &mut self
+  ) {
+    self.tx = broadcast::channel(self.capacity).0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::clock::TokioClock;
+
+  fn channel(
+    overflow : OverflowStrategy,
+    counters : &ChannelCounters
+  ) -> SubscriberChannel<u32> {
+    let config = ChannelConfig { capacity: 2, overflow, block_timeout: 50 };
+    SubscriberChannel::new("ro_reports", &config, counters.clone())
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn full_subscribers_are_handled_by_the_overflow_strategy() {
+
+    let clock = TokioClock;
+
+    let counters = ChannelCounters::default();
+    let oldest = channel(OverflowStrategy::DropOldest, &counters);
+    let mut rx = oldest.subscribe();
+    for value in 1..=3 {
+      oldest.send(value, &clock).await;
+    }
+    assert!(matches!(rx.recv().await, Err(broadcast::error::RecvError::Lagged(1))));
+    assert_eq!((rx.recv().await.unwrap(), rx.recv().await.unwrap()), (2, 3));
+    assert_eq!(counters.snapshot().dropped["ro_reports"], 1);
+
+    let counters = ChannelCounters::default();
+    let newest = channel(OverflowStrategy::DropNewest, &counters);
+    let mut rx = newest.subscribe();
+    for value in 1..=3 {
+      newest.send(value, &clock).await;
+    }
+    assert_eq!((rx.recv().await.unwrap(), rx.recv().await.unwrap()), (1, 2));
+    assert!(rx.try_recv().is_err());
+    assert_eq!(counters.snapshot().dropped["ro_reports"], 1);
+
+    let counters = ChannelCounters::default();
+    let block = channel(OverflowStrategy::Block, &counters);
+    let mut rx = block.subscribe();
+    block.send(1, &clock).await;
+    block.send(2, &clock).await;
+
+    let consumer = tokio::spawn(async move {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+      let first = rx.recv().await.unwrap();
+      (first, rx)
+    });
+    block.send(3, &clock).await;
+    let (first, mut rx) = consumer.await.unwrap();
+    assert_eq!(first, 1);
+    assert!(counters.snapshot().dropped.is_empty());
+    assert!(counters.snapshot().blocked_ms["ro_reports"] >= 10);
+
+    block.send(4, &clock).await;
+    assert!(matches!(rx.recv().await, Err(broadcast::error::RecvError::Lagged(1))));
+    assert_eq!(counters.snapshot().dropped["ro_reports"], 1);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,619 @@
+#![allow(dead_code)]
+
+mod alerts;
+mod channel;
+mod chain;
+mod clock;
+mod annotate;
+mod config;
+mod custom;
+mod duration;
+mod error;
+mod gpio;
+mod params;
+mod region;
+mod rospec;
+mod secrets;
+mod llrp;
+mod setup;
+mod stream;
+mod stats;
+mod tag_stream;
+#[cfg(feature = "tls")]
+mod tls;
+mod client;
+mod history;
+mod introspect;
+mod journal;
+mod liveness;
+#[cfg(feature = "impinj")]
+mod impinj;
+#[cfg(feature = "live")]
+mod live;
+mod log_context;
+mod manager;
+mod schedule;
+mod sealing;
+mod sinks;
+mod tuning;
+#[cfg(test)]
+mod scripted_reader;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use chain::SpecChain;
+use client::{rospec_report_extensions, LlrpClient};
+use config::{Config, ROReportTrigger, load_config};
+use llrp::{LlrpMessage, LlrpResponseData};
+use manager::{ReaderManager, DEFAULT_FLEET_CONCURRENCY};
+use params::{LlrpParameterData, TransmitPowerLevelTableEntry};
+use schedule::EnableScheduler;
+
+fn usage() -> ! {
+  eprintln!("Usage: llrp <command> [options]");
+  eprintln!();
+  eprintln!("Commands:");
+  eprintln!("  init [--host <address:port>] [--out <path>]   Generate a config.json from reader capabilities");
+  eprintln!("  encode-rospec [--config <path>]                Print the ADD_ROSPEC bytes a config produces, without connecting");
+  eprintln!("  capabilities [--config <path>] [--out <path>]  Write the decoded reader capabilities as JSON, to stdout by default");
+  eprintln!("  fleet <firmware|capabilities> [--concurrency <n>] <config.json>...");
+  eprintln!("                                                Query many readers concurrently");
+  eprintln!("  monitor [--config <path>] [--interval <secs>] [--live]");
+  eprintln!("                                                Attach in monitor mode and print tag and message counts,");
+  eprintln!("                                                or with --live show a table of the tags seen");
+  eprintln!("  chain [--config <path>]                        Run the configured spec_chain until it ends or Ctrl-C aborts it");
+  eprintln!("  schedule [--config <path>]                     Follow the configured enable_schedule until Ctrl-C");
+  eprintln!("  run [--concurrency <n>] <config.json>...");
+  eprintln!("                                                Read with many readers and publish the tags to the configured");
+  eprintln!("                                                sinks until Ctrl-C, following any report_tuning and enable_schedule");
+  std::process::exit(2);
+}
+
+fn option_value(
+  args : &[String],
+  name : &str
+) -> Option<String> {
+  args.iter()
+    .position(|arg| arg == name)
+    .and_then(|index| args.get(index + 1))
+    .cloned()
+}
+
+fn prompt(
+  question : &str,
+  default  : &str
+) -> String {
+
+  print!("{} [{}]: ", question, default);
+  let _ = io::stdout().flush();
+
+  let mut answer = String::new();
+  if io::stdin().lock().read_line(&mut answer).is_err() {
+    return default.to_string();
+  }
+
+  let answer = answer.trim();
+  if answer.is_empty() { default.to_string() } else { answer.to_string() }
+}
+
+fn prompt_parsed<T>(
+  question : &str,
+  default  : T
+) -> T
+where
+  T: std::str::FromStr + std::fmt::Display + Copy
+{
+  loop {
+    match prompt(question, &default.to_string()).parse::<T>() {
+      Ok(value) => return value,
+      Err(_) => println!("  Invalid value, please try again.")
+    }
+  }
+}
+
+/// Builds a configuration from JSON so that fields not covered by the wizard
+/// take their serde defaults.
+fn bootstrap_config(
+  host: &str
+) -> Result<Config, Box<dyn Error>> {
+  Ok(serde_json::from_value(json!({
+    "host": host,
+    "log_level": "info",
+    "log_response_ack": false,
+    "response_timeout": 1000,
+    "reader_config": {
+      "hop_table_id": 1,
+      "channel_index": 1,
+      "tx_power_table_index": 1,
+      "rx_power_table_index": 1
+    },
+    "rospec": {
+      "rospec_id": 1,
+      "priority": 0,
+      "antenna_count": 1,
+      "antennas": [1],
+      "ROSpecStartTriggerType": 1,
+      "ROSpecStopTriggerType": 0,
+      "AISpecStopTriggerType": 0,
+      "InventoryParamSpecID": 1,
+      "AIProtocol": 1,
+      "ROReportTriggerType": 1,
+      "ROReportTrigger_N": 1,
+      "ReportContentSelector": 1
+    }
+  }))?)
+}
+
+fn nearest_power_level(
+  power_levels : &[&TransmitPowerLevelTableEntry],
+  dbm          : f64
+) -> Option<u16> {
+  power_levels.iter()
+    .min_by_key(|entry| ((entry.transmit_power_value as f64 / 100.0 - dbm).abs() * 100.0) as u64)
+    .map(|entry| entry.index)
+}
+
+async fn init(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let host = match option_value(args, "--host") {
+    Some(host) => host,
+    None => prompt("Reader address", "192.168.1.100:5084")
+  };
+  let out_path = option_value(args, "--out").unwrap_or_else(|| "config.json".to_string());
+
+  println!("Connecting to {} ...", host);
+
+  let mut client = LlrpClient::initialize_with_config(bootstrap_config(&host)?).await?;
+
+  let mut capabilities = Vec::new();
+  client.send_get_reader_capabilities(|response_data| {
+    if let LlrpResponseData::ReaderCapabilities(parameters) = response_data {
+      capabilities = parameters;
+    }
+    async {}
+  }).await?;
+
+  let _ = client.send_close_connection().await;
+
+  let mut max_antennas = 1;
+  let mut firmware = String::new();
+  let mut power_levels = Vec::new();
+  let mut hop_table_ids = Vec::new();
+
+  for parameter in &capabilities {
+    match parameter {
+
+      LlrpParameterData::GeneralDeviceCapabilities(gdc) => {
+        max_antennas = gdc.max_number_of_antennas_supported.max(1);
+        firmware = gdc.reader_firmware_version.clone();
+      }
+
+      LlrpParameterData::RegulatoryCapabilities(reg_caps) => {
+        if let Some(uhf) = &reg_caps.uhf_band_capabilities {
+          power_levels.extend(uhf.transmit_power_levels.iter());
+          if let Some(freq_info) = &uhf.frequency_information {
+            hop_table_ids.extend(freq_info.frequency_hop_tables.iter().map(|table| table.hop_table_id));
+          }
+        }
+      }
+
+      _ => {}
+    }
+  }
+
+  println!();
+  println!("Reader firmware     : {}", if firmware.is_empty() { "unknown" } else { &firmware });
+  println!("Antennas supported  : {}", max_antennas);
+  if let (Some(min), Some(max)) = (
+    power_levels.iter().map(|entry| entry.transmit_power_value).min(),
+    power_levels.iter().map(|entry| entry.transmit_power_value).max()
+  ) {
+    println!("Transmit power      : {:.2} - {:.2} dBm ({} levels)", min as f64 / 100.0, max as f64 / 100.0, power_levels.len());
+  }
+  println!();
+
+  let all_antennas = (1..=max_antennas).map(|id| id.to_string()).collect::<Vec<String>>().join(",");
+  let antennas: Vec<u16> = loop {
+    let answer = prompt("Antennas to use (comma separated)", &all_antennas);
+    let parsed: Result<Vec<u16>, _> = answer.split(',').map(|id| id.trim().parse::<u16>()).collect();
+    match parsed {
+      Ok(ids) if !ids.is_empty() && ids.iter().all(|id| (1..=max_antennas).contains(id)) => break ids,
+      _ => println!("  Antenna IDs must be between 1 and {}.", max_antennas)
+    }
+  };
+
+  let tx_power_table_index = if power_levels.is_empty() {
+    prompt_parsed("Transmit power table index", 1u16)
+  } else {
+    let max_dbm = power_levels.iter().map(|entry| entry.transmit_power_value).max().unwrap_or(0) as f64 / 100.0;
+    let dbm = prompt_parsed("Transmit power (dBm)", max_dbm);
+    let index = nearest_power_level(&power_levels, dbm).unwrap_or(1);
+    println!("  Using transmit power table index {}", index);
+    index
+  };
+
+  let hop_table_id = prompt_parsed("Hop table ID", hop_table_ids.first().copied().unwrap_or(1));
+
+  println!("ROSpec start trigger: 0 = Null (start via START_ROSPEC), 1 = Immediate");
+  let start_trigger = prompt_parsed("ROSpec start trigger type", 0u8);
+
+  println!("Report trigger: 0 = None, 1 = Upon N tags or end of AISpec, 2 = Upon N tags or end of ROSpec");
+  let report_trigger = loop {
+    match ROReportTrigger::from_value(prompt_parsed("ROReportTrigger type", 1u8)) {
+      Some(trigger) if !trigger.is_periodic() => break trigger,
+      _ => println!("  Invalid value, please try again.")
+    }
+  };
+  let report_n = match report_trigger {
+    ROReportTrigger::None => 0,
+    _ => prompt_parsed("Tags per report (N, 0 - only at the end)", 1u16)
+  };
+
+  let mut config = bootstrap_config(&host)?;
+  config.reader_config.hop_table_id = hop_table_id;
+  config.reader_config.tx_power_table_index = tx_power_table_index;
+  config.rospec.antenna_count = antennas.len() as u16;
+  config.rospec.antennas = antennas;
+  config.rospec.ROSpecStartTriggerType = start_trigger;
+  config.rospec.ROReportTriggerType = report_trigger;
+  config.rospec.ROReportTrigger_N = report_n;
+
+  fs::write(&out_path, serde_json::to_string_pretty(&config)?)?;
+  println!();
+  println!("Configuration written to {}", out_path);
+
+  Ok(())
+}
+
+fn encode_rospec(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let config_path = option_value(args, "--config").unwrap_or_else(|| "config.json".to_string());
+  let config = load_config(&config_path).map_err(|e| format!("Failed to load {}: {}", config_path, e))?;
+
+  let message = LlrpMessage::new_add_rospec(1, &config.rospec, &rospec_report_extensions(&config));
+  print!("{}", annotate::annotate_message(&message.encode())?);
+
+  Ok(())
+}
+
+/// Attaches to the reader in monitor mode and writes its decoded capability tree
+/// as JSON.
+async fn capabilities(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let config_path = option_value(args, "--config").unwrap_or_else(|| "config.json".to_string());
+  let mut config = load_config(&config_path).map_err(|e| format!("Failed to load {}: {}", config_path, e))?;
+  config.monitor_mode = true;
+
+  let mut client = LlrpClient::initialize_with_config(config).await?;
+  let capabilities = client.capabilities_json().await;
+  let _ = client.send_close_connection().await;
+
+  let capabilities_json = serde_json::to_string_pretty(&capabilities?)?;
+
+  match option_value(args, "--out") {
+    Some(out_path) => {
+      fs::write(&out_path, capabilities_json)?;
+      println!("Capabilities written to {}", out_path);
+    }
+    None => println!("{}", capabilities_json)
+  }
+
+  Ok(())
+}
+
+/// Reads `[--concurrency <n>] <config.json>...` and loads the configurations.
+fn fleet_configs(
+  args: &[String]
+) -> Result<(usize, Vec<Config>), Box<dyn Error>> {
+
+  let mut concurrency = DEFAULT_FLEET_CONCURRENCY;
+  let mut config_paths = Vec::new();
+
+  let mut remaining = args.iter();
+  while let Some(arg) = remaining.next() {
+    if arg == "--concurrency" {
+      concurrency = remaining.next().and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+    } else {
+      config_paths.push(arg.clone());
+    }
+  }
+
+  if config_paths.is_empty() {
+    usage();
+  }
+
+  let mut configs = Vec::with_capacity(config_paths.len());
+  for path in &config_paths {
+    configs.push(load_config(path).map_err(|e| format!("Failed to load {}: {}", path, e))?);
+  }
+
+  Ok((concurrency, configs))
+}
+
+async fn fleet(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let operation = args.first().map(|arg| arg.as_str()).unwrap_or_else(|| usage());
+  let (concurrency, configs) = fleet_configs(&args[1..])?;
+  let reader_count = configs.len();
+
+  let mut manager = ReaderManager::new();
+  let connected = manager.connect_all(configs, concurrency).await;
+
+  println!("{:<24} {:<8} {:>8}  DETAIL", "READER", "RESULT", "MS");
+
+  for (reader_id, e) in connected.failed() {
+    println!("{:<24} {:<8} {:>8}  connect: {}", reader_id, "FAILED", "-", e);
+  }
+
+  let rows: Vec<(String, u64, Result<String, String>)> = match operation {
+
+    "firmware" => manager.fetch_firmware_versions(concurrency).await.results.into_iter()
+      .map(|result| (result.reader_id, result.duration_ms, result.outcome))
+      .collect(),
+
+    "capabilities" => manager.fetch_capabilities(concurrency).await.results.into_iter()
+      .map(|result| (result.reader_id, result.duration_ms, result.outcome.map(|parameters| format!("{} parameters", parameters.len()))))
+      .collect(),
+
+    _ => usage()
+  };
+
+  for (reader_id, duration_ms, outcome) in &rows {
+    match outcome {
+      Ok(detail) => println!("{:<24} {:<8} {:>8}  {}", reader_id, "OK", duration_ms, detail),
+      Err(e) => println!("{:<24} {:<8} {:>8}  {}", reader_id, "FAILED", duration_ms, e)
+    }
+  }
+
+  manager.close_all(concurrency).await;
+
+  let failures = connected.failure_count() + rows.iter().filter(|(_, _, outcome)| outcome.is_err()).count();
+  if failures > 0 {
+    return Err(format!("{} of {} readers failed", failures, reader_count).into());
+  }
+
+  Ok(())
+}
+
+/// Attaches to the reader without modifying it and periodically prints the tags
+/// reported since the last line and the message counts of the session. With
+/// `--live`, shows a table of the tags seen, refreshed in place, instead.
+async fn monitor(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let live = args.iter().any(|arg| arg == "--live");
+  if live && !cfg!(feature = "live") {
+    return Err("monitor --live requires the \"live\" feature, which this build does not include".into());
+  }
+
+  let config_path = option_value(args, "--config").unwrap_or_else(|| "config.json".to_string());
+  let interval_secs: u64 = match option_value(args, "--interval") {
+    Some(value) => value.parse().unwrap_or_else(|_| usage()),
+    None => 5
+  };
+
+  let mut config = load_config(&config_path).map_err(|e| format!("Failed to load {}: {}", config_path, e))?;
+  config.monitor_mode = true;
+
+  let mut client = LlrpClient::initialize_with_config(config).await?;
+  let mut ro_report_rx = client.subscribe_ro_reports();
+  client.send_enable_events_and_reports().await?;
+
+  #[cfg(feature = "live")]
+  if live {
+    return live::run(&client, ro_report_rx).await;
+  }
+
+  let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+  ticker.tick().await;
+
+  let mut tag_count = 0usize;
+
+  loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          if let Ok(LlrpResponseData::TagReport(tag_reports)) = report.decode() {
+            tag_count += tag_reports.len();
+          }
+        }
+        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+      },
+
+      _ = ticker.tick() => {
+
+        let stats = client.protocol_stats();
+
+        println!("{} tags in the last {} s", tag_count, interval_secs);
+
+        let drift = client.clock_drift_stats();
+        if let Some(drift_ms) = drift.drift_ms {
+          println!("Reader clock drift {} ms (max {} ms, {} resyncs)", drift_ms, drift.max_abs_drift_ms, drift.resyncs);
+        }
+
+        let telemetry = match client.request_telemetry().await {
+          Ok(telemetry) => telemetry,
+          Err(e) => {
+            eprintln!("Failed to request reader telemetry: {}", e);
+            client.telemetry_stats()
+          }
+        };
+        if let Some(temperature_c) = telemetry.temperature_c {
+          println!("Reader temperature {:.1} C (max {:.1} C)", temperature_c, telemetry.max_temperature_c.unwrap_or(temperature_c));
+        }
+        if telemetry.cpu_utilization_pct.is_some() || telemetry.rf_utilization_pct.is_some() {
+          let percent = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.0}%", value));
+          println!("Reader CPU {}, RF {}", percent(telemetry.cpu_utilization_pct), percent(telemetry.rf_utilization_pct));
+        }
+
+        println!("  {:<32} {:>10} {:>10}", "MESSAGE", "SENT", "RECEIVED");
+
+        let message_types: std::collections::BTreeSet<&String> = stats.sent.keys().chain(stats.received.keys()).collect();
+        for message_type in message_types {
+          println!(
+            "  {:<32} {:>10} {:>10}",
+            message_type,
+            stats.sent.get(message_type).copied().unwrap_or(0),
+            stats.received.get(message_type).copied().unwrap_or(0)
+          );
+        }
+
+        tag_count = 0;
+      }
+
+      _ = tokio::signal::ctrl_c() => break
+    }
+  }
+
+  Ok(())
+}
+
+async fn chain(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let config_path = option_value(args, "--config").unwrap_or_else(|| "config.json".to_string());
+  let config = load_config(&config_path).map_err(|e| format!("Failed to load {}: {}", config_path, e))?;
+
+  let client = Arc::new(Mutex::new(LlrpClient::initialize_with_config(config).await?));
+
+  let Some(spec_chain) = SpecChain::start(client).await else {
+    return Err(format!("{} has no valid spec_chain section", config_path).into());
+  };
+
+  let mut ticker = tokio::time::interval(std::time::Duration::from_millis(100));
+  let mut current_rospec = None;
+
+  loop {
+    tokio::select! {
+
+      _ = ticker.tick() => {
+
+        if spec_chain.current_rospec() != current_rospec {
+          current_rospec = spec_chain.current_rospec();
+          if let Some(rospec_id) = current_rospec {
+            println!("Running ROSpec {}", rospec_id);
+          }
+        }
+
+        if spec_chain.is_finished() {
+          println!("Spec chain finished");
+          break;
+        }
+      }
+
+      _ = tokio::signal::ctrl_c() => {
+        spec_chain.abort().await;
+        println!("Spec chain aborted");
+        break;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+async fn schedule(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let config_path = option_value(args, "--config").unwrap_or_else(|| "config.json".to_string());
+  let config = load_config(&config_path).map_err(|e| format!("Failed to load {}: {}", config_path, e))?;
+
+  let client = Arc::new(Mutex::new(LlrpClient::initialize_with_config(config).await?));
+
+  let Some(scheduler) = EnableScheduler::start(client).await else {
+    return Err(format!("{} has no enable_schedule section", config_path).into());
+  };
+
+  tokio::signal::ctrl_c().await?;
+  scheduler.stop();
+
+  Ok(())
+}
+
+/// Connects to every reader, starts its inventory with the report tuning and
+/// enable schedule its configuration declares, and publishes the tags read to the configured sinks
+/// until Ctrl-C.
+async fn run(
+  args: &[String]
+) -> Result<(), Box<dyn Error>> {
+
+  let (concurrency, configs) = fleet_configs(args)?;
+
+  let mut manager = ReaderManager::new();
+  let connected = manager.connect_all(configs, concurrency).await;
+
+  for (reader_id, e) in connected.failed() {
+    eprintln!("{}: connect failed: {}", reader_id, e);
+  }
+
+  let started = manager.for_each(concurrency, |mut client| async move {
+    client.start_inventory().await
+  }).await;
+
+  for (reader_id, e) in started.failed() {
+    eprintln!("{}: start failed: {}", reader_id, e);
+  }
+
+  let running: Vec<String> = started.succeeded().map(|(reader_id, _)| reader_id.to_string()).collect();
+
+  for reader_id in &running {
+    if manager.start_report_tuning(reader_id).await {
+      println!("{}: tuning ROReportTrigger_N", reader_id);
+    }
+    if manager.start_enable_schedule(reader_id).await {
+      println!("{}: following the enable schedule", reader_id);
+    }
+  }
+
+  println!("Reading with {} readers, press Ctrl-C to stop", running.len());
+  tokio::signal::ctrl_c().await?;
+
+  manager.for_each(concurrency, |mut client| async move {
+    client.stop_inventory().await
+  }).await;
+  manager.close_all(concurrency).await;
+
+  Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+
+  let args: Vec<String> = env::args().skip(1).collect();
+
+  let result = match args.first().map(|arg| arg.as_str()) {
+    Some("init") => init(&args[1..]).await,
+    Some("encode-rospec") => encode_rospec(&args[1..]),
+    Some("capabilities") => capabilities(&args[1..]).await,
+    Some("fleet") => fleet(&args[1..]).await,
+    Some("monitor") => monitor(&args[1..]).await,
+    Some("chain") => chain(&args[1..]).await,
+    Some("schedule") => schedule(&args[1..]).await,
+    Some("run") => run(&args[1..]).await,
+    _ => usage()
+  };
+
+  if let Err(e) = result {
+    eprintln!("Error: {}", e);
+    std::process::exit(1);
+  }
+}
\ No newline at end of file
@@ -1,61 +1,522 @@
 use bytes::BytesMut;
+use futures::future::BoxFuture;
 use tokio::io::{self, split, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
-use tokio::net::TcpStream;
-use tokio::sync::{broadcast, Mutex};
-use tokio::time::{timeout, Instant};
-use std::error::Error;
+use tokio::net::{lookup_host, TcpListener, TcpStream};
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_socks::tcp::Socks5Stream;
 use std::future::Future;
-use std::sync::{Arc, Once};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex as StdMutex, PoisonError, RwLock};
 use std::time::Duration;
-use bytes::Buf;
 use env_logger::{self, Builder};
 use std::fs::OpenOptions;
+use std::path::Path;
 use chrono::Local;
 use std::io::Write;
 use log::{info, debug, warn, error, LevelFilter};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::alerts::{Alert, AlertMonitor};
+use crate::clock::{Clock, SharedClock, TokioClock};
+use crate::custom::{CustomMessage, CustomParameter};
+use crate::error::{LlrpError, LlrpStatusError};
+#[cfg(feature = "impinj")]
+use crate::impinj;
+use crate::config::{ AccessSpecConfig, C1G2BlockEraseConfig, C1G2KillConfig, C1G2LockConfig, C1G2LockPayloadConfig, C1G2RFControlConfig, C1G2TargetTagConfig, C1G2WriteConfig, ClockDriftConfig, Config, DuplicateConnectionAction, GpiPortConfig, GpoOutputConfig, ListenConfig, ROReportTrigger, ROSpecConfig, ReaderConfig, StartupAction, diff_configs, load_config };
+use crate::log_context::{self, ReaderLogWriter};
+use crate::setup::SetupTransaction;
+use crate::tag_stream::TagReportStream;
+use crate::channel::SubscriberChannel;
+use crate::stats::{ChannelCounters, ChannelStats, ClockDriftStats, ClockDriftTracker, ProtocolCounters, ProtocolStats, TelemetrySample, TelemetryStats, TelemetryTracker};
+use crate::gpio::{GpoPort, PinState};
+use crate::region::resolve_region_preset;
+use crate::introspect::{ParameterField, ParameterSnapshot};
+use crate::journal::MessageJournal;
+use crate::rospec::ROSpec;
+use crate::params::{AccessSpec, AntennaConfiguration, AntennaProperties, C1G2LLRPCapabilities, C1G2UHFRFModeTableEntry, ConnectionAttemptStatus, GPIEvent, GPIPortCurrentState, LlrpParameterData, ReaderEventNotificationData};
+use crate::history::{ConnectionEvent, ConnectionEventKind, ConnectionHistory};
+use crate::liveness::{LivenessSettings, LivenessTracker, LIVENESS_PROBE_ID};
+use crate::stream::LlrpStream;
+use crate::llrp::{get_message_type_str, LlrpHeader, LlrpMessage, LlrpMessageType, LlrpResponse, LlrpResponseData, LlrpVersion, ReaderConfigRequest, ReceiveTimestamp, LLRP_HEADER_LENGTH};
+
+/// Whether the logger has been installed; it is installed by the first client.
+static LOGGER_CONFIGURED: std::sync::Mutex<bool> = std::sync::Mutex::new(false);
+
+/// The sessions clients of this process hold, by reader host.
+static READER_SESSIONS: std::sync::Mutex<BTreeMap<String, HostSessions>> = std::sync::Mutex::new(BTreeMap::new());
+
+/// Settings that are read once when the client is initialized or an inventory is
+/// started, so a changed value does not take effect on a connected client.
+const DEFERRED_SETTINGS: &[&str] = &[
+  "reader_id",
+  "allow_shared_connection",
+  "log_level",
+  "log_file",
+  "per_reader_log_files",
+  "connection_history_size",
+  "alerts",
+  "sinks",
+  "power_schedules",
+  "report_tuning",
+  "spec_chain",
+  "region_presets",
+  "clock_drift",
+  "enable_schedule",
+  "rospec"
+];
+
+/// Outcome of `LlrpClient::apply_config`.
+///
+/// Fields:
+/// - `changed`: Dotted paths of every setting that differs from the previous configuration.
+/// - `applied`: Actions taken against the reader, `"reconnected"` and/or `"reader_config"`.
+/// - `deferred`: Changed settings that take effect only after the client is
+///   re-initialized, or for `rospec`, when the inventory is next started.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChangeSummary {
+  pub changed  : Vec<String>,
+  pub applied  : Vec<String>,
+  pub deferred : Vec<String>
+}
+
+/// AccessSpec IDs used by `write_tag_memory`, `lock_tag` and `kill_tag`, chosen
+/// high to stay clear of AccessSpecs added from configuration.
+const WRITE_ACCESS_SPEC_ID : u32 = 0xFFFF_0001;
+const LOCK_ACCESS_SPEC_ID  : u32 = 0xFFFF_0002;
+const KILL_ACCESS_SPEC_ID  : u32 = 0xFFFF_0003;
+
+/// Upper bound of the backoff between connection attempts while the reader is
+/// held by another client.
+const MAX_DUPLICATE_CONNECTION_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delay between peeks while only part of the first message header has arrived.
+/// `peek` returns as soon as any byte is readable, so retrying without a pause
+/// would spin.
+const HEADER_PEEK_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Direction of a raw LLRP frame handed to a `FrameObserver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+  Sent,
+  Received
+}
+
+/// Receives every complete LLRP frame written to or read from the reader,
+/// including its header. Called on the sending task or the receive loop, so it
+/// should return quickly.
+pub type FrameObserver = Arc<dyn Fn(FrameDirection, &[u8]) + Send + Sync>;
+
+type SharedFrameObserver = Arc<RwLock<Option<FrameObserver>>>;
+
+/// A handshake run on every new connection before the LLRP session begins, for
+/// readers reached through a gateway that expects a preamble or token exchange
+/// first. The hook must consume the gateway's part of the exchange; whatever it
+/// leaves unread is taken as LLRP traffic. An error fails the connection attempt.
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use tokio::io::{AsyncReadExt, AsyncWriteExt};
+/// # use tokio::net::TcpStream;
+/// # use futures::FutureExt;
+/// # use llrp_lib::client::ConnectHook;
+/// let connect_hook: ConnectHook = Arc::new(|stream: &mut TcpStream| async move {
+///   stream.write_all(b"AUTH secret-token\n").await?;
+///   let mut reply = [0u8; 3];
+///   stream.read_exact(&mut reply).await?;
+///   if &reply != b"OK\n" {
+///     return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Gateway refused the token"));
+///   }
+///   Ok(())
+/// }.boxed());
+/// ```
+pub type ConnectHook = Arc<dyn for<'a> Fn(&'a mut TcpStream) -> BoxFuture<'a, io::Result<()>> + Send + Sync>;
+
+/// Produces the message ID of each request the client sends, in order.
+pub type MessageIdGenerator = Box<dyn FnMut() -> u32 + Send + Sync>;
+
+/// Message ID of the first request of a client using the default generator.
+const FIRST_MESSAGE_ID: u32 = 1001;
+
+/// Settings of a client that are not part of its configuration, for embedding it
+/// in a host, a test or a harness replaying recorded sessions. With a clock whose
+/// UTC time is fixed and a message ID generator, a client sends the same bytes
+/// every time it is run against the same reader traffic.
+///
+/// Fields:
+/// - `clock`: Time source of timeouts, intervals, alerts and the UTC time sent to
+///   and compared with the reader (default - `TokioClock`).
+/// - `connect_hook`: Handshake run before the LLRP session on every connection.
+/// - `message_ids`: Generator of request message IDs (default - Counting up from 1001).
+/// - `frame_observer`: Observer of every frame, installed before the first is sent.
+pub struct ClientOptions {
+  pub clock          : SharedClock,
+  pub connect_hook   : Option<ConnectHook>,
+  pub message_ids    : Option<MessageIdGenerator>,
+  pub frame_observer : Option<FrameObserver>
+}
+
+impl Default for ClientOptions {
+
+  fn default() -> Self {
+    ClientOptions {
+      clock          : Arc::new(TokioClock),
+      connect_hook   : None,
+      message_ids    : None,
+      frame_observer : None
+    }
+  }
+}
+
+/// Message IDs counting up from `first`.
+fn sequential_message_ids(
+  first: u32
+) -> MessageIdGenerator {
+  let mut next = first;
+  Box::new(move || {
+    let current = next;
+    next = next.wrapping_add(1);
+    current
+  })
+}
+
+fn observe_frame(
+  frame_observer : &SharedFrameObserver,
+  direction      : FrameDirection,
+  frame          : &[u8]
+) {
+  if let Some(observer) = frame_observer.read().unwrap().as_ref() {
+    observer(direction, frame);
+  }
+}
+
+/// Writes a frame to the reader, recording it in the journal if there is one.
+async fn write_frame(
+  writer  : &mut WriteHalf<LlrpStream>,
+  journal : &Option<Arc<MessageJournal>>,
+  frame   : &[u8]
+) -> io::Result<()> {
+  let result = writer.write_all(frame).await;
+  if let Some(journal) = journal {
+    journal.record_sent(frame, &result);
+  }
+  result
+}
+
+/// Rejects a frame longer than `max_message_length` before its body is read, so a
+/// corrupt length field cannot trigger a huge allocation.
+fn check_message_length(
+  header             : &LlrpHeader,
+  max_message_length : usize
+) -> io::Result<()> {
+
+  if header.message_length as usize > max_message_length {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!(
+        "{} message length {} exceeds max_message_length {}",
+        get_message_type_str(header.message_type_value), header.message_length, max_message_length
+      )
+    ));
+  }
+
+  Ok(())
+}
+
+/// The `clock_drift` settings handed to the receive loop. A client in monitor mode
+/// only samples drift and never sets the reader clock.
+fn receive_clock_drift_config(
+  config: &Config
+) -> Option<ClockDriftConfig> {
+
+  let mut drift_config = config.clock_drift.clone()?;
+  if config.monitor_mode {
+    drift_config.resync = None;
+  }
+
+  Some(drift_config)
+}
+
+/// The vendor Custom parameters added to the ROReportSpec of the configured ROSpec.
+#[cfg_attr(not(feature = "impinj"), allow(unused_variables))]
+pub fn rospec_report_extensions(
+  config: &Config
+) -> Vec<CustomParameter> {
+
+  #[cfg(feature = "impinj")]
+  if let Some(impinj_config) = &config.impinj {
+    return vec![impinj::tag_report_content_selector(impinj_config)];
+  }
+
+  Vec::new()
+}
+
+/// The vendor Custom parameters added to the GET_READER_CONFIG of
+/// `request_telemetry`, asking for telemetry the reader does not report unasked.
+#[cfg_attr(not(feature = "impinj"), allow(unused_variables))]
+fn telemetry_request_extensions(
+  config: &Config
+) -> Vec<CustomParameter> {
+
+  #[cfg(feature = "impinj")]
+  if config.impinj.is_some() {
+    return vec![impinj::reader_temperature_request()];
+  }
+
+  Vec::new()
+}
+
+/// Requests awaiting their response, by message ID. The receive loop hands each
+/// response to the request it answers, so responses cannot cross between requests
+/// and one arriving after its request gave up is dropped.
+#[derive(Clone, Default)]
+struct PendingRequests {
+  waiting: Arc<StdMutex<HashMap<u32, oneshot::Sender<LlrpResponse>>>>
+}
+
+impl PendingRequests {
+
+  fn register(
+    &self,
+    message_id: u32
+  ) -> oneshot::Receiver<LlrpResponse> {
+    let (response_tx, response_rx) = oneshot::channel();
+    self.waiting.lock().unwrap().insert(message_id, response_tx);
+    response_rx
+  }
+
+  fn cancel(
+    &self,
+    message_id: u32
+  ) {
+    self.waiting.lock().unwrap().remove(&message_id);
+  }
+
+  /// Delivers a response to its request, handing it back if none is waiting.
+  fn complete(
+    &self,
+    response: LlrpResponse
+  ) -> Result<(), LlrpResponse> {
+    match self.waiting.lock().unwrap().remove(&response.message_id) {
+      Some(response_tx) => response_tx.send(response),
+      None => Err(response)
+    }
+  }
 
-use crate::config::{ Config, load_config };
-use crate::llrp::{get_message_type_str, LlrpMessage, LlrpMessageType, LlrpResponse, LlrpResponseData};
+  /// Fails every waiting request, once the connection they were sent on is gone.
+  fn close(
+    &self
+  ) {
+    self.waiting.lock().unwrap().clear();
+  }
+}
 
-static INIT_LOGGER: Once = Once::new();
+/// Where the receive loop delivers what it reads. Shared with the client so
+/// subscribers and observers carry over to new sessions on reconnect.
+#[derive(Clone)]
+struct ReceiveTargets {
+  pending            : PendingRequests,
+  ro_report_tx       : SubscriberChannel<LlrpResponse>,
+  reader_event_tx    : SubscriberChannel<LlrpResponse>,
+  event_tx           : SubscriberChannel<ReaderEventNotificationData>,
+  gpi_event_tx       : SubscriberChannel<GPIEvent>,
+  keepalive_tx       : SubscriberChannel<LlrpResponse>,
+  alerts             : AlertMonitor,
+  frame_observer     : SharedFrameObserver,
+  journal            : Option<Arc<MessageJournal>>,
+  protocol_counters  : ProtocolCounters,
+  clock_drift        : ClockDriftTracker,
+  clock_drift_config : Option<ClockDriftConfig>,
+  telemetry          : TelemetryTracker,
+  liveness           : LivenessTracker,
+  liveness_settings  : Option<LivenessSettings>,
+  clock              : SharedClock,
+  max_message_length : usize
+}
 
 pub struct LlrpClient {
-  reader            : Arc<Mutex<ReadHalf<TcpStream>>>,
-  writer            : Arc<Mutex<WriteHalf<TcpStream>>>,
-  message_id        : u32,
+  reader            : Arc<Mutex<ReadHalf<LlrpStream>>>,
+  writer            : Arc<Mutex<WriteHalf<LlrpStream>>>,
+  message_ids       : MessageIdGenerator,
+  protocol_version  : LlrpVersion,
   config            : Config,
-  message_tx        : broadcast::Sender<LlrpResponse>,
-  ro_report_tx      : broadcast::Sender<LlrpResponse>
+  pending           : PendingRequests,
+  ro_report_tx      : SubscriberChannel<LlrpResponse>,
+  reader_event_tx   : SubscriberChannel<LlrpResponse>,
+  event_tx          : SubscriberChannel<ReaderEventNotificationData>,
+  gpi_event_tx      : SubscriberChannel<GPIEvent>,
+  channel_counters  : ChannelCounters,
+  keepalive_tx      : SubscriberChannel<LlrpResponse>,
+  receive_task      : Option<JoinHandle<()>>,
+  connected_at      : Instant,
+  history           : ConnectionHistory,
+  alerts            : AlertMonitor,
+  frame_observer    : SharedFrameObserver,
+  journal           : Option<Arc<MessageJournal>>,
+  protocol_counters : ProtocolCounters,
+  clock_drift       : ClockDriftTracker,
+  telemetry         : TelemetryTracker,
+  clock             : SharedClock,
+  parameters        : ParameterSnapshot,
+  config_state      : Option<u32>,
+  connect_hook      : Option<ConnectHook>,
+  session           : ReaderSession
 }
 
-fn configure_logger(log_level: &str) {
-  INIT_LOGGER.call_once(|| {
+#[derive(Default)]
+struct HostSessions {
+  count     : usize,
+  exclusive : usize
+}
 
-    let file = OpenOptions::new()
-      .create(true) // Create file if it does not exist
-      .append(true) // Append to file instead of truncating it
-      .open("system.log")
-      .expect("Failed to open system.log");
+/// A client's claim on its reader, released when the client is dropped. Readers
+/// answer a second session in confusing ways, from refusing it outright to
+/// dropping the first, so a process holds one per reader unless every client
+/// holding one sets `allow_shared_connection`.
+struct ReaderSession {
+  host      : String,
+  exclusive : bool
+}
 
-    let mut builder = Builder::from_default_env();
+impl ReaderSession {
 
-    builder.format(move |buf, record| {
-      let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
-      writeln!(buf, "[{}] {} - {}", timestamp, record.level(), record.args())
-    });
+  /// Claims `config.host`, failing if another client of this process holds a
+  /// session with it that is not shared. Hosts are compared as written, ignoring
+  /// case, so a reader reached by two different names is not detected.
+  fn claim(
+    config: &Config
+  ) -> Result<Self, LlrpError> {
 
-    if let Some(level) = parse_log_level(log_level) {
-      builder.filter(None, level);
-    } else {
-      eprintln!("Invalid log level: {}. Defaulting to Debug.", log_level);
-      builder.filter(None, LevelFilter::Debug);
+    let host = config.host.trim().to_ascii_lowercase();
+    let exclusive = !config.allow_shared_connection;
+
+    let mut sessions = READER_SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+    let sessions_for_host = sessions.entry(host.clone()).or_default();
+
+    if sessions_for_host.count > 0 && (exclusive || sessions_for_host.exclusive > 0) {
+      return Err(LlrpError::InvalidState(format!(
+        "Another client in this process is already connected to {}; set allow_shared_connection on both clients to open a second session",
+        config.host
+      )));
     }
 
-    builder.target(env_logger::Target::Pipe(Box::new(file)));
-    
-    builder.init();
+    sessions_for_host.count += 1;
+    sessions_for_host.exclusive += exclusive as usize;
+
+    Ok(ReaderSession { host, exclusive })
+  }
+
+  fn is_for(
+    &self,
+    config: &Config
+  ) -> bool {
+    self.host == config.host.trim().to_ascii_lowercase()
+  }
+}
+
+impl Drop for ReaderSession {
+
+  fn drop(
+    &mut self
+  ) {
+
+    let mut sessions = READER_SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(sessions_for_host) = sessions.get_mut(&self.host) {
+      sessions_for_host.count -= 1;
+      sessions_for_host.exclusive -= self.exclusive as usize;
+      if sessions_for_host.count == 0 {
+        sessions.remove(&self.host);
+      }
+    }
+  }
+}
+
+/// Treats the reader refusing the spec ID of a cleanup step, e.g. deleting an
+/// AccessSpec that was never added, as success.
+fn ignore_missing_spec(
+  result: Result<(), LlrpError>
+) -> Result<(), LlrpError> {
+  match result {
+    Err(LlrpError::ReaderStatus(error)) if error.rejects_spec_id() => {
+      debug!("Ignoring {}", error);
+      Ok(())
+    }
+    result => result
+  }
+}
+
+/// One single-word C1G2Write per word of `data`, numbered from op spec ID 1.
+/// Fails when the words run past the last word address of the memory bank.
+fn word_writes(
+  memory_bank     : u8,
+  word_pointer    : u16,
+  data            : &[u16],
+  access_password : u32
+) -> Result<Vec<C1G2WriteConfig>, LlrpError> {
+
+  if data.len() > u16::MAX as usize || word_pointer as usize + data.len() > u16::MAX as usize + 1 {
+    return Err(LlrpError::InvalidArgument(format!("{} words from word {} run past the end of the memory bank", data.len(), word_pointer)));
+  }
+
+  Ok(data.iter().enumerate().map(|(index, word)| C1G2WriteConfig {
+    op_spec_id   : index as u16 + 1,
+    access_password,
+    memory_bank,
+    word_pointer : word_pointer + index as u16,
+    data         : vec![*word]
+  }).collect())
+}
+
+/// Installs the logger writing to `log_file`, once per process. An unwritable log
+/// file fails the call, leaving it to the next client to try again; when the host
+/// already installed a logger, the client logs to that one instead.
+fn configure_logger(log_level: &str, log_file: &Path, per_reader_log_files: bool) -> io::Result<()> {
+
+  let mut configured = LOGGER_CONFIGURED.lock().unwrap_or_else(PoisonError::into_inner);
+  if *configured {
+    return Ok(());
+  }
+
+  let file = OpenOptions::new()
+    .create(true) // Create file if it does not exist
+    .append(true) // Append to file instead of truncating it
+    .open(log_file)
+    .map_err(|e| io::Error::new(e.kind(), format!("Failed to open log file {}: {}", log_file.display(), e)))?;
+
+  // Per-reader log files are written next to the system log.
+  let reader_log_dir = per_reader_log_files.then(|| {
+    log_file.parent().map(Path::to_path_buf).unwrap_or_default()
   });
+
+  let mut builder = Builder::from_default_env();
+
+  builder.format(move |buf, record| {
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+    match log_context::current_reader_id() {
+      Some(reader_id) => writeln!(buf, "[{}] {} [{}] - {}", timestamp, record.level(), reader_id, record.args()),
+      None => writeln!(buf, "[{}] {} - {}", timestamp, record.level(), record.args())
+    }
+  });
+
+  if let Some(level) = parse_log_level(log_level) {
+    builder.filter(None, level);
+  } else {
+    eprintln!("Invalid log level: {}. Defaulting to Debug.", log_level);
+    builder.filter(None, LevelFilter::Debug);
+  }
+
+  builder.target(env_logger::Target::Pipe(Box::new(ReaderLogWriter::new(file, reader_log_dir))));
+
+  if builder.try_init().is_err() {
+    eprintln!("A logger is already installed, client logs go to it instead of {}", log_file.display());
+  }
+
+  *configured = true;
+
+  Ok(())
 }
 
 fn parse_log_level(level: &str) -> Option<LevelFilter> {
@@ -77,321 +538,2281 @@ impl LlrpClient {
   fn next_message_id(
     &mut self
   ) -> u32 {
-
-    let current_id = self.message_id;
-    self.message_id += 1;
-    
-    current_id
+    (self.message_ids)()
   }
 
-  pub async fn initialize(
-    configuration_path: &str
-  ) -> io::Result<Self> {
+  pub async fn initialize<P: AsRef<Path>>(
+    configuration_path: P
+  ) -> Result<Self, LlrpError> {
 
     let config = load_config(configuration_path).map_err(|e| {
-      io::Error::new(
-        io::ErrorKind::InvalidInput,
-        "Failed to load LLRP configuration. Please verify the configuration file path and content."
-      )
+      LlrpError::Config(format!(
+        "Failed to load LLRP configuration. Please verify the configuration file path and content: {}",
+        e
+      ))
     })?;
 
-    configure_logger(config.log_level.as_str());
+    LlrpClient::initialize_with_config(config).await
+  }
 
-    let connect_timeout = Duration::from_secs(5);
-    let stream = timeout(connect_timeout, TcpStream::connect(&config.host))
-      .await
-      .map_err(|_| {
-        error!("Connection attempt timed out after {} seconds", connect_timeout.as_secs());
-        io::Error::new(
-          io::ErrorKind::TimedOut,
-          "Timeout while connecting to LLRP server"
-        )
+  /// Connects to the reader described by an already loaded configuration.
+  pub async fn initialize_with_config(
+    config: Config
+  ) -> Result<Self, LlrpError> {
+    LlrpClient::initialize_with_clock(config, Arc::new(TokioClock)).await
+  }
+
+  /// Like `initialize_with_config`, running `connect_hook` on the socket before the
+  /// LLRP session begins, on the first connection and every reconnect.
+  pub async fn initialize_with_connect_hook(
+    config       : Config,
+    connect_hook : ConnectHook
+  ) -> Result<Self, LlrpError> {
+    LlrpClient::initialize_with_options(config, ClientOptions { connect_hook: Some(connect_hook), ..ClientOptions::default() }).await
+  }
+
+  /// Like `initialize_with_config`, with the clock used for response timeouts,
+  /// reconnect intervals, keepalive round trips and health alerts.
+  pub async fn initialize_with_clock(
+    config : Config,
+    clock  : SharedClock
+  ) -> Result<Self, LlrpError> {
+    LlrpClient::initialize_with_options(config, ClientOptions { clock, ..ClientOptions::default() }).await
+  }
+
+  /// Like `initialize_with_config`, with any of the `options`.
+  pub async fn initialize_with_options(
+    config  : Config,
+    options : ClientOptions
+  ) -> Result<Self, LlrpError> {
+
+    let ClientOptions { clock, connect_hook, message_ids, frame_observer } = options;
+
+    configure_logger(config.log_level.as_str(), &config.log_file, config.per_reader_log_files)?;
+
+    let session = ReaderSession::claim(&config)?;
+    let history = ConnectionHistory::new(config.connection_history_size);
+    let alerts = AlertMonitor::new(config.reader_id(), config.alerts.clone(), clock.clone());
+    let frame_observer: SharedFrameObserver = Arc::new(RwLock::new(frame_observer));
+    let journal = config.journal.as_ref()
+      .map(|journal| MessageJournal::open(journal, &config.reader_id(), clock.clone()).map(Arc::new))
+      .transpose()?;
+    let protocol_counters = ProtocolCounters::default();
+    let clock_drift = ClockDriftTracker::default();
+    let telemetry = TelemetryTracker::default();
+    let stream = LlrpClient::connect(&config, &history, clock.as_ref(), connect_hook.as_ref()).await?;
+
+    let (reader, writer) = split(stream);
+    let pending = PendingRequests::default();
+    let channel_counters = ChannelCounters::default();
+    let ro_report_tx = SubscriberChannel::new("ro_reports", &config.channels, channel_counters.clone());
+    let reader_event_tx = SubscriberChannel::new("reader_events", &config.channels, channel_counters.clone());
+    let event_tx = SubscriberChannel::new("events", &config.channels, channel_counters.clone());
+    let gpi_event_tx = SubscriberChannel::new("gpi_events", &config.channels, channel_counters.clone());
+    let keepalive_tx = SubscriberChannel::new("reader_keepalives", &config.channels, channel_counters.clone());
+
+    let reader = Arc::new(Mutex::new(reader));
+    let writer = Arc::new(Mutex::new(writer));
+    let receive_task = LlrpClient::spawn_receive_loop(
+      config.reader_id(),
+      reader.clone(),
+      writer.clone(),
+      history.clone(),
+      ReceiveTargets {
+        pending            : pending.clone(),
+        ro_report_tx       : ro_report_tx.clone(),
+        reader_event_tx    : reader_event_tx.clone(),
+        event_tx           : event_tx.clone(),
+        gpi_event_tx       : gpi_event_tx.clone(),
+        keepalive_tx       : keepalive_tx.clone(),
+        alerts             : alerts.clone(),
+        frame_observer     : frame_observer.clone(),
+        journal            : journal.clone(),
+        protocol_counters  : protocol_counters.clone(),
+        clock_drift        : clock_drift.clone(),
+        clock_drift_config : receive_clock_drift_config(&config),
+        telemetry          : telemetry.clone(),
+        liveness           : LivenessTracker::new(clock.now()),
+        liveness_settings  : LivenessSettings::from_config(&config),
+        clock              : clock.clone(),
+        max_message_length : config.max_message_length as usize
+      }
+    );
+
+    alerts.spawn_watchdog();
+
+    if config.monitor_mode {
+      log_context::sync_scope(config.reader_id(), || {
+        info!("Client attached in monitor mode, reader configuration and ROSpecs will not be modified");
+      });
+    }
+
+    let mut client = LlrpClient {
+      reader,
+      writer,
+      message_ids: message_ids.unwrap_or_else(|| sequential_message_ids(FIRST_MESSAGE_ID)),
+      protocol_version: LlrpVersion::V1_0_1,
+      config,
+      pending,
+      ro_report_tx,
+      reader_event_tx,
+      event_tx,
+      gpi_event_tx,
+      channel_counters,
+      keepalive_tx,
+      receive_task: Some(receive_task),
+      connected_at: clock.now(),
+      history,
+      alerts,
+      frame_observer,
+      journal,
+      protocol_counters,
+      clock_drift,
+      telemetry,
+      clock,
+      parameters: ParameterSnapshot::default(),
+      config_state: None,
+      connect_hook,
+      session
+    };
+
+    if client.config.negotiate_protocol_version {
+      let reader_id = client.config.reader_id();
+      log_context::scope(reader_id, client.negotiate_protocol_version()).await
+        .map_err(|e| e.context("Protocol version negotiation failed"))?;
+    }
+
+    #[cfg(feature = "impinj")]
+    if client.config.impinj.is_some() {
+      let reader_id = client.config.reader_id();
+      log_context::scope(reader_id, client.enable_impinj_extensions()).await?;
+    }
+
+    if !client.config.region_presets.is_empty() {
+      let reader_id = client.config.reader_id();
+      log_context::scope(reader_id, client.apply_region_preset()).await?;
+    }
+
+    if !client.config.startup_actions.is_empty() {
+      let reader_id = client.config.reader_id();
+      log_context::scope(reader_id, client.run_startup_actions()).await?;
+    }
+
+    Ok(client)
+  }
+
+  /// Runs the configured `startup_actions` in order, stopping at the first that fails.
+  async fn run_startup_actions(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    for action in self.config.startup_actions.clone() {
+
+      info!("Running startup action {:?}", action);
+
+      let result = match action {
+        StartupAction::FactoryReset => self.send_factory_reset().await,
+        StartupAction::SetConfig    => self.send_set_reader_config().await,
+        StartupAction::AddRospec    => self.send_add_rospec().await,
+        StartupAction::Enable       => self.send_enable_rospec().await,
+        StartupAction::Start        => self.send_start_rospec().await
+      };
+
+      result.map_err(|e| e.context(&format!("Startup action {:?} failed", action)))?;
+    }
+
+    Ok(())
+  }
+
+  /// Registers the Impinj tag report decoders and, unless in monitor mode, enables
+  /// the Impinj extensions for this connection.
+  #[cfg(feature = "impinj")]
+  async fn enable_impinj_extensions(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    impinj::register_decoders();
+
+    if self.config.monitor_mode {
+      return Ok(());
+    }
+
+    let reply = self.send_custom_message(&impinj::enable_extensions()).await
+      .map_err(|e| e.context("Failed to enable Impinj extensions"))?;
+    impinj::check_enable_extensions_response(&reply)?;
+
+    info!("Impinj extensions enabled");
+
+    Ok(())
+  }
+
+  /// Overrides the RF channel and transmit power of `reader_config` with the
+  /// region preset matching the reader's RegulatoryCapabilities.
+  async fn apply_region_preset(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    let mut regulatory_capabilities = None;
+
+    self.send_get_reader_capabilities(|response_data| {
+      if let LlrpResponseData::ReaderCapabilities(parameters) = response_data {
+        regulatory_capabilities = parameters.into_iter().find_map(|parameter| match parameter {
+          LlrpParameterData::RegulatoryCapabilities(capabilities) => Some(capabilities),
+          _ => None
+        });
+      }
+      async {}
+    }).await.map_err(|e| e.context("Failed to query regulatory capabilities"))?;
+
+    let Some(regulatory_capabilities) = regulatory_capabilities else {
+      warn!("Reader reports no RegulatoryCapabilities, keeping the configured RF settings");
+      return Ok(());
+    };
+
+    let settings = resolve_region_preset(&self.config.region_presets, &regulatory_capabilities)
+      .map_err(|e| LlrpError::Config(format!("Cannot apply region preset: {}", e)))?;
+
+    let Some(settings) = settings else {
+      warn!(
+        "No region preset matches communications standard {}, keeping the configured RF settings",
+        regulatory_capabilities.communications_standard
+      );
+      return Ok(());
+    };
+
+    let reader_config = &mut self.config.reader_config;
+    reader_config.hop_table_id = settings.hop_table_id;
+    reader_config.channel_index = settings.channel_index;
+    if let Some(tx_power_table_index) = settings.tx_power_table_index {
+      reader_config.tx_power_table_index = tx_power_table_index;
+    }
+
+    info!(
+      "Applied {} region preset: hop table {}, channel index {}, transmit power index {}",
+      settings.region, reader_config.hop_table_id, reader_config.channel_index, reader_config.tx_power_table_index
+    );
+
+    Ok(())
+  }
+
+  /// Re-establishes the TCP connection to the reader using the loaded configuration.
+  ///
+  /// The current receive loop is stopped, and up to `reconnect_attempts` connection
+  /// attempts are made, `reconnect_interval` milliseconds apart. Existing subscribers
+  /// keep receiving messages from the new session.
+  pub async fn reconnect(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    let reader_id = self.config.reader_id();
+    log_context::scope(reader_id, self.reconnect_session()).await
+  }
+
+  async fn reconnect_session(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    self.stop_receive_loop("Reconnecting").await;
+
+    let max_attempts = self.config.reconnect_attempts.max(1);
+    let mut attempt = 0;
+
+    let stream = loop {
+
+      attempt += 1;
+      self.history.record(ConnectionEventKind::ReconnectAttempt { attempt });
+      info!("Reconnecting to LLRP server: {} (attempt {}/{})", self.config.host, attempt, max_attempts);
+
+      match LlrpClient::connect(&self.config, &self.history, self.clock.as_ref(), self.connect_hook.as_ref()).await {
+        Ok(stream) => break stream,
+        Err(e) if attempt < max_attempts => {
+          warn!("Reconnect attempt {} failed: {}", attempt, e);
+          self.clock.sleep(Duration::from_millis(self.config.reconnect_interval)).await;
+        }
+        Err(e) => return Err(e.into())
+      }
+    };
+
+    let (reader, writer) = split(stream);
+
+    self.reader = Arc::new(Mutex::new(reader));
+    self.writer = Arc::new(Mutex::new(writer));
+    self.connected_at = self.clock.now();
+    self.receive_task = Some(LlrpClient::spawn_receive_loop(
+      self.config.reader_id(),
+      self.reader.clone(),
+      self.writer.clone(),
+      self.history.clone(),
+      self.receive_targets()
+    ));
+
+    self.alerts.record_reconnect();
+
+    // A new connection starts out at LLRP 1.0.1.
+    if self.config.negotiate_protocol_version {
+      self.protocol_version = LlrpVersion::V1_0_1;
+      self.negotiate_protocol_version().await
+        .map_err(|e| e.context("Protocol version negotiation failed"))?;
+    }
+
+    #[cfg(feature = "impinj")]
+    if self.config.impinj.is_some() {
+      self.enable_impinj_extensions().await?;
+    }
+
+    if !self.config.startup_actions.is_empty() {
+      self.run_startup_actions().await?;
+    }
+
+    Ok(())
+  }
+
+  pub fn config(
+    &self
+  ) -> &Config {
+    &self.config
+  }
+
+  /// Returns the LLRP protocol version used to encode outgoing messages.
+  pub fn protocol_version(
+    &self
+  ) -> LlrpVersion {
+    self.protocol_version
+  }
+
+  /// Sets the LLRP protocol version used to encode outgoing messages,
+  /// typically after negotiating it with the reader.
+  pub fn set_protocol_version(
+    &mut self,
+    version: LlrpVersion
+  ) {
+    self.protocol_version = version;
+  }
+
+  /// Asks the reader which LLRP versions it supports and switches the connection to
+  /// the highest one both support, LLRP 1.1 at most. A reader answering the LLRP 1.1
+  /// GET_SUPPORTED_VERSION with an error, or not at all, stays at the current version.
+  pub async fn negotiate_protocol_version(
+    &mut self
+  ) -> Result<LlrpVersion, LlrpError> {
+
+    let fallback = self.protocol_version;
+
+    let supported_version = match self.send_versioned(LlrpVersion::V1_1, LlrpMessage::new_get_supported_version(0), LlrpMessageType::GetSupportedVersionResponse).await {
+      Ok(response) => match self.decode_response(&response)? {
+        LlrpResponseData::SupportedVersion(supported_version) => supported_version,
+        _ => return Err(LlrpError::ProtocolDecode("Unexpected GetSupportedVersion response".to_string()))
+      },
+      Err(e) => {
+        info!("Reader does not report its supported versions, keeping LLRP {:?}: {}", fallback, e);
+        return Ok(fallback);
+      }
+    };
+
+    let version = LlrpVersion::from_value(supported_version.supported_version.min(LlrpVersion::V1_1.value()))
+      .unwrap_or(LlrpVersion::V1_0_1);
+
+    if supported_version.current_version != version.value() {
+      if let Err(e) = self.send_versioned(version, LlrpMessage::new_set_protocol_version(0, version), LlrpMessageType::SetProtocolVersionResponse).await {
+        warn!("Reader refused LLRP {:?}, keeping LLRP {:?}: {}", version, fallback, e);
+        return Ok(fallback);
+      }
+    }
+
+    info!("Negotiated LLRP {:?}", version);
+    self.protocol_version = version;
+
+    Ok(version)
+  }
+
+  /// Sends a message with the given header version rather than the connection's.
+  async fn send_versioned(
+    &mut self,
+    version                : LlrpVersion,
+    mut message            : LlrpMessage,
+    expected_response_type : LlrpMessageType
+  ) -> Result<LlrpResponse, LlrpError> {
+
+    message.message_id = self.next_message_id();
+
+    let connection_version = self.protocol_version;
+    self.protocol_version = version;
+    let response = self.send_message_ack(message, expected_response_type).await;
+    self.protocol_version = connection_version;
+
+    response
+  }
+
+  /// Returns the recent connection events (connects, failures, disconnects and
+  /// reconnect attempts), oldest first.
+  pub fn connection_history(
+    &self
+  ) -> Vec<ConnectionEvent> {
+    self.history.snapshot()
+  }
+
+  /// Subscribes to connection events as they are recorded, e.g. to learn of a
+  /// lost or dead connection and reconnect.
+  pub fn subscribe_connection_events(
+    &self
+  ) -> broadcast::Receiver<ConnectionEvent> {
+    self.history.subscribe()
+  }
+
+  /// Subscribes to health alerts raised for this reader.
+  pub fn subscribe_alerts(
+    &self
+  ) -> broadcast::Receiver<Alert> {
+    self.alerts.subscribe()
+  }
+
+  /// Installs or removes the observer that receives every raw frame exchanged
+  /// with the reader. The observer is kept across reconnects.
+  pub fn set_frame_observer(
+    &self,
+    observer: Option<FrameObserver>
+  ) {
+    *self.frame_observer.write().unwrap() = observer;
+  }
+
+  /// Replaces the configuration of the connected client.
+  ///
+  /// The new configuration is validated and compared with the current one. A
+  /// changed `host` reconnects the client, unless another client of this process
+  /// holds a session with the new host, and changed `reader_config` settings
+  /// are sent to the reader with SET_READER_CONFIG. If either step fails the
+  /// previous configuration is restored, reconnecting to its host if that had
+  /// changed, and the error returned. Settings read per
+  /// request, such as timeouts, apply from the next request; the remaining changes
+  /// are listed as deferred in the returned summary.
+  pub async fn apply_config(
+    &mut self,
+    config: Config
+  ) -> Result<ConfigChangeSummary, LlrpError> {
+
+    config.validate()?;
+
+    let changed = diff_configs(&self.config, &config);
+    let mut summary = ConfigChangeSummary {
+      changed  : changed.clone(),
+      applied  : vec![],
+      deferred : changed.iter()
+        .filter(|path| DEFERRED_SETTINGS.iter().any(|setting| *path == setting || path.starts_with(&format!("{}.", setting))))
+        .cloned()
+        .collect()
+    };
+
+    if changed.is_empty() {
+      return Ok(summary);
+    }
+
+    let host_changed = changed.iter().any(|path| path == "host");
+    let reader_config_changed = changed.iter().any(|path| path.starts_with("reader_config."));
+
+    let session = match self.session.is_for(&config) {
+      true => None,
+      false => Some(ReaderSession::claim(&config)?)
+    };
+
+    let previous = std::mem::replace(&mut self.config, config);
+
+    if host_changed {
+      if let Err(e) = self.reconnect().await {
+        self.restore_config(previous).await;
+        return Err(e);
+      }
+      summary.applied.push("reconnected".to_string());
+    }
+
+    if reader_config_changed && !self.config.monitor_mode {
+      let reader_config = self.config.reader_config.clone();
+      if let Err(e) = self.send_reader_config(&reader_config).await {
+        self.restore_config(previous).await;
+        return Err(e);
       }
-    )??;
+      summary.applied.push("reader_config".to_string());
+    }
+
+    if let Some(session) = session {
+      self.session = session;
+    }
+
+    log_context::sync_scope(self.config.reader_id(), || {
+      info!("Applied configuration changes: {}", summary.changed.join(", "));
+    });
+
+    Ok(summary)
+  }
+
+  /// Puts back the configuration `apply_config` replaced, reconnecting to its host
+  /// if the new configuration had moved the client to another reader.
+  async fn restore_config(
+    &mut self,
+    previous: Config
+  ) {
+
+    let host_changed = self.config.host != previous.host;
+    self.config = previous;
+
+    if host_changed {
+      if let Err(e) = self.reconnect().await {
+        warn!("Failed to reconnect to {} after a failed configuration change: {}", self.config.host, e);
+      }
+    }
+  }
+
+  /// Lists the fields of the last GET_READER_CAPABILITIES response as name, path
+  /// and value rows, for tools that render parameters generically. Empty until the
+  /// capabilities are first queried.
+  pub fn capability_fields(
+    &self
+  ) -> Vec<ParameterField> {
+    self.parameters.capabilities()
+  }
+
+  /// Lists the fields of the last GET_READER_CONFIG response, as `capability_fields`
+  /// does for the capabilities.
+  pub fn reader_config_fields(
+    &self
+  ) -> Vec<ParameterField> {
+    self.parameters.reader_config()
+  }
+
+  /// Returns how many messages of each type were sent to and received from the
+  /// reader, e.g. to spot a flood of ReaderEventNotifications.
+  pub fn protocol_stats(
+    &self
+  ) -> ProtocolStats {
+    self.protocol_counters.snapshot()
+  }
+
+  /// Returns how many reports and events subscribers lost, or the receive loop
+  /// waited for them, because they fell `channels.capacity` messages behind.
+  pub fn channel_stats(
+    &self
+  ) -> ChannelStats {
+    self.channel_counters.snapshot()
+  }
+
+  /// Returns how far the reader clock was from the host clock when last sampled.
+  pub fn clock_drift_stats(
+    &self
+  ) -> ClockDriftStats {
+    self.clock_drift.snapshot()
+  }
+
+  /// Returns the latest temperature and utilization the reader reported, for
+  /// readers whose vendor extensions report them.
+  pub fn telemetry_stats(
+    &self
+  ) -> TelemetryStats {
+    self.telemetry.snapshot()
+  }
+
+  /// Asks the reader for its configuration, including the vendor parameters
+  /// carrying its temperature and utilization, and returns the telemetry updated
+  /// from the response. Readers that only report telemetry when asked, like Impinj
+  /// readers, need this polled for `telemetry_stats` and the temperature alert to
+  /// stay current. Allowed in monitor mode, as it does not modify the reader.
+  pub async fn request_telemetry(
+    &mut self
+  ) -> Result<TelemetryStats, LlrpError> {
+
+    let message_id = self.next_message_id();
+    let extensions = telemetry_request_extensions(&self.config);
+
+    let message = LlrpMessage::new_get_reader_config_with_extensions(message_id, &ReaderConfigRequest::default(), &extensions);
+    self.send_message_ack(message, LlrpMessageType::GetReaderConfigResponse).await?;
+
+    Ok(self.telemetry.snapshot())
+  }
+
+  /// Subscribes to the temperature and utilization samples the reader reports.
+  pub fn subscribe_telemetry(
+    &self
+  ) -> broadcast::Receiver<TelemetrySample> {
+    self.telemetry.subscribe()
+  }
+
+  /// A handle to the telemetry of this client, which stays readable while the
+  /// client is locked by a long request, e.g. behind the manager's mutex.
+  pub fn telemetry_tracker(
+    &self
+  ) -> TelemetryTracker {
+    self.telemetry.clone()
+  }
+
+  /// Returns the most recently raised health alerts, oldest first.
+  pub fn recent_alerts(
+    &self
+  ) -> Vec<Alert> {
+    self.alerts.recent_alerts()
+  }
+
+  /// Connects to the reader, or with `listen` waits for it to connect, and checks
+  /// the ConnectionAttemptEvent it answers with. A reader that refuses the
+  /// connection because another client holds it is retried with backoff when
+  /// `duplicate_connection` is `retry`.
+  async fn connect(
+    config       : &Config,
+    history      : &ConnectionHistory,
+    clock        : &dyn Clock,
+    connect_hook : Option<&ConnectHook>
+  ) -> io::Result<LlrpStream> {
+
+    log_context::scope(config.reader_id(), async {
+
+      let first_attempt_time = clock.now();
+      let retry_timeout = Duration::from_millis(config.duplicate_connection_timeout);
+      let mut backoff = Duration::from_millis(config.reconnect_interval.max(1));
+
+      loop {
+
+        let start_time = clock.now();
+        let mut stream = LlrpClient::open_stream(config, history, clock, connect_hook).await?;
+
+        let status = LlrpClient::read_connection_attempt_status(
+          &mut stream,
+          Duration::from_millis(config.response_timeout),
+          config.max_message_length as usize,
+          clock
+        ).await?;
+
+        if status.is_none() && config.listen.is_some() {
+          let reason = "Reader connected without a ConnectionAttemptEvent".to_string();
+          error!("{}", reason);
+          history.record(ConnectionEventKind::ConnectFailed {
+            host   : config.host.clone(),
+            reason : reason.clone()
+          });
+          return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+        }
+
+        let status = match status {
+          None | Some(ConnectionAttemptStatus::Success) => {
+            info!("Client Successfully Connected to LLRP server: {}", config.host);
+            history.record(ConnectionEventKind::Connected {
+              host                : config.host.clone(),
+              connect_duration_ms : clock.elapsed(start_time).as_millis() as u64
+            });
+            return Ok(stream);
+          }
+          Some(status) => status
+        };
+
+        let reason = format!("Reader refused the connection: {:?}", status);
+        history.record(ConnectionEventKind::ConnectFailed {
+          host   : config.host.clone(),
+          reason : reason.clone()
+        });
+
+        let retry = status.is_duplicate_connection()
+          && config.duplicate_connection == DuplicateConnectionAction::Retry
+          && clock.elapsed(first_attempt_time) + backoff <= retry_timeout;
+
+        if !retry {
+          error!("{}", reason);
+          return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason));
+        }
+
+        warn!("{}, retrying in {} ms", reason, backoff.as_millis());
+
+        drop(stream);
+        clock.sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_DUPLICATE_CONNECTION_BACKOFF);
+      }
+    }).await
+  }
+
+  async fn open_stream(
+    config       : &Config,
+    history      : &ConnectionHistory,
+    clock        : &dyn Clock,
+    connect_hook : Option<&ConnectHook>
+  ) -> io::Result<LlrpStream> {
+
+    let connect_timeout = Duration::from_secs(5);
+
+    let mut result = match &config.listen {
+      Some(listen) => LlrpClient::accept_reader(&config.host, listen, clock).await,
+      None => match clock.timeout(connect_timeout, LlrpClient::connect_tcp(config)).await {
+        Ok(result) => result,
+        Err(_) => {
+          error!("Connection attempt timed out after {} seconds", connect_timeout.as_secs());
+          Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Timeout while connecting to LLRP server"
+          ))
+        }
+      }
+    };
+
+    if let (Ok(stream), Some(connect_hook)) = (&mut result, connect_hook) {
+      let handshake = match clock.timeout(connect_timeout, connect_hook(stream)).await {
+        Ok(handshake) => handshake,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Timeout in connect hook"))
+      };
+      if let Err(e) = handshake {
+        error!("Connect hook failed: {}", e);
+        result = Err(io::Error::new(e.kind(), format!("Connect hook failed: {}", e)));
+      }
+    }
+
+    let result = match result {
+      Ok(stream) => LlrpClient::start_tls(config, clock, connect_timeout, stream).await,
+      Err(e) => Err(e)
+    };
+
+    if let Err(e) = &result {
+      history.record(ConnectionEventKind::ConnectFailed {
+        host   : config.host.clone(),
+        reason : e.to_string()
+      });
+    }
+
+    result
+  }
+
+  /// Sets up the TLS session when `tls.enabled`, else keeps the plain connection.
+  #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+  async fn start_tls(
+    config          : &Config,
+    clock           : &dyn Clock,
+    connect_timeout : Duration,
+    stream          : TcpStream
+  ) -> io::Result<LlrpStream> {
+
+    #[cfg(feature = "tls")]
+    if let Some(tls_config) = config.tls.as_ref().filter(|tls| tls.enabled) {
+      let handshake = match clock.timeout(connect_timeout, crate::tls::connect(tls_config, &config.host, stream)).await {
+        Ok(handshake) => handshake,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Timeout in TLS handshake"))
+      };
+      return handshake
+        .map(|stream| LlrpStream::Tls { stream: Box::new(stream), peeked: BytesMut::new() })
+        .inspect_err(|e| error!("{}", e));
+    }
+
+    Ok(LlrpStream::Tcp(stream))
+  }
+
+  /// Waits for the reader at `host` to open the connection to `listen.address`.
+  /// Connections from addresses `host` does not resolve to are closed.
+  async fn accept_reader(
+    host   : &str,
+    listen : &ListenConfig,
+    clock  : &dyn Clock
+  ) -> io::Result<TcpStream> {
+
+    let reader_ips: HashSet<IpAddr> = lookup_host(host).await
+      .map_err(|e| io::Error::new(e.kind(), format!("Failed to resolve reader host {}: {}", host, e)))?
+      .map(|address| address.ip().to_canonical())
+      .collect();
+
+    let listener = TcpListener::bind(&listen.address).await
+      .map_err(|e| io::Error::new(e.kind(), format!("Failed to listen on {}: {}", listen.address, e)))?;
+
+    info!("Waiting for the reader to connect to {}", listen.address);
+
+    let accept = async {
+      loop {
+        let (stream, peer) = listener.accept().await?;
+        if reader_ips.contains(&peer.ip().to_canonical()) {
+          info!("Reader connected from {}", peer);
+          return Ok(stream);
+        }
+        warn!("Closed connection from {}, which is not reader {}", peer, host);
+      }
+    };
+
+    match clock.timeout(Duration::from_millis(listen.accept_timeout), accept).await {
+      Ok(result) => result,
+      Err(_) => {
+        error!("No reader connected to {} within {} ms", listen.address, listen.accept_timeout);
+        Err(io::Error::new(
+          io::ErrorKind::TimedOut,
+          format!("Timeout while waiting for the reader to connect to {}", listen.address)
+        ))
+      }
+    }
+  }
+
+  /// Opens the TCP connection to the reader, through the SOCKS5 proxy if one is
+  /// configured.
+  async fn connect_tcp(
+    config: &Config
+  ) -> io::Result<TcpStream> {
+
+    let Some(proxy) = &config.proxy else {
+      return TcpStream::connect(&config.host).await;
+    };
+
+    let proxy_addr = format!("{}:{}", proxy.host, proxy.port);
+
+    let stream = match &proxy.credentials {
+      Some(credentials) => {
+        let password = credentials.password.resolve()?;
+        Socks5Stream::connect_with_password(proxy_addr.as_str(), config.host.as_str(), &credentials.username, &password).await
+      }
+      None => Socks5Stream::connect(proxy_addr.as_str(), config.host.as_str()).await
+    };
+
+    stream.map(Socks5Stream::into_inner).map_err(|e| match e {
+      tokio_socks::Error::Io(e) => e,
+      e => io::Error::other(format!("SOCKS5 proxy {} could not connect to {}: {}", proxy_addr, config.host, e))
+    })
+  }
+
+  /// Reads the ConnectionAttemptEvent a reader sends right after accepting a
+  /// connection. Returns `None`, leaving the stream untouched, if the first message
+  /// is not a ReaderEventNotification or none arrives within `timeout`.
+  async fn read_connection_attempt_status(
+    stream             : &mut LlrpStream,
+    timeout            : Duration,
+    max_message_length : usize,
+    clock              : &dyn Clock
+  ) -> io::Result<Option<ConnectionAttemptStatus>> {
+
+    let mut header = [0u8; LLRP_HEADER_LENGTH];
+
+    let peeked = clock.timeout(timeout, async {
+      loop {
+        let n = stream.peek(&mut header).await?;
+        if n == 0 {
+          return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed"
+          ));
+        }
+        if n == LLRP_HEADER_LENGTH {
+          return Ok(());
+        }
+        clock.sleep(HEADER_PEEK_INTERVAL).await;
+      }
+    }).await;
+
+    match peeked {
+      Ok(result) => result?,
+      Err(_) => {
+        warn!("No ConnectionAttemptEvent received within {} ms", timeout.as_millis());
+        return Ok(None);
+      }
+    }
+
+    let header = LlrpHeader::decode(&header)?;
+    if header.message_type_value != LlrpMessageType::ReaderEventNotification.value() {
+      return Ok(None);
+    }
+
+    check_message_length(&header, max_message_length)?;
+
+    let mut buf = BytesMut::zeroed(header.message_length as usize);
+    stream.read_exact(&mut buf).await?;
+
+    match LlrpResponse::from_message(LlrpMessage::decode(&mut buf)?).decode()? {
+      LlrpResponseData::ReaderEvent(event_data) => Ok(event_data.connection_attempt),
+      _ => Ok(None)
+    }
+  }
+
+  /// The state shared with the receive loop, handed to each new session.
+  fn receive_targets(
+    &self
+  ) -> ReceiveTargets {
+    ReceiveTargets {
+      pending            : self.pending.clone(),
+      ro_report_tx       : self.ro_report_tx.clone(),
+      reader_event_tx    : self.reader_event_tx.clone(),
+      event_tx           : self.event_tx.clone(),
+      gpi_event_tx       : self.gpi_event_tx.clone(),
+      keepalive_tx       : self.keepalive_tx.clone(),
+      alerts             : self.alerts.clone(),
+      frame_observer     : self.frame_observer.clone(),
+      journal            : self.journal.clone(),
+      protocol_counters  : self.protocol_counters.clone(),
+      clock_drift        : self.clock_drift.clone(),
+      clock_drift_config : receive_clock_drift_config(&self.config),
+      telemetry          : self.telemetry.clone(),
+      liveness           : LivenessTracker::new(self.clock.now()),
+      liveness_settings  : LivenessSettings::from_config(&self.config),
+      clock              : self.clock.clone(),
+      max_message_length : self.config.max_message_length as usize
+    }
+  }
+
+  fn spawn_receive_loop(
+    reader_id : String,
+    reader    : Arc<Mutex<ReadHalf<LlrpStream>>>,
+    writer    : Arc<Mutex<WriteHalf<LlrpStream>>>,
+    history   : ConnectionHistory,
+    targets   : ReceiveTargets
+  ) -> JoinHandle<()> {
+
+    let clock = targets.clock.clone();
+    let connected_at = clock.now();
+    let pending = targets.pending.clone();
+
+    tokio::spawn(log_context::scope(reader_id, async move {
+      let result = match targets.liveness_settings {
+        Some(settings) => tokio::select! {
+          result = LlrpClient::receive_loop(reader, writer.clone(), targets.clone()) => result,
+          e = LlrpClient::watch_liveness(&writer, &targets, settings) => Err(e)
+        },
+        None => LlrpClient::receive_loop(reader, writer, targets).await
+      };
+      pending.close();
+      let reason = match result {
+        Ok(()) => "Connection closed".to_string(),
+        Err(e) => {
+          error!("Error in response handler loop: {}", e);
+          e.to_string()
+        }
+      };
+      history.record(ConnectionEventKind::Disconnected {
+        reason,
+        session_duration_ms : clock.elapsed(connected_at).as_millis() as u64
+      });
+    }))
+  }
+
+  /// Stops the receive loop of the current session. A loop that is still running
+  /// is aborted and the disconnect recorded with `reason`; one that already ended
+  /// has recorded its own.
+  async fn stop_receive_loop(
+    &mut self,
+    reason: &str
+  ) {
+
+    let Some(receive_task) = self.receive_task.take() else {
+      return;
+    };
+
+    receive_task.abort();
+    if matches!(receive_task.await, Err(e) if e.is_cancelled()) {
+      self.history.record(ConnectionEventKind::Disconnected {
+        reason              : reason.to_string(),
+        session_duration_ms : self.clock.elapsed(self.connected_at).as_millis() as u64
+      });
+    }
+  }
+
+  async fn send_message(
+    &mut self,
+    mut message: LlrpMessage,
+    expected_response_type : LlrpMessageType
+  ) -> Result<LlrpResponse, LlrpError> {
+
+    message.version = self.protocol_version.value();
+
+    if expected_response_type == LlrpMessageType::None {
+      self.write_message(&message).await?;
+      return Ok(LlrpResponse {
+        message_type: LlrpMessageType::None,
+        message_id: message.message_id,
+        payload: vec![],
+        received_at: ReceiveTimestamp { utc_us: self.clock.utc_now_us(), ..ReceiveTimestamp::now() }
+      });
+    }
+
+    // Register before writing so a response arriving immediately is not missed.
+    let response_rx = self.pending.register(message.message_id);
+    if let Err(e) = self.write_message(&message).await {
+      self.pending.cancel(message.message_id);
+      return Err(e);
+    }
+
+    let timeout_duration = Duration::from_millis(self.config.response_timeout);
+
+    let llrp_response = match self.clock.timeout(timeout_duration, response_rx).await {
+      Ok(Ok(llrp_response)) => llrp_response,
+      Ok(Err(_)) => {
+        return Err(LlrpError::ConnectionClosed("Connection closed while waiting for response".to_string()));
+      }
+      Err(_) => {
+        self.pending.cancel(message.message_id);
+        return Err(LlrpError::Timeout("Timeout while waiting for response".to_string()));
+      }
+    };
+
+    if llrp_response.message_type == LlrpMessageType::ErrorMessage {
+      return match self.decode_response(&llrp_response) {
+        Ok(LlrpResponseData::Error(status)) => Err(LlrpError::ReaderStatus(LlrpStatusError { request: message.message_type, status })),
+        _ => Err(LlrpError::ProtocolDecode(format!("Reader answered {:?} with an ErrorMessage of unknown status", message.message_type)))
+      };
+    }
+
+    if llrp_response.message_type != expected_response_type {
+      return Err(LlrpError::ProtocolDecode(format!(
+        "Reader answered {:?} with {:?}, expected {:?}",
+        message.message_type,
+        llrp_response.message_type,
+        expected_response_type
+      )));
+    }
+
+    Ok(llrp_response)
+  }
+
+  async fn write_message(
+    &self,
+    message: &LlrpMessage
+  ) -> Result<(), LlrpError> {
+
+    let frame = message.encode();
+    observe_frame(&self.frame_observer, FrameDirection::Sent, &frame);
+    self.protocol_counters.record_sent(message.message_type);
+
+    let mut writer = self.writer.lock().await;
+    write_frame(&mut writer, &self.journal, &frame).await?;
+
+    Ok(())
+  }
+
+  async fn send_message_ack(
+    &mut self,
+    message                : LlrpMessage,
+    expected_response_type : LlrpMessageType
+  ) -> Result<LlrpResponse, LlrpError> {
+
+    let reader_id = self.config.reader_id();
+
+    log_context::scope(reader_id, async move {
+
+      let request = message.message_type;
+      let response = self.send_message(message, expected_response_type).await?;
+      if expected_response_type.changes_configuration_state() {
+        // The reader's configuration state changes with our own writes too.
+        self.config_state = None;
+      }
+      if self.config.log_response_ack && expected_response_type != LlrpMessageType::None {
+        self.log_response_acknowledgment(expected_response_type, response.message_type);
+      }
+
+      let status = response.message_type.status_offset().and_then(|fixed_length| response.status(fixed_length));
+      if let Some(status) = status.filter(|status| !status.succeeded()) {
+        return Err(LlrpError::ReaderStatus(LlrpStatusError { request, status }));
+      }
+
+      Ok(response)
+    }).await
+  }
+
+  pub async fn send_close_connection(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new(LlrpMessageType::CloseConnection, message_id, vec![]);
+    let _ = self.send_message_ack(message, LlrpMessageType::CloseConnectionResponse).await;
+
+    Ok(())
+  }
+
+  /// Ends the session with the reader: sends CLOSE_CONNECTION and waits for its
+  /// response, stops the receive loop and closes the socket. Subscribers receive
+  /// the messages already delivered and then see their channel closed; a later
+  /// `reconnect` starts new channels. The session is torn down even when the
+  /// reader does not answer, and that error is returned afterwards.
+  ///
+  /// The receive loop is stopped by aborting its task rather than through a
+  /// cancellation token, so a message it is reading at that moment is discarded.
+  /// Once the session is shut down, further calls return `Ok` and do nothing.
+  pub async fn shutdown(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    let reader_id = self.config.reader_id();
+    log_context::scope(reader_id, self.shutdown_session()).await
+  }
+
+  async fn shutdown_session(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    let Some(receive_task) = &self.receive_task else {
+      return Ok(());
+    };
+
+    // A connection that is already gone has nobody left to answer CLOSE_CONNECTION.
+    let result = if receive_task.is_finished() {
+      Ok(())
+    } else {
+      let message_id = self.next_message_id();
+      let message = LlrpMessage::new(LlrpMessageType::CloseConnection, message_id, vec![]);
+      self.send_message_ack(message, LlrpMessageType::CloseConnectionResponse).await.map(|_| ())
+    };
+
+    if let Err(e) = &result {
+      warn!("Reader did not confirm CLOSE_CONNECTION, closing the connection anyway: {}", e);
+    }
+
+    // A receive loop that ended by itself, e.g. on the reader closing the
+    // connection after its response, has already recorded the disconnect.
+    self.stop_receive_loop("Closed by the client").await;
+
+    self.pending.close();
+    let _ = self.writer.lock().await.shutdown().await;
+
+    // Dropping the last senders ends the subscribers' channels once drained.
+    self.ro_report_tx.renew();
+    self.reader_event_tx.renew();
+    self.event_tx.renew();
+    self.gpi_event_tx.renew();
+    self.keepalive_tx.renew();
+
+    info!("Closed connection to LLRP server: {}", self.config.host);
+
+    result
+  }
+
+  pub async fn send_keep_alive(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new(LlrpMessageType::Keepalive, message_id, vec![]);
+    let start_time = self.clock.now();
+    let _ = self.send_message_ack(message, LlrpMessageType::KeepaliveAck).await?;
+
+    self.alerts.record_keepalive_rtt(self.clock.elapsed(start_time));
+
+    Ok(())
+  }
+
+  pub async fn send_enable_events_and_reports(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+
+    let message_id = self.next_message_id();
+    
+    let message = LlrpMessage::new_enable_events_and_reports(message_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::None).await?;
+
+    Ok(())
+  }
+
+  /// Asks the reader to send the tag reports it has buffered, e.g. while holding
+  /// reports until ENABLE_EVENTS_AND_REPORTS. GET_REPORT has no response of its own;
+  /// the ROAccessReports it triggers are delivered to `subscribe_ro_reports`.
+  pub async fn send_get_report(
+    &mut self,
+  ) -> Result<(), LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_report(message_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::None).await?;
+
+    Ok(())
+  }
+
+  pub async fn send_get_reader_capabilities<Fut, F>(
+    &mut self,
+    mut response_callback: F
+  ) -> Result<(), LlrpError> 
+  where
+    F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
+    Fut : Future<Output = ()> + Send 
+  {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_reader_capabilities(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderCapabilitiesResponse)
+      .await?;
+
+    match self.decode_response(&response) {
+
+      Ok(response_data) => {
+        response_callback(response_data).await;
+        Ok(())
+      }
+
+      Err(e) => Err(e.into())
+    }
+  }
+
+  /// Queries the reader capabilities and returns the complete decoded capability
+  /// tree as a JSON object keyed by parameter name, e.g.
+  /// `{"GeneralDeviceCapabilities": {...}, "RegulatoryCapabilities": {...}}`.
+  /// Allowed in monitor mode, as it does not modify the reader.
+  pub async fn capabilities_json(
+    &mut self
+  ) -> Result<serde_json::Value, LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_reader_capabilities(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderCapabilitiesResponse)
+      .await?;
+
+    let LlrpResponseData::ReaderCapabilities(parameters) = self.decode_response(&response)? else {
+      return Err(LlrpError::ProtocolDecode("Unexpected GetReaderCapabilities response".to_string()));
+    };
+
+    let mut capabilities = serde_json::Map::new();
+    let mut custom = Vec::new();
+
+    for parameter in parameters {
+      let (name, value) = match parameter {
+        LlrpParameterData::GeneralDeviceCapabilities(gdc)    => ("GeneralDeviceCapabilities", serde_json::to_value(gdc)?),
+        LlrpParameterData::LLRPCapabilities(llrp_caps)       => ("LLRPCapabilities", serde_json::to_value(llrp_caps)?),
+        LlrpParameterData::RegulatoryCapabilities(reg_caps)  => ("RegulatoryCapabilities", serde_json::to_value(reg_caps)?),
+        LlrpParameterData::C1G2LLRPCapabilities(c1g2_caps)   => ("C1G2LLRPCapabilities", serde_json::to_value(c1g2_caps)?),
+        LlrpParameterData::Custom(custom_param)              => {
+          custom.push(serde_json::to_value(custom_param)?);
+          continue;
+        }
+        _ => continue
+      };
+      capabilities.insert(name.to_string(), value);
+    }
+
+    // Readers may report several vendor extensions, so these are listed rather than keyed.
+    if !custom.is_empty() {
+      capabilities.insert("Custom".to_string(), serde_json::Value::Array(custom));
+    }
+
+    Ok(serde_json::Value::Object(capabilities))
+  }
+
+  /// Sends a vendor CUSTOM_MESSAGE and returns the CUSTOM_MESSAGE the reader
+  /// answers it with.
+  pub async fn send_custom_message(
+    &mut self,
+    custom: &CustomMessage
+  ) -> Result<CustomMessage, LlrpError> {
+
+    self.ensure_not_monitor_mode("CustomMessage")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_custom_message(message_id, custom);
+    let response = self.send_message_ack(message, LlrpMessageType::CustomMessage).await?;
+
+    match self.decode_response(&response)? {
+      LlrpResponseData::CustomMessage(reply) => Ok(reply),
+      _ => Err(LlrpError::ProtocolDecode("Unexpected CustomMessage response".to_string()))
+    }
+  }
+
+  /// Reads the parts of the reader configuration `request` selects, e.g. every
+  /// part with `ReaderConfigRequest::default()`.
+  pub async fn send_get_reader_config<Fut, F>(
+    &mut self,
+    request               : ReaderConfigRequest,
+    mut response_callback : F
+  ) -> Result<(), LlrpError> 
+  where
+    F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
+    Fut : Future<Output = ()> + Send 
+  {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_reader_config(message_id, &request);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
+      .await?;
+    
+    match self.decode_response(&response) {
+
+      Ok(response_data) => {
+        response_callback(response_data).await;
+        Ok(())
+      }
+
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  /// Resets the reader to its factory settings, which also deletes its ROSpecs and
+  /// AccessSpecs.
+  pub async fn send_factory_reset(
+    &mut self
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("SetReaderConfig")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_factory_reset(message_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+
+    self.alerts.set_spec_active(false);
+
+    Ok(())
+  }
+
+  pub async fn send_set_reader_config(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+    
+    let reader_config = self.config.reader_config.clone();
+    self.send_reader_config(&reader_config).await
+  }
+
+  /// Sends SET_READER_CONFIG for an explicit reader configuration rather than
+  /// the one loaded from the configuration file.
+  pub async fn send_reader_config(
+    &mut self,
+    reader_config: &ReaderConfig
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("SetReaderConfig")?;
+
+    let message_id = self.next_message_id();
+    
+    let message = LlrpMessage::new_set_reader_config(message_id, reader_config);
+    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+
+    Ok(())
+  }
+
+  /// Sends SET_READER_CONFIG changing the RF settings of a single antenna without
+  /// resetting the rest of the reader configuration.
+  pub async fn send_antenna_config(
+    &mut self,
+    antenna_id    : u16,
+    reader_config : &ReaderConfig
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("SetReaderConfig")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_set_antenna_configuration(message_id, antenna_id, reader_config, false);
+    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+
+    Ok(())
+  }
+
+  /// Drives GPO `port` to `state` without changing the rest of the reader
+  /// configuration.
+  pub async fn set_gpo(
+    &mut self,
+    port  : GpoPort,
+    state : PinState
+  ) -> Result<(), LlrpError> {
+    self.set_gpos(&[GpoOutputConfig { port, state }]).await
+  }
+
+  /// Drives several GPO ports in a single SetReaderConfig, e.g. switching a light
+  /// stack from green to red without an intermediate state. Ports not listed keep
+  /// their current level.
+  pub async fn set_gpos(
+    &mut self,
+    outputs: &[GpoOutputConfig]
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("SetReaderConfig")?;
+
+    if let Some(output) = outputs.iter().find(|output| output.state == PinState::Unknown) {
+      return Err(LlrpError::InvalidArgument(
+        format!("{} must be driven low or high", output.port)
+      ));
+    }
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_set_gpos(message_id, outputs);
+    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+
+    Ok(())
+  }
+
+  /// Enables or disables GPI ports without changing the rest of the reader
+  /// configuration. Only enabled ports report GPIEvents.
+  pub async fn set_gpi_ports(
+    &mut self,
+    ports: &[GpiPortConfig]
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("SetReaderConfig")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_set_gpi_ports(message_id, ports);
+    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+
+    Ok(())
+  }
+
+  /// Reads the configuration and current level of every GPI port. Allowed in
+  /// monitor mode, as it does not modify the reader.
+  pub async fn get_gpi_states(
+    &mut self
+  ) -> Result<Vec<GPIPortCurrentState>, LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_gpi_port_states(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
+      .await?;
+
+    match self.decode_response(&response)? {
+
+      LlrpResponseData::ReaderConfig(parameters) => Ok(parameters.into_iter().filter_map(|parameter| match parameter {
+        LlrpParameterData::GPIPortCurrentState(gpi_state) => Some(gpi_state),
+        _ => None
+      }).collect()),
+
+      _ => Err(LlrpError::ProtocolDecode("Unexpected GetReaderConfig response".to_string()))
+    }
+  }
+
+  /// Reads the AntennaProperties of every antenna port, which tell whether an
+  /// antenna is attached to it. Allowed in monitor mode, as it does not modify the
+  /// reader.
+  pub async fn get_antenna_properties(
+    &mut self
+  ) -> Result<Vec<AntennaProperties>, LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_antenna_properties(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
+      .await?;
+
+    match self.decode_response(&response)? {
+
+      LlrpResponseData::ReaderConfig(parameters) => Ok(parameters.into_iter().filter_map(|parameter| match parameter {
+        LlrpParameterData::AntennaProperties(antenna) => Some(antenna),
+        _ => None
+      }).collect()),
+
+      _ => Err(LlrpError::ProtocolDecode("Unexpected GetReaderConfig response".to_string()))
+    }
+  }
+
+  /// The IDs of the antenna ports the reader reports an antenna attached to.
+  pub async fn get_connected_antennas(
+    &mut self
+  ) -> Result<Vec<u16>, LlrpError> {
+    Ok(self.get_antenna_properties().await?.into_iter()
+      .filter(|antenna| antenna.antenna_connected)
+      .map(|antenna| antenna.antenna_id)
+      .collect())
+  }
+
+  /// Reads the RF and inventory settings of every antenna, e.g. to compare them with
+  /// the configured ones through `AntennaConfiguration::differences`. Allowed in
+  /// monitor mode, as it does not modify the reader.
+  pub async fn get_antenna_configurations(
+    &mut self
+  ) -> Result<Vec<AntennaConfiguration>, LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_antenna_configurations(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
+      .await?;
+
+    match self.decode_response(&response)? {
+
+      LlrpResponseData::ReaderConfig(parameters) => Ok(parameters.into_iter().filter_map(|parameter| match parameter {
+        LlrpParameterData::AntennaConfiguration(antenna) => Some(antenna),
+        _ => None
+      }).collect()),
+
+      _ => Err(LlrpError::ProtocolDecode("Unexpected GetReaderConfig response".to_string()))
+    }
+  }
+
+  /// Reads the reader's LLRPConfigurationStateValue. Allowed in monitor mode, as it
+  /// does not modify the reader.
+  pub async fn get_configuration_state(
+    &mut self
+  ) -> Result<u32, LlrpError> {
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_get_configuration_state(message_id);
+    let response = self
+      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
+      .await?;
+
+    match self.decode_response(&response)? {
+
+      LlrpResponseData::ReaderConfig(parameters) => parameters.into_iter().find_map(|parameter| match parameter {
+        LlrpParameterData::LLRPConfigurationStateValue(state) => Some(state.state_value),
+        _ => None
+      }).ok_or_else(|| LlrpError::ProtocolDecode("Reader did not report an LLRPConfigurationStateValue".to_string())),
+
+      _ => Err(LlrpError::ProtocolDecode("Unexpected GetReaderConfig response".to_string()))
+    }
+  }
+
+  /// Reads the reader's configuration state and reports whether it differs from the
+  /// one seen by the previous call, i.e. whether the configuration was changed by
+  /// someone else in between, such as another controller. The first call, and the
+  /// first after a configuration change sent by this client, e.g. a SetReaderConfig
+  /// or an AddROSpec, only records the state and returns false.
+  pub async fn check_configuration_changed(
+    &mut self
+  ) -> Result<bool, LlrpError> {
+
+    let state = self.get_configuration_state().await?;
+
+    match self.config_state.replace(state) {
+      Some(previous) if previous != state => {
+        warn!("Reader configuration state changed from {} to {} outside this client", previous, state);
+        Ok(true)
+      }
+      _ => Ok(false)
+    }
+  }
+
+  /// The configuration state recorded by the last `check_configuration_changed`.
+  pub fn configuration_state(
+    &self
+  ) -> Option<u32> {
+    self.config_state
+  }
+
+  pub async fn send_add_rospec(
+    &mut self,
+  ) -> Result<(), LlrpError> {
+    
+    self.ensure_not_monitor_mode("AddROSpec")?;
+    self.ensure_report_trigger_supported(self.config.rospec.ROReportTriggerType)?;
+
+    let message_id = self.next_message_id();
+    
+    let message = LlrpMessage::new_add_rospec(message_id, &self.config.rospec, &rospec_report_extensions(&self.config));
+    let _ = self.send_message_ack(message, LlrpMessageType::AddROspecResponse).await?;
+
+    Ok(())
+  }
+
+  /// Adds an ROSpec composed with `ROSpecBuilder` instead of the configured one. It
+  /// is enabled and started by its ID, with `send_enable_rospec_with_id` and
+  /// `send_start_rospec_with_id`. An ROSpec with a LoopSpec needs the connection
+  /// at LLRP 1.1, e.g. through `negotiate_protocol_version`.
+  pub async fn send_add_built_rospec(
+    &mut self,
+    rospec: &ROSpec
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("AddROSpec")?;
+
+    if rospec.loop_count.is_some() && self.protocol_version != LlrpVersion::V1_1 {
+      return Err(LlrpError::Unsupported(format!("LoopSpec requires LLRP 1.1, but the connection uses {:?}", self.protocol_version)));
+    }
+
+    if let Some(report_spec) = &rospec.report_spec {
+      self.ensure_report_trigger_supported(report_spec.trigger_type)?;
+    }
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_add_built_rospec(message_id, rospec);
+    let _ = self.send_message_ack(message, LlrpMessageType::AddROspecResponse).await?;
+
+    Ok(())
+  }
+
+  pub async fn send_enable_rospec(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+
+    self.send_enable_rospec_with_id(self.config.rospec.rospec_id).await?;
+
+    // Specs with an immediate start trigger begin on their own once enabled. Periodic
+    // ones are idle between runs, so are not watched for missing tag reads.
+    if self.config.rospec.ROSpecStartTriggerType == 1 {
+      self.alerts.set_spec_active(true);
+    }
+
+    Ok(())
+  }
+
+  /// Enables an ROSpec already added to the reader, which need not be the
+  /// configured one.
+  pub async fn send_enable_rospec_with_id(
+    &mut self,
+    rospec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("EnableROSpec")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_enable_rospec(message_id, rospec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::EnableROSpecResponse).await?;
+
+    Ok(())
+  }
+
+  pub async fn send_start_rospec(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+    self.send_start_rospec_with_id(self.config.rospec.rospec_id).await
+  }
+
+  /// Starts an ROSpec already added to the reader, which need not be the
+  /// configured one.
+  pub async fn send_start_rospec_with_id(
+    &mut self,
+    rospec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("StartROSpec")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_start_rospec(message_id, rospec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::StartROSpecResponse).await?;
+
+    self.alerts.set_spec_active(true);
+
+    Ok(())
+  }
+
+  /// Stops the configured ROSpec. With `get_report_on_stop` enabled, GET_REPORT is
+  /// sent afterwards and the call returns only once the reader's final
+  /// ROAccessReports have been delivered to subscribers.
+  pub async fn send_stop_rospec(
+    &mut self, 
+  ) -> Result<(), LlrpError> {
+    self.send_stop_rospec_with_id(self.config.rospec.rospec_id).await
+  }
+
+  /// Stops the given ROSpec, collecting final reports as `send_stop_rospec` does.
+  pub async fn send_stop_rospec_with_id(
+    &mut self,
+    rospec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("StopROSpec")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_stop_rospec(message_id, rospec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::StopROSpecResponse).await?;
+
+    self.alerts.set_spec_active(false);
+
+    if self.config.get_report_on_stop {
+      self.collect_final_reports().await?;
+    }
+
+    Ok(())
+  }
+
+  /// Sends GET_REPORT and waits until no further ROAccessReport arrives for
+  /// `final_report_timeout` milliseconds. Returns the number of reports received.
+  async fn collect_final_reports(
+    &mut self
+  ) -> Result<usize, LlrpError> {
+
+    let mut ro_report_rx = self.ro_report_tx.subscribe();
+
+    self.send_get_report().await?;
+
+    let quiet_period = Duration::from_millis(self.config.final_report_timeout);
+    let mut received = 0;
+
+    loop {
+      match self.clock.timeout(quiet_period, ro_report_rx.recv()).await {
+        Ok(Ok(_)) => received += 1,
+        Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => received += skipped as usize,
+        Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break
+      }
+    }
+
+    log_context::sync_scope(self.config.reader_id(), || {
+      info!("Received {} final ROAccessReports after StopROSpec", received);
+    });
+
+    Ok(received)
+  }
+
+  /// Disables an ROSpec (0 - All) without deleting it. A running spec is stopped,
+  /// and the spec stays on the reader until it is enabled again.
+  pub async fn send_disable_rospec(
+    &mut self,
+    rospec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("DisableROSpec")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_disable_rospec(message_id, rospec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::DisableROSpecResponse).await?;
+
+    if rospec_id == 0 || rospec_id == self.config.rospec.rospec_id {
+      self.alerts.set_spec_active(false);
+    }
+
+    Ok(())
+  }
+
+  pub async fn send_delete_rospec(
+    &mut self,
+    rospec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("DeleteROSpec")?;
+
+    let message_id = self.next_message_id();
+
+    let message = LlrpMessage::new_delete_rospec(message_id, rospec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::DeleteROSpecResponse).await?;
+
+    if rospec_id == 0 || rospec_id == self.config.rospec.rospec_id {
+      self.alerts.set_spec_active(false);
+    }
+
+    Ok(())
+  }
+
+  /// Writes `data` to tag memory starting at `word_pointer` in `memory_bank`
+  /// (0 - Reserved, 1 - EPC, 2 - TID, 3 - User).
+  ///
+  /// A single-use AccessSpec carrying the C1G2Write is added and enabled. The reader
+  /// performs the write on the next tag singulated by a running inventory, reports
+  /// the outcome as a `C1G2WriteOpSpecResult` with that tag, and then deletes the
+  /// AccessSpec. A pending write that has not executed yet is replaced.
+  pub async fn write_tag_memory(
+    &mut self,
+    memory_bank     : u8,
+    word_pointer    : u16,
+    data            : &[u16],
+    access_password : u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("Tag memory write")?;
+
+    let access_spec = AccessSpecConfig {
+      access_spec_id  : WRITE_ACCESS_SPEC_ID,
+      target          : None,
+      antenna_id      : 0,
+      rospec_id       : 0,
+      operation_count : 1,
+      reads           : vec![],
+      writes          : vec![C1G2WriteConfig {
+        op_spec_id : 1,
+        access_password,
+        memory_bank,
+        word_pointer,
+        data       : data.to_vec()
+      }],
+      block_erases    : vec![],
+      block_writes    : vec![],
+      kills           : vec![],
+      locks           : vec![]
+    };
+
+    self.replace_access_spec(&access_spec).await
+  }
+
+  /// Writes `data` to the next tag singulated by a running inventory with a single
+  /// C1G2BlockWrite, starting at `word_pointer` of `memory_bank`.
+  ///
+  /// Readers not reporting `supports_block_write` in their C1G2 capabilities get
+  /// one C1G2Write per word in the same AccessSpec instead. The outcome is reported
+  /// with the tag as a `block_write_results` entry, or one `write_results` entry per
+  /// word. Like `write_tag_memory`, this replaces a pending write.
+  pub async fn block_write_tag_memory(
+    &mut self,
+    memory_bank     : u8,
+    word_pointer    : u16,
+    data            : &[u16],
+    access_password : u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("Tag memory block write")?;
+
+    let block_write = self.c1g2_capabilities().await?
+      .is_some_and(|capabilities| capabilities.supports_block_write);
+
+    let (writes, block_writes) = if block_write {
+      (vec![], vec![C1G2WriteConfig {
+        op_spec_id : 1,
+        access_password,
+        memory_bank,
+        word_pointer,
+        data       : data.to_vec()
+      }])
+    } else {
+      info!("Reader does not support BlockWrite, writing {} words individually", data.len());
+      (word_writes(memory_bank, word_pointer, data, access_password)?, vec![])
+    };
+
+    let access_spec = AccessSpecConfig {
+      access_spec_id  : WRITE_ACCESS_SPEC_ID,
+      target          : None,
+      antenna_id      : 0,
+      rospec_id       : 0,
+      operation_count : 1,
+      reads           : vec![],
+      writes,
+      block_erases    : vec![],
+      block_writes,
+      kills           : vec![],
+      locks           : vec![]
+    };
+
+    self.replace_access_spec(&access_spec).await
+  }
+
+  /// Sets `word_count` words of the next tag singulated by a running inventory to
+  /// zero, starting at `word_pointer` of `memory_bank`.
+  ///
+  /// Readers not reporting `supports_block_erase` in their C1G2 capabilities get
+  /// one C1G2Write of a zero word per word instead. The outcome is reported with the
+  /// tag as a `block_erase_results` entry, or one `write_results` entry per word.
+  /// Like `write_tag_memory`, this replaces a pending write.
+  pub async fn block_erase_tag_memory(
+    &mut self,
+    memory_bank     : u8,
+    word_pointer    : u16,
+    word_count      : u16,
+    access_password : u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("Tag memory block erase")?;
+
+    let block_erase = self.c1g2_capabilities().await?
+      .is_some_and(|capabilities| capabilities.supports_block_erase);
+
+    let (writes, block_erases) = if block_erase {
+      (vec![], vec![C1G2BlockEraseConfig {
+        op_spec_id : 1,
+        access_password,
+        memory_bank,
+        word_pointer,
+        word_count
+      }])
+    } else {
+      info!("Reader does not support BlockErase, zeroing {} words individually", word_count);
+      (word_writes(memory_bank, word_pointer, &vec![0; word_count as usize], access_password)?, vec![])
+    };
+
+    let access_spec = AccessSpecConfig {
+      access_spec_id  : WRITE_ACCESS_SPEC_ID,
+      target          : None,
+      antenna_id      : 0,
+      rospec_id       : 0,
+      operation_count : 1,
+      reads           : vec![],
+      writes,
+      block_erases,
+      block_writes    : vec![],
+      kills           : vec![],
+      locks           : vec![]
+    };
+
+    self.replace_access_spec(&access_spec).await
+  }
+
+  /// The reader's C1G2 capabilities, or `None` when its GetReaderCapabilities
+  /// response does not include them.
+  async fn c1g2_capabilities(
+    &mut self
+  ) -> Result<Option<C1G2LLRPCapabilities>, LlrpError> {
+
+    let mut c1g2_capabilities = None;
+
+    self.send_get_reader_capabilities(|response_data| {
+      if let LlrpResponseData::ReaderCapabilities(parameters) = response_data {
+        c1g2_capabilities = parameters.into_iter().find_map(|parameter| match parameter {
+          LlrpParameterData::C1G2LLRPCapabilities(capabilities) => Some(capabilities),
+          _ => None
+        });
+      }
+      async {}
+    }).await?;
+
+    Ok(c1g2_capabilities)
+  }
+
+  /// Checks `rf_control` against the C1G2UHFRFModeTable of the reader's
+  /// RegulatoryCapabilities, returning the mode it selects, before the mode is used
+  /// in an inventory command the reader would otherwise reject.
+  pub async fn check_rf_control(
+    &mut self,
+    rf_control: &C1G2RFControlConfig
+  ) -> Result<C1G2UHFRFModeTableEntry, LlrpError> {
+
+    let mut mode_table = None;
+
+    self.send_get_reader_capabilities(|response_data| {
+      if let LlrpResponseData::ReaderCapabilities(parameters) = response_data {
+        mode_table = parameters.into_iter().find_map(|parameter| match parameter {
+          LlrpParameterData::RegulatoryCapabilities(capabilities) => capabilities.uhf_band_capabilities?.c1g2_uhf_rf_mode_table,
+          _ => None
+        });
+      }
+      async {}
+    }).await?;
+
+    let mode_table = mode_table.ok_or_else(|| LlrpError::Unsupported("Reader reports no C1G2UHFRFModeTable".to_string()))?;
+    let mode = mode_table.check_rf_control(rf_control).map_err(LlrpError::InvalidArgument)?;
+
+    Ok(mode.clone())
+  }
+
+  /// Applies the lock `payloads` to the next tag singulated by a running inventory,
+  /// authenticating with the tag's `access_password`.
+  ///
+  /// Like `write_tag_memory`, this adds a single-use AccessSpec, replacing a pending
+  /// lock that has not executed yet, and the outcome is reported as a
+  /// `C1G2LockOpSpecResult` with the tag.
+  pub async fn lock_tag(
+    &mut self,
+    payloads        : &[C1G2LockPayloadConfig],
+    access_password : u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("Tag lock")?;
+
+    let access_spec = AccessSpecConfig {
+      access_spec_id  : LOCK_ACCESS_SPEC_ID,
+      target          : None,
+      antenna_id      : 0,
+      rospec_id       : 0,
+      operation_count : 1,
+      reads           : vec![],
+      writes          : vec![],
+      block_erases    : vec![],
+      block_writes    : vec![],
+      kills           : vec![],
+      locks           : vec![C1G2LockConfig {
+        op_spec_id : 1,
+        access_password,
+        payloads   : payloads.to_vec()
+      }]
+    };
+
+    self.replace_access_spec(&access_spec).await
+  }
 
-    info!("Client Successfully Connected to LLRP server: {}", config.host);
-    
-    let (reader, writer) = split(stream);
-    let (message_tx, _) = broadcast::channel(100);
-    let (ro_report_tx, _) = broadcast::channel(100);
+  /// Kills the next tag singulated by a running inventory whose EPC starts with
+  /// `epc_mask`, using the tag's non-zero `kill_password`.
+  ///
+  /// Like `write_tag_memory`, this adds a single-use AccessSpec, replacing a pending
+  /// kill that has not executed yet. The outcome is reported as a
+  /// `C1G2KillOpSpecResult` with the tag; check `succeeded()` to confirm the kill.
+  pub async fn kill_tag(
+    &mut self,
+    epc_mask      : &[u8],
+    kill_password : u32
+  ) -> Result<(), LlrpError> {
 
-    let client_message_tx = message_tx.clone();
+    self.ensure_not_monitor_mode("Tag kill")?;
 
-    let client = LlrpClient {
-      reader: Arc::new(Mutex::new(reader)),
-      writer: Arc::new(Mutex::new(writer)),
-      message_id: 1001, 
-      config,
-      message_tx: client_message_tx,
-      ro_report_tx
+    if kill_password == 0 {
+      return Err(LlrpError::InvalidArgument("Tags cannot be killed with a zero kill password".to_string()));
+    }
+
+    let tag_data: String = epc_mask.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let access_spec = AccessSpecConfig {
+      access_spec_id  : KILL_ACCESS_SPEC_ID,
+      target          : Some(C1G2TargetTagConfig {
+        memory_bank : 1,
+        pointer     : 32, // EPC starts after the CRC and PC words
+        tag_mask    : "ff".repeat(epc_mask.len()),
+        tag_data
+      }),
+      antenna_id      : 0,
+      rospec_id       : 0,
+      operation_count : 1,
+      reads           : vec![],
+      writes          : vec![],
+      block_erases    : vec![],
+      block_writes    : vec![],
+      kills           : vec![C1G2KillConfig { op_spec_id: 1, kill_password }],
+      locks           : vec![]
     };
 
-    let reader_clone = client.reader.clone();
-    let message_tx_clone = message_tx.clone();
-    let ro_report_tx_clone = client.ro_report_tx.clone();
+    self.replace_access_spec(&access_spec).await
+  }
 
-    tokio::spawn(async move {
-      if let Err(e) = LlrpClient::receive_loop(
-        reader_clone,
-        message_tx_clone,
-        ro_report_tx_clone
-      ).await {
-        error!("Error in response handler loop: {}", e);
-      }
-    });
+  /// Deletes any AccessSpec with the same ID, then adds and enables `access_spec`.
+  /// An invalid `access_spec` fails before the pending one is deleted.
+  async fn replace_access_spec(
+    &mut self,
+    access_spec: &AccessSpecConfig
+  ) -> Result<(), LlrpError> {
 
-    Ok(client)
+    access_spec.validate().map_err(|e| LlrpError::InvalidArgument(e.to_string()))?;
+    ignore_missing_spec(self.send_delete_access_spec(access_spec.access_spec_id).await)?;
+    self.send_add_access_spec(access_spec).await?;
+    self.send_enable_access_spec(access_spec.access_spec_id).await?;
+
+    Ok(())
   }
 
-  async fn send_message(
+  /// Changes the ROSpec's `ROReportTrigger_N`. The ROSpec is stopped, deleted and
+  /// added again with the new value, then enabled and, for a null start trigger,
+  /// started, so a running inventory continues with the new batching. If the reader
+  /// refuses the new ROSpec, the previous one is added back and the configured
+  /// value stays unchanged.
+  pub async fn set_report_trigger_n(
     &mut self,
-    message: LlrpMessage,
-    expected_response_type : LlrpMessageType
-  ) -> Result<LlrpResponse, Box<dyn Error>> {
+    report_n: u16
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("ROReportTrigger_N change")?;
 
-    {
-      let mut writer = self.writer.lock().await;
-      writer.write_all(&message.encode()).await?;
+    if !self.config.rospec.ROReportTriggerType.counts_tags() {
+      return Err(LlrpError::Config(format!("ROReportTrigger_N is not a tag count for ROReportTriggerType {:?}", self.config.rospec.ROReportTriggerType)));
     }
 
-    if expected_response_type == LlrpMessageType::None {
-      return Ok(LlrpResponse {
-        message_type: LlrpMessageType::None,
-        message_id: message.message_id,
-        payload: vec![]
-      });
+    if self.config.rospec.ROReportTrigger_N == report_n {
+      return Ok(());
     }
-    
-    let mut message_rx = self.message_tx.subscribe();
-    let timeout_duration = Duration::from_millis(self.config.response_timeout);
-    let start_time = Instant::now();
 
-    loop {
+    let mut rospec = self.config.rospec.clone();
+    rospec.ROReportTrigger_N = report_n;
 
-      let elapsed = start_time.elapsed();
-      if elapsed >= timeout_duration {
-        return Err(Box::new(io::Error::new(
-          io::ErrorKind::TimedOut,
-          "Timeout while waiting for response"
-        )));
-      }
+    let rospec_id = self.config.rospec.rospec_id;
+    ignore_missing_spec(self.send_stop_rospec().await)?;
+    ignore_missing_spec(self.send_delete_rospec(rospec_id).await)?;
 
-      match timeout(timeout_duration - elapsed, message_rx.recv()).await {
+    self.replace_rospec(rospec).await
+  }
 
-        Ok(Ok(llrp_response)) => {
-          if llrp_response.message_type == expected_response_type {
-            return Ok(llrp_response);
-          } else {
-            warn!(
-              "Received unexpected message type: {:?}",
-              llrp_response.message_type
-            );
-          }
-        }
+  /// Adds `rospec` in place of the configured ROSpec, which the caller has already
+  /// deleted from the reader, then enables it and, for a null start trigger, starts
+  /// it. `rospec` becomes the configured ROSpec only once the reader accepts it; if
+  /// the reader refuses it, the previous ROSpec is added back on a best-effort basis
+  /// so the reader and the configuration keep agreeing.
+  async fn replace_rospec(
+    &mut self,
+    rospec: ROSpecConfig
+  ) -> Result<(), LlrpError> {
+
+    let previous = std::mem::replace(&mut self.config.rospec, rospec);
+
+    if let Err(e) = self.send_add_rospec().await {
+      self.config.rospec = previous;
+      let restored = match self.send_add_rospec().await {
+        Ok(()) => self.enable_configured_rospec().await,
+        Err(restore_error) => Err(restore_error)
+      };
+      if let Err(restore_error) = restored {
+        warn!("Failed to restore ROSpec {}: {}", self.config.rospec.rospec_id, restore_error);
+      }
+      return Err(e);
+    }
 
-        Ok(Err(broadcast::error::RecvError::Closed)) => {
-          return Err(Box::new(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "Message channel closed"
-          )));
-        }
+    self.enable_configured_rospec().await
+  }
 
-        Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => {
-          warn!("Missed {} messages due to buffer overflow", skipped);
-        }
+  /// Enables the configured ROSpec and, for a null start trigger, starts it.
+  async fn enable_configured_rospec(
+    &mut self
+  ) -> Result<(), LlrpError> {
 
-        Err(_) => {
-          return Err(Box::new(io::Error::new(
-            io::ErrorKind::TimedOut,
-            "Timeout while waiting for response"
-          )));
-        }
-      }
+    self.send_enable_rospec().await?;
+
+    if self.config.rospec.ROSpecStartTriggerType == 0 {
+      self.send_start_rospec().await?;
     }
+
+    Ok(())
   }
 
-  async fn send_message_ack(
+  /// Changes the antennas the ROSpec reads with. The ROSpec is disabled, deleted and
+  /// added again with the new antennas, then enabled and, for a null start trigger,
+  /// started. Disabling rather than stopping also works on an ROSpec that is not
+  /// running, so this re-enables a disabled ROSpec as well. If the reader refuses
+  /// the new ROSpec, the previous one is added back and the configured antennas
+  /// stay unchanged.
+  pub async fn set_rospec_antennas(
     &mut self,
-    message                : LlrpMessage,
-    expected_response_type : LlrpMessageType
-  ) -> Result<LlrpResponse, Box<dyn Error>> {
+    antennas: Vec<u16>
+  ) -> Result<(), LlrpError> {
 
-    let response = self.send_message(message, expected_response_type).await?;
-    if self.config.log_response_ack && expected_response_type != LlrpMessageType::None {
-      self.log_response_acknowledgment(expected_response_type, response.message_type);
+    self.ensure_not_monitor_mode("ROSpec antenna change")?;
+
+    if antennas.is_empty() {
+      return Err(LlrpError::InvalidArgument("An ROSpec needs at least one antenna".to_string()));
     }
 
-    Ok(response)
+    let mut rospec = self.config.rospec.clone();
+    rospec.antenna_count = antennas.len() as u16;
+    rospec.antennas = antennas;
+
+    let rospec_id = self.config.rospec.rospec_id;
+    ignore_missing_spec(self.send_disable_rospec(rospec_id).await)?;
+    ignore_missing_spec(self.send_delete_rospec(rospec_id).await)?;
+
+    self.replace_rospec(rospec).await
   }
 
-  pub async fn send_close_connection(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
+  /// Adds an AccessSpec reading tag memory. The AccessSpec is added disabled;
+  /// enable it with `send_enable_access_spec`.
+  pub async fn send_add_access_spec(
+    &mut self,
+    access_spec: &AccessSpecConfig
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("AddAccessSpec")?;
+    access_spec.validate().map_err(|e| LlrpError::InvalidArgument(e.to_string()))?;
 
     let message_id = self.next_message_id();
 
-    let message = LlrpMessage::new(LlrpMessageType::CloseConnection, message_id, vec![]);
-    let _ = self.send_message_ack(message, LlrpMessageType::CloseConnectionResponse).await;
+    let message = LlrpMessage::new_add_access_spec(message_id, access_spec);
+    let _ = self.send_message_ack(message, LlrpMessageType::AddAccessSpecResponse).await?;
 
     Ok(())
   }
 
-  pub async fn send_keep_alive(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
+  pub async fn send_enable_access_spec(
+    &mut self,
+    access_spec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("EnableAccessSpec")?;
 
     let message_id = self.next_message_id();
 
-    let message = LlrpMessage::new(LlrpMessageType::Keepalive, message_id, vec![]);
-    let _ = self.send_message_ack(message, LlrpMessageType::KeepaliveAck).await?;
+    let message = LlrpMessage::new_enable_access_spec(message_id, access_spec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::EnableAccessSpecResponse).await?;
 
     Ok(())
   }
 
-  pub async fn send_enable_events_and_reports(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
+  pub async fn send_delete_access_spec(
+    &mut self,
+    access_spec_id: u32
+  ) -> Result<(), LlrpError> {
+
+    self.ensure_not_monitor_mode("DeleteAccessSpec")?;
 
     let message_id = self.next_message_id();
-    
-    let message = LlrpMessage::new_enable_events_and_reports(message_id);
-    let _ = self.send_message_ack(message, LlrpMessageType::None).await?;
+
+    let message = LlrpMessage::new_delete_access_spec(message_id, access_spec_id);
+    let _ = self.send_message_ack(message, LlrpMessageType::DeleteAccessSpecResponse).await?;
 
     Ok(())
   }
 
-  pub async fn send_get_reader_capabilities<Fut, F>(
-    &mut self,
-    mut response_callback: F
-  ) -> Result<(), Box<dyn Error>> 
-  where
-    F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
-    Fut : Future<Output = ()> + Send 
-  {
+  /// Lists the AccessSpecs currently loaded on the reader, including those added
+  /// by other clients. Allowed in monitor mode, as it does not modify the reader.
+  pub async fn send_get_access_specs(
+    &mut self
+  ) -> Result<Vec<AccessSpec>, LlrpError> {
 
     let message_id = self.next_message_id();
 
-    let message = LlrpMessage::new_get_reader_capabilities(message_id);
+    let message = LlrpMessage::new_get_access_specs(message_id);
     let response = self
-      .send_message_ack(message, LlrpMessageType::GetReaderCapabilitiesResponse)
+      .send_message_ack(message, LlrpMessageType::GetAccessSpecsResponse)
       .await?;
 
-    match response.decode() {
+    match self.decode_response(&response)? {
 
-      Ok(response_data) => {
-        response_callback(response_data).await;
-        Ok(())
-      }
+      LlrpResponseData::AccessSpecs(parameters) => Ok(parameters.into_iter().filter_map(|parameter| match parameter {
+        LlrpParameterData::AccessSpec(access_spec) => Some(access_spec),
+        _ => None
+      }).collect()),
 
-      Err(e) => Err(Box::new(e))
+      _ => Err(LlrpError::ProtocolDecode("Unexpected GetAccessSpecs response".to_string()))
     }
   }
 
-  pub async fn send_get_reader_config<Fut, F>(
-    &mut self,
-    mut response_callback: F
-  ) -> Result<(), Box<dyn Error>> 
-  where
-    F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
-    Fut : Future<Output = ()> + Send 
-  {
+  /// Runs the canonical sequence to start an inventory from the loaded configuration:
+  /// SET_READER_CONFIG, ADD_ROSPEC, ENABLE_ROSPEC, START_ROSPEC (only for a null start
+  /// trigger, other triggers start the spec on their own) and ENABLE_EVENTS_AND_REPORTS.
+  ///
+  /// The steps run inside a `SetupTransaction`, so a failure part-way through stops and
+  /// deletes the ROSpec and restores the previous reader configuration. If any of that
+  /// fails too, the returned error says how many steps were left applied.
+  pub async fn start_inventory(
+    &mut self
+  ) -> Result<(), LlrpError> {
 
-    let message_id = self.next_message_id();
+    let mut transaction = SetupTransaction::new();
 
-    let message = LlrpMessage::new_get_reader_config(message_id);
-    let response = self
-      .send_message_ack(message, LlrpMessageType::GetReaderConfigResponse)
-      .await?;
-    
-    match response.decode() {
+    match self.apply_inventory_setup(&mut transaction).await {
 
-      Ok(response_data) => {
-        response_callback(response_data).await;
+      Ok(()) => {
+        transaction.commit();
         Ok(())
       }
 
-      Err(e) => Err(Box::new(e)),
-    }
-  }
-
-  pub async fn send_set_reader_config(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
-    
-    let message_id = self.next_message_id();
-    
-    let message = LlrpMessage::new_set_reader_config(message_id, &self.config.reader_config);
-    let _ = self.send_message_ack(message, LlrpMessageType::SetReaderConfigResponse).await?;
+      Err(e) => {
 
-    Ok(())
-  }
+        warn!("Inventory start failed, rolling back: {}", e);
 
-  pub async fn send_add_rospec(
-    &mut self,
-  ) -> Result<(), Box<dyn Error>> {
-    
-    let message_id = self.next_message_id();
-    
-    let message = LlrpMessage::new_add_rospec(message_id, &self.config.rospec);
-    let _ = self.send_message_ack(message, LlrpMessageType::AddROspecResponse).await?;
+        let failed = transaction.rollback(self).await;
+        if failed.is_empty() {
+          return Err(e);
+        }
 
-    Ok(())
+        error!("Rollback left {} steps applied on the reader: {:?}", failed.len(), failed);
+        Err(e.context(&format!("Rollback left {} steps applied", failed.len())))
+      }
+    }
   }
 
-  pub async fn send_enable_rospec(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
-    
-    let message_id = self.next_message_id();
-
-    let message = LlrpMessage::new_enable_rospec(message_id, self.config.rospec.rospec_id);
-    let _ = self.send_message_ack(message, LlrpMessageType::EnableROSpecResponse).await?;
-
-    Ok(())
-  }
+  async fn apply_inventory_setup(
+    &mut self,
+    transaction: &mut SetupTransaction
+  ) -> Result<(), LlrpError> {
 
-  pub async fn send_start_rospec(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
+    let reader_config = self.config.reader_config.clone();
 
-    let message_id = self.next_message_id();
+    transaction.set_reader_config(self, &reader_config).await?;
+    transaction.add_rospec(self).await?;
+    transaction.enable_rospec(self).await?;
 
-    let message = LlrpMessage::new_start_rospec(message_id, self.config.rospec.rospec_id);
-    let _ = self.send_message_ack(message, LlrpMessageType::StartROSpecResponse).await?;
+    if self.config.rospec.ROSpecStartTriggerType == 0 {
+      transaction.start_rospec(self).await?;
+    }
 
-    Ok(())
+    self.send_enable_events_and_reports().await
   }
 
-  pub async fn send_stop_rospec(
-    &mut self, 
-  ) -> Result<(), Box<dyn Error>> {
+  /// Stops the inventory started by `start_inventory` and removes its ROSpec.
+  pub async fn stop_inventory(
+    &mut self
+  ) -> Result<(), LlrpError> {
 
-    let message_id = self.next_message_id();
+    let rospec_id = self.config.rospec.rospec_id;
 
-    let message = LlrpMessage::new_stop_rospec(message_id, self.config.rospec.rospec_id);
-    let _ = self.send_message_ack(message, LlrpMessageType::StopROSpecResponse).await?;
+    if let Err(e) = self.send_stop_rospec().await {
+      warn!("StopROSpec failed, deleting ROSpec {} anyway: {}", rospec_id, e);
+    }
 
-    Ok(())
+    self.send_delete_rospec(rospec_id).await
   }
 
-  pub async fn send_delete_rospec(
+  pub async fn await_ro_access_report<Fut, F>(
     &mut self,
-    rospec_id: u32
-  ) -> Result<(), Box<dyn Error>> {
-
-    let message_id = self.next_message_id();
-
-    let message = LlrpMessage::new_delete_rospec(message_id, rospec_id);
-    let _ = self.send_message_ack(message, LlrpMessageType::DeleteROSpecResponse).await?;
-
-    Ok(())
+    response_callback: F
+  ) -> Result<(), LlrpError> 
+  where
+    F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
+    Fut : Future<Output = ()> + Send 
+  {
+    self.await_ro_access_report_where(|_| true, response_callback).await
   }
 
-  pub async fn await_ro_access_report<Fut, F>(
+  /// Waits for an ROAccessReport like `await_ro_access_report`, skipping the reports
+  /// `keep` returns false for, e.g. ones a report filter left without tags. The
+  /// response timeout covers the whole wait, skipped reports included.
+  pub async fn await_ro_access_report_where<Fut, F, K>(
     &mut self,
-    mut response_callback: F
-  ) -> Result<(), Box<dyn Error>> 
+    mut keep              : K,
+    mut response_callback : F
+  ) -> Result<(), LlrpError> 
   where
+    K   : FnMut(&mut LlrpResponseData) -> bool + Send,
     F   : FnMut(LlrpResponseData) -> Fut + Send + Sync,
     Fut : Future<Output = ()> + Send 
   {
@@ -399,32 +2820,32 @@ impl LlrpClient {
     let mut ro_report_rx = self.ro_report_tx.subscribe();
 
     let timeout_duration = Duration::from_millis(self.config.response_timeout);
-    let start_time = Instant::now();
+    let start_time = self.clock.now();
 
     loop {
 
-      let elapsed = start_time.elapsed();
+      let elapsed = self.clock.elapsed(start_time);
       if elapsed >= timeout_duration {
-        return Err(Box::new(std::io::Error::new(
-          std::io::ErrorKind::TimedOut,
-          "Timeout waiting for ROAccessReport",
-        )));
+        return Err(LlrpError::Timeout("Timeout waiting for ROAccessReport".to_string()));
       }
 
       let remaining_timeout = timeout_duration - elapsed;
 
-      match timeout(remaining_timeout, ro_report_rx.recv()).await {
+      match self.clock.timeout(remaining_timeout, ro_report_rx.recv()).await {
 
         Ok(Ok(response)) => {
-          match response.decode() {
+          match self.decode_response(&response) {
 
-            Ok(response_data) => {
+            Ok(mut response_data) => {
+              if !keep(&mut response_data) {
+                continue;
+              }
               response_callback(response_data).await;
               break;
             }
 
             Err(e) => {
-              return Err(Box::new(e));
+              return Err(e.into());
             }
           }
         }
@@ -435,17 +2856,11 @@ impl LlrpClient {
         }
 
         Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) => {
-          return Err(Box::new(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "ROAccessReport channel closed"
-          )));
+          return Err(LlrpError::ConnectionClosed("ROAccessReport channel closed".to_string()));
         }
 
         Err(_) => {
-          return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::TimedOut,
-            "Timeout waiting for ROAccessReport"
-          )));
+          return Err(LlrpError::Timeout("Timeout waiting for ROAccessReport".to_string()));
         }
       }
     }
@@ -453,6 +2868,98 @@ impl LlrpClient {
     Ok(())
   }
 
+  /// Rejects operations that would modify reader configuration or ROSpecs
+  /// while the client is attached in monitor mode.
+  fn ensure_not_monitor_mode(
+    &self,
+    operation: &str
+  ) -> Result<(), LlrpError> {
+
+    if self.config.monitor_mode {
+      return Err(LlrpError::Unsupported(
+        format!("{} is not permitted in monitor mode", operation)
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Rejects the periodic report triggers on a connection below LLRP 1.1, which
+  /// does not define them.
+  fn ensure_report_trigger_supported(
+    &self,
+    trigger: ROReportTrigger
+  ) -> Result<(), LlrpError> {
+
+    if trigger.is_periodic() && self.protocol_version != LlrpVersion::V1_1 {
+      return Err(LlrpError::Unsupported(format!("ROReportTrigger {:?} requires LLRP 1.1, but the connection uses {:?}", trigger, self.protocol_version)));
+    }
+
+    Ok(())
+  }
+
+  pub fn subscribe_ro_reports(
+    &self
+  ) -> broadcast::Receiver<LlrpResponse> {
+    self.ro_report_tx.subscribe()
+  }
+
+  /// Streams the tags of every ROAccessReport from now on, e.g. for continuous
+  /// inventory without re-arming `await_ro_access_report` for each report.
+  pub fn tag_report_stream(
+    &self
+  ) -> TagReportStream {
+    TagReportStream::new(self.ro_report_tx.subscribe())
+  }
+
+  /// Subscribes to the READER_EVENT_NOTIFICATIONs sent by the reader, e.g. the
+  /// ROSpecEvents marking the start and end of each ROSpec.
+  pub fn subscribe_reader_events(
+    &self
+  ) -> broadcast::Receiver<LlrpResponse> {
+    self.reader_event_tx.subscribe()
+  }
+
+  /// Subscribes to the decoded reader events, e.g. antennas being connected or
+  /// disconnected, GPI changes, ROSpec starts and ends, and reader exceptions.
+  pub fn subscribe_events(
+    &self
+  ) -> broadcast::Receiver<ReaderEventNotificationData> {
+    self.event_tx.subscribe()
+  }
+
+  /// Subscribes to the level changes of enabled GPI ports, e.g. a light barrier
+  /// or door contact wired to the reader.
+  pub fn subscribe_gpi_events(
+    &self
+  ) -> broadcast::Receiver<GPIEvent> {
+    self.gpi_event_tx.subscribe()
+  }
+
+  /// Subscribes to the KEEPALIVEs sent by readers configured with a KeepaliveSpec.
+  /// The receive loop acknowledges them itself, whether or not anyone subscribes.
+  pub fn subscribe_reader_keepalives(
+    &self
+  ) -> broadcast::Receiver<LlrpResponse> {
+    self.keepalive_tx.subscribe()
+  }
+
+  fn decode_response(
+    &self,
+    response: &LlrpResponse
+  ) -> io::Result<LlrpResponseData> {
+
+    let response_data = log_context::sync_scope(self.config.reader_id(), || response.decode())?;
+
+    match &response_data {
+      LlrpResponseData::ReaderCapabilities(parameters) => self.parameters.record_capabilities(parameters),
+      LlrpResponseData::ReaderConfig(parameters) => self.parameters.record_reader_config(parameters),
+      _ => {}
+    }
+
+    Ok(response_data)
+  }
+
   fn log_response_acknowledgment(
     &mut self, 
     expected_response_type : LlrpMessageType, 
@@ -464,11 +2971,119 @@ impl LlrpClient {
     }
   }
 
+  /// Records the temperature and utilization reported in the Custom parameters of
+  /// a reader event or configuration, checking the temperature against the alerts.
+  fn sample_telemetry(
+    targets  : &ReceiveTargets,
+    response : &LlrpResponse
+  ) {
+
+    let Some(sample) = TelemetrySample::from_custom_parameters(&response.custom_parameters(), response.received_at.utc_us / 1000) else {
+      return;
+    };
+
+    if let Some(temperature_c) = sample.temperature_c {
+      targets.alerts.record_temperature(temperature_c);
+    }
+
+    targets.telemetry.record(sample);
+  }
+
+  /// Compares a reader timestamp with the host time its message was received at,
+  /// and sets the reader clock when `clock_drift.resync` allows. The clock is set
+  /// from the receive loop, outside the request sequence, so the CustomMessage uses
+  /// message ID 0 and its reply is not awaited.
+  async fn sample_clock_drift(
+    writer        : &Arc<Mutex<WriteHalf<LlrpStream>>>,
+    targets       : &ReceiveTargets,
+    version       : u8,
+    reader_utc_us : u64,
+    host_utc_us   : i64
+  ) -> io::Result<()> {
+
+    let drift_ms = targets.clock_drift.record(reader_utc_us, host_utc_us);
+    debug!("Reader clock drift: {} ms", drift_ms);
+    targets.alerts.record_clock_drift(drift_ms);
+
+    let Some(drift_config) = &targets.clock_drift_config else {
+      return Ok(());
+    };
+
+    let Some(resync) = &drift_config.resync else {
+      return Ok(());
+    };
+
+    let interval = Duration::from_secs(drift_config.sample_interval_secs);
+    if drift_ms.unsigned_abs() <= resync.threshold_ms || !targets.clock_drift.resync_due(targets.clock.now(), interval) {
+      return Ok(());
+    }
+
+    info!("Reader clock is {} ms off, setting it to host time", drift_ms);
+
+    let mut message = LlrpMessage::new_set_reader_clock(0, resync.vendor_id, resync.message_subtype, targets.clock.utc_now_us() as u64);
+    message.version = version;
+
+    let frame = message.encode();
+    observe_frame(&targets.frame_observer, FrameDirection::Sent, &frame);
+    targets.protocol_counters.record_sent(message.message_type);
+    write_frame(&mut *writer.lock().await, &targets.journal, &frame).await?;
+    targets.clock_drift.record_resync();
+
+    Ok(())
+  }
+
+  /// Sends the client's own KEEPALIVEs when `liveness.keepalive_interval` is set,
+  /// and returns once the reader has been silent for `settings.dead_after`, after
+  /// closing the connection. Like the reader clock resync, the KEEPALIVEs are sent
+  /// outside the request sequence, with message ID 0, and their answers are taken
+  /// by the receive loop.
+  async fn watch_liveness(
+    writer   : &Arc<Mutex<WriteHalf<LlrpStream>>>,
+    targets  : &ReceiveTargets,
+    settings : LivenessSettings
+  ) -> LlrpError {
+
+    loop {
+
+      targets.clock.sleep(settings.check_interval).await;
+      let now = targets.clock.now();
+
+      let silent_for = targets.liveness.silent_for(now);
+      if silent_for >= settings.dead_after {
+        warn!("No message from the reader for {} ms, closing the connection as dead", silent_for.as_millis());
+        // The write half may be held by a write the dead peer never drains.
+        let _ = targets.clock.timeout(settings.check_interval, async {
+          writer.lock().await.shutdown().await
+        }).await;
+        return LlrpError::ConnectionClosed(format!("No message from the reader for {} ms", silent_for.as_millis()));
+      }
+
+      let Some(probe_interval) = settings.probe_interval else {
+        continue;
+      };
+
+      if targets.liveness.probe_due(now, probe_interval) {
+
+        let mut message = LlrpMessage::new(LlrpMessageType::Keepalive, LIVENESS_PROBE_ID, vec![]);
+        message.version = targets.liveness.version();
+
+        let frame = message.encode();
+        observe_frame(&targets.frame_observer, FrameDirection::Sent, &frame);
+        targets.protocol_counters.record_sent(message.message_type);
+        if let Err(e) = write_frame(&mut *writer.lock().await, &targets.journal, &frame).await {
+          return e.into();
+        }
+
+        targets.liveness.record_probe(now);
+      }
+    }
+  }
+
   async fn receive_loop(
-    reader            : Arc<Mutex<ReadHalf<TcpStream>>>,
-    message_tx        : broadcast::Sender<LlrpResponse>,
-    ro_report_tx      : broadcast::Sender<LlrpResponse>
-  ) -> Result<(), Box<dyn Error>> {
+    reader  : Arc<Mutex<ReadHalf<LlrpStream>>>,
+    writer  : Arc<Mutex<WriteHalf<LlrpStream>>>,
+    targets : ReceiveTargets
+  ) -> Result<(), LlrpError> {
     
     let mut buf = BytesMut::with_capacity(1024);
 
@@ -477,62 +3092,120 @@ impl LlrpClient {
 
         let mut reader = reader.lock().await;
         
-        while buf.len() < 10 {
+        while buf.len() < LLRP_HEADER_LENGTH {
           let n = reader.read_buf(&mut buf).await?;
           if n == 0 {
-            return Err(Box::new(io::Error::new(
-              io::ErrorKind::UnexpectedEof,
-              "Connected closed"
-            )));
+            return Err(LlrpError::ConnectionClosed("Connected closed".to_string()));
           }
         }
       }
   
-      let mut header_buf = buf.clone();
-      let version_type = header_buf.get_u16();
-      let _version = (version_type >> 10) & 0x7;
-      let _message_type = version_type & 0x3FF;
-      let message_length = header_buf.get_u32();
-      let message_id = header_buf.get_u32();
-  
-      if message_length < 10 {
-        return Err(Box::new(io::Error::new(
-          io::ErrorKind::InvalidData,
-          "Invalid message length in header"
-        )));
-      }
+      let header = LlrpHeader::decode(&buf)?;
+      check_message_length(&header, targets.max_message_length)?;
+
+      // Reserve the whole frame at once so large reports are not read through
+      // repeated small regrowths of the buffer.
+      buf.reserve((header.message_length as usize).saturating_sub(buf.len()));
   
-      while buf.len() < message_length as usize {
+      while buf.len() < header.message_length as usize {
 
         let mut reader = reader.lock().await;
         
         let n = reader.read_buf(&mut buf).await?;
         if n == 0 {
-          return Err(Box::new(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "Connection closed"
-          )));
+          return Err(LlrpError::ConnectionClosed("Connection closed".to_string()));
         }
       }
 
+      observe_frame(&targets.frame_observer, FrameDirection::Received, &buf[..header.message_length as usize]);
+      let journal_frame = targets.journal.as_ref().map(|_| buf[..header.message_length as usize].to_vec());
+
       let llrp_message = LlrpMessage::decode(&mut buf)?;
-      let llrp_response = LlrpResponse::from_message(llrp_message);
+      let version = llrp_message.version;
+      let mut llrp_response = LlrpResponse::from_message(llrp_message);
+      llrp_response.received_at.utc_us = targets.clock.utc_now_us();
+      targets.liveness.record_message(targets.clock.now(), version);
+
+      if let (Some(journal), Some(frame)) = (&targets.journal, &journal_frame) {
+        journal.record_received(frame, &llrp_response);
+      }
+
+      targets.protocol_counters.record_received(llrp_response.message_type);
 
       match llrp_response.message_type {
 
         LlrpMessageType::ROAccessReport => {
-          let _ = ro_report_tx.send(llrp_response);
+
+          targets.alerts.record_tag_report();
+          targets.ro_report_tx.send(llrp_response, targets.clock.as_ref()).await;
         }
 
         LlrpMessageType::ReaderEventNotification => {
-          continue;
+
+          let event = llrp_response.reader_event_data();
+
+          if let Some(reader_utc_us) = event.as_ref().and_then(|event| event.timestamp_us) {
+            LlrpClient::sample_clock_drift(&writer, &targets, version, reader_utc_us, llrp_response.received_at.utc_us).await?;
+          }
+
+          if let Some(event) = event {
+            if let Some(gpi_event) = event.gpi_event.clone() {
+              targets.gpi_event_tx.send(gpi_event, targets.clock.as_ref()).await;
+            }
+            targets.event_tx.send(event, targets.clock.as_ref()).await;
+          }
+
+          LlrpClient::sample_telemetry(&targets, &llrp_response);
+          targets.reader_event_tx.send(llrp_response, targets.clock.as_ref()).await;
+        }
+
+        LlrpMessageType::GetReaderConfigResponse => {
+          LlrpClient::sample_telemetry(&targets, &llrp_response);
+          LlrpClient::complete_request(&targets, llrp_response);
+        }
+
+        // Readers with a KeepaliveSpec close the connection when KEEPALIVEs go
+        // unacknowledged.
+        LlrpMessageType::Keepalive => {
+
+          let mut ack = LlrpMessage::new(LlrpMessageType::KeepaliveAck, llrp_response.message_id, vec![]);
+          ack.version = version;
+
+          let frame = ack.encode();
+          observe_frame(&targets.frame_observer, FrameDirection::Sent, &frame);
+          targets.protocol_counters.record_sent(ack.message_type);
+          write_frame(&mut *writer.lock().await, &targets.journal, &frame).await?;
+
+          debug!("Acknowledged reader KEEPALIVE (ID {})", llrp_response.message_id);
+          targets.keepalive_tx.send(llrp_response, targets.clock.as_ref()).await;
+        }
+
+        LlrpMessageType::KeepaliveAck if llrp_response.message_id == LIVENESS_PROBE_ID => {
+          match targets.liveness.probe_answered(targets.clock.now()) {
+            Some(rtt) => targets.alerts.record_keepalive_rtt(rtt),
+            None => LlrpClient::complete_request(&targets, llrp_response)
+          }
         }
 
         _ => {
-          let _ = message_tx.send(llrp_response);
+          LlrpClient::complete_request(&targets, llrp_response);
         }
       }
     }
   }
 
+  fn complete_request(
+    targets  : &ReceiveTargets,
+    response : LlrpResponse
+  ) {
+    // Readers echo the request's message ID, so a response nobody waits for belongs
+    // to a request that already timed out.
+    if let Err(response) = targets.pending.complete(response) {
+      warn!(
+        "Ignoring {:?} with message ID {}, no request is waiting for it",
+        response.message_type, response.message_id
+      );
+    }
+  }
+
 }
\ No newline at end of file
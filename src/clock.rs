@@ -0,0 +1,155 @@
+//! Time source for the client's timeout, retry and keepalive logic, and for the
+//! UTC time it sends to and compares with the reader.
+//!
+//! `TokioClock` follows Tokio's clock, so tests running with
+//! `tokio::time::pause` advance it in virtual time instead of sleeping.
+//! `ReplayClock` also fixes the UTC time, for replaying recorded sessions.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::time::Instant;
+
+#[async_trait]
+pub trait Clock: Send + Sync {
+
+  fn now(&self) -> Instant;
+
+  async fn sleep(&self, duration: Duration);
+
+  /// UTC time in microseconds since the Unix epoch.
+  fn utc_now_us(&self) -> i64 {
+    Utc::now().timestamp_micros()
+  }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The clock of the current Tokio runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  async fn sleep(&self, duration: Duration) {
+    tokio::time::sleep(duration).await
+  }
+}
+
+/// Tokio's clock with a UTC time that changes only when set, so the timestamps a
+/// client sends and stamps received messages with do not depend on when it runs.
+#[derive(Debug, Default)]
+pub struct ReplayClock {
+  utc_us: AtomicI64
+}
+
+impl ReplayClock {
+
+  pub fn new(
+    utc_us: i64
+  ) -> Self {
+    ReplayClock { utc_us: AtomicI64::new(utc_us) }
+  }
+
+  pub fn set_utc_us(
+    &self,
+    utc_us: i64
+  ) {
+    self.utc_us.store(utc_us, Ordering::SeqCst);
+  }
+}
+
+#[async_trait]
+impl Clock for ReplayClock {
+
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  async fn sleep(&self, duration: Duration) {
+    tokio::time::sleep(duration).await
+  }
+
+  fn utc_now_us(&self) -> i64 {
+    self.utc_us.load(Ordering::SeqCst)
+  }
+}
+
+/// Returned by `Clock::timeout` when the deadline passed first.
+#[derive(Debug)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    write!(f, "deadline has elapsed")
+  }
+}
+
+impl std::error::Error for Elapsed {}
+
+impl dyn Clock + '_ {
+
+  /// Time elapsed on this clock since `since`.
+  pub fn elapsed(
+    &self,
+    since: Instant
+  ) -> Duration {
+    self.now().saturating_duration_since(since)
+  }
+
+  /// Runs `future` until it completes or `duration` passes on this clock.
+  pub async fn timeout<F: Future>(
+    &self,
+    duration : Duration,
+    future   : F
+  ) -> Result<F::Output, Elapsed> {
+    tokio::select! {
+      biased;
+      output = future => Ok(output),
+      _ = self.sleep(duration) => Err(Elapsed)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  #[tokio::test(start_paused = true)]
+  async fn timeout_elapses_in_virtual_time() {
+
+    let clock: SharedClock = Arc::new(TokioClock);
+    let start_time = clock.now();
+
+    let result = clock.timeout(Duration::from_secs(30), std::future::pending::<()>()).await;
+
+    assert!(result.is_err());
+    assert_eq!(clock.elapsed(start_time), Duration::from_secs(30));
+  }
+
+  #[tokio::test(start_paused = true)]
+  async fn timeout_returns_output_completed_before_the_deadline() {
+
+    let clock: SharedClock = Arc::new(TokioClock);
+
+    let result = clock.timeout(Duration::from_secs(5), async {
+      tokio::time::sleep(Duration::from_secs(4)).await;
+      42
+    }).await;
+
+    assert_eq!(result.unwrap(), 42);
+  }
+}
\ No newline at end of file
@@ -1,42 +1,1636 @@
+use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use serde_json;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{self, Value};
+
+use crate::duration::{millis, option_millis, option_secs, secs};
+use crate::gpio::{GpiPort, GpoPort, PinState};
+use crate::llrp::{decode_hex, LLRP_HEADER_LENGTH};
+use crate::region::Region;
+use crate::secrets::{with_resolved_secrets, Secret};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
-  pub host                     : String,
-  pub log_level                : String,
-  pub log_response_ack         : bool,
-  pub response_timeout         : u64,
-  pub reader_config            : ReaderConfig,
-  pub rospec                   : ROSpecConfig
+  pub host                         : String,
+  #[serde(default)]
+  pub reader_id                    : Option<String>,
+  pub log_level                    : String,
+  #[serde(default = "default_log_file")]
+  pub log_file                     : PathBuf,
+  #[serde(default)]
+  pub per_reader_log_files         : bool,
+  pub log_response_ack             : bool,
+  #[serde(with = "millis")]
+  pub response_timeout             : u64,
+  #[serde(default)]
+  pub get_report_on_stop           : bool,
+  #[serde(default = "default_final_report_timeout", with = "millis")]
+  pub final_report_timeout         : u64,
+  #[serde(default)]
+  pub monitor_mode                 : bool,
+  #[serde(default = "default_connection_history_size")]
+  pub connection_history_size      : usize,
+  #[serde(default = "default_reconnect_attempts")]
+  pub reconnect_attempts           : u32,
+  #[serde(default = "default_reconnect_interval", with = "millis")]
+  pub reconnect_interval           : u64,
+  #[serde(default)]
+  pub duplicate_connection         : DuplicateConnectionAction,
+  #[serde(default = "default_duplicate_connection_timeout", with = "millis")]
+  pub duplicate_connection_timeout : u64,
+  #[serde(default)]
+  pub allow_shared_connection      : bool,
+  #[serde(default = "default_max_message_length")]
+  pub max_message_length           : u32,
+  #[serde(default)]
+  pub negotiate_protocol_version   : bool,
+  #[serde(default)]
+  pub alerts                       : AlertRules,
+  #[serde(default)]
+  pub channels                     : ChannelConfig,
+  #[serde(default)]
+  pub sinks                        : Vec<SinkConfig>,
+  #[serde(default)]
+  pub power_schedules              : Vec<PowerSchedule>,
+  #[serde(default)]
+  pub report_tuning                : Option<ReportTuning>,
+  #[serde(default)]
+  pub spec_chain                   : Option<SpecChainConfig>,
+  #[serde(default)]
+  pub region_presets               : Vec<RegionPreset>,
+  #[serde(default)]
+  pub startup_actions              : Vec<StartupAction>,
+  #[serde(default)]
+  pub clock_drift                  : Option<ClockDriftConfig>,
+  #[serde(default)]
+  pub liveness                     : Option<LivenessConfig>,
+  #[serde(default)]
+  pub enable_schedule              : Option<EnableSchedule>,
+  #[serde(default)]
+  pub impinj                       : Option<ImpinjConfig>,
+  #[serde(default)]
+  pub large_population             : Option<LargePopulationConfig>,
+  #[serde(default)]
+  pub proxy                        : Option<ProxyConfig>,
+  #[serde(default)]
+  pub tls                          : Option<TlsConfig>,
+  #[serde(default)]
+  pub listen                       : Option<ListenConfig>,
+  #[serde(default)]
+  pub groups                       : Vec<String>,
+  #[serde(default)]
+  pub journal                      : Option<JournalConfig>,
+  pub reader_config                : ReaderConfig,
+  pub rospec                       : ROSpecConfig
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Config {
+
+  /// Identifier used to tag log output for this reader, defaulting to the host address.
+  pub fn reader_id(
+    &self
+  ) -> String {
+    self.reader_id.clone().unwrap_or_else(|| self.host.clone())
+  }
+
+  /// The reader's keepalive interval, from a periodic `reader_config.keepalive_spec`
+  /// the client applies with a `SetConfig` startup action. A spec the client never
+  /// sends tells nothing about when the reader sends KEEPALIVEs.
+  pub fn applied_keepalive_interval(
+    &self
+  ) -> Option<u32> {
+    match self.reader_config.keepalive_spec {
+      Some(KeepaliveSpecConfig { trigger: KeepaliveTrigger::Periodic, interval })
+        if interval > 0 && !self.monitor_mode && self.startup_actions.contains(&StartupAction::SetConfig) => Some(interval),
+      _ => None
+    }
+  }
+
+  /// Checks settings that deserialize fine but cannot be used, e.g. an antenna
+  /// count that does not match the antenna list the ROSpec is encoded from.
+  pub fn validate(
+    &self
+  ) -> io::Result<()> {
+
+    let invalid = |message: String| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+
+    if self.host.rsplit_once(':').and_then(|(_, port)| port.parse::<u16>().ok()).is_none() {
+      return invalid(format!("host {:?} must be of the form <address>:<port>", self.host));
+    }
+
+    if (self.max_message_length as usize) < LLRP_HEADER_LENGTH {
+      return invalid(format!("max_message_length must be at least {} bytes", LLRP_HEADER_LENGTH));
+    }
+
+    if self.response_timeout == 0 {
+      return invalid("response_timeout must be greater than 0".to_string());
+    }
+
+    if self.rospec.antenna_count as usize != self.rospec.antennas.len() {
+      return invalid(format!(
+        "rospec.antenna_count is {} but rospec.antennas lists {} antennas",
+        self.rospec.antenna_count, self.rospec.antennas.len()
+      ));
+    }
+
+    if self.rospec.ROSpecStartTriggerType == 2 && self.rospec.periodic_trigger.is_none() {
+      return invalid("rospec.ROSpecStartTriggerType 2 (periodic) requires rospec.periodic_trigger".to_string());
+    }
+
+    let duration_triggers = [
+      ("rospec_stop_duration_ms", "ROSpecStopTriggerType", self.rospec.ROSpecStopTriggerType, self.rospec.rospec_stop_duration_ms),
+      ("aispec_stop_duration_ms", "AISpecStopTriggerType", self.rospec.AISpecStopTriggerType, self.rospec.aispec_stop_duration_ms)
+    ];
+
+    for (name, trigger_type, value, duration_ms) in duration_triggers {
+      if value == 1 && duration_ms.unwrap_or(0) == 0 {
+        return invalid(format!("rospec.{} 1 (duration) requires rospec.{} greater than 0", trigger_type, name));
+      }
+    }
+
+    // The AISpecStopTrigger is encoded without its GPI and tag-observation sub-parameters.
+    if self.rospec.AISpecStopTriggerType > 1 {
+      return invalid(format!(
+        "rospec.AISpecStopTriggerType {} is not supported; use 0 (null) or 1 (duration)",
+        self.rospec.AISpecStopTriggerType
+      ));
+    }
+
+    let gpi_triggers = [
+      ("start_gpi_trigger", "ROSpecStartTriggerType 3", self.rospec.ROSpecStartTriggerType == 3, &self.rospec.start_gpi_trigger),
+      ("stop_gpi_trigger", "ROSpecStopTriggerType 2", self.rospec.ROSpecStopTriggerType == 2, &self.rospec.stop_gpi_trigger)
+    ];
+
+    for (name, trigger_type, required, trigger) in gpi_triggers {
+      match trigger {
+        None if required => {
+          return invalid(format!("rospec.{} (GPI) requires rospec.{}", trigger_type, name));
+        }
+        Some(trigger) if trigger.event == PinState::Unknown => {
+          return invalid(format!("rospec.{}.event must be low or high", name));
+        }
+        Some(trigger) if self.reader_config.gpi_ports.iter().any(|gpi| gpi.port == trigger.port && !gpi.enabled) => {
+          return invalid(format!("rospec.{} watches {}, which reader_config.gpi_ports disables", name, trigger.port));
+        }
+        _ => {}
+      }
+    }
+
+    if let Err(e) = self.rospec.ROReportTriggerType.check_n(self.rospec.ROReportTrigger_N) {
+      return invalid(format!("rospec: {}", e));
+    }
+
+    if self.report_tuning.is_some() && !self.rospec.ROReportTriggerType.counts_tags() {
+      return invalid(format!("report_tuning adjusts a tag count, but rospec.ROReportTriggerType is {:?}", self.rospec.ROReportTriggerType));
+    }
+
+    if let Some(tuning) = &self.report_tuning {
+      if tuning.min_n == 0 || tuning.min_n > tuning.max_n {
+        return invalid(format!("report_tuning bounds {}..{} are invalid", tuning.min_n, tuning.max_n));
+      }
+    }
+
+    if let Some(chain) = &self.spec_chain {
+      if chain.rospec_ids.is_empty() || chain.rospec_ids.contains(&0) {
+        return invalid("spec_chain.rospec_ids must list at least one ROSpec and may not contain 0".to_string());
+      }
+    }
+
+    if self.monitor_mode && !self.startup_actions.is_empty() {
+      return invalid("startup_actions modify the reader and cannot be used in monitor mode".to_string());
+    }
+
+    if self.monitor_mode && self.clock_drift.as_ref().is_some_and(|drift| drift.resync.is_some()) {
+      return invalid("clock_drift.resync sets the reader clock and cannot be used in monitor mode".to_string());
+    }
+
+    if let Some(schedule) = &self.enable_schedule {
+
+      if self.monitor_mode {
+        return invalid("enable_schedule enables and disables the ROSpec and cannot be used in monitor mode".to_string());
+      }
+
+      if schedule.windows.is_empty() || schedule.check_interval == 0 {
+        return invalid("enable_schedule must list at least one window and have a check_interval greater than 0".to_string());
+      }
+
+      for window in &schedule.windows {
+
+        if window.bounds().is_none_or(|(start, end)| start == end) {
+          return invalid(format!("enable_schedule window {}-{} must be two different \"HH:MM\" times", window.start, window.end));
+        }
+
+        if let Some(antenna_id) = window.antennas.iter().find(|antenna_id| !self.rospec.antennas.contains(antenna_id)) {
+          return invalid(format!("enable_schedule window {}-{} lists antenna {} which rospec.antennas does not", window.start, window.end, antenna_id));
+        }
+      }
+    }
+
+    if self.impinj.is_some() && !cfg!(feature = "impinj") {
+      return invalid("impinj requires the \"impinj\" feature, which this build does not include".to_string());
+    }
+
+    if let Some(listen) = &self.listen {
+      if listen.address.rsplit_once(':').and_then(|(_, port)| port.parse::<u16>().ok()).is_none() {
+        return invalid(format!("listen.address {:?} must be of the form <address>:<port>", listen.address));
+      }
+      if listen.accept_timeout == 0 {
+        return invalid("listen.accept_timeout must be greater than 0".to_string());
+      }
+      if self.proxy.is_some() {
+        return invalid("listen cannot be combined with proxy, the reader opens the connection".to_string());
+      }
+      if self.tls.as_ref().is_some_and(|tls| tls.enabled) {
+        return invalid("listen cannot be combined with tls".to_string());
+      }
+    }
+
+    if let Some(tls) = self.tls.as_ref().filter(|tls| tls.enabled) {
+      if !cfg!(feature = "tls") {
+        return invalid("tls requires the \"tls\" feature, which this build does not include".to_string());
+      }
+      if tls.ca_path.is_none() {
+        return invalid("tls.ca_path must be set to verify the reader's certificate".to_string());
+      }
+      if tls.client_cert.is_some() != tls.client_key.is_some() {
+        return invalid("tls.client_cert and tls.client_key must be set together".to_string());
+      }
+    }
+
+    if let Some(Err(e)) = self.rospec.inventory_command.as_ref().map(C1G2InventoryCommandConfig::validate) {
+      return invalid(format!("rospec.inventory_command.{}", e));
+    }
+
+    if let Some(large_population) = &self.large_population {
+      if large_population.memory_limit == 0 || large_population.milestone_interval == 0 {
+        return invalid("large_population.memory_limit and milestone_interval must be greater than 0".to_string());
+      }
+    }
+
+    if let Some(journal) = &self.journal {
+      if journal.max_file_bytes == 0 {
+        return invalid("journal.max_file_bytes must be greater than 0".to_string());
+      }
+    }
+
+    if let Some(proxy) = &self.proxy {
+      if proxy.host.is_empty() || proxy.port == 0 {
+        return invalid("proxy.host must be set and proxy.port greater than 0".to_string());
+      }
+      if proxy.credentials.as_ref().is_some_and(|credentials| credentials.username.is_empty() || credentials.username.len() > 255) {
+        return invalid("proxy.credentials.username must be 1 to 255 bytes".to_string());
+      }
+    }
+
+    let mut regions = HashSet::new();
+    if let Some(preset) = self.region_presets.iter().find(|preset| !regions.insert(preset.region)) {
+      return invalid(format!("region_presets lists region {} more than once", preset.region));
+    }
+
+    if let Some(KeepaliveSpecConfig { trigger: KeepaliveTrigger::Periodic, interval: 0 }) = self.reader_config.keepalive_spec {
+      return invalid("reader_config.keepalive_spec.interval must be greater than 0 for a periodic trigger".to_string());
+    }
+
+    if self.channels.capacity == 0 {
+      return invalid("channels.capacity must be greater than 0".to_string());
+    }
+
+    if self.channels.overflow == OverflowStrategy::Block && self.channels.block_timeout == 0 {
+      return invalid("channels.block_timeout must be greater than 0 for the block overflow strategy".to_string());
+    }
+
+    if self.channels.overflow == OverflowStrategy::Block && self.liveness.is_some() {
+      return invalid("channels.overflow \"block\" pauses the receive loop and cannot be used with liveness".to_string());
+    }
+
+    if let Some(liveness) = &self.liveness {
+      if liveness.missed_keepalives == 0 {
+        return invalid("liveness.missed_keepalives must be greater than 0".to_string());
+      }
+      if liveness.keepalive_interval == Some(0) {
+        return invalid("liveness.keepalive_interval must be greater than 0".to_string());
+      }
+      if self.applied_keepalive_interval().is_none() && liveness.keepalive_interval.is_none() {
+        return invalid("liveness requires liveness.keepalive_interval, or a periodic reader_config.keepalive_spec applied by a set_config startup action".to_string());
+      }
+    }
+
+    if let Some(output) = self.reader_config.gpo_outputs.iter().find(|output| output.state == PinState::Unknown) {
+      return invalid(format!("reader_config.gpo_outputs {} must be driven low or high", output.port));
+    }
+
+    Ok(())
+  }
+}
+
+/// Returns the dotted paths of the settings that differ between two
+/// configurations, e.g. `["reader_config.tx_power_table_index", "response_timeout"]`.
+/// Arrays are compared as a whole, and secrets by their resolved values, so a
+/// rotated credential counts as a change and a moved but equal one does not.
+pub fn diff_configs(
+  old : &Config,
+  new : &Config
+) -> Vec<String> {
+
+  fn diff_values(
+    path    : &str,
+    old     : &Value,
+    new     : &Value,
+    changed : &mut Vec<String>
+  ) {
+    match (old, new) {
+
+      (Value::Object(old), Value::Object(new)) => {
+        let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        for key in keys {
+          let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+          diff_values(&path, old.get(key).unwrap_or(&Value::Null), new.get(key).unwrap_or(&Value::Null), changed);
+        }
+      }
+
+      (old, new) if old != new => changed.push(path.to_string()),
+
+      _ => {}
+    }
+  }
+
+  let mut changed = Vec::new();
+
+  let values = with_resolved_secrets(|| (serde_json::to_value(old), serde_json::to_value(new)));
+
+  if let (Ok(old), Ok(new)) = values {
+    diff_values("", &old, &new, &mut changed);
+  }
+
+  changed
+}
+
+fn default_log_file() -> PathBuf { PathBuf::from("system.log") }
+fn default_final_report_timeout() -> u64 { 500 }
+fn default_connection_history_size() -> usize { 64 }
+fn default_reconnect_attempts() -> u32 { 3 }
+fn default_reconnect_interval() -> u64 { 1000 }
+fn default_duplicate_connection_timeout() -> u64 { 60000 }
+fn default_max_message_length() -> u32 { 16 * 1024 * 1024 }
+fn default_tuning_interval() -> u64 { 5000 }
+fn default_tuning_hysteresis() -> f64 { 0.25 }
+fn default_drift_sample_interval() -> u64 { 60 }
+fn default_enable_check_interval() -> u64 { 30 }
+fn default_missed_keepalives() -> u32 { 3 }
+fn default_accept_timeout() -> u64 { 60000 }
+fn default_channel_capacity() -> usize { 100 }
+fn default_block_timeout() -> u64 { 1000 }
+fn default_population_memory_limit() -> usize { 100_000 }
+fn default_population_milestone_interval() -> u64 { 10_000 }
+fn default_journal_max_file_bytes() -> u64 { 64 * 1024 * 1024 }
+fn default_journal_max_files() -> u32 { 10 }
+
+/// What to do when the reader refuses a connection because another client is
+/// already connected to it.
+///
+/// - `Fail`: Fail the connection attempt.
+/// - `Retry`: Reconnect with exponential backoff, starting at `reconnect_interval`,
+///   until the other client releases the reader or `duplicate_connection_timeout`
+///   milliseconds have passed. LLRP has no standard message for taking over another
+///   client's connection, so this is the portable way to take over a reader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateConnectionAction {
+  #[default]
+  Fail,
+  Retry
+}
+
+/// A step of `startup_actions`, run in order after the client connects and after
+/// each reconnect.
+///
+/// - `FactoryReset`: Reset the reader to its factory settings, deleting its ROSpecs
+///   and AccessSpecs.
+/// - `SetConfig`: Send `reader_config` with SET_READER_CONFIG.
+/// - `AddRospec`: Add the configured `rospec`.
+/// - `Enable`: Enable the configured `rospec`.
+/// - `Start`: Start the configured `rospec`.
+///
+/// A reader keeps its ROSpecs across a dropped connection, so a sequence that adds
+/// the ROSpec should begin with `FactoryReset` to be repeatable on reconnect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupAction {
+  FactoryReset,
+  SetConfig,
+  AddRospec,
+  Enable,
+  Start
+}
+
+/// Thresholds for reader health alerts. A rule is disabled when its value is absent.
+///
+/// Fields:
+/// - `no_tag_reads_secs`: Alert when no tags are reported for this many seconds while an ROSpec is active.
+/// - `max_reconnects_per_hour`: Alert when the client reconnects more often than this within an hour.
+/// - `max_keepalive_rtt_ms`: Alert when a KEEPALIVE round trip takes longer than this.
+/// - `max_clock_drift_ms`: Alert when the reader clock is further than this from the host clock.
+/// - `max_temperature_c`: Alert when the reader reports a temperature above this, in
+///   degrees Celsius; see `TelemetrySample` for how temperatures are reported.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AlertRules {
+  #[serde(default, with = "option_secs")]
+  pub no_tag_reads_secs       : Option<u64>,
+  #[serde(default)]
+  pub max_reconnects_per_hour : Option<u32>,
+  #[serde(default, with = "option_millis")]
+  pub max_keepalive_rtt_ms    : Option<u64>,
+  #[serde(default, with = "option_millis")]
+  pub max_clock_drift_ms      : Option<u64>,
+  #[serde(default)]
+  pub max_temperature_c       : Option<f64>
+}
+
+/// Compares the reader's UTC clock with the host's. The UTCTimestamp of every
+/// reader event is sampled, with or without this section; tag report timestamps
+/// are not, as they tell when a tag was seen rather than when the report was sent.
+///
+/// Fields:
+/// - `sample_interval_secs`: Minimum time between two resyncs of the reader clock (default - 60).
+/// - `resync`: Sets the reader clock to host time when drift grows too large (default - Never).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClockDriftConfig {
+  #[serde(default = "default_drift_sample_interval", with = "secs")]
+  pub sample_interval_secs : u64,
+  #[serde(default)]
+  pub resync               : Option<ClockResync>
+}
+
+/// Declares the connection dead once the reader has sent nothing, not even a
+/// KEEPALIVE, for `missed_keepalives` keepalive intervals. The interval is that of
+/// a periodic `reader_config.keepalive_spec` the client applies with a `set_config`
+/// startup action, else `keepalive_interval`. A dead
+/// connection is closed and recorded as a disconnect, so its requests fail and the
+/// application can reconnect.
+///
+/// Fields:
+/// - `missed_keepalives`: Intervals without a message after which the connection is dead (default - 3).
+/// - `keepalive_interval`: Time in milliseconds between KEEPALIVEs sent by the client, whose KEEPALIVE_ACKs
+///   keep a connection without reader KEEPALIVEs alive (default - None).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LivenessConfig {
+  #[serde(default = "default_missed_keepalives")]
+  pub missed_keepalives  : u32,
+  #[serde(default, with = "option_millis")]
+  pub keepalive_interval : Option<u64>
+}
+
+/// Reads only during time-of-day windows, e.g. business hours. Outside every window
+/// the ROSpec is disabled; while windows are open it is enabled with their antennas.
+///
+/// Fields:
+/// - `windows`: The windows, in the host's local time.
+/// - `check_interval`: Time in seconds between checks of the schedule (default - 30).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnableSchedule {
+  pub windows        : Vec<EnableWindow>,
+  #[serde(default = "default_enable_check_interval", with = "secs")]
+  pub check_interval : u64
+}
+
+/// Fields:
+/// - `start` / `end`: Local time of day as `"HH:MM"`. A window ending before it starts runs past midnight.
+/// - `antennas`: Antennas read during the window (default - Every antenna in `rospec.antennas`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnableWindow {
+  pub start    : String,
+  pub end      : String,
+  #[serde(default)]
+  pub antennas : Vec<u16>
+}
+
+impl EnableWindow {
+
+  /// The parsed `start` and `end`, or `None` if either is not `"HH:MM"`.
+  pub fn bounds(
+    &self
+  ) -> Option<(NaiveTime, NaiveTime)> {
+    let start = NaiveTime::parse_from_str(&self.start, "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(&self.end, "%H:%M").ok()?;
+    Some((start, end))
+  }
+
+  pub fn contains(
+    &self,
+    time: NaiveTime
+  ) -> bool {
+    match self.bounds() {
+      Some((start, end)) if start < end => start <= time && time < end,
+      Some((start, end)) => time >= start || time < end,
+      None => false
+    }
+  }
+}
+
+/// Impinj reader extensions. With this section the client sends IMPINJ_ENABLE_EXTENSIONS
+/// after each connect, and decodes the Impinj parameters of tag reports. Monitor mode
+/// clients only decode them, relying on the controlling client to enable them.
+///
+/// Fields:
+/// - `rf_phase_angle`: Report the RF phase angle of each tag read (default - false).
+/// - `peak_rssi`: Report the peak RSSI in 1/100 dBm (default - false).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ImpinjConfig {
+  #[serde(default)]
+  pub rf_phase_angle : bool,
+  #[serde(default)]
+  pub peak_rssi      : bool
+}
+
+/// The channels delivering reports, events and reader KEEPALIVEs to subscribers,
+/// e.g. of `subscribe_ro_reports`. Each subscriber may fall `capacity` messages
+/// behind the reader before the `overflow` strategy applies; the capacity is rounded
+/// up to a power of two.
+///
+/// The `"block"` strategy waits in the receive loop, so while a subscriber is full
+/// nothing else is read from the reader either: responses are late and requests may
+/// time out. As the pause looks like a silent reader, it cannot be combined with
+/// `liveness`.
+///
+/// Fields:
+/// - `capacity`: Messages a subscriber may fall behind by (default - 100).
+/// - `overflow`: What happens to a message arriving for a full subscriber (default - `"drop_oldest"`).
+/// - `block_timeout`: Time in milliseconds the `"block"` strategy waits for room before dropping the oldest (default - 1000).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChannelConfig {
+  #[serde(default = "default_channel_capacity")]
+  pub capacity      : usize,
+  #[serde(default)]
+  pub overflow      : OverflowStrategy,
+  #[serde(default = "default_block_timeout", with = "millis")]
+  pub block_timeout : u64
+}
+
+impl Default for ChannelConfig {
+  fn default() -> Self {
+    ChannelConfig {
+      capacity      : default_channel_capacity(),
+      overflow      : OverflowStrategy::default(),
+      block_timeout : default_block_timeout()
+    }
+  }
+}
+
+/// Variants:
+/// - `DropOldest`: The subscriber loses its oldest message and sees a lag.
+/// - `DropNewest`: The new message is not delivered to any subscriber.
+/// - `Block`: Reading from the reader pauses until the subscriber makes room, up to `block_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+  #[default]
+  DropOldest,
+  DropNewest,
+  Block
+}
+
+/// Counts very large tag populations by passing on only the tags read for the first
+/// time. The EPCs seen are kept in memory up to `memory_limit`, then move to a
+/// temporary store on disk, so cycle counts of 100k+ tags use bounded memory.
+///
+/// Fields:
+/// - `memory_limit`: Unique EPCs held in memory before spilling to disk, which needs the `spill` feature (default - 100000).
+/// - `milestone_interval`: Report progress each time this many more unique tags are seen (default - 10000).
+/// - `spill_dir`: Directory of the on-disk store, removed with the count (default - the system temporary directory).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LargePopulationConfig {
+  #[serde(default = "default_population_memory_limit")]
+  pub memory_limit       : usize,
+  #[serde(default = "default_population_milestone_interval")]
+  pub milestone_interval : u64,
+  #[serde(default)]
+  pub spill_dir          : Option<PathBuf>
+}
+
+impl Default for LargePopulationConfig {
+  fn default() -> Self {
+    LargePopulationConfig {
+      memory_limit       : default_population_memory_limit(),
+      milestone_interval : default_population_milestone_interval(),
+      spill_dir          : None
+    }
+  }
+}
+
+/// An append-only journal of every message sent to and received from the reader,
+/// with timestamps and outcomes, for audit. Give each reader its own `path`.
+///
+/// Fields:
+/// - `path`: File the journal is appended to.
+/// - `format`: `"jsonl"` - One JSON object per message, `"binary"` - Raw frames with a short prefix (default - jsonl).
+/// - `max_file_bytes`: Size after which the file is rotated to `<path>.1` (default - 64 MiB).
+/// - `max_files`: Rotated files kept, `<path>.1` being the newest (default - 10).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JournalConfig {
+  pub path           : PathBuf,
+  #[serde(default)]
+  pub format         : JournalFormat,
+  #[serde(default = "default_journal_max_file_bytes")]
+  pub max_file_bytes : u64,
+  #[serde(default = "default_journal_max_files")]
+  pub max_files      : u32
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalFormat {
+  #[default]
+  Jsonl,
+  Binary
+}
+
+/// A SOCKS5 proxy the reader connection is made through, for readers on isolated
+/// networks reachable only via a jump host. The reader's `host` is resolved by the
+/// proxy, so it may name a host only the proxy's network knows.
+///
+/// Fields:
+/// - `host`: Address of the proxy.
+/// - `port`: Port of the proxy, usually 1080.
+/// - `credentials`: Username and password, if the proxy requires them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProxyConfig {
+  pub host        : String,
+  pub port        : u16,
+  #[serde(default)]
+  pub credentials : Option<ProxyCredentials>
+}
+
+/// Waits for the reader to connect instead of connecting to it, for readers set up
+/// to initiate the LLRP connection. The client listens only while connecting and
+/// reconnecting, and the reader must open with a successful ConnectionAttemptEvent.
+/// `host` still names the reader, e.g. in logs and in the one-session-per-reader check,
+/// and only connections from an address it resolves to are accepted.
+///
+/// Fields:
+/// - `address`: Local address and port to listen on, e.g. `"0.0.0.0:5084"`.
+/// - `accept_timeout`: Time in milliseconds to wait for the reader to connect (default - 60000).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ListenConfig {
+  pub address        : String,
+  #[serde(default = "default_accept_timeout", with = "millis")]
+  pub accept_timeout : u64
+}
+
+/// Encrypted LLRP, which readers accept on port 5085 rather than 5084. The TLS
+/// session is set up after any `proxy` and connect hook, on the connection they
+/// leave open.
+///
+/// Fields:
+/// - `enabled`: Whether to connect over TLS.
+/// - `ca_path`: PEM file of the CA certificates the reader's certificate is verified against.
+/// - `client_cert` / `client_key`: PEM files of the certificate chain and private key the client
+///   authenticates with, for readers requiring client certificates (default - None).
+/// - `server_name`: Name the reader's certificate is checked against (default - The address in `host`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TlsConfig {
+  #[serde(default)]
+  pub enabled     : bool,
+  #[serde(default)]
+  pub ca_path     : Option<PathBuf>,
+  #[serde(default)]
+  pub client_cert : Option<PathBuf>,
+  #[serde(default)]
+  pub client_key  : Option<PathBuf>,
+  #[serde(default)]
+  pub server_name : Option<String>
+}
+
+/// Username and password authentication (RFC 1929) with a SOCKS5 proxy. The
+/// password is a [`Secret`] reference so it can live outside the JSON file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProxyCredentials {
+  pub username : String,
+  pub password : Secret
+}
+
+/// LLRP has no standard message for setting the reader clock, so the host time is
+/// sent in the reader vendor's CUSTOM_MESSAGE: VendorIdentifier, MessageSubtype, then
+/// the UTC time in microseconds since the Unix epoch. The reader is set at most once
+/// per `sample_interval_secs`, and never by a client in monitor mode.
+///
+/// Fields:
+/// - `threshold_ms`: Drift, in either direction, above which the clock is set.
+/// - `vendor_id`: IANA Private Enterprise Number of the reader vendor.
+/// - `message_subtype`: The vendor's message subtype for setting the clock.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClockResync {
+  #[serde(with = "millis")]
+  pub threshold_ms    : u64,
+  pub vendor_id       : u32,
+  pub message_subtype : u8
+}
+
+/// Periodically cycles the transmit power of one antenna, e.g. alternating between
+/// a near-field and a far-field level on a single portal.
+///
+/// Fields:
+/// - `antenna_id`: The antenna whose power is changed.
+/// - `interval`: Time in milliseconds each power level is held.
+/// - `tx_power_table_indices`: Transmit power table indices applied in turn.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PowerSchedule {
+  pub antenna_id             : u16,
+  #[serde(with = "millis")]
+  pub interval               : u64,
+  pub tx_power_table_indices : Vec<u16>
+}
+
+/// Adjusts `ROReportTrigger_N` to the observed tag rate so reports arrive roughly
+/// every `target_latency_ms`: busy portals batch many tags per report, quiet ones
+/// report each tag promptly.
+///
+/// Fields:
+/// - `min_n` / `max_n`: Bounds for N.
+/// - `target_latency_ms`: Desired time between reports.
+/// - `evaluation_interval`: Time in milliseconds over which the tag rate is measured.
+/// - `hysteresis`: Relative change of N required before the ROSpec is re-applied.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportTuning {
+  pub min_n               : u16,
+  pub max_n               : u16,
+  #[serde(with = "millis")]
+  pub target_latency_ms   : u64,
+  #[serde(default = "default_tuning_interval", with = "millis")]
+  pub evaluation_interval : u64,
+  #[serde(default = "default_tuning_hysteresis")]
+  pub hysteresis          : f64
+}
+
+/// Runs ROSpecs back to back, starting each one when the reader reports the end
+/// of the previous one, e.g. an inventory spec followed by an RF survey spec. The
+/// specs must already be added and enabled on the reader, and each needs a stop
+/// trigger so that it ends on its own.
+///
+/// Fields:
+/// - `rospec_ids`: The ROSpecs to run, in order.
+/// - `repeat`: Whether to start over from the first ROSpec after the last one ends.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpecChainConfig {
+  pub rospec_ids : Vec<u32>,
+  #[serde(default)]
+  pub repeat     : bool
+}
+
+/// RF settings for one regulatory region. When a configuration lists presets,
+/// the client looks up the reader's region in its RegulatoryCapabilities at
+/// connect time and overrides the matching `reader_config` fields, so a single
+/// configuration can be deployed across regions.
+///
+/// Fields:
+/// - `region`: `"fcc"`, `"etsi"`, `"hong_kong"`, `"taiwan"` or `"korea"`.
+/// - `hop_table_id`: Hop table for readers that hop (default - The first one reported).
+/// - `frequency_khz`: Channel for fixed-frequency readers (default - The first one reported).
+/// - `tx_power_table_index`: Transmit power for the region (default - `reader_config.tx_power_table_index`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegionPreset {
+  pub region               : Region,
+  #[serde(default)]
+  pub hop_table_id         : Option<u16>,
+  #[serde(default)]
+  pub frequency_khz        : Option<u32>,
+  #[serde(default)]
+  pub tx_power_table_index : Option<u16>
+}
+
+/// A tag event sink declared in the configuration. Credentials are given as
+/// [`Secret`] references so they can live outside the JSON file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+  File {
+    name       : String,
+    path       : String,
+    #[serde(default)]
+    protection : Option<PayloadProtectionConfig>
+  },
+  Webhook {
+    name       : String,
+    url        : String,
+    #[serde(default)]
+    token      : Option<Secret>,
+    #[serde(default)]
+    protection : Option<PayloadProtectionConfig>
+  },
+  /// Publishes to `broker` (`host:port`) on `topic`, in which `{reader_id}` is
+  /// replaced by the reporting reader. The client ID defaults to `llrp-<name>`.
+  Mqtt {
+    name       : String,
+    broker     : String,
+    topic      : String,
+    #[serde(default)]
+    client_id  : Option<String>,
+    #[serde(default)]
+    username   : Option<String>,
+    #[serde(default)]
+    password   : Option<Secret>,
+    #[serde(default)]
+    protection : Option<PayloadProtectionConfig>
+  }
+}
+
+impl SinkConfig {
+
+  pub fn name(
+    &self
+  ) -> &str {
+    match self {
+      SinkConfig::File { name, .. } => name,
+      SinkConfig::Webhook { name, .. } => name,
+      SinkConfig::Mqtt { name, .. } => name
+    }
+  }
+}
+
+/// Encryption and signing of the payloads a sink writes, for tag data that passes
+/// through shared brokers or storage. Keys are base64 [`Secret`] references; at
+/// least one of them must be given. Needs the `sealing` feature.
+///
+/// Fields:
+/// - `encryption_key`: 32-byte AES-256-GCM key (default - Payloads are not encrypted).
+/// - `signing_key`: HMAC-SHA256 key (default - Payloads are not signed).
+/// - `key_id`: Written into every envelope so consumers can select the keys (default - None).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PayloadProtectionConfig {
+  #[serde(default)]
+  pub encryption_key : Option<Secret>,
+  #[serde(default)]
+  pub signing_key    : Option<Secret>,
+  #[serde(default)]
+  pub key_id         : Option<String>
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ROSpecConfig {
-  pub rospec_id              : u32,
-  pub priority               : u8,
-  pub antenna_count          : u16,
-  pub antennas               : Vec<u16>,
-  pub ROSpecStartTriggerType : u8,
-  pub ROSpecStopTriggerType  : u8,
-  pub AISpecStopTriggerType  : u8,
-  pub InventoryParamSpecID   : u16,
-  pub AIProtocol             : u8,
-  pub ROReportTriggerType    : u8,
-  pub ROReportTrigger_N      : u16,
-  pub ReportContentSelector  : u16,
+  pub rospec_id               : u32,
+  pub priority                : u8,
+  pub antenna_count           : u16,
+  pub antennas                : Vec<u16>,
+  pub ROSpecStartTriggerType  : u8,
+  pub ROSpecStopTriggerType   : u8,
+  pub AISpecStopTriggerType   : u8,
+  pub InventoryParamSpecID    : u16,
+  pub AIProtocol              : u8,
+  #[serde(deserialize_with = "deserialize_report_trigger")]
+  pub ROReportTriggerType     : ROReportTrigger,
+  #[serde(default)]
+  pub ROReportTrigger_N       : u16,
+  #[serde(default, deserialize_with = "deserialize_report_content")]
+  pub ReportContentSelector   : TagReportContentConfig,
+  #[serde(default)]
+  pub inventory_command       : Option<C1G2InventoryCommandConfig>,
+  #[serde(default)]
+  pub periodic_trigger        : Option<PeriodicTriggerConfig>,
+  #[serde(default)]
+  pub start_gpi_trigger       : Option<GpiTriggerConfig>,
+  #[serde(default)]
+  pub stop_gpi_trigger        : Option<GpiTriggerConfig>,
+  #[serde(default, with = "option_millis")]
+  pub rospec_stop_duration_ms : Option<u32>,
+  #[serde(default, with = "option_millis")]
+  pub aispec_stop_duration_ms : Option<u32>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// When the reader sends an ROAccessReport, the ROReportSpec's `ROReportTrigger`.
+/// Written as the name below or as its LLRP value. Every trigger but `None` also
+/// reports at the end of the AISpec or ROSpec; `ROReportTrigger_N` adds an earlier
+/// report after N tags, seconds or milliseconds:
+///
+/// - `none` (0): Only on GET_REPORT; N is not used.
+/// - `n_tags_or_end_of_ai_spec` (1), alias `end_of_ai_spec`: After N tags (0 - Only at the end).
+/// - `n_tags_or_end_of_rospec` (2), alias `end_of_rospec`: After N tags (0 - Only at the end).
+/// - `n_seconds_or_end_of_ai_spec` (3) / `n_seconds_or_end_of_rospec` (4): Every N
+///   seconds, N required; LLRP 1.1 only.
+/// - `n_milliseconds_or_end_of_ai_spec` (5) / `n_milliseconds_or_end_of_rospec` (6):
+///   Every N milliseconds, N required; LLRP 1.1 only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ROReportTrigger {
+  None,
+  #[serde(alias = "end_of_ai_spec")]
+  NTagsOrEndOfAISpec,
+  #[serde(alias = "end_of_rospec")]
+  NTagsOrEndOfROSpec,
+  NSecondsOrEndOfAISpec,
+  NSecondsOrEndOfROSpec,
+  NMillisecondsOrEndOfAISpec,
+  NMillisecondsOrEndOfROSpec
+}
+
+impl ROReportTrigger {
+
+  pub fn value(
+    &self
+  ) -> u8 {
+    match self {
+      ROReportTrigger::None                       => 0,
+      ROReportTrigger::NTagsOrEndOfAISpec         => 1,
+      ROReportTrigger::NTagsOrEndOfROSpec         => 2,
+      ROReportTrigger::NSecondsOrEndOfAISpec      => 3,
+      ROReportTrigger::NSecondsOrEndOfROSpec      => 4,
+      ROReportTrigger::NMillisecondsOrEndOfAISpec => 5,
+      ROReportTrigger::NMillisecondsOrEndOfROSpec => 6
+    }
+  }
+
+  pub fn from_value(
+    value: u8
+  ) -> Option<Self> {
+    match value {
+      0 => Some(ROReportTrigger::None),
+      1 => Some(ROReportTrigger::NTagsOrEndOfAISpec),
+      2 => Some(ROReportTrigger::NTagsOrEndOfROSpec),
+      3 => Some(ROReportTrigger::NSecondsOrEndOfAISpec),
+      4 => Some(ROReportTrigger::NSecondsOrEndOfROSpec),
+      5 => Some(ROReportTrigger::NMillisecondsOrEndOfAISpec),
+      6 => Some(ROReportTrigger::NMillisecondsOrEndOfROSpec),
+      _ => None
+    }
+  }
+
+  /// Whether N counts tags, as opposed to being unused or a period.
+  pub fn counts_tags(
+    &self
+  ) -> bool {
+    matches!(self, ROReportTrigger::NTagsOrEndOfAISpec | ROReportTrigger::NTagsOrEndOfROSpec)
+  }
+
+  /// Whether N is a period, which the trigger cannot do without and which only
+  /// LLRP 1.1 defines.
+  pub fn is_periodic(
+    &self
+  ) -> bool {
+    self.value() >= 3
+  }
+
+  /// Checks `n` against the trigger: a period must be greater than 0.
+  pub fn check_n(
+    &self,
+    n: u16
+  ) -> Result<(), String> {
+    if self.is_periodic() && n == 0 {
+      return Err(format!("ROReportTrigger {} ({:?}) requires N greater than 0", self.value(), self));
+    }
+    Ok(())
+  }
+}
+
+/// Reads `ROReportTriggerType` as either a trigger name or its LLRP value.
+fn deserialize_report_trigger<'de, D>(
+  deserializer: D
+) -> Result<ROReportTrigger, D::Error>
+where
+  D : serde::Deserializer<'de>
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Written {
+    Value(u8),
+    Name(ROReportTrigger)
+  }
+
+  match Written::deserialize(deserializer)? {
+    Written::Value(value) => ROReportTrigger::from_value(value)
+      .ok_or_else(|| serde::de::Error::custom(format!("ROReportTriggerType {} is not defined, expected 0 to 6", value))),
+    Written::Name(trigger) => Ok(trigger)
+  }
+}
+
+/// What the reader reports with each tag, the ROReportSpec's TagReportContentSelector.
+/// Written as an object of the flags below, e.g. `{"antenna_id": true, "peak_rssi": true}`,
+/// or as the selector's raw `EnableFlags` value.
+///
+/// Fields:
+/// - `rospec_id`: The ROSpec whose inventory read the tag.
+/// - `spec_index`: The index of the AISpec within the ROSpec.
+/// - `inventory_parameter_spec_id`: The InventoryParameterSpec whose inventory read the tag.
+/// - `antenna_id`: The antenna that last saw the tag.
+/// - `channel_index`: The channel the tag was last read on.
+/// - `peak_rssi`: The highest RSSI of the tag's reads.
+/// - `first_seen_timestamp` / `last_seen_timestamp`: When the tag was first and last read.
+/// - `tag_seen_count`: How often the tag was read since the last report.
+/// - `access_spec_id`: The AccessSpec whose operations ran on the tag.
+/// - `crc` / `pc_bits`: The tag's CRC and PC bits, selected through a C1G2EPCMemorySelector.
+/// - `xpc_bits`: The tag's XPC words, through the same selector; LLRP 1.1 only.
+///
+/// All default to false.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TagReportContentConfig {
+  pub rospec_id                   : bool,
+  pub spec_index                  : bool,
+  pub inventory_parameter_spec_id : bool,
+  pub antenna_id                  : bool,
+  pub channel_index               : bool,
+  pub peak_rssi                   : bool,
+  pub first_seen_timestamp        : bool,
+  pub last_seen_timestamp         : bool,
+  pub tag_seen_count              : bool,
+  pub access_spec_id              : bool,
+  pub crc                         : bool,
+  pub pc_bits                     : bool,
+  pub xpc_bits                    : bool
+}
+
+impl TagReportContentConfig {
+
+  /// The content enabled by a TagReportContentSelector `EnableFlags` value. The
+  /// reserved low bits are ignored.
+  pub fn from_flags(
+    flags: u16
+  ) -> Self {
+    TagReportContentConfig {
+      rospec_id                   : flags & 0x8000 != 0,
+      spec_index                  : flags & 0x4000 != 0,
+      inventory_parameter_spec_id : flags & 0x2000 != 0,
+      antenna_id                  : flags & 0x1000 != 0,
+      channel_index               : flags & 0x0800 != 0,
+      peak_rssi                   : flags & 0x0400 != 0,
+      first_seen_timestamp        : flags & 0x0200 != 0,
+      last_seen_timestamp         : flags & 0x0100 != 0,
+      tag_seen_count              : flags & 0x0080 != 0,
+      access_spec_id              : flags & 0x0040 != 0,
+      ..TagReportContentConfig::default()
+    }
+  }
+
+  /// The TagReportContentSelector `EnableFlags` field.
+  pub fn flags(
+    &self
+  ) -> u16 {
+    [
+      (self.rospec_id, 0x8000),
+      (self.spec_index, 0x4000),
+      (self.inventory_parameter_spec_id, 0x2000),
+      (self.antenna_id, 0x1000),
+      (self.channel_index, 0x0800),
+      (self.peak_rssi, 0x0400),
+      (self.first_seen_timestamp, 0x0200),
+      (self.last_seen_timestamp, 0x0100),
+      (self.tag_seen_count, 0x0080),
+      (self.access_spec_id, 0x0040)
+    ].into_iter().filter(|(enabled, _)| *enabled).fold(0, |flags, (_, bit)| flags | bit)
+  }
+
+  /// The C1G2EPCMemorySelector flags byte, if any of its content is enabled.
+  pub fn epc_memory_flags(
+    &self
+  ) -> Option<u8> {
+    let flags = [(self.crc, 0x80), (self.pc_bits, 0x40), (self.xpc_bits, 0x20)]
+      .into_iter()
+      .filter(|(enabled, _)| *enabled)
+      .fold(0, |flags, (_, bit)| flags | bit);
+    (flags != 0).then_some(flags)
+  }
+}
+
+/// Reads `ReportContentSelector` as either an object of flags or a raw `EnableFlags` number.
+fn deserialize_report_content<'de, D>(
+  deserializer: D
+) -> Result<TagReportContentConfig, D::Error>
+where
+  D : serde::Deserializer<'de>
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Written {
+    Flags(u16),
+    Content(TagReportContentConfig)
+  }
+
+  Ok(match Written::deserialize(deserializer)? {
+    Written::Flags(flags) => TagReportContentConfig::from_flags(flags),
+    Written::Content(content) => content
+  })
+}
+
+/// The PeriodicTriggerValue of an ROSpec with `ROSpecStartTriggerType` 2, which
+/// starts the ROSpec `offset_ms` after it is enabled, or after `utc_timestamp_us`
+/// when given, then every `period_ms`.
+///
+/// Fields:
+/// - `offset_ms`: Delay before the first start.
+/// - `period_ms`: Interval between starts (0 - Start only once).
+/// - `utc_timestamp_us`: UTC time the offset counts from, in microseconds since the Unix epoch.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PeriodicTriggerConfig {
+  #[serde(default, with = "millis")]
+  pub offset_ms        : u32,
+  #[serde(with = "millis")]
+  pub period_ms        : u32,
+  #[serde(default)]
+  pub utc_timestamp_us : Option<u64>
+}
+
+/// The GPITriggerValue of an ROSpec starting on a GPI event (`ROSpecStartTriggerType`
+/// 3) or stopping on one (`ROSpecStopTriggerType` 2), e.g. a light barrier at a dock
+/// door. The port must be enabled in `reader_config.gpi_ports` or on the reader.
+///
+/// Fields:
+/// - `port`: The GPI port watched.
+/// - `event`: `"high"` to trigger as the port goes high, `"low"` as it goes low.
+/// - `timeout_ms`: Time after which the trigger fires without the event (0 - No timeout).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GpiTriggerConfig {
+  pub port       : GpiPort,
+  pub event      : PinState,
+  #[serde(default, with = "millis")]
+  pub timeout_ms : u32
+}
+
+/// Most words a C1G2Write or C1G2BlockWrite carries, as its 15 bytes of fields and
+/// two bytes per word must fit the 16-bit parameter length.
+pub const MAX_WRITE_WORDS: usize = (u16::MAX as usize - 15) / 2;
+
+/// A single C1G2Write or C1G2BlockWrite operation of an AccessSpec.
+///
+/// Fields:
+/// - `op_spec_id`: Identifier reported back with the write result.
+/// - `access_password`: Tag access password (0 - Not secured).
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `word_pointer`: First 16-bit word to write.
+/// - `data`: The words to write.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2WriteConfig {
+  pub op_spec_id      : u16,
+  #[serde(default)]
+  pub access_password : u32,
+  pub memory_bank     : u8,
+  #[serde(default)]
+  pub word_pointer    : u16,
+  pub data            : Vec<u16>
+}
+
+/// A single C1G2BlockErase operation of an AccessSpec, setting a range of words
+/// to zero. Requires a reader reporting `supports_block_erase`.
+///
+/// Fields:
+/// - `op_spec_id`: Identifier reported back with the erase result.
+/// - `access_password`: Tag access password (0 - Not secured).
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `word_pointer`: First 16-bit word to erase.
+/// - `word_count`: Number of words to erase.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2BlockEraseConfig {
+  pub op_spec_id      : u16,
+  #[serde(default)]
+  pub access_password : u32,
+  pub memory_bank     : u8,
+  #[serde(default)]
+  pub word_pointer    : u16,
+  pub word_count      : u16
+}
+
+/// A single C1G2Lock operation of an AccessSpec. Locking requires the tag's
+/// access password to be set.
+///
+/// Fields:
+/// - `op_spec_id`: Identifier reported back with the lock result.
+/// - `access_password`: Tag access password.
+/// - `payloads`: The privilege to apply to each memory field.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2LockConfig {
+  pub op_spec_id      : u16,
+  pub access_password : u32,
+  pub payloads        : Vec<C1G2LockPayloadConfig>
+}
+
+/// Fields:
+/// - `privilege`: 0 - Read/write (password protected), 1 - Perma-lock, 2 - Perma-unlock, 3 - Unlock.
+/// - `data_field`: 0 - Kill password, 1 - Access password, 2 - EPC, 3 - TID, 4 - User.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2LockPayloadConfig {
+  pub privilege  : u8,
+  pub data_field : u8
+}
+
+/// C1G2 inventory settings encoded into the InventoryParameterSpec of the ROSpec,
+/// for tuning tag filtering and anti-collision.
+///
+/// State-aware filters and singulation actions require a reader reporting
+/// `can_do_tag_inventory_state_aware_singulation` in its C1G2 capabilities.
+///
+/// Fields:
+/// - `tag_inventory_state_aware`: Use state-aware filter actions and singulation.
+/// - `filters`: Select filters applied before each inventory round.
+/// - `rf_control`: RF mode and Tari of the inventory (default - The reader's).
+/// - `singulation`: Session, expected population and transit time of the inventory.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct C1G2InventoryCommandConfig {
+  #[serde(default)]
+  pub tag_inventory_state_aware : bool,
+  #[serde(default)]
+  pub filters                   : Vec<C1G2FilterConfig>,
+  #[serde(default)]
+  pub rf_control                : Option<C1G2RFControlConfig>,
+  #[serde(default)]
+  pub singulation               : Option<C1G2SingulationConfig>
+}
+
+impl C1G2InventoryCommandConfig {
+
+  /// Checks the filters and singulation settings against the rules LLRP places on
+  /// them, which a reader would otherwise reject the ROSpec for, and that the
+  /// filter masks are hex.
+  pub fn validate(
+    &self
+  ) -> Result<(), String> {
+
+    let max_action = if self.tag_inventory_state_aware { 7 } else { 5 };
+
+    for (index, filter) in self.filters.iter().enumerate() {
+
+      if filter.action > max_action {
+        return Err(format!("filters[{}].action {} is not a valid action (0-{})", index, filter.action, max_action));
+      }
+
+      if self.tag_inventory_state_aware && filter.target > 4 {
+        return Err(format!("filters[{}].target {} is not a valid target (0-4)", index, filter.target));
+      }
+
+      let mask = decode_hex(&filter.mask).ok_or_else(|| format!("filters[{}].mask {:?} is not a hex string", index, filter.mask))?;
+
+      if let Some(mask_bit_count) = filter.mask_bit_count.filter(|&bits| bits as usize > mask.len() * 8) {
+        return Err(format!("filters[{}].mask_bit_count {} is longer than the mask", index, mask_bit_count));
+      }
+
+      if filter.truncate == C1G2FilterTruncate::Truncate {
+        if index + 1 != self.filters.len() {
+          return Err(format!("filters[{}] truncates, which only the last filter may", index));
+        }
+        if filter.memory_bank != 1 {
+          return Err(format!("filters[{}] truncates, which requires a mask on the EPC memory bank (1)", index));
+        }
+      }
+    }
+
+    if let Some(rf_control) = self.rf_control.as_ref().filter(|rf_control| rf_control.tari != 0) {
+      if !(MIN_TARI_NS..=MAX_TARI_NS).contains(&rf_control.tari) {
+        return Err(format!("rf_control.tari {} is outside the Gen2 range of {}-{} ns", rf_control.tari, MIN_TARI_NS, MAX_TARI_NS));
+      }
+    }
+
+    if let Some(singulation) = &self.singulation {
+
+      if singulation.session > 3 {
+        return Err(format!("singulation.session {} is not a Gen2 session (0-3)", singulation.session));
+      }
+
+      if let Some(action) = &singulation.state_aware_action {
+        if !self.tag_inventory_state_aware {
+          return Err("singulation.state_aware_action requires tag_inventory_state_aware".to_string());
+        }
+        if action.inventoried_state > 1 || action.sl > 1 {
+          return Err("singulation.state_aware_action.inventoried_state and sl must be 0 or 1".to_string());
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A C1G2 Select filter.
+///
+/// Fields:
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `pointer`: Bit address the mask is compared at.
+/// - `mask`: Mask as a hex string, e.g. `"E280"`.
+/// - `mask_bit_count`: Length of the mask in bits, defaulting to four bits per hex digit.
+/// - `target`: State-aware target flag (0-3 - Inventoried S0-S3, 4 - SL).
+/// - `action`: State-aware action (0-7), or the state-unaware action (0-5) when
+///   `tag_inventory_state_aware` is off.
+/// - `truncate`: Whether matching tags backscatter only the EPC bits after the mask
+///   (default - unspecified). Only the last filter, on the EPC memory bank, may truncate.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2FilterConfig {
+  pub memory_bank    : u8,
+  #[serde(default)]
+  pub pointer        : u16,
+  pub mask           : String,
+  #[serde(default)]
+  pub mask_bit_count : Option<u16>,
+  #[serde(default)]
+  pub target         : u8,
+  pub action         : u8,
+  #[serde(default)]
+  pub truncate       : C1G2FilterTruncate
+}
+
+impl C1G2FilterConfig {
+
+  /// A state-unaware filter inventorying only tags whose EPC starts with `prefix`,
+  /// a hex string, e.g. the company prefix of one's own tags. The EPC starts at bit
+  /// 0x20 of the EPC memory bank, after the StoredCRC and StoredPC words.
+  pub fn epc_prefix(
+    prefix: &str
+  ) -> Self {
+    C1G2FilterConfig {
+      memory_bank    : 1,
+      pointer        : 0x20,
+      mask           : prefix.to_string(),
+      mask_bit_count : None,
+      target         : 0,
+      action         : 0, // Matching tags assert SL, the others deassert it
+      truncate       : C1G2FilterTruncate::Unspecified
+    }
+  }
+}
+
+/// The `T` field of a C1G2Filter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum C1G2FilterTruncate {
+  #[default]
+  Unspecified,
+  DoNotTruncate,
+  Truncate
+}
+
+impl C1G2FilterTruncate {
+
+  pub fn value(
+    &self
+  ) -> u8 {
+    match self {
+      C1G2FilterTruncate::Unspecified   => 0,
+      C1G2FilterTruncate::DoNotTruncate => 1,
+      C1G2FilterTruncate::Truncate      => 2
+    }
+  }
+}
+
+/// Shortest and longest Tari, the duration of a data-0 symbol, Gen2 allows.
+pub const MIN_TARI_NS: u16 = 6250;
+pub const MAX_TARI_NS: u16 = 25000;
+
+/// C1G2 RF control, selecting one of the modes of the reader's C1G2UHFRFModeTable.
+///
+/// Fields:
+/// - `mode_index`: Mode identifier of the table entry to use.
+/// - `tari`: Tari in nanoseconds, within the mode's range (default - 0, the mode's own).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2RFControlConfig {
+  pub mode_index : u16,
+  #[serde(default)]
+  pub tari       : u16
+}
+
+/// C1G2 singulation control.
+///
+/// Fields:
+/// - `session`: Gen2 session (0-3).
+/// - `tag_population`: Expected number of tags in the field.
+/// - `tag_transit_time`: Expected time in milliseconds a tag stays in the field.
+/// - `state_aware_action`: Inventoried state and SL flag to singulate, when state aware.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2SingulationConfig {
+  pub session            : u8,
+  #[serde(default)]
+  pub tag_population     : u16,
+  #[serde(default)]
+  pub tag_transit_time   : u32,
+  #[serde(default)]
+  pub state_aware_action : Option<StateAwareSingulationConfig>
+}
+
+impl C1G2SingulationConfig {
+
+  pub fn new(
+    session          : u8,
+    tag_population   : u16,
+    tag_transit_time : u32
+  ) -> Self {
+    C1G2SingulationConfig {
+      session,
+      tag_population,
+      tag_transit_time,
+      state_aware_action : None
+    }
+  }
+
+  /// Singulates only tags in `inventoried_state` of the session and with the given
+  /// SL flag; needs a state-aware inventory.
+  pub fn state_aware(
+    mut self,
+    inventoried_state : u8,
+    sl                : u8
+  ) -> Self {
+    self.state_aware_action = Some(StateAwareSingulationConfig { inventoried_state, sl });
+    self
+  }
+}
+
+/// Fields:
+/// - `inventoried_state`: 0 - State A, 1 - State B.
+/// - `sl`: 0 - SL, 1 - Not SL.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StateAwareSingulationConfig {
+  pub inventoried_state : u8,
+  pub sl                : u8
+}
+
+/// An AccessSpec reading tag memory banks, e.g. the TID or user memory, on
+/// every tag singulated by the matching ROSpec.
+///
+/// Fields:
+/// - `access_spec_id`: Identifier of the AccessSpec on the reader.
+/// - `antenna_id`: Antenna the AccessSpec applies to (0 - All).
+/// - `rospec_id`: ROSpec the AccessSpec applies to (0 - Any).
+/// - `operation_count`: Executions after which the reader deletes the AccessSpec (0 - Never).
+/// - `reads`: The C1G2Read operations performed on each tag.
+/// - `writes`: The C1G2Write operations performed on each tag, after the reads.
+/// - `block_erases`: The C1G2BlockErase operations performed on each tag, after the writes.
+/// - `block_writes`: The C1G2BlockWrite operations performed on each tag, after the block
+///   erases. Requires a reader reporting `supports_block_write`.
+/// - `target`: Tags the AccessSpec applies to, every tag when absent.
+/// - `kills`: The C1G2Kill operations performed on each tag, after the block writes.
+/// - `locks`: The C1G2Lock operations performed on each tag, after the kills.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessSpecConfig {
+  pub access_spec_id  : u32,
+  #[serde(default)]
+  pub target          : Option<C1G2TargetTagConfig>,
+  #[serde(default)]
+  pub antenna_id      : u16,
+  #[serde(default)]
+  pub rospec_id       : u32,
+  #[serde(default)]
+  pub operation_count : u16,
+  #[serde(default)]
+  pub reads           : Vec<C1G2ReadConfig>,
+  #[serde(default)]
+  pub writes          : Vec<C1G2WriteConfig>,
+  #[serde(default)]
+  pub block_erases    : Vec<C1G2BlockEraseConfig>,
+  #[serde(default)]
+  pub block_writes    : Vec<C1G2WriteConfig>,
+  #[serde(default)]
+  pub kills           : Vec<C1G2KillConfig>,
+  #[serde(default)]
+  pub locks           : Vec<C1G2LockConfig>
+}
+
+impl AccessSpecConfig {
+
+  /// Checks that the target's mask and data are valid hex and that every write
+  /// fits in an LLRP parameter and in the memory bank's word addresses.
+  pub fn validate(
+    &self
+  ) -> io::Result<()> {
+
+    for write in self.writes.iter().chain(&self.block_writes) {
+      let fits = write.data.len() <= MAX_WRITE_WORDS
+        && (write.word_pointer as usize).checked_add(write.data.len()).is_some_and(|end| end <= u16::MAX as usize + 1);
+      if !fits {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidInput,
+          format!(
+            "AccessSpec {} op spec {} writes {} words from word {}, more than fit in a parameter ({}) or the bank",
+            self.access_spec_id, write.op_spec_id, write.data.len(), write.word_pointer, MAX_WRITE_WORDS
+          )
+        ));
+      }
+    }
+
+    if let Some(target) = &self.target {
+      for (field, hex) in [("tag_mask", &target.tag_mask), ("tag_data", &target.tag_data)] {
+        if decode_hex(hex).is_none() {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("AccessSpec {} target {} {:?} is not valid hex", self.access_spec_id, field, hex)
+          ));
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Selects the tags an AccessSpec applies to: those whose `memory_bank` bits,
+/// starting at bit `pointer` and masked by `tag_mask`, equal `tag_data`.
+///
+/// Fields:
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `pointer`: First bit compared. The EPC itself starts at bit 32 of the EPC bank.
+/// - `tag_mask` / `tag_data`: Hex strings, four bits per digit.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2TargetTagConfig {
+  pub memory_bank : u8,
+  pub pointer     : u16,
+  pub tag_mask    : String,
+  pub tag_data    : String
+}
+
+/// A single C1G2Kill operation of an AccessSpec, permanently disabling the tag.
+///
+/// Fields:
+/// - `op_spec_id`: Identifier reported back with the kill result.
+/// - `kill_password`: The tag's non-zero kill password.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2KillConfig {
+  pub op_spec_id    : u16,
+  pub kill_password : u32
+}
+
+/// A single C1G2Read operation of an AccessSpec.
+///
+/// Fields:
+/// - `op_spec_id`: Identifier reported back with the read result.
+/// - `access_password`: Tag access password (0 - Not secured).
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `word_pointer`: First 16-bit word to read.
+/// - `word_count`: Number of words to read (0 - Entire bank).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct C1G2ReadConfig {
+  pub op_spec_id      : u16,
+  #[serde(default)]
+  pub access_password : u32,
+  pub memory_bank     : u8,
+  #[serde(default)]
+  pub word_pointer    : u16,
+  pub word_count      : u16
+}
+
+/// Reader settings applied with SET_READER_CONFIG.
+///
+/// Fields:
+/// - `hop_table_id` / `channel_index`: RF channel of the antennas.
+/// - `tx_power_table_index` / `rx_power_table_index`: Transmit power and receive sensitivity table entries.
+/// - `gpi_ports`: GPI ports to enable or disable; ports not listed keep their current setting.
+/// - `gpo_outputs`: Levels to drive GPO ports to; ports not listed keep their current level.
+/// - `keepalive_spec`: When the reader sends KEEPALIVEs; absent keeps the reader's setting.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ReaderConfig {
   pub hop_table_id         : u16,
   pub channel_index        : u16,
   pub tx_power_table_index : u16,
-  pub rx_power_table_index : u16
+  pub rx_power_table_index : u16,
+  #[serde(default)]
+  pub gpi_ports            : Vec<GpiPortConfig>,
+  #[serde(default)]
+  pub gpo_outputs          : Vec<GpoOutputConfig>,
+  #[serde(default)]
+  pub keepalive_spec       : Option<KeepaliveSpecConfig>
+}
+
+/// Fields:
+/// - `trigger`: `"null"` for no reader KEEPALIVEs, `"periodic"` for one every `interval`.
+/// - `interval`: Time in milliseconds between KEEPALIVEs of a periodic trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeepaliveSpecConfig {
+  pub trigger  : KeepaliveTrigger,
+  #[serde(default, with = "millis")]
+  pub interval : u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepaliveTrigger {
+  Null,
+  Periodic
+}
+
+impl KeepaliveTrigger {
+
+  /// The KeepaliveSpec `KeepaliveTriggerType` field value.
+  pub fn value(
+    &self
+  ) -> u8 {
+    match self {
+      KeepaliveTrigger::Null     => 0,
+      KeepaliveTrigger::Periodic => 1
+    }
+  }
+}
+
+/// Fields:
+/// - `port`: The GPI port.
+/// - `enabled`: Whether the reader monitors the port and reports its events.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GpiPortConfig {
+  pub port    : GpiPort,
+  pub enabled : bool
+}
+
+/// Fields:
+/// - `port`: The GPO port.
+/// - `state`: `"low"` or `"high"`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GpoOutputConfig {
+  pub port  : GpoPort,
+  pub state : PinState
 }
 
-pub fn load_config(file_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+pub fn load_config<P: AsRef<Path>>(file_path: P) -> Result<Config, Box<dyn std::error::Error>> {
   
   let config_data = fs::read_to_string(file_path)?;
   let config: Config = serde_json::from_str(&config_data)?;
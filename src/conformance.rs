@@ -0,0 +1,211 @@
+#![allow(dead_code)]
+
+mod alerts;
+mod channel;
+mod clock;
+mod config;
+mod custom;
+mod duration;
+mod error;
+mod gpio;
+mod params;
+mod region;
+mod rospec;
+mod secrets;
+mod llrp;
+mod setup;
+mod stream;
+mod stats;
+mod tag_stream;
+#[cfg(feature = "tls")]
+mod tls;
+mod client;
+mod history;
+mod introspect;
+mod journal;
+mod liveness;
+#[cfg(feature = "impinj")]
+mod impinj;
+mod log_context;
+
+use std::env;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+use client::LlrpClient;
+use config::load_config;
+use error::LlrpError;
+use llrp::{LlrpResponseData, ReaderConfigRequest};
+use params::LlrpParameterData;
+
+/// Outcome of a single message exchange in the conformance matrix.
+struct MatrixResult {
+  message  : &'static str,
+  accepted : bool,
+  detail   : String
+}
+
+fn usage() -> ! {
+  eprintln!("Usage: conformance <config.json> [--spawn <server-executable> [args...]]");
+  eprintln!();
+  eprintln!("Runs the client's message matrix against the reader configured in <config.json>.");
+  eprintln!("With --spawn, the given executable (e.g. the LTK llrp_server example or a vendor");
+  eprintln!("emulator) is started first and terminated once the matrix completes.");
+  std::process::exit(2);
+}
+
+fn record(
+  results : &mut Vec<MatrixResult>,
+  message : &'static str,
+  result  : Result<String, LlrpError>
+) {
+  let (accepted, detail) = match result {
+    Ok(detail) => (true, detail),
+    Err(e) => (false, e.to_string())
+  };
+
+  results.push(MatrixResult { message, accepted, detail });
+}
+
+fn describe_parameters(
+  parameters: &[LlrpParameterData]
+) -> String {
+  parameters.iter()
+    .map(|parameter| format!("{:?}", parameter).split(['(', ' ']).next().unwrap_or("").to_string())
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+fn describe_response(
+  response_data: LlrpResponseData
+) -> String {
+  match response_data {
+    LlrpResponseData::ReaderCapabilities(parameters) => describe_parameters(&parameters),
+    LlrpResponseData::ReaderConfig(parameters) => describe_parameters(&parameters),
+    LlrpResponseData::AccessSpecs(parameters) => describe_parameters(&parameters),
+    LlrpResponseData::TagReport(tag_reports) => format!("{} tag reports", tag_reports.len()),
+    LlrpResponseData::ReaderEvent(event_data) => format!("{:?}", event_data),
+    LlrpResponseData::CustomMessage(custom) => format!("{:?}", custom),
+    LlrpResponseData::SupportedVersion(supported_version) => format!("{:?}", supported_version),
+    LlrpResponseData::Error(status) => status.to_string()
+  }
+}
+
+async fn spawn_server(
+  args: &[String]
+) -> std::io::Result<Child> {
+
+  let child = Command::new(&args[0])
+    .args(&args[1..])
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .kill_on_drop(true)
+    .spawn()?;
+
+  // Give the server time to bind its listening socket.
+  tokio::time::sleep(Duration::from_secs(1)).await;
+
+  Ok(child)
+}
+
+async fn run_matrix(
+  client    : &mut LlrpClient,
+  rospec_id : u32,
+  results   : &mut Vec<MatrixResult>
+) {
+
+  record(results, "KEEPALIVE", client.send_keep_alive().await.map(|_| String::new()));
+
+  let mut capabilities = String::new();
+  let result = client.send_get_reader_capabilities(|response_data| {
+    capabilities = describe_response(response_data);
+    async {}
+  }).await;
+  record(results, "GET_READER_CAPABILITIES", result.map(|_| capabilities));
+
+  let mut reader_config = String::new();
+  let result = client.send_get_reader_config(ReaderConfigRequest::default(), |response_data| {
+    reader_config = describe_response(response_data);
+    async {}
+  }).await;
+  record(results, "GET_READER_CONFIG", result.map(|_| reader_config));
+
+  let result = client.send_get_access_specs().await;
+  record(results, "GET_ACCESSSPECS", result.map(|access_specs| format!("{} AccessSpecs", access_specs.len())));
+
+  record(results, "SET_READER_CONFIG", client.send_set_reader_config().await.map(|_| String::new()));
+  record(results, "DELETE_ROSPEC (all)", client.send_delete_rospec(0).await.map(|_| String::new()));
+  record(results, "ADD_ROSPEC", client.send_add_rospec().await.map(|_| String::new()));
+  record(results, "ENABLE_ROSPEC", client.send_enable_rospec().await.map(|_| String::new()));
+  record(results, "START_ROSPEC", client.send_start_rospec().await.map(|_| String::new()));
+  record(results, "STOP_ROSPEC", client.send_stop_rospec().await.map(|_| String::new()));
+  record(results, "ENABLE_EVENTS_AND_REPORTS", client.send_enable_events_and_reports().await.map(|_| String::new()));
+  record(results, "DELETE_ROSPEC", client.send_delete_rospec(rospec_id).await.map(|_| String::new()));
+  record(results, "CLOSE_CONNECTION", client.send_close_connection().await.map(|_| String::new()));
+}
+
+#[tokio::main]
+async fn main() {
+
+  let args: Vec<String> = env::args().skip(1).collect();
+
+  if args.is_empty() {
+    usage();
+  }
+
+  let config_path = &args[0];
+
+  let mut server = match args.get(1).map(|arg| arg.as_str()) {
+    None => None,
+    Some("--spawn") if args.len() > 2 => {
+      match spawn_server(&args[2..]).await {
+        Ok(child) => Some(child),
+        Err(e) => {
+          eprintln!("Failed to start server {}: {}", args[2], e);
+          std::process::exit(1);
+        }
+      }
+    }
+    Some(_) => usage()
+  };
+
+  let rospec_id = match load_config(config_path) {
+    Ok(config) => config.rospec.rospec_id,
+    Err(e) => {
+      eprintln!("Failed to load configuration {}: {}", config_path, e);
+      std::process::exit(1);
+    }
+  };
+
+  let mut results = Vec::new();
+
+  match LlrpClient::initialize(config_path).await {
+    Ok(mut client) => run_matrix(&mut client, rospec_id, &mut results).await,
+    Err(e) => {
+      record(&mut results, "CONNECT", Err(e));
+    }
+  }
+
+  if let Some(server) = server.as_mut() {
+    let _ = server.kill().await;
+  }
+
+  println!("{:<28} {:<10} DETAIL", "MESSAGE", "RESULT");
+  for result in &results {
+    println!(
+      "{:<28} {:<10} {}",
+      result.message,
+      if result.accepted { "ACCEPTED" } else { "REJECTED" },
+      result.detail
+    );
+  }
+
+  let rejected = results.iter().filter(|result| !result.accepted).count();
+  println!();
+  println!("{} of {} messages accepted", results.len() - rejected, results.len());
+
+  if rejected > 0 {
+    std::process::exit(1);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,114 @@
+//! Corpus of malformed messages the decoders must reject with a classified error.
+//!
+//! Each file in `tests/fixtures/decode_errors` holds one complete LLRP message, in
+//! the hex layout of the golden fixtures, and is listed below with the
+//! `ParameterDecodeError` it must fail with. Every message is also mutated, by
+//! truncating it at each byte and flipping each byte, to check the decoders return
+//! an error rather than panic on the inputs around it. A malformed message seen in
+//! the field is added by saving it to the directory and listing its expected error.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use bytes::BytesMut;
+
+use crate::llrp::{LlrpMessage, LlrpResponse, LLRP_HEADER_LENGTH};
+use crate::params::{parse_parameter_tree, ParameterDecodeError};
+
+fn expected_errors() -> Vec<(&'static str, ParameterDecodeError)> {
+
+  let too_short = |path: &str, needed, available| ParameterDecodeError::TooShort { path: path.to_string(), needed, available };
+  let bad_length = |path: &str, length| ParameterDecodeError::BadLength { path: path.to_string(), length };
+
+  vec![
+    ("epc_shorter_than_its_bit_length", too_short("ROAccessReport/TagReportData/EPCData.EPC", 12, 4)),
+    ("epc_bit_length_overflow", ParameterDecodeError::Overflow { path: "ROAccessReport/TagReportData/EPCData.EPCLengthBits".to_string() }),
+    ("parameter_length_below_header", bad_length("ROAccessReport/TagReportData", 2)),
+    ("parameter_length_past_message_end", bad_length("ROAccessReport/TagReportData", 64)),
+    ("unknown_tv_parameter", ParameterDecodeError::UnknownType { path: "ROAccessReport/TagReportData".to_string(), param_type: 127 }),
+    ("status_description_past_end", too_short("GetReaderCapabilitiesResponse/LLRPStatus.ErrorDescription", 32, 1)),
+    ("status_description_not_utf8", ParameterDecodeError::Utf8 { path: "GetReaderCapabilitiesResponse/LLRPStatus.ErrorDescription".to_string() }),
+    ("reader_exception_not_utf8", ParameterDecodeError::Utf8 { path: "ReaderEventNotification/ReaderEventNotificationData/ReaderExceptionEvent.Message".to_string() }),
+    ("supported_version_truncated", too_short("GetSupportedVersionResponse", 2, 1))
+  ]
+}
+
+fn corpus_dir() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/decode_errors")
+}
+
+fn read_corpus() -> Vec<(String, Vec<u8>)> {
+
+  let mut corpus: Vec<(String, Vec<u8>)> = fs::read_dir(corpus_dir()).unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().is_some_and(|extension| extension == "hex"))
+    .map(|path| {
+      let hex = fs::read_to_string(&path).unwrap();
+      let bytes = hex.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).unwrap()).collect();
+      (path.file_stem().unwrap().to_string_lossy().into_owned(), bytes)
+    })
+    .collect();
+
+  corpus.sort();
+  corpus
+}
+
+/// Decodes `bytes` as a message from the reader, returning the classified error
+/// if the message or its parameters fail to decode.
+fn decode(
+  bytes: &[u8]
+) -> Option<ParameterDecodeError> {
+
+  let message = LlrpMessage::decode(&mut BytesMut::from(bytes)).ok()?;
+  let _ = parse_parameter_tree(&message.payload);
+
+  let error = LlrpResponse::from_message(message).decode().err()?;
+  error.get_ref().and_then(|inner| inner.downcast_ref::<ParameterDecodeError>()).cloned()
+}
+
+/// `message` cut to `length` bytes, with the header's length field updated so the
+/// cut reaches the payload decoders.
+fn truncated(
+  message : &[u8],
+  length  : usize
+) -> Vec<u8> {
+
+  let mut bytes = message[..length].to_vec();
+  bytes[2..6].copy_from_slice(&(length as u32).to_be_bytes());
+  bytes
+}
+
+#[test]
+fn corpus_messages_fail_with_their_expected_errors() {
+
+  let corpus = read_corpus();
+  let expected = expected_errors();
+
+  let files: BTreeSet<&str> = corpus.iter().map(|(name, _)| name.as_str()).collect();
+  let listed: BTreeSet<&str> = expected.iter().map(|(name, _)| *name).collect();
+  assert_eq!(files, listed, "Every corpus message needs an expected error, and every expected error a message");
+
+  for (name, error) in expected {
+    let (_, bytes) = corpus.iter().find(|(file, _)| file == name).unwrap();
+    assert_eq!(decode(bytes), Some(error), "{}", name);
+  }
+}
+
+#[test]
+fn mutated_corpus_messages_do_not_panic_the_decoders() {
+
+  for (_, message) in read_corpus() {
+
+    for length in LLRP_HEADER_LENGTH..message.len() {
+      decode(&truncated(&message, length));
+    }
+
+    for index in LLRP_HEADER_LENGTH..message.len() {
+      for flip in [0x01, 0x80, 0xFF] {
+        let mut mutated = message.clone();
+        mutated[index] ^= flip;
+        decode(&mutated);
+      }
+    }
+  }
+}
\ No newline at end of file
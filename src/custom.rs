@@ -0,0 +1,157 @@
+//! Vendor extensions: CUSTOM_MESSAGE and the Custom parameter.
+//!
+//! Both carry a vendor identifier (the vendor's IANA Private Enterprise Number)
+//! and a vendor-defined subtype ahead of their data. Custom parameters embedded
+//! in standard messages are kept as raw data unless a decoder was registered for
+//! their vendor and subtype with `register_custom_parameter`.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use bytes::{Buf, BufMut, BytesMut};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::llrp::LlrpParameterType;
+use crate::params::{DecodeContext, ParameterDecodeError};
+
+/// Turns the data of a vendor's Custom parameter, following its vendor and subtype
+/// fields, into JSON.
+pub type CustomParameterDecoder = fn(&[u8]) -> Result<serde_json::Value, ParameterDecodeError>;
+
+struct RegisteredDecoder {
+  name    : String,
+  decoder : CustomParameterDecoder
+}
+
+static CUSTOM_PARAMETER_DECODERS:
+Lazy<RwLock<HashMap<(u32, u32), RegisteredDecoder>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Registers the decoder for one vendor's Custom parameter subtype, replacing any
+/// decoder already registered for it. `name` labels the parameter in decoded output
+/// and decode error paths.
+pub fn register_custom_parameter(
+  vendor_id : u32,
+  subtype   : u32,
+  name      : &str,
+  decoder   : CustomParameterDecoder
+) {
+  CUSTOM_PARAMETER_DECODERS.write().unwrap().insert((vendor_id, subtype), RegisteredDecoder {
+    name: name.to_string(),
+    decoder
+  });
+}
+
+/// A Custom parameter (type 1023).
+///
+/// Fields:
+/// - `vendor_id`: IANA Private Enterprise Number of the vendor defining the parameter.
+/// - `subtype`: Vendor-defined parameter subtype.
+/// - `data`: The parameter data following the subtype.
+/// - `name` / `decoded`: Set when a decoder is registered for the vendor and subtype.
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomParameter {
+  pub vendor_id : u32,
+  pub subtype   : u32,
+  pub data      : Vec<u8>,
+  pub name      : Option<String>,
+  pub decoded   : Option<serde_json::Value>
+}
+
+impl CustomParameter {
+
+  /// A Custom parameter to encode into an outgoing message.
+  pub fn new(
+    vendor_id : u32,
+    subtype   : u32,
+    data      : Vec<u8>
+  ) -> Self {
+    CustomParameter { vendor_id, subtype, data, name: None, decoded: None }
+  }
+
+  /// Encodes the parameter, header included. Nested Custom parameters are carried
+  /// already encoded in `data`.
+  pub fn encode(
+    &self
+  ) -> Vec<u8> {
+
+    let length = 12 + self.data.len();
+
+    let mut buffer = BytesMut::with_capacity(length);
+    buffer.put_u16(LlrpParameterType::Custom.value());
+    buffer.put_u16(length as u16);
+    buffer.put_u32(self.vendor_id);
+    buffer.put_u32(self.subtype);
+    buffer.put_slice(&self.data);
+
+    buffer.to_vec()
+  }
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 8 {
+      return Err(ParameterDecodeError::too_short("Custom", 8, buf.remaining()));
+    }
+
+    let vendor_id = buf.get_u32();
+    let subtype = buf.get_u32();
+    let data = buf.to_vec();
+
+    let (name, decoded) = match CUSTOM_PARAMETER_DECODERS.read().unwrap().get(&(vendor_id, subtype)) {
+      Some(registered) => {
+        let decoded = (registered.decoder)(&data).within(&registered.name)?;
+        (Some(registered.name.clone()), Some(decoded))
+      }
+      None => (None, None)
+    };
+
+    Ok(CustomParameter { vendor_id, subtype, data, name, decoded })
+  }
+}
+
+/// The content of a CUSTOM_MESSAGE (type 1023).
+///
+/// Fields:
+/// - `vendor_id`: IANA Private Enterprise Number of the vendor defining the message.
+/// - `message_subtype`: Vendor-defined message subtype.
+/// - `data`: The message data following the subtype.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CustomMessage {
+  pub vendor_id       : u32,
+  pub message_subtype : u8,
+  pub data            : Vec<u8>
+}
+
+impl CustomMessage {
+
+  pub fn encode(
+    &self
+  ) -> Vec<u8> {
+
+    let mut payload = BytesMut::with_capacity(5 + self.data.len());
+    payload.put_u32(self.vendor_id);
+    payload.put_u8(self.message_subtype);
+    payload.put_slice(&self.data);
+
+    payload.to_vec()
+  }
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 5 {
+      return Err(ParameterDecodeError::too_short("CustomMessage", 5, buf.remaining()));
+    }
+
+    let vendor_id = buf.get_u32();
+    let message_subtype = buf.get_u8();
+
+    Ok(CustomMessage { vendor_id, message_subtype, data: buf.to_vec() })
+  }
+}
\ No newline at end of file
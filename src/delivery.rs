@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use log::{debug, warn};
+
+use crate::llrp::{LlrpResponse, LlrpResponseData};
+
+/// Delivers decoded ROAccessReports to a (potentially slow) consumer.
+///
+/// Reports are buffered in a bounded queue between the receive loop and a
+/// dedicated delivery thread. Once the queue is full, further reports are
+/// dropped and counted rather than stalling the broadcast channel, and the
+/// dropped count is periodically handed to an overflow handler.
+pub struct ReportDelivery {
+  task   : JoinHandle<()>,
+  worker : thread::JoinHandle<()>
+}
+
+impl ReportDelivery {
+
+  /// Starts delivering reports received on `ro_report_rx`. Dropped reports are
+  /// handed to `on_overflow` at most once per `overflow_interval`, which must not
+  /// be zero.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub fn start<R, O>(
+    mut ro_report_rx  : broadcast::Receiver<LlrpResponse>,
+    capacity          : usize,
+    overflow_interval : Duration,
+    on_report         : R,
+    on_overflow       : O
+  ) -> Self
+  where
+    R : Fn(LlrpResponseData) + Send + 'static,
+    O : Fn(u64) + Send + 'static
+  {
+
+    let (queue_tx, queue_rx) = mpsc::sync_channel::<LlrpResponseData>(capacity.max(1));
+    let dropped = Arc::new(AtomicU64::new(0));
+
+    let task_dropped = dropped.clone();
+    let task = tokio::spawn(async move {
+      loop {
+        match ro_report_rx.recv().await {
+
+          Ok(response) => {
+            match response.decode() {
+              Ok(response_data) => enqueue_report(&queue_tx, response_data, &task_dropped),
+              Err(e) => warn!("Failed to decode ROAccessReport for delivery: {}", e)
+            }
+          }
+
+          Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            task_dropped.fetch_add(skipped, Ordering::Relaxed);
+          }
+
+          Err(broadcast::error::RecvError::Closed) => {
+            debug!("ROAccessReport channel closed, stopping report delivery");
+            break;
+          }
+        }
+      }
+    });
+
+    let worker_dropped = dropped;
+    let worker = thread::spawn(move || {
+
+      let mut last_notification = Instant::now();
+
+      loop {
+
+        let disconnected = match queue_rx.recv_timeout(overflow_interval) {
+          Ok(response_data) => {
+            on_report(response_data);
+            false
+          }
+          Err(RecvTimeoutError::Timeout) => false,
+          Err(RecvTimeoutError::Disconnected) => true
+        };
+
+        if disconnected || last_notification.elapsed() >= overflow_interval {
+          let count = worker_dropped.swap(0, Ordering::Relaxed);
+          if count > 0 {
+            warn!("Dropped {} ROAccessReports due to slow report consumer", count);
+            on_overflow(count);
+          }
+          last_notification = Instant::now();
+        }
+
+        if disconnected {
+          break;
+        }
+      }
+    });
+
+    ReportDelivery {
+      task,
+      worker
+    }
+  }
+
+  /// Stops receiving new reports and waits for the delivery thread to drain
+  /// the queue and emit a final overflow notification.
+  pub fn stop(
+    self
+  ) {
+    self.task.abort();
+    let _ = self.worker.join();
+  }
+}
+
+fn enqueue_report(
+  queue_tx      : &SyncSender<LlrpResponseData>,
+  response_data : LlrpResponseData,
+  dropped       : &AtomicU64
+) {
+  match queue_tx.try_send(response_data) {
+    Ok(()) => {}
+    Err(TrySendError::Full(_)) => {
+      dropped.fetch_add(1, Ordering::Relaxed);
+    }
+    Err(TrySendError::Disconnected(_)) => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use std::sync::Mutex;
+  use tokio::sync::mpsc as tokio_mpsc;
+
+  use crate::llrp::{LlrpMessage, LlrpMessageType};
+
+  fn empty_report() -> LlrpResponse {
+    LlrpResponse::from_message(LlrpMessage::new(LlrpMessageType::ROAccessReport, 0, vec![]))
+  }
+
+  #[tokio::test]
+  async fn reports_beyond_a_full_queue_are_dropped_and_reported_as_overflow() {
+
+    let (report_tx, report_rx) = broadcast::channel(16);
+    let (started_tx, mut started_rx) = tokio_mpsc::unbounded_channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+
+    let delivered = Arc::new(AtomicU64::new(0));
+    let overflows = Arc::new(Mutex::new(Vec::new()));
+
+    let delivered_count = delivered.clone();
+    let overflow_counts = overflows.clone();
+    let delivery = ReportDelivery::start(
+      report_rx,
+      1,
+      Duration::from_millis(10),
+      move |_| {
+        let _ = started_tx.send(());
+        let _ = release_rx.lock().unwrap().recv();
+        delivered_count.fetch_add(1, Ordering::SeqCst);
+      },
+      move |dropped| overflow_counts.lock().unwrap().push(dropped)
+    );
+
+    // The first report holds up the consumer, the second fills the queue, and the
+    // last two are dropped.
+    report_tx.send(empty_report()).unwrap();
+    started_rx.recv().await.unwrap();
+    for _ in 0..3 {
+      report_tx.send(empty_report()).unwrap();
+    }
+    while !report_tx.is_empty() {
+      tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    drop(release_tx);
+    drop(report_tx);
+    tokio::task::spawn_blocking(move || delivery.stop()).await.unwrap();
+
+    assert_eq!(delivered.load(Ordering::SeqCst), 2);
+    assert_eq!(overflows.lock().unwrap().iter().sum::<u64>(), 2);
+  }
+}
\ No newline at end of file
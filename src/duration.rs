@@ -0,0 +1,286 @@
+//! Serde helpers for the duration fields of the configuration. A field accepts
+//! either a bare number in its own unit, e.g. `"response_timeout": 5000`, or a
+//! string with units, e.g. `"5s"`, `"500ms"`, `"1m30s"` or `"2h"`. Values are
+//! serialized back as bare numbers.
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use llrp_lib::duration::parse_duration;
+//! assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+//! assert!(parse_duration("5").is_err());
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+
+/// Parses a duration written as one or more `<number><unit>` pairs, with units `us`,
+/// `ms`, `s`, `m` and `h`. A number without a unit is rejected, as it is exactly
+/// the ambiguity the units are there to remove.
+pub fn parse_duration(
+  text: &str
+) -> Result<Duration, String> {
+
+  let invalid = || format!("invalid duration {:?}, expected e.g. \"500ms\", \"2s\" or \"1m\"", text);
+
+  let mut rest = text.trim();
+  if rest.is_empty() {
+    return Err(invalid());
+  }
+
+  let mut total = Duration::ZERO;
+
+  while !rest.is_empty() {
+
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+    rest = &rest[digits..];
+
+    let unit_length = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+    let unit = match rest[..unit_length].trim() {
+      "us" => Duration::from_micros(1),
+      "ms" => Duration::from_millis(1),
+      "s"  => Duration::from_secs(1),
+      "m"  => Duration::from_secs(60),
+      "h"  => Duration::from_secs(3600),
+      _    => return Err(invalid())
+    };
+    rest = &rest[unit_length..];
+
+    let part = u32::try_from(amount).ok().and_then(|amount| unit.checked_mul(amount)).ok_or_else(invalid)?;
+    total = total.checked_add(part).ok_or_else(invalid)?;
+  }
+
+  Ok(total)
+}
+
+/// A duration field's value as written: a bare number in the field's unit, or a
+/// parsed string.
+enum Written {
+  Bare(u64),
+  Parsed(Duration)
+}
+
+struct WrittenVisitor;
+
+impl Visitor<'_> for WrittenVisitor {
+
+  type Value = Written;
+
+  fn expecting(
+    &self,
+    formatter: &mut fmt::Formatter
+  ) -> fmt::Result {
+    formatter.write_str("a number or a duration such as \"500ms\", \"2s\" or \"1m\"")
+  }
+
+  fn visit_u64<E: de::Error>(
+    self,
+    value: u64
+  ) -> Result<Written, E> {
+    Ok(Written::Bare(value))
+  }
+
+  fn visit_i64<E: de::Error>(
+    self,
+    value: i64
+  ) -> Result<Written, E> {
+    u64::try_from(value)
+      .map(Written::Bare)
+      .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+  }
+
+  fn visit_str<E: de::Error>(
+    self,
+    value: &str
+  ) -> Result<Written, E> {
+    parse_duration(value).map(Written::Parsed).map_err(E::custom)
+  }
+}
+
+/// Reads a duration field as a whole number of `unit`.
+fn deserialize_in<'de, D, T>(
+  deserializer : D,
+  unit         : Duration,
+  unit_name    : &str
+) -> Result<T, D::Error>
+where
+  D : Deserializer<'de>,
+  T : TryFrom<u64>
+{
+  let amount = match deserializer.deserialize_any(WrittenVisitor)? {
+    Written::Bare(amount) => amount,
+    Written::Parsed(duration) => {
+      if duration.as_nanos() % unit.as_nanos() != 0 {
+        return Err(de::Error::custom(format!("duration {:?} is not a whole number of {}", duration, unit_name)));
+      }
+      u64::try_from(duration.as_nanos() / unit.as_nanos()).map_err(de::Error::custom)?
+    }
+  };
+
+  T::try_from(amount).map_err(|_| de::Error::custom(format!("duration of {} {} is out of range", amount, unit_name)))
+}
+
+/// Reads an optional duration field, present as a number or a string.
+fn deserialize_option_in<'de, D, T>(
+  deserializer : D,
+  unit         : Duration,
+  unit_name    : &str
+) -> Result<Option<T>, D::Error>
+where
+  D : Deserializer<'de>,
+  T : TryFrom<u64>
+{
+  struct OptionVisitor<T>(Duration, String, PhantomData<T>);
+
+  impl<'de, T: TryFrom<u64>> Visitor<'de> for OptionVisitor<T> {
+
+    type Value = Option<T>;
+
+    fn expecting(
+      &self,
+      formatter: &mut fmt::Formatter
+    ) -> fmt::Result {
+      formatter.write_str("null, a number or a duration such as \"500ms\", \"2s\" or \"1m\"")
+    }
+
+    fn visit_none<E: de::Error>(
+      self
+    ) -> Result<Option<T>, E> {
+      Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(
+      self
+    ) -> Result<Option<T>, E> {
+      Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+      self,
+      deserializer: D
+    ) -> Result<Option<T>, D::Error> {
+      deserialize_in(deserializer, self.0, &self.1).map(Some)
+    }
+  }
+
+  deserializer.deserialize_option(OptionVisitor(unit, unit_name.to_string(), PhantomData))
+}
+
+/// Writes a duration field back as the bare number it holds.
+pub fn serialize<S, T>(
+  value      : &T,
+  serializer : S
+) -> Result<S::Ok, S::Error>
+where
+  S : Serializer,
+  T : Serialize
+{
+  value.serialize(serializer)
+}
+
+/// For fields holding milliseconds.
+pub mod millis {
+
+  use super::*;
+  pub use super::serialize;
+
+  pub fn deserialize<'de, D, T>(
+    deserializer: D
+  ) -> Result<T, D::Error>
+  where
+    D : Deserializer<'de>,
+    T : TryFrom<u64>
+  {
+    deserialize_in(deserializer, Duration::from_millis(1), "milliseconds")
+  }
+}
+
+/// For fields holding seconds.
+pub mod secs {
+
+  use super::*;
+  pub use super::serialize;
+
+  pub fn deserialize<'de, D, T>(
+    deserializer: D
+  ) -> Result<T, D::Error>
+  where
+    D : Deserializer<'de>,
+    T : TryFrom<u64>
+  {
+    deserialize_in(deserializer, Duration::from_secs(1), "seconds")
+  }
+}
+
+/// For optional fields holding milliseconds.
+pub mod option_millis {
+
+  use super::*;
+  pub use super::serialize;
+
+  pub fn deserialize<'de, D, T>(
+    deserializer: D
+  ) -> Result<Option<T>, D::Error>
+  where
+    D : Deserializer<'de>,
+    T : TryFrom<u64>
+  {
+    deserialize_option_in(deserializer, Duration::from_millis(1), "milliseconds")
+  }
+}
+
+/// For optional fields holding seconds.
+pub mod option_secs {
+
+  use super::*;
+  pub use super::serialize;
+
+  pub fn deserialize<'de, D, T>(
+    deserializer: D
+  ) -> Result<Option<T>, D::Error>
+  where
+    D : Deserializer<'de>,
+    T : TryFrom<u64>
+  {
+    deserialize_option_in(deserializer, Duration::from_secs(1), "seconds")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use serde::Deserialize;
+
+  #[derive(Debug, Deserialize)]
+  struct Timeouts {
+    #[serde(with = "millis")]
+    response_timeout : u64,
+    #[serde(default, with = "option_secs")]
+    no_tag_reads     : Option<u64>,
+    #[serde(default, with = "millis")]
+    keepalive        : u32
+  }
+
+  #[test]
+  fn durations_accept_bare_numbers_and_units() {
+
+    assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("250us"), Ok(Duration::from_micros(250)));
+    assert!(parse_duration("5").is_err());
+    assert!(parse_duration("5 minutes").is_err());
+
+    let timeouts: Timeouts = serde_json::from_str(r#"{ "response_timeout": "5s", "no_tag_reads": "2m", "keepalive": 1000 }"#).unwrap();
+    assert_eq!((timeouts.response_timeout, timeouts.no_tag_reads, timeouts.keepalive), (5000, Some(120), 1000));
+
+    let timeouts: Timeouts = serde_json::from_str(r#"{ "response_timeout": 5, "no_tag_reads": null }"#).unwrap();
+    assert_eq!((timeouts.response_timeout, timeouts.no_tag_reads, timeouts.keepalive), (5, None, 0));
+
+    let error = serde_json::from_str::<Timeouts>(r#"{ "response_timeout": "5", "no_tag_reads": "1500ms" }"#).unwrap_err();
+    assert!(error.to_string().contains("invalid duration"));
+    assert!(serde_json::from_str::<Timeouts>(r#"{ "response_timeout": 5, "no_tag_reads": "1500ms" }"#).is_err());
+  }
+}
\ No newline at end of file
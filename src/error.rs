@@ -0,0 +1,237 @@
+//! The error returned by `LlrpClient`. Its variants tell apart the failures a
+//! caller reacts to differently, e.g. retrying after a timeout or a lost connection
+//! but not after the reader refused a request, and each has a stable numeric code,
+//! which is what `get_last_error_code` returns across the C API.
+//!
+//! | Code | Variant            |
+//! |------|--------------------|
+//! | 1    | `Io`               |
+//! | 2    | `Timeout`          |
+//! | 3    | `ConnectionClosed` |
+//! | 4    | `ProtocolDecode`   |
+//! | 5    | `ReaderStatus`     |
+//! | 6    | `Config`           |
+//! | 7    | `Unsupported`      |
+//! | 8    | `InvalidArgument`  |
+//! | 9    | `InvalidState`     |
+//! | 99   | `Other`            |
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::llrp::LlrpMessageType;
+use crate::params::{LLRPStatus, ParameterDecodeError};
+
+/// A request the reader answered with a non-success LLRPStatus, in its response or
+/// in an ErrorMessage.
+///
+/// Fields:
+/// - `request`: Type of the failed request, e.g. `AddROSpec`.
+/// - `status`: The reader's status, with its description and any field or parameter error.
+#[derive(Debug, Clone)]
+pub struct LlrpStatusError {
+  pub request : LlrpMessageType,
+  pub status  : LLRPStatus
+}
+
+impl LlrpStatusError {
+
+  pub fn status_code(
+    &self
+  ) -> u16 {
+    self.status.status_code
+  }
+
+  /// Whether the reader refused the spec ID the request names, as readers answer
+  /// a request for an ROSpec or AccessSpec that does not exist or is not in a
+  /// state the request applies to: with A_Invalid or an M_FieldError.
+  pub fn rejects_spec_id(
+    &self
+  ) -> bool {
+    matches!(self.status.status_code, 101 | 300)
+  }
+}
+
+impl fmt::Display for LlrpStatusError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    write!(f, "{:?} failed: {}", self.request, self.status)
+  }
+}
+
+impl Error for LlrpStatusError {}
+
+/// Why a client request failed.
+///
+/// Variants:
+/// - `Io`: Reading from or writing to the connection failed.
+/// - `Timeout`: The reader did not answer, or connect, in time.
+/// - `ConnectionClosed`: The connection was closed while the request was outstanding.
+/// - `ProtocolDecode`: The reader sent a message the client could not decode, or not the one expected.
+/// - `ReaderStatus`: The reader answered with a non-success LLRPStatus.
+/// - `Config`: The configuration does not allow the request, e.g. an ROSpec without antennas.
+/// - `Unsupported`: The reader, the protocol version or the client's mode does not support the request.
+/// - `InvalidArgument`: An argument of the request is out of range.
+/// - `InvalidState`: The request does not fit what the caller set up before, e.g. no callback is registered.
+/// - `Other`: Any other failure, e.g. of a connect hook.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LlrpError {
+  Io(io::Error),
+  Timeout(String),
+  ConnectionClosed(String),
+  ProtocolDecode(String),
+  ReaderStatus(LlrpStatusError),
+  Config(String),
+  Unsupported(String),
+  InvalidArgument(String),
+  InvalidState(String),
+  Other(String)
+}
+
+impl LlrpError {
+
+  /// The variant's stable numeric code, as listed in the module documentation.
+  pub fn code(
+    &self
+  ) -> i32 {
+    match self {
+      LlrpError::Io(_)               => 1,
+      LlrpError::Timeout(_)          => 2,
+      LlrpError::ConnectionClosed(_) => 3,
+      LlrpError::ProtocolDecode(_)   => 4,
+      LlrpError::ReaderStatus(_)     => 5,
+      LlrpError::Config(_)           => 6,
+      LlrpError::Unsupported(_)      => 7,
+      LlrpError::InvalidArgument(_)  => 8,
+      LlrpError::InvalidState(_)     => 9,
+      LlrpError::Other(_)            => 99
+    }
+  }
+
+  /// Whether sending the request again may succeed, i.e. it timed out or the
+  /// connection was lost.
+  pub fn is_transient(
+    &self
+  ) -> bool {
+    matches!(self, LlrpError::Io(_) | LlrpError::Timeout(_) | LlrpError::ConnectionClosed(_))
+  }
+
+  /// Prefixes the message with what the client was doing, keeping the variant.
+  pub fn context(
+    self,
+    context: &str
+  ) -> Self {
+    match self {
+      LlrpError::Io(e)                     => LlrpError::Io(io::Error::new(e.kind(), format!("{}: {}", context, e))),
+      LlrpError::Timeout(message)          => LlrpError::Timeout(format!("{}: {}", context, message)),
+      LlrpError::ConnectionClosed(message) => LlrpError::ConnectionClosed(format!("{}: {}", context, message)),
+      LlrpError::ProtocolDecode(message)   => LlrpError::ProtocolDecode(format!("{}: {}", context, message)),
+      LlrpError::ReaderStatus(e)           => LlrpError::ReaderStatus(e),
+      LlrpError::Config(message)           => LlrpError::Config(format!("{}: {}", context, message)),
+      LlrpError::Unsupported(message)      => LlrpError::Unsupported(format!("{}: {}", context, message)),
+      LlrpError::InvalidArgument(message)  => LlrpError::InvalidArgument(format!("{}: {}", context, message)),
+      LlrpError::InvalidState(message)     => LlrpError::InvalidState(format!("{}: {}", context, message)),
+      LlrpError::Other(message)            => LlrpError::Other(format!("{}: {}", context, message))
+    }
+  }
+}
+
+impl fmt::Display for LlrpError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    match self {
+      LlrpError::Io(e)                     => write!(f, "{}", e),
+      LlrpError::Timeout(message)
+      | LlrpError::ConnectionClosed(message)
+      | LlrpError::ProtocolDecode(message)
+      | LlrpError::Config(message)
+      | LlrpError::Unsupported(message)
+      | LlrpError::InvalidArgument(message)
+      | LlrpError::InvalidState(message)
+      | LlrpError::Other(message)          => write!(f, "{}", message),
+      LlrpError::ReaderStatus(e)           => write!(f, "{}", e)
+    }
+  }
+}
+
+impl Error for LlrpError {
+  fn source(
+    &self
+  ) -> Option<&(dyn Error + 'static)> {
+    match self {
+      LlrpError::Io(e)           => Some(e),
+      LlrpError::ReaderStatus(e) => Some(e),
+      _                          => None
+    }
+  }
+}
+
+/// Sorts I/O errors by their kind: timeouts, lost connections and undecodable
+/// data get their own variants.
+impl From<io::Error> for LlrpError {
+  fn from(
+    e: io::Error
+  ) -> Self {
+    match e.kind() {
+      io::ErrorKind::TimedOut => LlrpError::Timeout(e.to_string()),
+      io::ErrorKind::UnexpectedEof
+      | io::ErrorKind::ConnectionReset
+      | io::ErrorKind::ConnectionAborted
+      | io::ErrorKind::BrokenPipe => LlrpError::ConnectionClosed(e.to_string()),
+      io::ErrorKind::InvalidData => LlrpError::ProtocolDecode(e.to_string()),
+      _ => LlrpError::Io(e)
+    }
+  }
+}
+
+impl From<ParameterDecodeError> for LlrpError {
+  fn from(
+    e: ParameterDecodeError
+  ) -> Self {
+    LlrpError::ProtocolDecode(e.to_string())
+  }
+}
+
+impl From<LlrpStatusError> for LlrpError {
+  fn from(
+    e: LlrpStatusError
+  ) -> Self {
+    LlrpError::ReaderStatus(e)
+  }
+}
+
+impl From<serde_json::Error> for LlrpError {
+  fn from(
+    e: serde_json::Error
+  ) -> Self {
+    LlrpError::Other(e.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  #[test]
+  fn io_errors_are_sorted_into_variants_that_keep_their_code() {
+
+    assert!(matches!(LlrpError::from(io::Error::new(io::ErrorKind::TimedOut, "no answer")), LlrpError::Timeout(_)));
+    assert!(matches!(LlrpError::from(io::Error::new(io::ErrorKind::UnexpectedEof, "closed")), LlrpError::ConnectionClosed(_)));
+    assert!(matches!(LlrpError::from(io::Error::from(ParameterDecodeError::Overflow { path: "ROSpec".into() })), LlrpError::ProtocolDecode(_)));
+
+    let error = LlrpError::from(io::Error::new(io::ErrorKind::TimedOut, "Timeout while waiting for response"))
+      .context("Startup action SetConfig failed");
+    assert_eq!(error.code(), 2);
+    assert_eq!(error.to_string(), "Startup action SetConfig failed: Timeout while waiting for response");
+
+    assert!(LlrpError::Timeout("no answer".into()).is_transient());
+    assert!(!LlrpError::Config("An ROSpec needs at least one antenna".into()).is_transient());
+  }
+}
\ No newline at end of file
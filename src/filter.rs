@@ -0,0 +1,119 @@
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::params::TagReportData;
+
+/// Selects the tags of ROAccessReports passed on to the host. A tag must meet every
+/// condition that is set; the default filter passes all tags.
+///
+/// Fields:
+/// - `antennas`: Antennas whose reads pass (default - All).
+/// - `epc_prefixes`: Hex prefixes, one of which the tag EPC must start with (default - Any EPC).
+/// - `min_rssi`: Lowest PeakRSSI, in dBm, that passes. Tags reported without a PeakRSSI
+///   do not pass (default - Any RSSI).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReportFilter {
+  #[serde(default)]
+  pub antennas     : Vec<u16>,
+  #[serde(default)]
+  pub epc_prefixes : Vec<String>,
+  #[serde(default)]
+  pub min_rssi     : Option<i8>
+}
+
+/// A filter shared between the code changing it and the report delivery applying it.
+pub type SharedReportFilter = Arc<RwLock<ReportFilter>>;
+
+impl ReportFilter {
+
+  /// Checks the EPC prefixes are hex and lowercases them to match the tag EPC format.
+  pub fn validated(
+    mut self
+  ) -> Result<Self, String> {
+
+    if let Some(prefix) = self.epc_prefixes.iter().find(|prefix| !prefix.chars().all(|c| c.is_ascii_hexdigit())) {
+      return Err(format!("epc_prefixes entry {:?} is not hex", prefix));
+    }
+
+    self.epc_prefixes.iter_mut().for_each(|prefix| prefix.make_ascii_lowercase());
+
+    Ok(self)
+  }
+
+  pub fn matches(
+    &self,
+    tag: &TagReportData
+  ) -> bool {
+
+    if !self.antennas.is_empty() && !tag.antenna_id.is_some_and(|antenna_id| self.antennas.contains(&antenna_id)) {
+      return false;
+    }
+
+    if let Some(min_rssi) = self.min_rssi {
+      if tag.peak_rssi.is_none_or(|peak_rssi| peak_rssi < min_rssi) {
+        return false;
+      }
+    }
+
+    if !self.epc_prefixes.is_empty() {
+      let epc = tag.to_string();
+      if !self.epc_prefixes.iter().any(|prefix| epc.starts_with(prefix.as_str())) {
+        return false;
+      }
+    }
+
+    true
+  }
+
+  /// Removes the tags that do not match.
+  pub fn apply(
+    &self,
+    tags: &mut Vec<TagReportData>
+  ) {
+    if *self != ReportFilter::default() {
+      tags.retain(|tag| self.matches(tag));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::llrp::ReceiveTimestamp;
+
+  /// TagReportData contents: EPC-96, AntennaID and, when given, PeakRSSI.
+  fn tag(
+    epc        : [u8; 12],
+    antenna_id : u16,
+    peak_rssi  : Option<i8>
+  ) -> TagReportData {
+
+    let mut buf = vec![0x8D];
+    buf.extend_from_slice(&epc);
+    buf.push(0x81);
+    buf.extend_from_slice(&antenna_id.to_be_bytes());
+    if let Some(peak_rssi) = peak_rssi {
+      buf.extend_from_slice(&[0x86, peak_rssi as u8]);
+    }
+
+    TagReportData::decode(&buf, ReceiveTimestamp::now()).unwrap()
+  }
+
+  #[test]
+  fn tags_must_meet_every_condition_set() {
+
+    let epc = [0xE2, 0x80, 0x11, 0x60, 0, 0, 0, 0, 0, 0, 0, 0x01];
+    let filter: ReportFilter = serde_json::from_str(r#"{"antennas":[1,2],"epc_prefixes":["E280"],"min_rssi":-60}"#).unwrap();
+    let filter = filter.validated().unwrap();
+
+    assert!(ReportFilter::default().matches(&tag([0; 12], 3, None)));
+    assert!(filter.matches(&tag(epc, 2, Some(-60))));
+    assert!(!filter.matches(&tag(epc, 3, Some(-50))));
+    assert!(!filter.matches(&tag(epc, 1, Some(-61))));
+    assert!(!filter.matches(&tag(epc, 1, None)));
+    assert!(!filter.matches(&tag([0x30; 12], 1, Some(-50))));
+
+    let bad: ReportFilter = serde_json::from_str(r#"{"epc_prefixes":["e2g0"]}"#).unwrap();
+    assert!(bad.validated().is_err());
+  }
+}
\ No newline at end of file
@@ -0,0 +1,241 @@
+//! Golden fixtures of the bytes each message constructor puts on the wire.
+//!
+//! Every `LlrpMessage::new_*` constructor is encoded from fixed inputs and compared
+//! with its fixture in `tests/fixtures/messages`, so a change altering the encoding
+//! fails here rather than at a reader. After an intended change, regenerate the
+//! fixtures and review their diff:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test golden
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{AccessSpecConfig, C1G2FilterConfig, C1G2FilterTruncate, C1G2RFControlConfig, C1G2SingulationConfig, GpiPortConfig, GpiTriggerConfig, GpoOutputConfig, PeriodicTriggerConfig, ROSpecConfig, ReaderConfig, TagReportContentConfig};
+use crate::custom::{CustomMessage, CustomParameter};
+use crate::gpio::{GpiPort, GpoPort, PinState};
+use crate::llrp::{LlrpMessage, LlrpVersion, ReaderConfigRequest};
+use crate::rospec::{AISpec, InventoryParameterSpec, ROBoundarySpec, ROReportSpec, ROSpec, ROSpecBuilder};
+
+const MESSAGE_ID: u32 = 0x0102_0304;
+
+fn reader_config() -> ReaderConfig {
+  serde_json::from_value(serde_json::json!({
+    "hop_table_id": 1,
+    "channel_index": 2,
+    "tx_power_table_index": 81,
+    "rx_power_table_index": 1,
+    "gpi_ports": [{ "port": 1, "enabled": true }],
+    "gpo_outputs": [{ "port": 2, "state": "high" }],
+    "keepalive_spec": { "trigger": "periodic", "interval": 5000 }
+  })).unwrap()
+}
+
+fn rospec_config() -> ROSpecConfig {
+  serde_json::from_value(serde_json::json!({
+    "rospec_id": 7,
+    "priority": 1,
+    "antenna_count": 2,
+    "antennas": [1, 2],
+    "ROSpecStartTriggerType": 0,
+    "ROSpecStopTriggerType": 0,
+    "AISpecStopTriggerType": 0,
+    "InventoryParamSpecID": 1,
+    "AIProtocol": 1,
+    "ROReportTriggerType": 1,
+    "ROReportTrigger_N": 1,
+    "ReportContentSelector": 1,
+    "inventory_command": {
+      "tag_inventory_state_aware": false,
+      "filters": [{ "memory_bank": 1, "pointer": 32, "mask": "E280", "action": 0 }],
+      "singulation": { "session": 1, "tag_population": 32, "tag_transit_time": 0 }
+    }
+  })).unwrap()
+}
+
+fn periodic_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  config.ROSpecStartTriggerType = 2;
+  config.ROSpecStopTriggerType = 1;
+  config.periodic_trigger = Some(PeriodicTriggerConfig { offset_ms: 1000, period_ms: 60_000, utc_timestamp_us: Some(1_700_000_000_000_000) });
+  config
+}
+
+fn gpi_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  config.ROSpecStartTriggerType = 3;
+  config.ROSpecStopTriggerType = 2;
+  config.start_gpi_trigger = Some(GpiTriggerConfig { port: GpiPort(1), event: PinState::High, timeout_ms: 0 });
+  config.stop_gpi_trigger = Some(GpiTriggerConfig { port: GpiPort(1), event: PinState::Low, timeout_ms: 30_000 });
+  config
+}
+
+fn duration_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  config.ROSpecStopTriggerType = 1;
+  config.AISpecStopTriggerType = 1;
+  config.rospec_stop_duration_ms = Some(30_000);
+  config.aispec_stop_duration_ms = Some(5_000);
+  config
+}
+
+fn truncated_filter_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  let command = config.inventory_command.as_mut().unwrap();
+  command.filters.insert(0, C1G2FilterConfig { memory_bank: 2, pointer: 0, mask: "E2801160".to_string(), mask_bit_count: Some(28), target: 0, action: 1, truncate: C1G2FilterTruncate::DoNotTruncate });
+  command.filters[1].truncate = C1G2FilterTruncate::Truncate;
+  config
+}
+
+fn rf_control_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  config.inventory_command.as_mut().unwrap().rf_control = Some(C1G2RFControlConfig { mode_index: 1002, tari: 12500 });
+  config
+}
+
+fn epc_memory_rospec_config() -> ROSpecConfig {
+  let mut config = rospec_config();
+  config.ReportContentSelector = serde_json::from_value(serde_json::json!({
+    "antenna_id": true,
+    "peak_rssi": true,
+    "crc": true,
+    "pc_bits": true
+  })).unwrap();
+  config
+}
+
+fn access_spec_config() -> AccessSpecConfig {
+  serde_json::from_value(serde_json::json!({
+    "access_spec_id": 3,
+    "target": { "memory_bank": 1, "pointer": 32, "tag_mask": "FFFF", "tag_data": "E280" },
+    "operation_count": 1,
+    "reads": [{ "op_spec_id": 1, "memory_bank": 2, "word_count": 2 }],
+    "writes": [{ "op_spec_id": 2, "memory_bank": 3, "word_pointer": 4, "data": [43981] }],
+    "block_erases": [{ "op_spec_id": 3, "memory_bank": 3, "word_count": 1 }],
+    "block_writes": [{ "op_spec_id": 4, "memory_bank": 3, "data": [1, 2] }],
+    "kills": [{ "op_spec_id": 5, "kill_password": 305419896 }],
+    "locks": [{ "op_spec_id": 6, "access_password": 1, "payloads": [{ "privilege": 0, "data_field": 3 }] }]
+  })).unwrap()
+}
+
+fn built_rospec() -> ROSpec {
+  ROSpecBuilder::new(8)
+    .priority(2)
+    .boundary_spec(ROBoundarySpec { start_trigger_type: 1, stop_trigger_type: 1, stop_duration_ms: 10_000, ..ROBoundarySpec::default() })
+    .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]).stop_after(500))
+    .ai_spec(AISpec::new(vec![2], vec![InventoryParameterSpec::new(2), InventoryParameterSpec::new(3).inventory_command(rospec_config().inventory_command.unwrap())]))
+    .report_spec(ROReportSpec::end_of_rospec(TagReportContentConfig::from_flags(0x0280)))
+    .build()
+    .unwrap()
+}
+
+fn looped_rospec() -> ROSpec {
+  ROSpecBuilder::new(9)
+    .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]).stop_after(1000))
+    .ai_spec(AISpec::new(vec![2], vec![InventoryParameterSpec::new(2)]).stop_after(1000))
+    .loop_count(5)
+    .build()
+    .unwrap()
+}
+
+fn state_aware_rospec() -> ROSpec {
+  ROSpecBuilder::new(10)
+    .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1).singulation(C1G2SingulationConfig::new(2, 64, 500).state_aware(1, 0))]))
+    .build()
+    .unwrap()
+}
+
+/// One message per constructor, with the name of its fixture.
+fn messages() -> Vec<(&'static str, LlrpMessage)> {
+
+  let report_extension = CustomParameter::new(25882, 50, vec![0x00, 0x01]);
+
+  vec![
+    ("enable_events_and_reports", LlrpMessage::new_enable_events_and_reports(MESSAGE_ID)),
+    ("get_reader_capabilities", LlrpMessage::new_get_reader_capabilities(MESSAGE_ID)),
+    ("get_access_specs", LlrpMessage::new_get_access_specs(MESSAGE_ID)),
+    ("get_reader_config", LlrpMessage::new_get_reader_config(MESSAGE_ID, &ReaderConfigRequest::default())),
+    ("get_reader_config_with_extensions", LlrpMessage::new_get_reader_config_with_extensions(MESSAGE_ID, &ReaderConfigRequest::default(), &[CustomParameter::new(25882, 21, 2004u32.to_be_bytes().to_vec())])),
+    ("get_gpi_port_states", LlrpMessage::new_get_gpi_port_states(MESSAGE_ID)),
+    ("get_configuration_state", LlrpMessage::new_get_configuration_state(MESSAGE_ID)),
+    ("get_antenna_properties", LlrpMessage::new_get_antenna_properties(MESSAGE_ID)),
+    ("get_antenna_configurations", LlrpMessage::new_get_antenna_configurations(MESSAGE_ID)),
+    ("set_reader_config", LlrpMessage::new_set_reader_config(MESSAGE_ID, &reader_config())),
+    ("set_gpo", LlrpMessage::new_set_gpo(MESSAGE_ID, GpoPort(2), PinState::High)),
+    ("set_gpos", LlrpMessage::new_set_gpos(MESSAGE_ID, &[GpoOutputConfig { port: GpoPort(1), state: PinState::High }, GpoOutputConfig { port: GpoPort(3), state: PinState::Low }])),
+    ("set_gpi_ports", LlrpMessage::new_set_gpi_ports(MESSAGE_ID, &[GpiPortConfig { port: GpiPort(1), enabled: true }, GpiPortConfig { port: GpiPort(2), enabled: false }])),
+    ("factory_reset", LlrpMessage::new_factory_reset(MESSAGE_ID)),
+    ("get_supported_version", LlrpMessage::new_get_supported_version(MESSAGE_ID)),
+    ("set_protocol_version", LlrpMessage::new_set_protocol_version(MESSAGE_ID, LlrpVersion::V1_1)),
+    ("custom_message", LlrpMessage::new_custom_message(MESSAGE_ID, &CustomMessage { vendor_id: 25882, message_subtype: 21, data: vec![0; 4] })),
+    ("set_reader_clock", LlrpMessage::new_set_reader_clock(MESSAGE_ID, 25254, 7, 1_700_000_000_000_000)),
+    ("set_antenna_configuration", LlrpMessage::new_set_antenna_configuration(MESSAGE_ID, 2, &reader_config(), false)),
+    ("add_rospec", LlrpMessage::new_add_rospec(MESSAGE_ID, &rospec_config(), &[])),
+    ("add_rospec_with_report_extension", LlrpMessage::new_add_rospec(MESSAGE_ID, &rospec_config(), &[report_extension])),
+    ("add_rospec_periodic_start", LlrpMessage::new_add_rospec(MESSAGE_ID, &periodic_rospec_config(), &[])),
+    ("add_rospec_gpi_triggers", LlrpMessage::new_add_rospec(MESSAGE_ID, &gpi_rospec_config(), &[])),
+    ("add_rospec_duration_stops", LlrpMessage::new_add_rospec(MESSAGE_ID, &duration_rospec_config(), &[])),
+    ("add_rospec_truncated_filter", LlrpMessage::new_add_rospec(MESSAGE_ID, &truncated_filter_rospec_config(), &[])),
+    ("add_rospec_rf_control", LlrpMessage::new_add_rospec(MESSAGE_ID, &rf_control_rospec_config(), &[])),
+    ("add_rospec_epc_memory_selector", LlrpMessage::new_add_rospec(MESSAGE_ID, &epc_memory_rospec_config(), &[])),
+    ("add_built_rospec", LlrpMessage::new_add_built_rospec(MESSAGE_ID, &built_rospec())),
+    ("add_built_rospec_loop", LlrpMessage::new_add_built_rospec(MESSAGE_ID, &looped_rospec())),
+    ("add_built_rospec_state_aware_singulation", LlrpMessage::new_add_built_rospec(MESSAGE_ID, &state_aware_rospec())),
+    ("enable_rospec", LlrpMessage::new_enable_rospec(MESSAGE_ID, 7)),
+    ("disable_rospec", LlrpMessage::new_disable_rospec(MESSAGE_ID, 7)),
+    ("start_rospec", LlrpMessage::new_start_rospec(MESSAGE_ID, 7)),
+    ("stop_rospec", LlrpMessage::new_stop_rospec(MESSAGE_ID, 7)),
+    ("delete_rospec", LlrpMessage::new_delete_rospec(MESSAGE_ID, 7)),
+    ("add_access_spec", LlrpMessage::new_add_access_spec(MESSAGE_ID, &access_spec_config())),
+    ("enable_access_spec", LlrpMessage::new_enable_access_spec(MESSAGE_ID, 3)),
+    ("delete_access_spec", LlrpMessage::new_delete_access_spec(MESSAGE_ID, 3)),
+    ("get_report", LlrpMessage::new_get_report(MESSAGE_ID))
+  ]
+}
+
+fn fixture_path(
+  name: &str
+) -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/messages").join(format!("{}.hex", name))
+}
+
+/// Hex, 16 bytes to a line, so fixture diffs point at the bytes that changed.
+fn to_hex_lines(
+  bytes: &[u8]
+) -> String {
+  bytes.chunks(16)
+    .map(|line| line.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<String>>().join(" ") + "\n")
+    .collect()
+}
+
+#[test]
+fn messages_encode_to_their_golden_fixtures() {
+
+  let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+  let mut mismatched = Vec::new();
+
+  for (name, message) in messages() {
+
+    let encoded = to_hex_lines(&message.encode());
+    let path = fixture_path(name);
+
+    if update {
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(&path, &encoded).unwrap();
+      continue;
+    }
+
+    match fs::read_to_string(&path) {
+      Ok(golden) if golden == encoded => {}
+      Ok(golden) => mismatched.push(format!("{}:\n  golden:  {}\n  encoded: {}", name, golden.trim_end(), encoded.trim_end())),
+      Err(e) => mismatched.push(format!("{}: cannot read {}: {}", name, path.display(), e))
+    }
+  }
+
+  assert!(
+    mismatched.is_empty(),
+    "Encoding differs from the golden fixtures (regenerate with UPDATE_GOLDEN=1 if intended):\n{}",
+    mismatched.join("\n")
+  );
+}
\ No newline at end of file
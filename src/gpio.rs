@@ -0,0 +1,71 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// A general purpose input port, numbered from 1 as reported in GPIOCapabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct GpiPort(pub u16);
+
+/// A general purpose output port, numbered from 1 as reported in GPIOCapabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct GpoPort(pub u16);
+
+impl fmt::Display for GpiPort {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    write!(f, "GPI {}", self.0)
+  }
+}
+
+impl fmt::Display for GpoPort {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    write!(f, "GPO {}", self.0)
+  }
+}
+
+/// The level of a GPIO pin. Readers report `Unknown` for GPI ports whose state
+/// they cannot determine; outputs are only ever driven `Low` or `High`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinState {
+  Low,
+  High,
+  Unknown
+}
+
+impl PinState {
+
+  /// The GPIPortCurrentState `State` field value.
+  pub fn value(
+    &self
+  ) -> u8 {
+    match self {
+      PinState::Low     => 0,
+      PinState::High    => 1,
+      PinState::Unknown => 2
+    }
+  }
+
+  pub fn from_value(
+    value: u8
+  ) -> Self {
+    match value {
+      0 => PinState::Low,
+      1 => PinState::High,
+      _ => PinState::Unknown
+    }
+  }
+
+  /// The GPOWriteData `GPOData` bit, set for `High`.
+  pub fn is_high(
+    &self
+  ) -> bool {
+    *self == PinState::High
+  }
+}
\ No newline at end of file
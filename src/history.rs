@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// A single entry in the connection history.
+///
+/// Fields:
+/// - `timestamp_ms`: UTC time of the event in milliseconds since the Unix epoch.
+/// - `kind`: What happened to the connection.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+  pub timestamp_ms : i64,
+  pub kind         : ConnectionEventKind
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+  Connected        { host: String, connect_duration_ms: u64 },
+  ConnectFailed    { host: String, reason: String },
+  Disconnected     { reason: String, session_duration_ms: u64 },
+  ReconnectAttempt { attempt: u32 }
+}
+
+/// Bounded ring buffer of recent connection events, shared between the
+/// client and its receive loop. Each event is also published to subscribers as
+/// it is recorded.
+#[derive(Debug, Clone)]
+pub struct ConnectionHistory {
+  capacity : usize,
+  events   : Arc<Mutex<VecDeque<ConnectionEvent>>>,
+  event_tx : broadcast::Sender<ConnectionEvent>
+}
+
+impl ConnectionHistory {
+
+  pub fn new(
+    capacity: usize
+  ) -> Self {
+    ConnectionHistory {
+      capacity : capacity.max(1),
+      events   : Arc::new(Mutex::new(VecDeque::with_capacity(capacity.max(1)))),
+      event_tx : broadcast::channel(capacity.max(1)).0
+    }
+  }
+
+  pub fn record(
+    &self,
+    kind: ConnectionEventKind
+  ) {
+
+    let mut events = self.events.lock().unwrap();
+
+    if events.len() == self.capacity {
+      events.pop_front();
+    }
+
+    let event = ConnectionEvent {
+      timestamp_ms: Utc::now().timestamp_millis(),
+      kind
+    };
+
+    let _ = self.event_tx.send(event.clone());
+    events.push_back(event);
+  }
+
+  pub fn subscribe(
+    &self
+  ) -> broadcast::Receiver<ConnectionEvent> {
+    self.event_tx.subscribe()
+  }
+
+  /// Returns a snapshot of the recorded events, oldest first.
+  pub fn snapshot(
+    &self
+  ) -> Vec<ConnectionEvent> {
+    self.events.lock().unwrap().iter().cloned().collect()
+  }
+}
\ No newline at end of file
@@ -0,0 +1,220 @@
+//! Impinj reader extensions (Speedway, xSpan, xArray and R700 readers).
+//!
+//! Impinj data travels in Custom parameters and CUSTOM_MESSAGEs under Impinj's
+//! vendor identifier. A reader only reports the extension parameters of tags after
+//! the client sends IMPINJ_ENABLE_EXTENSIONS on the connection, and then only those
+//! the ROSpec's ImpinjTagReportContentSelector enables.
+
+use std::io;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::config::ImpinjConfig;
+use crate::custom::{register_custom_parameter, CustomMessage, CustomParameter};
+use crate::llrp::LlrpParameterType;
+use crate::params::{parse_parameters, DecodeContext, LLRPStatus, ParameterDecodeError, TagReportData};
+
+/// Impinj's IANA Private Enterprise Number.
+pub const IMPINJ_VENDOR_ID: u32 = 25882;
+
+const IMPINJ_ENABLE_EXTENSIONS          : u8 = 21;
+const IMPINJ_ENABLE_EXTENSIONS_RESPONSE : u8 = 22;
+
+const IMPINJ_REQUESTED_DATA              : u32 = 21;
+const IMPINJ_READER_TEMPERATURE          : u32 = 37;
+const IMPINJ_TAG_REPORT_CONTENT_SELECTOR : u32 = 50;
+const IMPINJ_ENABLE_RF_PHASE_ANGLE       : u32 = 52;
+const IMPINJ_ENABLE_PEAK_RSSI            : u32 = 53;
+const IMPINJ_RF_PHASE_ANGLE              : u32 = 56;
+const IMPINJ_PEAK_RSSI                   : u32 = 57;
+
+/// The ImpinjRequestedData value asking GET_READER_CONFIG for the ImpinjReaderTemperature.
+const IMPINJ_REQUEST_READER_TEMPERATURE : u32 = 2004;
+
+/// Registers the decoders of the Impinj tag report parameters, so they are reported
+/// with their decoded values, and of ImpinjReaderTemperature, which the reader
+/// returns in GET_READER_CONFIG_RESPONSEs and feeds the client's telemetry.
+pub fn register_decoders() {
+  register_custom_parameter(IMPINJ_VENDOR_ID, IMPINJ_RF_PHASE_ANGLE, "ImpinjRFPhaseAngle", decode_rf_phase_angle);
+  register_custom_parameter(IMPINJ_VENDOR_ID, IMPINJ_PEAK_RSSI, "ImpinjPeakRSSI", decode_peak_rssi);
+  register_custom_parameter(IMPINJ_VENDOR_ID, IMPINJ_READER_TEMPERATURE, "ImpinjReaderTemperature", decode_reader_temperature);
+}
+
+fn decode_rf_phase_angle(
+  buf: &[u8]
+) -> Result<serde_json::Value, ParameterDecodeError> {
+  let phase_angle = read_u16(buf, "PhaseAngle")?;
+  Ok(json!({ "phase_angle": phase_angle, "degrees": phase_angle_degrees(phase_angle) }))
+}
+
+fn decode_peak_rssi(
+  buf: &[u8]
+) -> Result<serde_json::Value, ParameterDecodeError> {
+  let rssi_cdbm = read_u16(buf, "RSSI")? as i16;
+  Ok(json!({ "rssi_cdbm": rssi_cdbm }))
+}
+
+fn decode_reader_temperature(
+  buf: &[u8]
+) -> Result<serde_json::Value, ParameterDecodeError> {
+  let temperature_c = read_u16(buf, "Temperature")? as i16;
+  Ok(json!({ "temperature_c": temperature_c }))
+}
+
+fn read_u16(
+  buf   : &[u8],
+  field : &str
+) -> Result<u16, ParameterDecodeError> {
+  match buf {
+    [high, low, ..] => Ok(u16::from_be_bytes([*high, *low])),
+    _ => Err(ParameterDecodeError::too_short(format!(".{}", field), 2, buf.len()))
+  }
+}
+
+/// Readers report the phase angle in 4096ths of a full turn.
+fn phase_angle_degrees(
+  phase_angle: u16
+) -> f64 {
+  phase_angle as f64 * 360.0 / 4096.0
+}
+
+/// The IMPINJ_ENABLE_EXTENSIONS message; its only field is reserved.
+pub fn enable_extensions() -> CustomMessage {
+  CustomMessage {
+    vendor_id       : IMPINJ_VENDOR_ID,
+    message_subtype : IMPINJ_ENABLE_EXTENSIONS,
+    data            : vec![0; 4]
+  }
+}
+
+/// Checks the IMPINJ_ENABLE_EXTENSIONS_RESPONSE a reader answered with reports success.
+pub fn check_enable_extensions_response(
+  reply: &CustomMessage
+) -> io::Result<()> {
+
+  if reply.vendor_id != IMPINJ_VENDOR_ID || reply.message_subtype != IMPINJ_ENABLE_EXTENSIONS_RESPONSE {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+      "Expected IMPINJ_ENABLE_EXTENSIONS_RESPONSE, received CustomMessage {}/{}",
+      reply.vendor_id, reply.message_subtype
+    )));
+  }
+
+  let parameters = parse_parameters(&reply.data).within("ImpinjEnableExtensionsResponse")?;
+
+  let status = match parameters.iter().find(|parameter| parameter.param_type == LlrpParameterType::LLRPStatus) {
+    Some(parameter) => LLRPStatus::decode(&parameter.param_value).within("ImpinjEnableExtensionsResponse")?,
+    None => return Err(io::Error::new(io::ErrorKind::InvalidData, "IMPINJ_ENABLE_EXTENSIONS_RESPONSE carries no LLRPStatus"))
+  };
+
+  if !status.succeeded() {
+    return Err(io::Error::other(format!("Reader refused IMPINJ_ENABLE_EXTENSIONS: {}", status)));
+  }
+
+  Ok(())
+}
+
+/// The ImpinjRequestedData added to a GET_READER_CONFIG, so the reader returns its
+/// ImpinjReaderTemperature; Impinj readers do not report it unasked.
+pub fn reader_temperature_request() -> CustomParameter {
+  CustomParameter::new(IMPINJ_VENDOR_ID, IMPINJ_REQUESTED_DATA, IMPINJ_REQUEST_READER_TEMPERATURE.to_be_bytes().to_vec())
+}
+
+/// The ImpinjTagReportContentSelector added to the ROReportSpec, enabling the
+/// tag report parameters `config` asks for.
+pub fn tag_report_content_selector(
+  config: &ImpinjConfig
+) -> CustomParameter {
+
+  let enable = |subtype: u32, enabled: bool| {
+    CustomParameter::new(IMPINJ_VENDOR_ID, subtype, (enabled as u16).to_be_bytes().to_vec()).encode()
+  };
+
+  let mut data = enable(IMPINJ_ENABLE_RF_PHASE_ANGLE, config.rf_phase_angle);
+  data.extend(enable(IMPINJ_ENABLE_PEAK_RSSI, config.peak_rssi));
+
+  CustomParameter::new(IMPINJ_VENDOR_ID, IMPINJ_TAG_REPORT_CONTENT_SELECTOR, data)
+}
+
+/// The Impinj data of a tag report, for direction and location estimates.
+///
+/// Fields:
+/// - `antenna_port`: Antenna that read the tag, from the standard AntennaID; phase angles
+///   are only comparable between reads on the same antenna.
+/// - `rf_phase_angle`: Phase angle of the tag's backscatter, in 4096ths of a full turn.
+/// - `peak_rssi_cdbm`: Peak RSSI in 1/100 dBm, finer than the standard PeakRSSI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImpinjTagData {
+  pub antenna_port   : Option<u16>,
+  pub rf_phase_angle : Option<u16>,
+  pub peak_rssi_cdbm : Option<i16>
+}
+
+impl ImpinjTagData {
+
+  pub fn from_tag(
+    tag: &TagReportData
+  ) -> Self {
+
+    let field = |subtype: u32| tag.custom.iter()
+      .find(|parameter| parameter.vendor_id == IMPINJ_VENDOR_ID && parameter.subtype == subtype)
+      .and_then(|parameter| read_u16(&parameter.data, "").ok());
+
+    ImpinjTagData {
+      antenna_port   : tag.antenna_id,
+      rf_phase_angle : field(IMPINJ_RF_PHASE_ANGLE),
+      peak_rssi_cdbm : field(IMPINJ_PEAK_RSSI).map(|rssi| rssi as i16)
+    }
+  }
+
+  pub fn rf_phase_angle_degrees(
+    &self
+  ) -> Option<f64> {
+    self.rf_phase_angle.map(phase_angle_degrees)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::llrp::ReceiveTimestamp;
+
+  #[test]
+  fn impinj_tag_report_parameters_are_decoded() {
+
+    register_decoders();
+
+    let buf = [
+      0x8D, 0xE2, 0x80, 0x11, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // EPC-96
+      0x81, 0x00, 0x02,                                                             // AntennaID
+      0x03, 0xFF, 0x00, 0x0E, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x38, 0x04, 0x00, // ImpinjRFPhaseAngle
+      0x03, 0xFF, 0x00, 0x0E, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x39, 0xE8, 0x90  // ImpinjPeakRSSI
+    ];
+
+    let tag = TagReportData::decode(&buf, ReceiveTimestamp::now()).unwrap();
+    let impinj = ImpinjTagData::from_tag(&tag);
+
+    assert_eq!(impinj, ImpinjTagData { antenna_port: Some(2), rf_phase_angle: Some(1024), peak_rssi_cdbm: Some(-6000) });
+    assert_eq!(impinj.rf_phase_angle_degrees(), Some(90.0));
+    assert_eq!(tag.custom[0].name.as_deref(), Some("ImpinjRFPhaseAngle"));
+    assert_eq!(tag.custom[1].decoded, Some(json!({ "rssi_cdbm": -6000 })));
+  }
+
+  #[test]
+  fn reader_temperature_request_asks_for_the_reader_temperature() {
+    assert_eq!(reader_temperature_request().encode(), vec![
+      0x03, 0xFF, 0x00, 0x10, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x15, 0x00, 0x00, 0x07, 0xD4
+    ]);
+  }
+
+  #[test]
+  fn report_content_selector_nests_the_enabled_parameters() {
+
+    let selector = tag_report_content_selector(&ImpinjConfig { rf_phase_angle: true, peak_rssi: false });
+
+    assert_eq!(selector.encode(), vec![
+      0x03, 0xFF, 0x00, 0x28, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x32,
+      0x03, 0xFF, 0x00, 0x0E, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x34, 0x00, 0x01,
+      0x03, 0xFF, 0x00, 0x0E, 0x00, 0x00, 0x65, 0x1A, 0x00, 0x00, 0x00, 0x35, 0x00, 0x00
+    ]);
+  }
+}
\ No newline at end of file
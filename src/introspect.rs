@@ -0,0 +1,175 @@
+//! Read-only listing of decoded parameters as name/path/value rows, so generic
+//! tools (e.g. a GUI property grid) can show a reader's capabilities and
+//! configuration without knowing every parameter struct.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::params::LlrpParameterData;
+
+/// One leaf field of a decoded parameter.
+///
+/// Fields:
+/// - `name`: The field name, e.g. `"channel_index"`; array elements take the name of their array.
+/// - `path`: Where the field sits in the parameter tree, e.g.
+///   `"AntennaConfiguration[1].rf_transmitter.channel_index"`. Parameters reported
+///   more than once are indexed in the order the reader sent them.
+/// - `value`: The value as text, e.g. `"true"` or `"5000"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParameterField {
+  pub name  : String,
+  pub path  : String,
+  pub value : String
+}
+
+/// The fields of the most recent GET_READER_CAPABILITIES and GET_READER_CONFIG
+/// responses, shared between the client and its callers.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterSnapshot {
+  fields: Arc<Mutex<SnapshotFields>>
+}
+
+#[derive(Debug, Default)]
+struct SnapshotFields {
+  capabilities  : Vec<ParameterField>,
+  reader_config : Vec<ParameterField>
+}
+
+impl ParameterSnapshot {
+
+  pub fn record_capabilities(
+    &self,
+    parameters: &[LlrpParameterData]
+  ) {
+    self.fields.lock().unwrap().capabilities = parameter_fields(parameters);
+  }
+
+  pub fn record_reader_config(
+    &self,
+    parameters: &[LlrpParameterData]
+  ) {
+    self.fields.lock().unwrap().reader_config = parameter_fields(parameters);
+  }
+
+  pub fn capabilities(
+    &self
+  ) -> Vec<ParameterField> {
+    self.fields.lock().unwrap().capabilities.clone()
+  }
+
+  pub fn reader_config(
+    &self
+  ) -> Vec<ParameterField> {
+    self.fields.lock().unwrap().reader_config.clone()
+  }
+}
+
+/// The name and serialized form of a parameter, for the parameters found in
+/// capabilities and configuration responses.
+fn parameter_value(
+  parameter: &LlrpParameterData
+) -> Option<(&'static str, Value)> {
+
+  let (name, value) = match parameter {
+    LlrpParameterData::GeneralDeviceCapabilities(value)   => ("GeneralDeviceCapabilities", serde_json::to_value(value)),
+    LlrpParameterData::LLRPCapabilities(value)            => ("LLRPCapabilities", serde_json::to_value(value)),
+    LlrpParameterData::RegulatoryCapabilities(value)      => ("RegulatoryCapabilities", serde_json::to_value(value)),
+    LlrpParameterData::C1G2LLRPCapabilities(value)        => ("C1G2LLRPCapabilities", serde_json::to_value(value)),
+    LlrpParameterData::Identification(value)              => ("Identification", serde_json::to_value(value)),
+    LlrpParameterData::AntennaProperties(value)           => ("AntennaProperties", serde_json::to_value(value)),
+    LlrpParameterData::AntennaConfiguration(value)        => ("AntennaConfiguration", serde_json::to_value(value)),
+    LlrpParameterData::ReaderEventNotificationSpec(value) => ("ReaderEventNotificationSpec", serde_json::to_value(value)),
+    LlrpParameterData::ROReportSpec(value)                => ("ROReportSpec", serde_json::to_value(value)),
+    LlrpParameterData::GPIPortCurrentState(value)         => ("GPIPortCurrentState", serde_json::to_value(value)),
+    LlrpParameterData::GPOWriteData(value)                => ("GPOWriteData", serde_json::to_value(value)),
+    LlrpParameterData::KeepaliveSpec(value)               => ("KeepaliveSpec", serde_json::to_value(value)),
+    LlrpParameterData::LLRPConfigurationStateValue(value) => ("LLRPConfigurationStateValue", serde_json::to_value(value)),
+    LlrpParameterData::Custom(value)                      => ("Custom", serde_json::to_value(value)),
+    LlrpParameterData::LLRPStatus(_) | LlrpParameterData::AccessSpec(_) => return None
+  };
+
+  value.ok().map(|value| (name, value))
+}
+
+/// Lists every leaf field of `parameters`, in the order the reader sent them.
+/// Absent optional fields are left out.
+pub fn parameter_fields(
+  parameters: &[LlrpParameterData]
+) -> Vec<ParameterField> {
+
+  let values: Vec<(&'static str, Value)> = parameters.iter().filter_map(parameter_value).collect();
+
+  let mut occurrences: HashMap<&str, usize> = HashMap::new();
+  for (name, _) in &values {
+    *occurrences.entry(name).or_default() += 1;
+  }
+
+  let mut seen: HashMap<&str, usize> = HashMap::new();
+  let mut fields = Vec::new();
+
+  for (name, value) in &values {
+
+    let index = seen.entry(name).or_default();
+    let path = if occurrences[name] > 1 { format!("{}[{}]", name, index) } else { name.to_string() };
+    *index += 1;
+
+    flatten(name, &path, value, &mut fields);
+  }
+
+  fields
+}
+
+fn flatten(
+  name   : &str,
+  path   : &str,
+  value  : &Value,
+  fields : &mut Vec<ParameterField>
+) {
+  match value {
+
+    Value::Null => {}
+
+    Value::Object(members) => {
+      for (member_name, member) in members {
+        flatten(member_name, &format!("{}.{}", path, member_name), member, fields);
+      }
+    }
+
+    Value::Array(elements) => {
+      for (index, element) in elements.iter().enumerate() {
+        flatten(name, &format!("{}[{}]", path, index), element, fields);
+      }
+    }
+
+    Value::String(text) => fields.push(ParameterField { name: name.to_string(), path: path.to_string(), value: text.clone() }),
+
+    scalar => fields.push(ParameterField { name: name.to_string(), path: path.to_string(), value: scalar.to_string() })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::params::{AntennaProperties, KeepaliveSpec};
+
+  #[test]
+  fn repeated_parameters_are_indexed_and_fields_flattened() {
+
+    let parameters = vec![
+      LlrpParameterData::AntennaProperties(AntennaProperties { antenna_connected: true, antenna_id: 1, antenna_gain: 0 }),
+      LlrpParameterData::AntennaProperties(AntennaProperties { antenna_connected: false, antenna_id: 2, antenna_gain: 0 }),
+      LlrpParameterData::KeepaliveSpec(KeepaliveSpec { trigger_type: 1, periodic_trigger_value: 5000 })
+    ];
+
+    let fields = parameter_fields(&parameters);
+    let field = |path: &str| fields.iter().find(|field| field.path == path).map(|field| (field.name.as_str(), field.value.as_str()));
+
+    assert_eq!(field("AntennaProperties[0].antenna_connected"), Some(("antenna_connected", "true")));
+    assert_eq!(field("AntennaProperties[1].antenna_id"), Some(("antenna_id", "2")));
+    assert_eq!(field("KeepaliveSpec.periodic_trigger_value"), Some(("periodic_trigger_value", "5000")));
+    assert_eq!(fields.len(), 8);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,277 @@
+//! Append-only journal of every LLRP message exchanged with a reader, for audit.
+//!
+//! Each entry holds the time, direction, message type and ID, the outcome and the
+//! full frame. In the JSONL format an entry is one JSON object per line with the
+//! frame as hex. In the binary format an entry is the UTC time in microseconds
+//! (u64), the direction (u8, 0 - Sent, 1 - Received), the outcome's length (u16)
+//! and UTF-8 text, then the frame, whose LLRP header gives its length; integers
+//! are big-endian.
+//!
+//! Once a file would pass `max_file_bytes` it is renamed to `<path>.1`, older files
+//! move up by one, and the oldest beyond `max_files` is deleted.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use bytes::{BufMut, BytesMut};
+use log::warn;
+use serde::Serialize;
+
+use crate::client::FrameDirection;
+use crate::clock::SharedClock;
+use crate::config::{JournalConfig, JournalFormat};
+use crate::llrp::{get_message_type_str, LlrpHeader, LlrpResponse};
+
+/// A journal entry as written in the JSONL format.
+///
+/// Fields:
+/// - `timestamp_us`: UTC time the frame was written or read, in microseconds since the Unix epoch.
+/// - `reader_id`: Identifier of the reader.
+/// - `direction`: `"sent"` or `"received"`.
+/// - `message_type`: Name of the message type, e.g. `"AddRospec"`.
+/// - `message_id`: Message ID of the frame.
+/// - `outcome`: `"written"` or `"write failed: ..."` for sent messages; for received
+///   ones `"success"` or `"status <code>"` when they carry an LLRPStatus, else `"received"`.
+/// - `frame`: The complete frame, header included, as hex.
+#[derive(Debug, Serialize)]
+struct JournalEntry<'a> {
+  timestamp_us : i64,
+  reader_id    : &'a str,
+  direction    : &'static str,
+  message_type : &'static str,
+  message_id   : u32,
+  outcome      : &'a str,
+  frame        : String
+}
+
+struct JournalFile {
+  file  : File,
+  bytes : u64
+}
+
+/// The journal of one reader. Entries are written as they are recorded; a failure
+/// to write is logged and does not affect the LLRP session.
+pub struct MessageJournal {
+  reader_id      : String,
+  path           : PathBuf,
+  format         : JournalFormat,
+  max_file_bytes : u64,
+  max_files      : u32,
+  clock          : SharedClock,
+  file           : Mutex<JournalFile>
+}
+
+impl MessageJournal {
+
+  /// Opens the journal at `config.path`, appending to an existing file. Sent frames
+  /// are stamped with `clock`'s UTC time.
+  pub fn open(
+    config    : &JournalConfig,
+    reader_id : &str,
+    clock     : SharedClock
+  ) -> io::Result<Self> {
+
+    if let Some(parent) = config.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+      fs::create_dir_all(parent)?;
+    }
+
+    Ok(MessageJournal {
+      reader_id      : reader_id.to_string(),
+      path           : config.path.clone(),
+      format         : config.format,
+      max_file_bytes : config.max_file_bytes,
+      max_files      : config.max_files,
+      clock,
+      file           : Mutex::new(open_append(&config.path)?)
+    })
+  }
+
+  /// Records a frame written to the reader, with the result of the write.
+  pub fn record_sent(
+    &self,
+    frame  : &[u8],
+    result : &io::Result<()>
+  ) {
+    let outcome = match result {
+      Ok(()) => "written".to_string(),
+      Err(e) => format!("write failed: {}", e)
+    };
+    self.record(self.clock.utc_now_us(), FrameDirection::Sent, frame, &outcome);
+  }
+
+  /// Records a frame read from the reader, decoded as `response`.
+  pub fn record_received(
+    &self,
+    frame    : &[u8],
+    response : &LlrpResponse
+  ) {
+    let status_code = response.message_type.status_offset().and_then(|fixed_length| response.status_code(fixed_length));
+    let outcome = match status_code {
+      Some(0) => "success".to_string(),
+      Some(status_code) => format!("status {}", status_code),
+      None => "received".to_string()
+    };
+    self.record(response.received_at.utc_us, FrameDirection::Received, frame, &outcome);
+  }
+
+  fn record(
+    &self,
+    timestamp_us : i64,
+    direction    : FrameDirection,
+    frame        : &[u8],
+    outcome      : &str
+  ) {
+    let entry = match self.format {
+      JournalFormat::Jsonl => self.jsonl_entry(timestamp_us, direction, frame, outcome),
+      JournalFormat::Binary => binary_entry(timestamp_us, direction, frame, outcome)
+    };
+
+    if let Err(e) = self.append(&entry) {
+      warn!("Failed to write message journal {}: {}", self.path.display(), e);
+    }
+  }
+
+  fn jsonl_entry(
+    &self,
+    timestamp_us : i64,
+    direction    : FrameDirection,
+    frame        : &[u8],
+    outcome      : &str
+  ) -> Vec<u8> {
+
+    let header = LlrpHeader::decode(frame).ok();
+
+    let entry = JournalEntry {
+      timestamp_us,
+      reader_id    : &self.reader_id,
+      direction    : match direction { FrameDirection::Sent => "sent", FrameDirection::Received => "received" },
+      message_type : header.as_ref().map_or("Unknown", |header| get_message_type_str(header.message_type_value)),
+      message_id   : header.as_ref().map_or(0, |header| header.message_id),
+      outcome,
+      frame        : frame.iter().map(|byte| format!("{:02x}", byte)).collect()
+    };
+
+    let mut line = serde_json::to_vec(&entry).unwrap_or_default();
+    line.push(b'\n');
+    line
+  }
+
+  fn append(
+    &self,
+    entry: &[u8]
+  ) -> io::Result<()> {
+
+    let mut file = self.file.lock().unwrap();
+
+    if file.bytes > 0 && file.bytes + entry.len() as u64 > self.max_file_bytes {
+      *file = self.rotate()?;
+    }
+
+    file.file.write_all(entry)?;
+    file.bytes += entry.len() as u64;
+
+    Ok(())
+  }
+
+  /// Shifts `<path>.N` to `<path>.N+1`, dropping the oldest, and starts a new file.
+  fn rotate(
+    &self
+  ) -> io::Result<JournalFile> {
+
+    let rotated = |index: u32| PathBuf::from(format!("{}.{}", self.path.display(), index));
+
+    if self.max_files == 0 {
+      fs::remove_file(&self.path)?;
+    } else {
+      match fs::remove_file(rotated(self.max_files)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+      }
+      for index in (1..self.max_files).rev() {
+        if rotated(index).exists() {
+          fs::rename(rotated(index), rotated(index + 1))?;
+        }
+      }
+      fs::rename(&self.path, rotated(1))?;
+    }
+
+    open_append(&self.path)
+  }
+}
+
+fn open_append(
+  path: &Path
+) -> io::Result<JournalFile> {
+  let file = OpenOptions::new().create(true).append(true).open(path)?;
+  let bytes = file.metadata()?.len();
+  Ok(JournalFile { file, bytes })
+}
+
+fn binary_entry(
+  timestamp_us : i64,
+  direction    : FrameDirection,
+  frame        : &[u8],
+  outcome      : &str
+) -> Vec<u8> {
+
+  let outcome = &outcome.as_bytes()[..outcome.len().min(u16::MAX as usize)];
+
+  let mut entry = BytesMut::with_capacity(11 + outcome.len() + frame.len());
+  entry.put_u64(timestamp_us as u64);
+  entry.put_u8(match direction { FrameDirection::Sent => 0, FrameDirection::Received => 1 });
+  entry.put_u16(outcome.len() as u16);
+  entry.put_slice(outcome);
+  entry.put_slice(frame);
+
+  entry.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use std::sync::Arc;
+  use crate::clock::TokioClock;
+  use crate::llrp::{LlrpMessage, LlrpMessageType, ReaderConfigRequest};
+
+  #[test]
+  fn entries_are_appended_and_files_rotated() {
+
+    let dir = std::env::temp_dir().join(format!("llrp-journal-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let config = JournalConfig { path: dir.join("journal.jsonl"), format: JournalFormat::Jsonl, max_file_bytes: 400, max_files: 2 };
+    let journal = MessageJournal::open(&config, "dock-1", Arc::new(TokioClock)).unwrap();
+
+    let request = LlrpMessage::new_get_reader_config(42, &ReaderConfigRequest::default()).encode();
+    let response = LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, 42, vec![0x01, 0x1F, 0x00, 0x08, 0x00, 0x64, 0x00, 0x00]).encode();
+    let mut buf = BytesMut::from(&response[..]);
+    let decoded = LlrpResponse::from_message(LlrpMessage::decode(&mut buf).unwrap());
+
+    journal.record_sent(&request, &Ok(()));
+    journal.record_received(&response, &decoded);
+
+    let lines: Vec<serde_json::Value> = fs::read_to_string(&config.path).unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+
+    assert_eq!(lines[0]["direction"], "sent");
+    assert_eq!(lines[0]["message_type"], "GetReaderConfig");
+    assert_eq!(lines[0]["outcome"], "written");
+    assert_eq!(lines[1]["message_id"], 42);
+    assert_eq!(lines[1]["outcome"], "status 100");
+
+    for _ in 0..8 {
+      journal.record_sent(&request, &Ok(()));
+    }
+
+    assert!(fs::metadata(&config.path).unwrap().len() <= 400);
+    assert!(dir.join("journal.jsonl.1").exists());
+    assert!(dir.join("journal.jsonl.2").exists());
+    assert!(!dir.join("journal.jsonl.3").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
\ No newline at end of file
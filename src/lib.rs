@@ -1,28 +1,133 @@
 use std::os::raw::c_char;
 use std::ffi::{CStr, CString};
+use std::path::PathBuf;
 use std::ptr;
-use std::sync::Mutex;
-use llrp::LlrpResponseData;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use llrp::{LlrpResponseData, LlrpVersion, ReaderConfigData, ReaderConfigRequest};
 use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use lazy_static::lazy_static;
 
-mod client;
-mod config;
-mod llrp;
-mod params;
-
-use client::LlrpClient;
+pub mod alerts;
+mod channel;
+pub mod clock;
+pub mod client;
+pub mod config;
+pub mod custom;
+mod delivery;
+pub mod duration;
+pub mod error;
+pub mod filter;
+pub mod gpio;
+pub mod history;
+pub mod introspect;
+pub mod journal;
+#[cfg(feature = "impinj")]
+pub mod impinj;
+mod liveness;
+mod log_context;
+pub mod llrp;
+pub mod params;
+pub mod population;
+pub mod prelude;
+pub mod region;
+pub mod rospec;
+mod report_batch;
+pub mod secrets;
+mod setup;
+mod stream;
+pub mod stats;
+pub mod tag_stream;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(test)]
+mod golden;
+#[cfg(test)]
+mod corpus;
+#[cfg(test)]
+mod scripted_reader;
+#[cfg(test)]
+mod test_transport;
+
+use client::{FrameDirection, LlrpClient};
+use config::{AccessSpecConfig, C1G2LockPayloadConfig, Config, GpiPortConfig, GpoOutputConfig, LargePopulationConfig};
+use custom::CustomMessage;
+use error::LlrpError;
+use delivery::ReportDelivery;
+use filter::{ReportFilter, SharedReportFilter};
+use gpio::{GpoPort, PinState};
+use params::TagReportData;
+use population::{PopulationCount, SharedPopulationCount};
+use report_batch::{with_tag_report_batch, LlrpTagReportBatch, TAG_REPORT_BATCH_VERSION};
 
 type ReaderCapabilitiesCallback = extern "C" fn(capabilities: *const c_char);
 type ReaderConfigCallback       = extern "C" fn(config: *const c_char);
 type ROAccessReportCallback     = extern "C" fn(report: *const c_char);
+type ReportOverflowCallback     = extern "C" fn(dropped: u64);
+type TagReportBatchCallback     = extern "C" fn(batch: *const LlrpTagReportBatch);
+type AlertCallback              = extern "C" fn(alert: *const c_char);
+type ConnectionEventCallback    = extern "C" fn(event: *const c_char);
+type FrameCallback              = extern "C" fn(direction: i32, frame: *const u8, length: usize);
+type PopulationProgressCallback = extern "C" fn(progress: *const c_char);
 
 lazy_static! {
-  static ref RUNTIME: Runtime = Runtime::new().unwrap();
   static ref LAST_ERROR                   : Mutex<Option<String>>                     = Mutex::new(None);
+  static ref LAST_ERROR_CODE              : Mutex<i32>                                = Mutex::new(0);
   static ref READER_CAPABILITIES_CALLBACK : Mutex<Option<ReaderCapabilitiesCallback>> = Mutex::new(None);
   static ref READER_CONFIG_CALLBACK       : Mutex<Option<ReaderConfigCallback>>       = Mutex::new(None);
   static ref RO_ACCESS_REPORT_CALLBACK    : Mutex<Option<ROAccessReportCallback>>     = Mutex::new(None);
+  static ref REPORT_OVERFLOW_CALLBACK     : Mutex<Option<ReportOverflowCallback>>     = Mutex::new(None);
+  static ref TAG_REPORT_BATCH_CALLBACK    : Mutex<Option<TagReportBatchCallback>>     = Mutex::new(None);
+  static ref ALERT_CALLBACK               : Mutex<Option<AlertCallback>>              = Mutex::new(None);
+  static ref CONNECTION_EVENT_CALLBACK    : Mutex<Option<ConnectionEventCallback>>    = Mutex::new(None);
+  static ref FRAME_CALLBACK               : Mutex<Option<FrameCallback>>              = Mutex::new(None);
+  static ref POPULATION_PROGRESS_CALLBACK : Mutex<Option<PopulationProgressCallback>> = Mutex::new(None);
+}
+
+/// The runtime the clients' work runs on, created by `llrp_init` or on first use.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+fn runtime() -> Result<&'static Runtime, String> {
+
+  if let Some(runtime) = RUNTIME.get() {
+    return Ok(runtime);
+  }
+
+  // Should two threads race here, the runtime of the one losing is dropped.
+  let runtime = Runtime::new().map_err(|e| format!("Failed to start the async runtime: {}", e))?;
+
+  Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Evaluates to the runtime, or sets the last error and returns `$failure` from the
+/// calling function if it cannot be started.
+macro_rules! runtime_or_return {
+  ($failure:expr) => {
+    match runtime() {
+      Ok(runtime) => runtime,
+      Err(e) => {
+        set_last_client_error(&LlrpError::Other(e));
+        return $failure;
+      }
+    }
+  };
+}
+
+/// Starts the async runtime, e.g. its worker threads, up front. Returns 0 on
+/// success, or -1 with the reason in `get_last_error` where threads cannot be
+/// created. Optional: other functions start the runtime on first use and fail the
+/// same way, and calling it again once it succeeded does nothing.
+#[no_mangle]
+pub extern "C" fn llrp_init() -> i32 {
+  match runtime() {
+    Ok(_) => 0,
+    Err(e) => {
+      set_last_client_error(&LlrpError::Other(e));
+      -1
+    }
+  }
 }
 
 #[no_mangle]
@@ -40,27 +145,244 @@ pub extern "C" fn set_ro_access_report_callback(callback: ROAccessReportCallback
   *RO_ACCESS_REPORT_CALLBACK.lock().unwrap() = Some(callback);
 }
 
-pub struct LlrpClientWrapper(LlrpClient);
+#[no_mangle]
+pub extern "C" fn set_report_overflow_callback(callback: ReportOverflowCallback) {
+  *REPORT_OVERFLOW_CALLBACK.lock().unwrap() = Some(callback);
+}
+
+/// Receives each ROAccessReport delivered by `start_ro_access_report_delivery` as
+/// an `LlrpTagReportBatch`, in addition to or instead of the ROAccessReport
+/// callback. The batch is only valid for the duration of the call. Pass null to
+/// stop receiving batches.
+#[no_mangle]
+pub extern "C" fn set_tag_report_batch_callback(callback: Option<TagReportBatchCallback>) {
+  *TAG_REPORT_BATCH_CALLBACK.lock().unwrap() = callback;
+}
+
+/// The `struct_version` of the batches this library builds, so hosts can check
+/// compatibility before registering a batch callback.
+#[no_mangle]
+pub extern "C" fn tag_report_batch_version() -> u32 {
+  TAG_REPORT_BATCH_VERSION
+}
+
+/// Receives health alerts as JSON objects, e.g.
+/// `{"timestamp_ms":...,"reader_id":"dock-1","kind":{"alert":"no_tag_reads","silent_secs":30,"limit_secs":30}}`.
+#[no_mangle]
+pub extern "C" fn set_alert_callback(callback: AlertCallback) {
+  *ALERT_CALLBACK.lock().unwrap() = Some(callback);
+}
+
+/// Receives connection events as JSON objects as they happen, e.g.
+/// `{"timestamp_ms":...,"kind":{"event":"disconnected","reason":"No message from the reader for 30000 ms","session_duration_ms":...}}`,
+/// so the host can call `reconnect` once a connection is lost. Pass null to stop
+/// receiving events.
+#[no_mangle]
+pub extern "C" fn set_connection_event_callback(callback: Option<ConnectionEventCallback>) {
+  *CONNECTION_EVENT_CALLBACK.lock().unwrap() = callback;
+}
+
+/// Receives every raw LLRP frame, header included, exchanged with any reader.
+/// `direction` is 0 for frames sent to the reader and 1 for frames received from
+/// it. The bytes are only valid for the duration of the call. Pass null to stop
+/// capturing.
+#[no_mangle]
+pub extern "C" fn set_frame_callback(callback: Option<FrameCallback>) {
+  *FRAME_CALLBACK.lock().unwrap() = callback;
+}
+
+fn forward_frame(direction: FrameDirection, frame: &[u8]) {
+  if let Some(callback) = *FRAME_CALLBACK.lock().unwrap() {
+    let direction = match direction {
+      FrameDirection::Sent => 0,
+      FrameDirection::Received => 1
+    };
+    callback(direction, frame.as_ptr(), frame.len());
+  }
+}
+
+/// Receives the progress of a population count as a JSON object each time its unique
+/// tag count reaches a milestone, e.g.
+/// `{"unique_tags":20000,"tag_reads":184211,"spilled":false,"elapsed_ms":41870}`.
+/// Pass null to stop receiving progress.
+#[no_mangle]
+pub extern "C" fn set_population_progress_callback(callback: Option<PopulationProgressCallback>) {
+  *POPULATION_PROGRESS_CALLBACK.lock().unwrap() = callback;
+}
+
+pub struct LlrpClientWrapper {
+  client           : LlrpClient,
+  report_delivery  : Option<ReportDelivery>,
+  report_filter    : SharedReportFilter,
+  population_count : SharedPopulationCount,
+  alert_forwarder  : JoinHandle<()>,
+  event_forwarder  : JoinHandle<()>
+}
+
+/// Leaves only the tags the running population count, if any, has not seen before,
+/// and forwards the milestones they reach to the population progress callback. On a
+/// failure of the count's on-disk store the tags are passed on unfiltered.
+fn apply_population_count(population_count: &SharedPopulationCount, tag_reports: &mut Vec<TagReportData>) {
+
+  let mut population_count = population_count.lock().unwrap();
+  let Some(count) = population_count.as_mut() else {
+    return;
+  };
+
+  let milestones = match count.retain_new(tag_reports) {
+    Ok(milestones) => milestones,
+    Err(e) => {
+      log::error!("Population count failed: {}", e);
+      return;
+    }
+  };
+
+  // The callback may call back into the client, e.g. for the progress, so no lock
+  // is held while it runs.
+  drop(population_count);
+  let callback = *POPULATION_PROGRESS_CALLBACK.lock().unwrap();
+
+  if let Some(callback) = callback {
+    for progress in milestones {
+      if let Ok(progress_json) = serde_json::to_string(&progress) {
+        let c_progress = CString::new(progress_json).unwrap();
+        callback(c_progress.as_ptr());
+      }
+    }
+  }
+}
+
+/// Forwards the client's alerts to the registered alert callback, if any.
+fn spawn_alert_forwarder(runtime: &Runtime, client: &LlrpClient) -> JoinHandle<()> {
+
+  let mut alert_rx = client.subscribe_alerts();
+
+  runtime.spawn(async move {
+    loop {
+      match alert_rx.recv().await {
+
+        Ok(alert) => {
+          if let Some(callback) = *ALERT_CALLBACK.lock().unwrap() {
+            if let Ok(alert_json) = serde_json::to_string(&alert) {
+              let c_alert = CString::new(alert_json).unwrap();
+              callback(c_alert.as_ptr());
+            }
+          }
+        }
+
+        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(broadcast::error::RecvError::Closed) => break
+      }
+    }
+  })
+}
+
+/// Forwards the client's connection events to the registered connection event
+/// callback, if any.
+fn spawn_connection_event_forwarder(runtime: &Runtime, client: &LlrpClient) -> JoinHandle<()> {
+
+  let mut event_rx = client.subscribe_connection_events();
+
+  runtime.spawn(async move {
+    loop {
+      match event_rx.recv().await {
+
+        Ok(event) => {
+          if let Some(callback) = *CONNECTION_EVENT_CALLBACK.lock().unwrap() {
+            if let Ok(event_json) = serde_json::to_string(&event) {
+              let c_event = CString::new(event_json).unwrap();
+              callback(c_event.as_ptr());
+            }
+          }
+        }
+
+        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(broadcast::error::RecvError::Closed) => break
+      }
+    }
+  })
+}
 
 #[no_mangle]
 pub extern "C" fn initialize_client(config_path: *const c_char) -> *mut LlrpClientWrapper {
 
-  let config_path: String = unsafe {
+  let config_path: PathBuf = unsafe {
     
     if config_path.is_null() {
       set_last_error("Null config path pointer");
       return ptr::null_mut();
     }
 
-    CStr::from_ptr(config_path).to_string_lossy().into_owned()
+    narrow_path(CStr::from_ptr(config_path))
+  };
+
+  initialize_client_from_path(config_path)
+}
+
+/// Wide-character variant of `initialize_client` taking a NUL-terminated UTF-16
+/// path, for hosts (e.g. Windows) whose paths are not representable as UTF-8.
+#[no_mangle]
+pub extern "C" fn initialize_client_w(config_path: *const u16) -> *mut LlrpClientWrapper {
+
+  let config_path: PathBuf = unsafe {
+
+    if config_path.is_null() {
+      set_last_error("Null config path pointer");
+      return ptr::null_mut();
+    }
+
+    let mut length = 0;
+    while *config_path.add(length) != 0 {
+      length += 1;
+    }
+
+    wide_path(std::slice::from_raw_parts(config_path, length))
   };
 
-  let client_result = RUNTIME.block_on(LlrpClient::initialize(config_path.as_str()));
+  initialize_client_from_path(config_path)
+}
+
+#[cfg(unix)]
+fn narrow_path(path: &CStr) -> PathBuf {
+  use std::os::unix::ffi::OsStrExt;
+  PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes()))
+}
+
+#[cfg(not(unix))]
+fn narrow_path(path: &CStr) -> PathBuf {
+  PathBuf::from(path.to_string_lossy().into_owned())
+}
+
+#[cfg(windows)]
+fn wide_path(path: &[u16]) -> PathBuf {
+  use std::os::windows::ffi::OsStringExt;
+  PathBuf::from(std::ffi::OsString::from_wide(path))
+}
+
+#[cfg(not(windows))]
+fn wide_path(path: &[u16]) -> PathBuf {
+  PathBuf::from(String::from_utf16_lossy(path))
+}
+
+fn initialize_client_from_path(config_path: PathBuf) -> *mut LlrpClientWrapper {
+
+  let runtime = runtime_or_return!(ptr::null_mut());
+  let client_result = runtime.block_on(LlrpClient::initialize(&config_path));
 
   match client_result {
-    Ok(client) => Box::into_raw(Box::new(LlrpClientWrapper(client))),
+    Ok(client) => {
+      client.set_frame_observer(Some(Arc::new(forward_frame)));
+      Box::into_raw(Box::new(LlrpClientWrapper {
+        alert_forwarder: spawn_alert_forwarder(runtime, &client),
+        event_forwarder: spawn_connection_event_forwarder(runtime, &client),
+        population_count: Arc::new(Mutex::new(client.config().large_population.as_ref().map(PopulationCount::new))),
+        client,
+        report_delivery: None,
+        report_filter: Arc::new(RwLock::new(ReportFilter::default()))
+      }))
+    }
     Err(e) => {
-      set_last_error(&e.to_string());
+      set_last_client_error(&e);
       ptr::null_mut()
     }
   }
@@ -77,10 +399,33 @@ pub extern "C" fn send_keep_alive(client_ptr: *mut LlrpClientWrapper) -> i32 {
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_keep_alive()) {
+    match runtime_or_return!(-1).block_on(client.client.send_keep_alive()) {
       Ok(_) => 0,  
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Asks the reader to send its buffered tag reports, which are delivered to the
+/// tag report callbacks like any other ROAccessReport.
+#[no_mangle]
+pub extern "C" fn send_get_report(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.send_get_report()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
         -1
       }
     }
@@ -98,10 +443,10 @@ pub extern "C" fn send_enable_events_and_reports(client_ptr: *mut LlrpClientWrap
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_enable_events_and_reports()) {
+    match runtime_or_return!(-1).block_on(client.client.send_enable_events_and_reports()) {
       Ok(_) => 0,  
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -121,13 +466,13 @@ pub extern "C" fn send_get_reader_capabilities(client_ptr: *mut LlrpClientWrappe
     let callback_lock = READER_CAPABILITIES_CALLBACK.lock().unwrap();
 
     if callback_lock.is_none() {
-      set_last_error("No ReaderCapabilities callback registered");
+      set_last_client_error(&LlrpError::InvalidState("No ReaderCapabilities callback registered".to_string()));
       return -1;
     }
 
     let callback = callback_lock.unwrap();
 
-    match RUNTIME.block_on(client.0.send_get_reader_capabilities(move | response_data | async move {
+    match runtime_or_return!(-1).block_on(client.client.send_get_reader_capabilities(move | response_data | async move {
 
       let capabilities_str = match response_data {
 
@@ -145,7 +490,7 @@ pub extern "C" fn send_get_reader_capabilities(client_ptr: *mut LlrpClientWrappe
     })) {
       Ok(_) => 0,  
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -154,6 +499,21 @@ pub extern "C" fn send_get_reader_capabilities(client_ptr: *mut LlrpClientWrappe
 
 #[no_mangle]
 pub extern "C" fn send_get_reader_config(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  send_get_reader_config_for(client_ptr, ReaderConfigData::All.value(), 0, 0, 0)
+}
+
+/// Requests only part of the reader configuration, passed to the ReaderConfig
+/// callback. `requested_data` is the LLRP RequestedData value (0 - All,
+/// 1 - Identification, 3 - AntennaConfiguration, ...); `antenna_id`, `gpi_port`
+/// and `gpo_port` select a single antenna or port, 0 selecting all of them.
+#[no_mangle]
+pub extern "C" fn send_get_reader_config_for(
+  client_ptr     : *mut LlrpClientWrapper,
+  requested_data : u8,
+  antenna_id     : u16,
+  gpi_port       : u16,
+  gpo_port       : u16
+) -> i32 {
   unsafe {
 
     if client_ptr.is_null() {
@@ -161,17 +521,25 @@ pub extern "C" fn send_get_reader_config(client_ptr: *mut LlrpClientWrapper) ->
       return -1;
     }
 
+    let request = match ReaderConfigData::from_value(requested_data) {
+      Some(requested_data) => ReaderConfigRequest { requested_data, antenna_id, gpi_port, gpo_port },
+      None => {
+        set_last_error(&format!("Unknown GetReaderConfig RequestedData: {}", requested_data));
+        return -1;
+      }
+    };
+
     let client = &mut *client_ptr;
     let callback_lock = READER_CONFIG_CALLBACK.lock().unwrap();
 
     if callback_lock.is_none() {
-      set_last_error("No ReaderConfig callback registered");
+      set_last_client_error(&LlrpError::InvalidState("No ReaderConfig callback registered".to_string()));
       return -1;
     }
 
     let callback = callback_lock.unwrap();
 
-    match RUNTIME.block_on(client.0.send_get_reader_config(move | response_data | async move {
+    match runtime_or_return!(-1).block_on(client.client.send_get_reader_config(request, move | response_data | async move {
 
       let config_str = match response_data {
 
@@ -188,7 +556,7 @@ pub extern "C" fn send_get_reader_config(client_ptr: *mut LlrpClientWrapper) ->
     })) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -206,10 +574,40 @@ pub extern "C" fn send_set_reader_config(client_ptr: *mut LlrpClientWrapper) ->
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_set_reader_config()) {
+    match runtime_or_return!(-1).block_on(client.client.send_set_reader_config()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Changes the transmit power table index of a single antenna, keeping the other
+/// RF settings from the loaded configuration and without a factory reset.
+#[no_mangle]
+pub extern "C" fn set_antenna_tx_power(
+  client_ptr           : *mut LlrpClientWrapper,
+  antenna_id           : u16,
+  tx_power_table_index : u16
+) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    let mut reader_config = client.client.config().reader_config.clone();
+    reader_config.tx_power_table_index = tx_power_table_index;
+
+    match runtime_or_return!(-1).block_on(client.client.send_antenna_config(antenna_id, &reader_config)) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -227,10 +625,10 @@ pub extern "C" fn send_add_rospec(client_ptr: *mut LlrpClientWrapper) -> i32 {
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_add_rospec()) {
+    match runtime_or_return!(-1).block_on(client.client.send_add_rospec()) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -248,10 +646,10 @@ pub extern "C" fn send_enable_rospec(client_ptr: *mut LlrpClientWrapper) -> i32
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_enable_rospec()) {
+    match runtime_or_return!(-1).block_on(client.client.send_enable_rospec()) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -269,10 +667,10 @@ pub extern "C" fn send_start_rospec(client_ptr: *mut LlrpClientWrapper) -> i32 {
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_start_rospec()) {
+    match runtime_or_return!(-1).block_on(client.client.send_start_rospec()) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -290,10 +688,10 @@ pub extern "C" fn send_stop_rospec(client_ptr: *mut LlrpClientWrapper) -> i32 {
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_stop_rospec()) {
+    match runtime_or_return!(-1).block_on(client.client.send_stop_rospec()) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -301,7 +699,7 @@ pub extern "C" fn send_stop_rospec(client_ptr: *mut LlrpClientWrapper) -> i32 {
 }
 
 #[no_mangle]
-pub extern "C" fn send_delete_rospec(client_ptr: *mut LlrpClientWrapper, rospec_id: u32) -> i32 {
+pub extern "C" fn send_disable_rospec(client_ptr: *mut LlrpClientWrapper, rospec_id: u32) -> i32 {
   unsafe {
 
     if client_ptr.is_null() {
@@ -311,10 +709,10 @@ pub extern "C" fn send_delete_rospec(client_ptr: *mut LlrpClientWrapper, rospec_
 
     let client = &mut *client_ptr;
 
-    match RUNTIME.block_on(client.0.send_delete_rospec(rospec_id)) {
+    match runtime_or_return!(-1).block_on(client.client.send_disable_rospec(rospec_id)) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
@@ -322,7 +720,7 @@ pub extern "C" fn send_delete_rospec(client_ptr: *mut LlrpClientWrapper, rospec_
 }
 
 #[no_mangle]
-pub extern "C" fn await_ro_access_report(client_ptr: *mut LlrpClientWrapper) -> i32 {
+pub extern "C" fn send_delete_rospec(client_ptr: *mut LlrpClientWrapper, rospec_id: u32) -> i32 {
   unsafe {
 
     if client_ptr.is_null() {
@@ -331,98 +729,1333 @@ pub extern "C" fn await_ro_access_report(client_ptr: *mut LlrpClientWrapper) ->
     }
 
     let client = &mut *client_ptr;
-    let callback_lock = RO_ACCESS_REPORT_CALLBACK.lock().unwrap();
 
-    if callback_lock.is_none() {
-      set_last_error("No ROAccessReport callback registered");
-      return -1;
+    match runtime_or_return!(-1).block_on(client.client.send_delete_rospec(rospec_id)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
     }
+  }
+}
 
-    let callback = callback_lock.unwrap();
-
-    match RUNTIME.block_on(client.0.await_ro_access_report(move | response_data | async move {
+/// Writes `word_count` 16-bit words from `data` to the next tag singulated by a
+/// running inventory. The outcome is reported with that tag in the ROAccessReport.
+#[no_mangle]
+pub extern "C" fn write_tag_memory(
+  client_ptr      : *mut LlrpClientWrapper,
+  memory_bank     : u8,
+  word_pointer    : u16,
+  data            : *const u16,
+  word_count      : usize,
+  access_password : u32
+) -> i32 {
+  unsafe {
 
-      let report_str = match response_data {
-        
-        LlrpResponseData::TagReport(epc_data) => {
-          format!("{:?}", epc_data)
-        }
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
 
-        _ => "Unexpected ROAccessReport response".to_string()
-      };
+    if data.is_null() && word_count > 0 {
+      set_last_error("Null data pointer");
+      return -1;
+    }
 
-      let c_report = CString::new(report_str).unwrap();
-      callback(c_report.as_ptr());
+    let data = if word_count == 0 { &[][..] } else { std::slice::from_raw_parts(data, word_count) };
+    let client = &mut *client_ptr;
 
-    })) {
+    match runtime_or_return!(-1).block_on(client.client.write_tag_memory(memory_bank, word_pointer, data, access_password)) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
   }
 }
 
+/// Like `write_tag_memory`, but with a single BlockWrite when the reader supports
+/// it and one Write per word otherwise.
 #[no_mangle]
-pub extern "C" fn send_close_connection(client_ptr: *mut LlrpClientWrapper) -> i32 {
+pub extern "C" fn block_write_tag_memory(
+  client_ptr      : *mut LlrpClientWrapper,
+  memory_bank     : u8,
+  word_pointer    : u16,
+  data            : *const u16,
+  word_count      : usize,
+  access_password : u32
+) -> i32 {
   unsafe {
-    
+
     if client_ptr.is_null() {
       set_last_error("Null client pointer");
       return -1;
     }
 
+    if data.is_null() && word_count > 0 {
+      set_last_error("Null data pointer");
+      return -1;
+    }
+
+    let data = if word_count == 0 { &[][..] } else { std::slice::from_raw_parts(data, word_count) };
     let client = &mut *client_ptr;
-    match RUNTIME.block_on(client.0.send_close_connection()) {
+
+    match runtime_or_return!(-1).block_on(client.client.block_write_tag_memory(memory_bank, word_pointer, data, access_password)) {
       Ok(_) => 0,
       Err(e) => {
-        set_last_error(&e.to_string());
+        set_last_client_error(&e);
         -1
       }
     }
   }
 }
 
+/// Sets `word_count` words of the next tag singulated by a running inventory to
+/// zero, with a BlockErase when the reader supports it and zero-word Writes otherwise.
 #[no_mangle]
-pub extern "C" fn free_client(client_ptr: *mut LlrpClientWrapper) -> i32 {
-  if !client_ptr.is_null() {
+pub extern "C" fn block_erase_tag_memory(
+  client_ptr      : *mut LlrpClientWrapper,
+  memory_bank     : u8,
+  word_pointer    : u16,
+  word_count      : u16,
+  access_password : u32
+) -> i32 {
+  unsafe {
 
-    unsafe {
-      let _ = Box::from_raw(client_ptr);
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.block_erase_tag_memory(memory_bank, word_pointer, word_count, access_password)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
     }
-    
-    0
-  } else {
-    set_last_error("Null client pointer");
-    return -1;
   }
 }
 
+/// Locks the next tag singulated by a running inventory. `payloads_json` is an
+/// array of lock payloads, e.g. `[{"privilege":0,"data_field":2}]` to password
+/// protect the EPC bank.
 #[no_mangle]
-pub extern "C" fn free_string(string_ptr: *mut c_char) -> i32 {
-  if !string_ptr.is_null() {
-    
-    unsafe {
-      let _ = CString::from_raw(string_ptr);
+pub extern "C" fn lock_tag(client_ptr: *mut LlrpClientWrapper, payloads_json: *const c_char, access_password: u32) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
     }
 
-    0
-  } else {
-    set_last_error("Null string pointer");
-    return -1;
+    if payloads_json.is_null() {
+      set_last_error("Null lock payloads pointer");
+      return -1;
+    }
+
+    let payloads: Vec<C1G2LockPayloadConfig> = match serde_json::from_slice(CStr::from_ptr(payloads_json).to_bytes()) {
+      Ok(payloads) => payloads,
+      Err(e) => {
+        set_last_error(&format!("Invalid lock payloads: {}", e));
+        return -1;
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.lock_tag(&payloads, access_password)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
   }
 }
 
+/// Kills the next tag singulated by a running inventory whose EPC starts with the
+/// `epc_mask_length` bytes at `epc_mask`. The outcome is reported with that tag in
+/// the ROAccessReport.
 #[no_mangle]
-pub extern "C" fn get_last_error() -> *const c_char {
-  let error = LAST_ERROR.lock().unwrap();
-  match &*error {
-    Some(err) => CString::new(err.clone()).unwrap().into_raw(),
-    None => ptr::null(),
+pub extern "C" fn kill_tag(
+  client_ptr      : *mut LlrpClientWrapper,
+  epc_mask        : *const u8,
+  epc_mask_length : usize,
+  kill_password   : u32
+) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    if epc_mask.is_null() && epc_mask_length > 0 {
+      set_last_error("Null EPC mask pointer");
+      return -1;
+    }
+
+    let epc_mask = if epc_mask_length == 0 { &[][..] } else { std::slice::from_raw_parts(epc_mask, epc_mask_length) };
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.kill_tag(epc_mask, kill_password)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
   }
 }
 
-fn set_last_error(err: &str) {
+/// Changes ROReportTrigger_N, re-adding the ROSpec and restarting a running inventory.
+#[no_mangle]
+pub extern "C" fn set_report_trigger_n(client_ptr: *mut LlrpClientWrapper, report_n: u16) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.set_report_trigger_n(report_n)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Adds an AccessSpec described as JSON, e.g.
+/// `{"access_spec_id":1,"reads":[{"op_spec_id":1,"memory_bank":2,"word_count":6}]}`.
+/// Read results are included in the tag reports passed to the ROAccessReport callback.
+#[no_mangle]
+pub extern "C" fn send_add_access_spec(client_ptr: *mut LlrpClientWrapper, access_spec_json: *const c_char) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    if access_spec_json.is_null() {
+      set_last_error("Null AccessSpec pointer");
+      return -1;
+    }
+
+    let access_spec: AccessSpecConfig = match serde_json::from_slice(CStr::from_ptr(access_spec_json).to_bytes()) {
+      Ok(access_spec) => access_spec,
+      Err(e) => {
+        set_last_error(&format!("Invalid AccessSpec: {}", e));
+        return -1;
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.send_add_access_spec(&access_spec)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn send_enable_access_spec(client_ptr: *mut LlrpClientWrapper, access_spec_id: u32) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.send_enable_access_spec(access_spec_id)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn send_delete_access_spec(client_ptr: *mut LlrpClientWrapper, access_spec_id: u32) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.send_delete_access_spec(access_spec_id)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn start_inventory(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.start_inventory()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn stop_inventory(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.stop_inventory()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn await_ro_access_report(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+    let callback_lock = RO_ACCESS_REPORT_CALLBACK.lock().unwrap();
+
+    if callback_lock.is_none() {
+      set_last_client_error(&LlrpError::InvalidState("No ROAccessReport callback registered".to_string()));
+      return -1;
+    }
+
+    let callback = callback_lock.unwrap();
+    let report_filter = client.report_filter.clone();
+    let population_count = client.population_count.clone();
+
+    let keep = move | response_data: &mut LlrpResponseData | {
+      match response_data {
+        LlrpResponseData::TagReport(tag_reports) => {
+          report_filter.read().unwrap().apply(tag_reports);
+          apply_population_count(&population_count, tag_reports);
+          !tag_reports.is_empty()
+        }
+        _ => true
+      }
+    };
+
+    match runtime_or_return!(-1).block_on(client.client.await_ro_access_report_where(keep, move | response_data | {
+      async move {
+
+        let report_str = match response_data {
+
+          LlrpResponseData::TagReport(epc_data) => {
+            format!("{:?}", epc_data)
+          }
+
+          _ => "Unexpected ROAccessReport response".to_string()
+        };
+
+        let c_report = CString::new(report_str).unwrap();
+        callback(c_report.as_ptr());
+      }
+    })) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn start_ro_access_report_delivery(
+  client_ptr           : *mut LlrpClientWrapper,
+  buffer_capacity      : u32,
+  overflow_interval_ms : u32
+) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    if client.report_delivery.is_some() {
+      set_last_client_error(&LlrpError::InvalidState("ROAccessReport delivery already started".to_string()));
+      return -1;
+    }
+
+    if overflow_interval_ms == 0 {
+      set_last_error("overflow_interval_ms must be greater than 0");
+      return -1;
+    }
+
+    let callback = *RO_ACCESS_REPORT_CALLBACK.lock().unwrap();
+    let batch_callback = *TAG_REPORT_BATCH_CALLBACK.lock().unwrap();
+
+    if callback.is_none() && batch_callback.is_none() {
+      set_last_client_error(&LlrpError::InvalidState("No ROAccessReport or tag report batch callback registered".to_string()));
+      return -1;
+    }
+
+    let overflow_callback = *REPORT_OVERFLOW_CALLBACK.lock().unwrap();
+    let reader_id = CString::new(client.client.config().reader_id()).unwrap_or_default();
+    let report_filter = client.report_filter.clone();
+    let population_count = client.population_count.clone();
+
+    let _guard = runtime_or_return!(-1).enter();
+
+    let delivery = ReportDelivery::start(
+      client.client.subscribe_ro_reports(),
+      buffer_capacity as usize,
+      Duration::from_millis(overflow_interval_ms as u64),
+      move | mut response_data | {
+
+        if let LlrpResponseData::TagReport(tag_reports) = &mut response_data {
+          report_filter.read().unwrap().apply(tag_reports);
+          apply_population_count(&population_count, tag_reports);
+          if tag_reports.is_empty() {
+            return;
+          }
+        }
+
+        if let (Some(batch_callback), LlrpResponseData::TagReport(tag_reports)) = (batch_callback, &response_data) {
+          with_tag_report_batch(&reader_id, tag_reports, |batch| batch_callback(batch));
+        }
+
+        let Some(callback) = callback else {
+          return;
+        };
+
+        let report_str = match response_data {
+
+          LlrpResponseData::TagReport(epc_data) => {
+            format!("{:?}", epc_data)
+          }
+
+          _ => "Unexpected ROAccessReport response".to_string()
+        };
+
+        let c_report = CString::new(report_str).unwrap();
+        callback(c_report.as_ptr());
+      },
+      move | dropped | {
+        if let Some(overflow_callback) = overflow_callback {
+          overflow_callback(dropped);
+        }
+      }
+    );
+
+    client.report_delivery = Some(delivery);
+
+    0
+  }
+}
+
+/// Sets the filter applied to the tags of ROAccessReports passed to the host by
+/// `await_ro_access_report` and `start_ro_access_report_delivery`, taking effect with
+/// the next report. Reports left without tags are not delivered.
+///
+/// `filter_json` is a JSON object whose fields are all optional; a tag must meet each
+/// field given, and `{}` clears the filter:
+///
+/// `{"antennas": [1, 2], "epc_prefixes": ["E280", "3000"], "min_rssi": -65}`
+#[no_mangle]
+pub extern "C" fn set_report_filter(
+  client_ptr  : *mut LlrpClientWrapper,
+  filter_json : *const c_char
+) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    if filter_json.is_null() {
+      set_last_error("Null report filter");
+      return -1;
+    }
+
+    let filter = serde_json::from_slice::<ReportFilter>(CStr::from_ptr(filter_json).to_bytes())
+      .map_err(|e| e.to_string())
+      .and_then(ReportFilter::validated);
+
+    match filter {
+      Ok(filter) => {
+        let client = &mut *client_ptr;
+        *client.report_filter.write().unwrap() = filter;
+        0
+      }
+      Err(e) => {
+        set_last_error(&format!("Invalid report filter: {}", e));
+        -1
+      }
+    }
+  }
+}
+
+/// Starts a count of the distinct tags in the field, replacing any count already
+/// running. From then on, the ROAccessReports passed to the host hold only the tags
+/// read for the first time in the count, after the report filter, and the progress
+/// callback is called at each milestone. The set of EPCs seen moves to disk once it
+/// outgrows memory, suiting cycle counts of 100k+ tags.
+///
+/// `config_json` is a JSON object whose fields are all optional, or null to use the
+/// `large_population` settings of the client configuration:
+///
+/// `{"memory_limit": 100000, "milestone_interval": 10000, "spill_dir": "/var/tmp"}`
+#[no_mangle]
+pub extern "C" fn start_population_count(
+  client_ptr  : *mut LlrpClientWrapper,
+  config_json : *const c_char
+) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    let config = if config_json.is_null() {
+      Ok(client.client.config().large_population.clone().unwrap_or_default())
+    } else {
+      serde_json::from_slice::<LargePopulationConfig>(CStr::from_ptr(config_json).to_bytes()).map_err(|e| e.to_string())
+    };
+
+    match config {
+      Ok(config) if config.memory_limit > 0 && config.milestone_interval > 0 => {
+        *client.population_count.lock().unwrap() = Some(PopulationCount::new(&config));
+        0
+      }
+      Ok(_) => {
+        set_last_client_error(&LlrpError::Config("Invalid population count configuration: memory_limit and milestone_interval must be greater than 0".to_string()));
+        -1
+      }
+      Err(e) => {
+        set_last_client_error(&LlrpError::Config(format!("Invalid population count configuration: {}", e)));
+        -1
+      }
+    }
+  }
+}
+
+/// Returns the progress of the running population count as a JSON object, in the
+/// form passed to the progress callback. The returned string must be freed with
+/// `free_string`. Returns null if no count is running.
+#[no_mangle]
+pub extern "C" fn get_population_progress(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    let Some(progress) = client.population_count.lock().unwrap().as_ref().map(PopulationCount::progress) else {
+      set_last_client_error(&LlrpError::InvalidState("Population count not started".to_string()));
+      return ptr::null_mut();
+    };
+
+    match serde_json::to_string(&progress) {
+      Ok(progress_json) => CString::new(progress_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Ends the running population count, removing its on-disk store, and returns the
+/// number of distinct tags it saw. Reports are passed on unfiltered again.
+#[no_mangle]
+pub extern "C" fn stop_population_count(client_ptr: *mut LlrpClientWrapper) -> i64 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match client.population_count.lock().unwrap().take() {
+      Some(count) => count.progress().unique_tags as i64,
+      None => {
+        set_last_client_error(&LlrpError::InvalidState("Population count not started".to_string()));
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn stop_ro_access_report_delivery(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match client.report_delivery.take() {
+      Some(delivery) => {
+        delivery.stop();
+        0
+      }
+      None => {
+        set_last_client_error(&LlrpError::InvalidState("ROAccessReport delivery not started".to_string()));
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn send_close_connection(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+    
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+    match runtime_or_return!(-1).block_on(client.client.send_close_connection()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn reconnect(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.reconnect()) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Returns the connection history as a JSON array. The returned string must be
+/// released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_connection_history(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.connection_history()) {
+      Ok(history_json) => CString::new(history_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Queries the reader capabilities and returns the decoded capability tree as a
+/// JSON object keyed by parameter name. The returned string must be released
+/// with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_capabilities_json(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(ptr::null_mut()).block_on(client.client.capabilities_json()) {
+      Ok(capabilities) => CString::new(capabilities.to_string()).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the fields of the last decoded capabilities (`source` 0) or reader
+/// configuration (`source` 1) response as a JSON array of rows, e.g.
+/// `[{"name":"channel_index","path":"AntennaConfiguration[0].rf_transmitter.channel_index","value":"1"}]`,
+/// for hosts rendering parameters without knowing each one. Nothing is sent to the
+/// reader; the array is empty until the response is first received. The returned
+/// string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_parameter_fields(client_ptr: *mut LlrpClientWrapper, source: i32) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    let fields = match source {
+      0 => client.client.capability_fields(),
+      1 => client.client.reader_config_fields(),
+      _ => {
+        set_last_error(&format!("Invalid parameter source {}, expected 0 (capabilities) or 1 (reader configuration)", source));
+        return ptr::null_mut();
+      }
+    };
+
+    match serde_json::to_string(&fields) {
+      Ok(fields_json) => CString::new(fields_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Validates the configuration in `config_json`, applies it to the connected
+/// client and returns a JSON summary of the change, e.g.
+/// `{"changed":["reader_config.tx_power_table_index"],"applied":["reader_config"],"deferred":[]}`.
+/// Returns null if the configuration is invalid or could not be applied, in which
+/// case the previous configuration stays in effect. The returned string must be
+/// released with `free_string`.
+#[no_mangle]
+pub extern "C" fn apply_config_json(client_ptr: *mut LlrpClientWrapper, config_json: *const c_char) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    if config_json.is_null() {
+      set_last_error("Null configuration pointer");
+      return ptr::null_mut();
+    }
+
+    let config: Config = match serde_json::from_slice(CStr::from_ptr(config_json).to_bytes()) {
+      Ok(config) => config,
+      Err(e) => {
+        set_last_client_error(&LlrpError::Config(format!("Invalid configuration: {}", e)));
+        return ptr::null_mut();
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(ptr::null_mut()).block_on(client.client.apply_config(config)) {
+      Ok(summary) => match serde_json::to_string(&summary) {
+        Ok(summary_json) => CString::new(summary_json).unwrap().into_raw(),
+        Err(e) => {
+          set_last_client_error(&LlrpError::from(e));
+          ptr::null_mut()
+        }
+      },
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Drives a GPO port low (`state` 0) or high (`state` 1).
+#[no_mangle]
+pub extern "C" fn set_gpo(client_ptr: *mut LlrpClientWrapper, port: u16, state: i32) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let state = match state {
+      0 => PinState::Low,
+      1 => PinState::High,
+      _ => {
+        set_last_error("GPO state must be 0 (low) or 1 (high)");
+        return -1;
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.set_gpo(GpoPort(port), state)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Drives several GPO ports in one request. `outputs_json` is an array of port
+/// levels, e.g. `[{"port":1,"state":"low"},{"port":3,"state":"high"}]`.
+#[no_mangle]
+pub extern "C" fn set_gpos(client_ptr: *mut LlrpClientWrapper, outputs_json: *const c_char) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    if outputs_json.is_null() {
+      set_last_error("Null GPO outputs pointer");
+      return -1;
+    }
+
+    let outputs: Vec<GpoOutputConfig> = match serde_json::from_slice(CStr::from_ptr(outputs_json).to_bytes()) {
+      Ok(outputs) => outputs,
+      Err(e) => {
+        set_last_client_error(&LlrpError::Config(format!("Invalid GPO outputs: {}", e)));
+        return -1;
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.set_gpos(&outputs)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Enables or disables GPI ports. `ports_json` is an array of port settings,
+/// e.g. `[{"port":1,"enabled":true}]`.
+#[no_mangle]
+pub extern "C" fn set_gpi_ports(client_ptr: *mut LlrpClientWrapper, ports_json: *const c_char) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    if ports_json.is_null() {
+      set_last_error("Null GPI ports pointer");
+      return -1;
+    }
+
+    let ports: Vec<GpiPortConfig> = match serde_json::from_slice(CStr::from_ptr(ports_json).to_bytes()) {
+      Ok(ports) => ports,
+      Err(e) => {
+        set_last_client_error(&LlrpError::Config(format!("Invalid GPI ports: {}", e)));
+        return -1;
+      }
+    };
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.set_gpi_ports(&ports)) {
+      Ok(_) => 0,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Returns the configuration and level of every GPI port as JSON, e.g.
+/// `[{"port":1,"enabled":true,"state":"high"}]`. The returned string must be
+/// released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_gpi_states(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    let states_json = runtime_or_return!(ptr::null_mut()).block_on(client.client.get_gpi_states())
+      .and_then(|states| Ok(serde_json::to_string(&states)?));
+
+    match states_json {
+      Ok(states_json) => CString::new(states_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the AntennaProperties of every antenna port as a JSON array, e.g.
+/// `[{"antenna_connected":true,"antenna_id":1,"antenna_gain":600}]`. The returned
+/// string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_antenna_properties(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    let antennas_json = runtime_or_return!(ptr::null_mut()).block_on(client.client.get_antenna_properties())
+      .and_then(|antennas| Ok(serde_json::to_string(&antennas)?));
+
+    match antennas_json {
+      Ok(antennas_json) => CString::new(antennas_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the RF and inventory settings of every antenna as a JSON array of
+/// AntennaConfiguration objects. The returned string must be released with
+/// `free_string`.
+#[no_mangle]
+pub extern "C" fn get_antenna_configurations(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    let antennas_json = runtime_or_return!(ptr::null_mut()).block_on(client.client.get_antenna_configurations())
+      .and_then(|antennas| Ok(serde_json::to_string(&antennas)?));
+
+    match antennas_json {
+      Ok(antennas_json) => CString::new(antennas_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Reads the reader's configuration state and returns 1 if it changed since the
+/// previous call, e.g. because another controller reconfigured the reader, 0 if
+/// not, or -1 on error. The first call only records the state.
+#[no_mangle]
+pub extern "C" fn check_configuration_changed(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.check_configuration_changed()) {
+      Ok(changed) => changed as i32,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Returns the AccessSpecs loaded on the reader, in the same debug text format as
+/// the ReaderCapabilities callback. The returned string must be released with
+/// `free_string`.
+#[no_mangle]
+pub extern "C" fn get_access_specs(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(ptr::null_mut()).block_on(client.client.send_get_access_specs()) {
+      Ok(access_specs) => CString::new(format!("{:?}", access_specs)).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Sends a vendor CUSTOM_MESSAGE carrying `data` and returns the reader's reply as
+/// JSON, e.g. `{"vendor_id":25882,"message_subtype":21,"data":[1,0]}`. The returned
+/// string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn send_custom_message(
+  client_ptr      : *mut LlrpClientWrapper,
+  vendor_id       : u32,
+  message_subtype : u8,
+  data            : *const u8,
+  data_length     : usize
+) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    if data.is_null() && data_length > 0 {
+      set_last_error("Null data pointer");
+      return ptr::null_mut();
+    }
+
+    let data = if data_length == 0 { &[][..] } else { std::slice::from_raw_parts(data, data_length) };
+    let client = &mut *client_ptr;
+
+    let custom = CustomMessage { vendor_id, message_subtype, data: data.to_vec() };
+
+    match runtime_or_return!(ptr::null_mut()).block_on(client.client.send_custom_message(&custom)) {
+      Ok(reply) => CString::new(serde_json::to_string(&reply).unwrap()).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&e);
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the number of messages of each type sent to and received from the
+/// reader as JSON, e.g. `{"sent":{"Keepalive":3},"received":{"KeepaliveAck":3}}`.
+/// The returned string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_protocol_stats(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.protocol_stats()) {
+      Ok(stats_json) => CString::new(stats_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the reports and events subscribers lost, and the time the client
+/// waited for them, by channel as JSON, e.g.
+/// `{"dropped":{"ro_reports":12},"blocked_ms":{}}`. The returned string must be
+/// released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_channel_stats(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.channel_stats()) {
+      Ok(stats_json) => CString::new(stats_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns how far the reader clock was from the host clock when last sampled as
+/// JSON, e.g. `{"drift_ms":-1200,"max_abs_drift_ms":1200,"samples":4,...}`. The
+/// returned string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_clock_drift_stats(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.clock_drift_stats()) {
+      Ok(stats_json) => CString::new(stats_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the latest temperature and utilization the reader reported as JSON, e.g.
+/// `{"temperature_c":41.5,"max_temperature_c":43.0,"cpu_utilization_pct":null,...}`.
+/// The returned string must be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_telemetry_stats(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.telemetry_stats()) {
+      Ok(stats_json) => CString::new(stats_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+/// Returns the most recent health alerts as a JSON array. The returned string must
+/// be released with `free_string`.
+#[no_mangle]
+pub extern "C" fn get_recent_alerts(client_ptr: *mut LlrpClientWrapper) -> *mut c_char {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return ptr::null_mut();
+    }
+
+    let client = &*client_ptr;
+
+    match serde_json::to_string(&client.client.recent_alerts()) {
+      Ok(alerts_json) => CString::new(alerts_json).unwrap().into_raw(),
+      Err(e) => {
+        set_last_client_error(&LlrpError::from(e));
+        ptr::null_mut()
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn set_protocol_version(client_ptr: *mut LlrpClientWrapper, version: u8) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match LlrpVersion::from_value(version) {
+      Some(version) => {
+        client.client.set_protocol_version(version);
+        0
+      }
+      None => {
+        set_last_client_error(&LlrpError::Unsupported(format!("Unsupported LLRP protocol version: {}", version)));
+        -1
+      }
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn get_protocol_version(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &*client_ptr;
+    client.client.protocol_version().value() as i32
+  }
+}
+
+/// Negotiates the highest LLRP version both the client and reader support and
+/// returns its header value (1 - LLRP 1.0.1, 2 - LLRP 1.1), or -1 on error.
+#[no_mangle]
+pub extern "C" fn negotiate_protocol_version(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  unsafe {
+
+    if client_ptr.is_null() {
+      set_last_error("Null client pointer");
+      return -1;
+    }
+
+    let client = &mut *client_ptr;
+
+    match runtime_or_return!(-1).block_on(client.client.negotiate_protocol_version()) {
+      Ok(version) => version.value() as i32,
+      Err(e) => {
+        set_last_client_error(&e);
+        -1
+      }
+    }
+  }
+}
+
+/// Closes the session with CLOSE_CONNECTION, stops the client's tasks and frees
+/// it. The client is freed even when the reader does not answer, which is only
+/// logged.
+#[no_mangle]
+pub extern "C" fn free_client(client_ptr: *mut LlrpClientWrapper) -> i32 {
+  if !client_ptr.is_null() {
+
+    unsafe {
+      let mut client = Box::from_raw(client_ptr);
+      if let Some(delivery) = client.report_delivery.take() {
+        delivery.stop();
+      }
+      if let Ok(runtime) = runtime() {
+        if let Err(e) = runtime.block_on(client.client.shutdown()) {
+          log::warn!("Freed client without a clean shutdown: {}", e);
+        }
+      }
+      client.alert_forwarder.abort();
+      client.event_forwarder.abort();
+    }
+    
+    0
+  } else {
+    set_last_error("Null client pointer");
+    return -1;
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn free_string(string_ptr: *mut c_char) -> i32 {
+  if !string_ptr.is_null() {
+    
+    unsafe {
+      let _ = CString::from_raw(string_ptr);
+    }
+
+    0
+  } else {
+    set_last_error("Null string pointer");
+    return -1;
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn get_last_error() -> *const c_char {
+  let error = LAST_ERROR.lock().unwrap();
+  match &*error {
+    Some(err) => CString::new(err.clone()).unwrap().into_raw(),
+    None => ptr::null(),
+  }
+}
+
+/// Returns the stable code of the last error, as listed in the `error` module: e.g.
+/// 2 for a timeout or 5 for a reader's non-success LLRPStatus. Errors of the C API
+/// itself are 8 (invalid argument) for a null pointer or malformed argument, 6 for
+/// an invalid configuration and 9 (invalid state) for a call out of order, such as
+/// awaiting reports without a registered callback. 0 if no error occurred.
+#[no_mangle]
+pub extern "C" fn get_last_error_code() -> i32 {
+  *LAST_ERROR_CODE.lock().unwrap()
+}
+
+/// Records an invalid argument passed to the C API.
+fn set_last_error(err: &str) {
+  *LAST_ERROR.lock().unwrap() = Some(err.to_string());
+  *LAST_ERROR_CODE.lock().unwrap() = LlrpError::InvalidArgument(String::new()).code();
+}
+
+fn set_last_client_error(err: &LlrpError) {
   *LAST_ERROR.lock().unwrap() = Some(err.to_string());
+  *LAST_ERROR_CODE.lock().unwrap() = err.code();
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::Constraint;
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, Row, Table};
+use ratatui::DefaultTerminal;
+use tokio::sync::broadcast;
+
+use crate::client::LlrpClient;
+use crate::llrp::{LlrpResponse, LlrpResponseData, ReceiveTimestamp};
+use crate::params::TagReportData;
+
+/// How often the table is redrawn and the keyboard polled.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A tag in the live view.
+///
+/// Fields:
+/// - `epc`: The tag EPC, as hex.
+/// - `antenna_id` / `peak_rssi`: From the most recent report of the tag.
+/// - `count`: Reads since the view started, using TagSeenCount when the reader reports it.
+/// - `last_seen`: When the client received the most recent report of the tag.
+pub struct LiveTagRow {
+  pub epc        : String,
+  pub antenna_id : Option<u16>,
+  pub peak_rssi  : Option<i8>,
+  pub count      : u64,
+  pub last_seen  : ReceiveTimestamp
+}
+
+/// The tags seen since the live view started, keyed by EPC.
+#[derive(Default)]
+pub struct LiveTags {
+  rows  : HashMap<String, LiveTagRow>,
+  reads : u64
+}
+
+impl LiveTags {
+
+  pub fn record(
+    &mut self,
+    tag: &TagReportData
+  ) {
+
+    let count = tag.tag_seen_count.map_or(1, u64::from);
+    self.reads += count;
+
+    let epc = tag.to_string();
+    let row = self.rows.entry(epc.clone()).or_insert(LiveTagRow {
+      epc,
+      antenna_id : None,
+      peak_rssi  : None,
+      count      : 0,
+      last_seen  : tag.received_at
+    });
+
+    row.antenna_id = tag.antenna_id.or(row.antenna_id);
+    row.peak_rssi = tag.peak_rssi.or(row.peak_rssi);
+    row.count += count;
+    row.last_seen = tag.received_at;
+  }
+
+  /// The rows, most recently seen first.
+  pub fn rows(
+    &self
+  ) -> Vec<&LiveTagRow> {
+    let mut rows: Vec<&LiveTagRow> = self.rows.values().collect();
+    rows.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.epc.cmp(&b.epc)));
+    rows
+  }
+}
+
+/// Shows the tags the reader reports in a table redrawn in place, until `q`,
+/// `Esc` or Ctrl-C is pressed or the client's report stream closes.
+pub async fn run(
+  client           : &LlrpClient,
+  mut ro_report_rx : broadcast::Receiver<LlrpResponse>
+) -> Result<(), Box<dyn Error>> {
+
+  let reader_id = client.config().reader_id();
+  let mut tags = LiveTags::default();
+  let mut ticker = tokio::time::interval(REFRESH_INTERVAL);
+
+  let mut terminal = ratatui::init();
+
+  let result = loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          if let Ok(LlrpResponseData::TagReport(tag_reports)) = report.decode() {
+            for tag in &tag_reports {
+              tags.record(tag);
+            }
+          }
+        }
+        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(broadcast::error::RecvError::Closed) => break Ok(())
+      },
+
+      _ = ticker.tick() => {
+
+        if let Err(e) = draw(&mut terminal, &reader_id, &tags) {
+          break Err(e.into());
+        }
+
+        match quit_requested() {
+          Ok(false) => {}
+          Ok(true) => break Ok(()),
+          Err(e) => break Err(e.into())
+        }
+      }
+    }
+  };
+
+  ratatui::restore();
+  result
+}
+
+fn draw(
+  terminal  : &mut DefaultTerminal,
+  reader_id : &str,
+  tags      : &LiveTags
+) -> std::io::Result<()> {
+
+  let now = ReceiveTimestamp::now();
+  let rows = tags.rows();
+
+  let title = format!(" {} - {} tags, {} reads - q to quit ", reader_id, rows.len(), tags.reads);
+
+  let table_rows = rows.iter().map(|row| Row::new(vec![
+    row.epc.clone(),
+    row.antenna_id.map(|antenna_id| antenna_id.to_string()).unwrap_or_default(),
+    row.peak_rssi.map(|peak_rssi| format!("{} dBm", peak_rssi)).unwrap_or_default(),
+    row.count.to_string(),
+    format!("{:.1} s ago", now.monotonic_us.saturating_sub(row.last_seen.monotonic_us) as f64 / 1_000_000.0)
+  ]));
+
+  let table = Table::new(table_rows, [
+      Constraint::Min(24),
+      Constraint::Length(7),
+      Constraint::Length(9),
+      Constraint::Length(8),
+      Constraint::Length(12)
+    ])
+    .header(Row::new(vec!["EPC", "ANTENNA", "RSSI", "COUNT", "LAST SEEN"]).style(Style::default().add_modifier(Modifier::BOLD)))
+    .block(Block::default().borders(Borders::ALL).title(title));
+
+  terminal.draw(|frame| frame.render_widget(table, frame.area()))?;
+
+  Ok(())
+}
+
+/// Drains pending key presses; raw mode delivers Ctrl-C as a key rather than a signal.
+fn quit_requested() -> std::io::Result<bool> {
+
+  while event::poll(Duration::ZERO)? {
+    if let Event::Key(key) = event::read()? {
+
+      if key.kind != KeyEventKind::Press {
+        continue;
+      }
+
+      let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+      if ctrl_c || key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
+        return Ok(true);
+      }
+    }
+  }
+
+  Ok(false)
+}
\ No newline at end of file
@@ -0,0 +1,138 @@
+//! Detection of connections whose reader has gone away without closing them, e.g.
+//! after losing power or its network link. Such a socket never reports an error,
+//! so the client watches for silence instead: every message from the reader counts
+//! as a sign of life, and the reader's KEEPALIVEs, or the answers to the client's
+//! own, make sure an idle reader still sends one now and then.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::Config;
+
+/// Message ID of the client's own KEEPALIVEs, sent outside the request sequence.
+pub const LIVENESS_PROBE_ID: u32 = 0;
+
+/// The `liveness` settings resolved against the reader's KeepaliveSpec.
+///
+/// Fields:
+/// - `dead_after`: Silence after which the connection is declared dead.
+/// - `check_interval`: Time between checks for silence, and for a KEEPALIVE to send.
+/// - `probe_interval`: Time between the client's own KEEPALIVEs, if it sends any.
+#[derive(Debug, Clone, Copy)]
+pub struct LivenessSettings {
+  pub dead_after     : Duration,
+  pub check_interval : Duration,
+  pub probe_interval : Option<Duration>
+}
+
+impl LivenessSettings {
+
+  /// Returns `None` when the configuration has no `liveness` section, or neither an
+  /// applied periodic KeepaliveSpec nor a `keepalive_interval` to measure silence against.
+  pub fn from_config(
+    config: &Config
+  ) -> Option<Self> {
+
+    let liveness = config.liveness.as_ref()?;
+
+    let reader_interval = config.applied_keepalive_interval().map(|interval| Duration::from_millis(interval as u64));
+    let probe_interval = liveness.keepalive_interval.filter(|interval| *interval > 0).map(Duration::from_millis);
+
+    let keepalive_interval = reader_interval.or(probe_interval)?;
+
+    Some(LivenessSettings {
+      dead_after     : keepalive_interval * liveness.missed_keepalives.max(1),
+      check_interval : reader_interval.into_iter().chain(probe_interval).min()?,
+      probe_interval
+    })
+  }
+}
+
+struct LivenessState {
+  last_message : Instant,
+  version      : u8,
+  last_probe   : Option<Instant>,
+  awaiting_ack : bool
+}
+
+/// When the reader was last heard from in the current session, and the state of
+/// the client's own KEEPALIVEs. Shared between the receive loop and the watchdog.
+#[derive(Clone)]
+pub struct LivenessTracker {
+  state: Arc<Mutex<LivenessState>>
+}
+
+impl LivenessTracker {
+
+  pub fn new(
+    now: Instant
+  ) -> Self {
+    LivenessTracker {
+      state: Arc::new(Mutex::new(LivenessState {
+        last_message : now,
+        version      : 1,
+        last_probe   : None,
+        awaiting_ack : false
+      }))
+    }
+  }
+
+  /// Records a message from the reader, with the protocol version it was encoded in.
+  pub fn record_message(
+    &self,
+    now     : Instant,
+    version : u8
+  ) {
+    let mut state = self.state.lock().unwrap();
+    state.last_message = now;
+    state.version = version;
+  }
+
+  pub fn silent_for(
+    &self,
+    now: Instant
+  ) -> Duration {
+    now.saturating_duration_since(self.state.lock().unwrap().last_message)
+  }
+
+  /// The protocol version the reader last used, for the client's own KEEPALIVEs.
+  pub fn version(
+    &self
+  ) -> u8 {
+    self.state.lock().unwrap().version
+  }
+
+  pub fn probe_due(
+    &self,
+    now      : Instant,
+    interval : Duration
+  ) -> bool {
+    self.state.lock().unwrap().last_probe.is_none_or(|sent_at| now.saturating_duration_since(sent_at) >= interval)
+  }
+
+  pub fn record_probe(
+    &self,
+    now: Instant
+  ) {
+    let mut state = self.state.lock().unwrap();
+    state.last_probe = Some(now);
+    state.awaiting_ack = true;
+  }
+
+  /// Takes the answer to the last KEEPALIVE the client sent, returning its round
+  /// trip, or `None` when no KEEPALIVE is awaiting an answer.
+  pub fn probe_answered(
+    &self,
+    now: Instant
+  ) -> Option<Duration> {
+
+    let mut state = self.state.lock().unwrap();
+    if !state.awaiting_ack {
+      return None;
+    }
+
+    state.awaiting_ack = false;
+    state.last_probe.map(|sent_at| now.saturating_duration_since(sent_at))
+  }
+}
\ No newline at end of file
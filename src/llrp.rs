@@ -3,9 +3,12 @@ use strum_macros::{EnumIter, EnumString};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use strum::IntoEnumIterator;
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use chrono::Utc;
+use std::time::Instant;
 use log::{info, debug, warn, error};
 
-use crate::{config::{ROSpecConfig, ReaderConfig}, params::{parse_parameters, AntennaConfiguration, AntennaProperties, C1G2LLRPCapabilities, GeneralDeviceCapabilities, Identification, LLRPCapabilities, LLRPStatus, LlrpParameterData, ROReportSpec, ReaderEventNotificationSpec, RegulatoryCapabilities, TagReportData}};
+use crate::{gpio::{GpiPort, GpoPort, PinState}, custom::{CustomMessage, CustomParameter}, rospec::ROSpec, config::{AccessSpecConfig, C1G2InventoryCommandConfig, GpiPortConfig, GpoOutputConfig, KeepaliveSpecConfig, ROSpecConfig, ReaderConfig}, params::{parse_parameter_tree, parse_parameters, AccessSpec, DecodeContext, AntennaConfiguration, AntennaProperties, C1G2LLRPCapabilities, GPIEvent, GPIPortCurrentState, GPOWriteData, GeneralDeviceCapabilities, Identification, KeepaliveSpec, LLRPCapabilities, LLRPConfigurationStateValue, LLRPStatus, LlrpParameterData, ParameterDecodeError, ROReportSpec, ReaderEventNotificationData, ReaderEventNotificationSpec, RegulatoryCapabilities, TagReportData}};
 
 #[derive(Debug, EnumIter, EnumString, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum LlrpMessageType {
@@ -32,6 +35,18 @@ pub enum LlrpMessageType {
   DisableROSpecResponse         = 35,
   GetROSpecs                    = 26,
   GetROSpecsResponse            = 36,
+  AddAccessSpec                 = 40,
+  AddAccessSpecResponse         = 50,
+  DeleteAccessSpec              = 41,
+  DeleteAccessSpecResponse      = 51,
+  EnableAccessSpec              = 42,
+  EnableAccessSpecResponse      = 52,
+  GetAccessSpecs                = 44,
+  GetAccessSpecsResponse        = 54,
+  GetSupportedVersion           = 46,
+  GetSupportedVersionResponse   = 56,
+  SetProtocolVersion            = 47,
+  SetProtocolVersionResponse    = 57,
   GetReport                     = 60,
   ROAccessReport                = 61,
   Keepalive                     = 62,
@@ -39,6 +54,7 @@ pub enum LlrpMessageType {
   ReaderEventNotification       = 63,
   EnableEventsAndReports        = 64,
   ErrorMessage                  = 100,
+  CustomMessage                 = 1023,
 }
 
 impl LlrpMessageType {
@@ -54,6 +70,38 @@ impl LlrpMessageType {
   ) -> Option<Self> {
     Self::iter().find(|&variant| variant as u16 == value)
   }
+
+  /// Length of the fields ahead of the LLRPStatus in messages of this type, or None
+  /// for CustomMessages, whose layout is the vendor's.
+  pub fn status_offset(
+    &self
+  ) -> Option<usize> {
+    match self {
+      // GetSupportedVersionResponse carries CurrentVersion and SupportedVersion ahead of its LLRPStatus.
+      LlrpMessageType::GetSupportedVersionResponse => Some(2),
+      LlrpMessageType::CustomMessage               => None,
+      _                                            => Some(0)
+    }
+  }
+
+  /// Whether a request answered with this response type changes the reader's
+  /// LLRPConfigurationStateValue, i.e. writes the reader configuration or adds,
+  /// deletes or enables an ROSpec or AccessSpec, or disables an ROSpec.
+  pub fn changes_configuration_state(
+    &self
+  ) -> bool {
+    matches!(
+      self,
+      LlrpMessageType::SetReaderConfigResponse
+        | LlrpMessageType::AddROspecResponse
+        | LlrpMessageType::DeleteROSpecResponse
+        | LlrpMessageType::EnableROSpecResponse
+        | LlrpMessageType::DisableROSpecResponse
+        | LlrpMessageType::AddAccessSpecResponse
+        | LlrpMessageType::DeleteAccessSpecResponse
+        | LlrpMessageType::EnableAccessSpecResponse
+    )
+  }
 }
 
 static LLRP_MESSAGE_TYPE_LUT: 
@@ -72,6 +120,173 @@ pub fn get_message_type_str(
     .unwrap_or("Unknown message type")
 }
 
+/// Length of the fixed LLRP message header in bytes.
+pub const LLRP_HEADER_LENGTH: usize = 10;
+
+/// LLRP protocol versions, as carried in the 3-bit `Ver` header field.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LlrpVersion {
+  V1_0_1 = 1,
+  V1_1   = 2,
+}
+
+impl LlrpVersion {
+
+  pub fn value(
+    &self
+  ) -> u8 {
+    *self as u8
+  }
+
+  pub fn from_value(
+    value: u8
+  ) -> Option<Self> {
+    match value {
+      1 => Some(LlrpVersion::V1_0_1),
+      2 => Some(LlrpVersion::V1_1),
+      _ => None
+    }
+  }
+}
+
+/// The LLRP versions a reader reports in its GET_SUPPORTED_VERSION_RESPONSE.
+///
+/// Fields:
+/// - `current_version`: Version the reader uses on this connection.
+/// - `supported_version`: Highest version the reader supports.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SupportedVersion {
+  pub current_version   : u8,
+  pub supported_version : u8
+}
+
+/// The `RequestedData` field of GET_READER_CONFIG.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum ReaderConfigData {
+  #[default]
+  All                         = 0,
+  Identification              = 1,
+  AntennaProperties           = 2,
+  AntennaConfiguration        = 3,
+  ROReportSpec                = 4,
+  ReaderEventNotificationSpec = 5,
+  AccessReportSpec            = 6,
+  LLRPConfigurationStateValue = 7,
+  KeepaliveSpec               = 8,
+  GPIPortCurrentState         = 9,
+  GPOWriteData                = 10,
+  EventsAndReports            = 11
+}
+
+impl ReaderConfigData {
+
+  pub fn value(
+    &self
+  ) -> u8 {
+    *self as u8
+  }
+
+  pub fn from_value(
+    value: u8
+  ) -> Option<Self> {
+    match value {
+      0  => Some(ReaderConfigData::All),
+      1  => Some(ReaderConfigData::Identification),
+      2  => Some(ReaderConfigData::AntennaProperties),
+      3  => Some(ReaderConfigData::AntennaConfiguration),
+      4  => Some(ReaderConfigData::ROReportSpec),
+      5  => Some(ReaderConfigData::ReaderEventNotificationSpec),
+      6  => Some(ReaderConfigData::AccessReportSpec),
+      7  => Some(ReaderConfigData::LLRPConfigurationStateValue),
+      8  => Some(ReaderConfigData::KeepaliveSpec),
+      9  => Some(ReaderConfigData::GPIPortCurrentState),
+      10 => Some(ReaderConfigData::GPOWriteData),
+      11 => Some(ReaderConfigData::EventsAndReports),
+      _  => None
+    }
+  }
+}
+
+/// What a GET_READER_CONFIG asks the reader for. The default requests everything.
+///
+/// Fields:
+/// - `requested_data`: The configuration to return.
+/// - `antenna_id`: Antenna whose AntennaProperties and AntennaConfiguration to return (0 - All).
+/// - `gpi_port`: GPI port whose GPIPortCurrentState to return (0 - All).
+/// - `gpo_port`: GPO port whose GPOWriteData to return (0 - All).
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ReaderConfigRequest {
+  pub requested_data : ReaderConfigData,
+  pub antenna_id     : u16,
+  pub gpi_port       : u16,
+  pub gpo_port       : u16
+}
+
+impl ReaderConfigRequest {
+
+  /// Requests only `requested_data`, of every antenna and port.
+  pub fn only(
+    requested_data: ReaderConfigData
+  ) -> Self {
+    ReaderConfigRequest { requested_data, ..Default::default() }
+  }
+}
+
+/// The fixed 10-byte header preceding every LLRP message.
+///
+/// The first 16 bits are laid out as `Rsvd (3) | Ver (3) | Message Type (10)`.
+/// Reserved bits are always encoded as zero and ignored on decode.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LlrpHeader {
+  pub version            : u8,
+  pub message_type_value : u16,
+  pub message_length     : u32,
+  pub message_id         : u32
+}
+
+impl LlrpHeader {
+
+  pub fn encode(
+    &self,
+    buffer: &mut BytesMut
+  ) {
+    let version_and_type = ((self.version as u16 & 0x7) << 10) | (self.message_type_value & 0x3FF);
+
+    buffer.put_u16(version_and_type);
+    buffer.put_u32(self.message_length);
+    buffer.put_u32(self.message_id);
+  }
+
+  /// Decodes a header from the start of `buf` without consuming it.
+  pub fn decode(
+    buf: &[u8]
+  ) -> io::Result<Self> {
+
+    if buf.len() < LLRP_HEADER_LENGTH {
+      return Err(Error::new(ErrorKind::InvalidData, "Buffer too short for LLRP header"));
+    }
+
+    let mut header = &buf[..LLRP_HEADER_LENGTH];
+
+    let version_and_type = header.get_u16();
+    let version = ((version_and_type >> 10) & 0x7) as u8;
+    let message_type_value = version_and_type & 0x3FF;
+    let message_length = header.get_u32();
+    let message_id = header.get_u32();
+
+    if (message_length as usize) < LLRP_HEADER_LENGTH {
+      return Err(Error::new(ErrorKind::InvalidData, "Invalid message length in header"));
+    }
+
+    Ok(LlrpHeader {
+      version,
+      message_type_value,
+      message_length,
+      message_id
+    })
+  }
+}
+
 #[derive(Debug, EnumIter, EnumString, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum LlrpParameterType {
   UTCTimeStamp                      = 128,
@@ -213,6 +428,9 @@ pub enum LlrpParameterType {
   C1G2TagPrivilegeOpSpecResult      = 379,
   ExtendOnTime                      = 381,
   Custom                            = 1023,
+  // A TLV type this client does not know; `LlrpParameter::param_type_value` keeps
+  // the type read from the wire. Never encoded.
+  Unknown                           = 0,
 }
 
 impl LlrpParameterType {
@@ -236,12 +454,14 @@ impl LlrpParameterType {
 /// including its type, length, ID, and payload.
 ///
 /// Fields:
+/// - `version`: The protocol version carried in the header.
 /// - `message_type`: The type of the LLRP message.
 /// - `message_length`: The total length of the message, including the header and payload.
 /// - `message_id`: A unique identifier for the message.
 /// - `payload`: The binary payload of the message.
 #[derive(Debug)]
 pub struct LlrpMessage {
+  pub version        : u8,
   pub message_type   : LlrpMessageType,
   pub message_length : u32,
   pub message_id     : u32,
@@ -267,15 +487,17 @@ impl LlrpMessage {
   /// Constructs a new LLRP message with the specified type, ID, and payload.
   ///
   /// Automatically calculates the message length based on the payload size.
+  /// The version defaults to LLRP 1.0.1.
   pub fn new(
     message_type : LlrpMessageType, 
     message_id   : u32, 
     payload      : Vec<u8>
   ) -> Self {
 
-    let message_length = 10 + payload.len() as u32;
+    let message_length = (LLRP_HEADER_LENGTH + payload.len()) as u32;
 
     LlrpMessage {
+      version: LlrpVersion::V1_0_1.value(),
       message_type,
       message_length,
       message_id,
@@ -302,28 +524,206 @@ impl LlrpMessage {
     LlrpMessage::new(LlrpMessageType::GetReaderCapabilities, message_id, payload.to_vec())
   }
 
+  /// Constructs a new `GetAccessSpecs` message, listing every AccessSpec loaded
+  /// on the reader.
+  pub fn new_get_access_specs(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::GetAccessSpecs, message_id, vec![])
+  }
+
   pub fn new_get_reader_config(
     message_id : u32,
+    request    : &ReaderConfigRequest
+  ) -> Self {
+    LlrpMessage::new_get_reader_config_with_extensions(message_id, request, &[])
+  }
+
+  /// Constructs a `GetReaderConfig` message carrying vendor `extensions`, e.g. to
+  /// request vendor configuration the reader only returns when asked for.
+  pub fn new_get_reader_config_with_extensions(
+    message_id : u32,
+    request    : &ReaderConfigRequest,
+    extensions : &[CustomParameter]
   ) -> Self {
 
     let mut payload = BytesMut::new();
 
-    payload.put_u16(0);
-    payload.put_u8(0);
-    payload.put_u16(0);
-    payload.put_u16(0);
+    payload.put_u16(request.antenna_id);            // AntennaID (0 - All)
+    payload.put_u8(request.requested_data.value()); // RequestedData
+    payload.put_u16(request.gpi_port);              // GPIPortNum (0 - All)
+    payload.put_u16(request.gpo_port);              // GPOPortNum (0 - All)
+
+    for extension in extensions {
+      payload.extend_from_slice(&extension.encode());
+    }
 
     LlrpMessage::new(LlrpMessageType::GetReaderConfig, message_id, payload.to_vec())
   }
-  
+
+  /// Constructs a `GetReaderConfig` message requesting only the configuration and
+  /// level of every GPI port.
+  pub fn new_get_gpi_port_states(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new_get_reader_config(message_id, &ReaderConfigRequest::only(ReaderConfigData::GPIPortCurrentState))
+  }
+
+  /// Constructs a `GetReaderConfig` message requesting only the reader's
+  /// LLRPConfigurationStateValue.
+  pub fn new_get_configuration_state(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new_get_reader_config(message_id, &ReaderConfigRequest::only(ReaderConfigData::LLRPConfigurationStateValue))
+  }
+
+  /// Constructs a `GetReaderConfig` message requesting only the AntennaProperties
+  /// of every antenna.
+  pub fn new_get_antenna_properties(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new_get_reader_config(message_id, &ReaderConfigRequest::only(ReaderConfigData::AntennaProperties))
+  }
+
+  /// Constructs a `GetReaderConfig` message requesting only the AntennaConfiguration
+  /// of every antenna.
+  pub fn new_get_antenna_configurations(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new_get_reader_config(message_id, &ReaderConfigRequest::only(ReaderConfigData::AntennaConfiguration))
+  }
+
   /// Constructs a new `SetReaderConfig` message
   /// 
-  /// This message resets reader configuration to factory settings.
+  /// This message resets reader configuration to factory settings, then applies the
+  /// RF settings to all antennas and the configured GPO levels and GPI ports.
   pub fn new_set_reader_config(
     message_id : u32,
     config     : &ReaderConfig,
   ) -> Self {
 
+    let mut message = LlrpMessage::new_set_antenna_configuration(message_id, 0, config, true);
+
+    // Parameters following AntennaConfiguration, in the order SET_READER_CONFIG defines.
+    let mut trailing = BytesMut::new();
+    if let Some(keepalive_spec) = &config.keepalive_spec {
+      encode_keepalive_spec(&mut trailing, keepalive_spec);
+    }
+    for output in &config.gpo_outputs {
+      encode_gpo_write_data(&mut trailing, output.port, output.state);
+    }
+    for input in &config.gpi_ports {
+      encode_gpi_port_current_state(&mut trailing, input.port, input.enabled);
+    }
+
+    message.payload.extend_from_slice(&trailing);
+    message.message_length += trailing.len() as u32;
+
+    message
+  }
+
+  /// Constructs a `SetReaderConfig` message driving a single GPO port to `state`,
+  /// leaving the rest of the reader configuration unchanged.
+  pub fn new_set_gpo(
+    message_id : u32,
+    port       : GpoPort,
+    state      : PinState
+  ) -> Self {
+    LlrpMessage::new_set_gpos(message_id, &[GpoOutputConfig { port, state }])
+  }
+
+  /// Constructs a `SetReaderConfig` message driving several GPO ports at once, in
+  /// the order given, leaving the rest of the reader configuration unchanged.
+  pub fn new_set_gpos(
+    message_id : u32,
+    outputs    : &[GpoOutputConfig]
+  ) -> Self {
+
+    let mut payload = BytesMut::new();
+    payload.put_u8(0); // ResetToFactoryDefault (false)
+    for output in outputs {
+      encode_gpo_write_data(&mut payload, output.port, output.state);
+    }
+
+    LlrpMessage::new(LlrpMessageType::SetReaderConfig, message_id, payload.to_vec())
+  }
+
+  /// Constructs a `SetReaderConfig` message enabling or disabling GPI ports,
+  /// leaving the rest of the reader configuration unchanged.
+  pub fn new_set_gpi_ports(
+    message_id : u32,
+    ports      : &[GpiPortConfig]
+  ) -> Self {
+
+    let mut payload = BytesMut::new();
+    payload.put_u8(0); // ResetToFactoryDefault (false)
+    for input in ports {
+      encode_gpi_port_current_state(&mut payload, input.port, input.enabled);
+    }
+
+    LlrpMessage::new(LlrpMessageType::SetReaderConfig, message_id, payload.to_vec())
+  }
+
+  /// Constructs a `SetReaderConfig` message that only resets the reader to its
+  /// factory settings.
+  pub fn new_factory_reset(
+    message_id: u32
+  ) -> Self {
+
+    let mut payload = BytesMut::new();
+    payload.put_u8(128); // ResetToFactoryDefault (true)
+
+    LlrpMessage::new(LlrpMessageType::SetReaderConfig, message_id, payload.to_vec())
+  }
+
+  /// Constructs a `GetSupportedVersion` message. It is an LLRP 1.1 message, to be
+  /// sent with a 1.1 header whatever version the connection uses.
+  pub fn new_get_supported_version(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::GetSupportedVersion, message_id, vec![])
+  }
+
+  /// Constructs a `SetProtocolVersion` message switching the connection to `version`.
+  pub fn new_set_protocol_version(
+    message_id : u32,
+    version    : LlrpVersion
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::SetProtocolVersion, message_id, vec![version.value()])
+  }
+
+  /// Constructs a vendor `CustomMessage`.
+  pub fn new_custom_message(
+    message_id : u32,
+    custom     : &CustomMessage
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::CustomMessage, message_id, custom.encode())
+  }
+
+  /// Constructs a vendor `CustomMessage` setting the reader clock to `utc_us`,
+  /// microseconds since the Unix epoch. LLRP has no standard message for this.
+  pub fn new_set_reader_clock(
+    message_id      : u32,
+    vendor_id       : u32,
+    message_subtype : u8,
+    utc_us          : u64
+  ) -> Self {
+    LlrpMessage::new_custom_message(message_id, &CustomMessage {
+      vendor_id,
+      message_subtype,
+      data: utc_us.to_be_bytes().to_vec()
+    })
+  }
+
+  /// Constructs a `SetReaderConfig` message carrying a single AntennaConfiguration
+  /// for `antenna_id` (0 - All), optionally resetting the reader to factory settings first.
+  pub fn new_set_antenna_configuration(
+    message_id               : u32,
+    antenna_id               : u16,
+    config                   : &ReaderConfig,
+    reset_to_factory_default : bool
+  ) -> Self {
+
     let rf_receiver = Parameter {
       param_type: LlrpParameterType::RFReceiver,
       payload: vec![]
@@ -341,12 +741,13 @@ impl LlrpMessage {
 
     let mut payload = BytesMut::new();
 
-    payload.put_u8(128); // ResetToFactoryDefault (First bit is boolean value)
+    payload.put_u8(if reset_to_factory_default { 128 } else { 0 }); // ResetToFactoryDefault (First bit is boolean value)
 
     fn encode_parameter(
-      param     : &Parameter, 
-      buffer    : &mut BytesMut,
-      config    : &ReaderConfig
+      param      : &Parameter, 
+      buffer     : &mut BytesMut,
+      config     : &ReaderConfig,
+      antenna_id : u16
     ) {
       
       let initial_length_pos = buffer.len();
@@ -357,7 +758,7 @@ impl LlrpMessage {
       match param.param_type {
 
         LlrpParameterType::AntennaConfiguration => {
-          buffer.put_u16(0); // Antenna ID (0 - All)
+          buffer.put_u16(antenna_id); // Antenna ID (0 - All)
         } 
 
         LlrpParameterType::RFReceiver => {
@@ -374,7 +775,7 @@ impl LlrpMessage {
       }
 
       for sub_param in &param.payload {
-        encode_parameter(sub_param, buffer, config); 
+        encode_parameter(sub_param, buffer, config, antenna_id); 
       }
 
       let final_length_pos = buffer.len();
@@ -383,7 +784,7 @@ impl LlrpMessage {
       buffer[initial_length_pos + 2..initial_length_pos + 4].copy_from_slice(&actual_length.to_be_bytes());
     };
 
-    encode_parameter(&antenna_configuration, &mut payload, config);
+    encode_parameter(&antenna_configuration, &mut payload, config, antenna_id);
 
     LlrpMessage::new(LlrpMessageType::SetReaderConfig, message_id, payload.to_vec())
   }
@@ -393,126 +794,22 @@ impl LlrpMessage {
   /// The ROSpec includes the following parameters:
   /// - `ROBoundarySpec`: Specifies start and stop triggers.
   /// - `AISpec`: Defines antenna configurations and stop triggers.
-  /// - `ROReportSpec`: Configures report generation.
+  /// - `ROReportSpec`: Configures report generation, followed by `report_extensions`,
+  ///   vendor Custom parameters selecting additional report content.
   pub fn new_add_rospec(
-    message_id : u32, 
-    config     : &ROSpecConfig
+    message_id        : u32, 
+    config            : &ROSpecConfig,
+    report_extensions : &[CustomParameter]
   ) -> Self {
-    
-    let ro_boundary_spec = Parameter {
-      param_type: LlrpParameterType::ROBoundarySpec,
-      payload: vec![]
-    };
-
-    let ai_spec = Parameter {
-      param_type: LlrpParameterType::AISpec,
-      payload: vec![]
-    };
-
-    let ro_report_spec = Parameter {
-      param_type: LlrpParameterType::ROReportSpec,
-      payload: vec![]
-    };
-
-    let ro_spec = Parameter {
-      param_type: LlrpParameterType::ROSpec,
-      payload: vec![ro_boundary_spec, ai_spec, ro_report_spec]
-    };
-
-    let mut payload = BytesMut::new();
-
-    fn encode_parameter(
-      param     : &Parameter, 
-      buffer    : &mut BytesMut,
-      config    : &ROSpecConfig
-    ) {
-      
-      let initial_length_pos = buffer.len();
-      buffer.put_u16(param.param_type.value());
-      buffer.put_u16(0); // Length (dynamic)
-
-      match param.param_type {
- 
-        LlrpParameterType::ROSpec => {
-          buffer.put_u32(config.rospec_id);
-          buffer.put_u8(config.priority); // Priority
-          buffer.put_u8(0);               // CurrentState
-        }
-
-        LlrpParameterType::ROBoundarySpec => {
-
-          // ROSpecStartTrigger
-          buffer.put_u16(LlrpParameterType::ROSpecStartTrigger.value());
-          buffer.put_u16(5); // Length (static)
-
-          /* Fields */
-          buffer.put_u8(config.ROSpecStartTriggerType); // ROSpecStartTriggerType
-
-          // ROSpecStopTrigger
-          buffer.put_u16(LlrpParameterType::ROSpecStopTrigger.value());
-          buffer.put_u16(9); // Length (static)
-          
-          /* Fields */
-          buffer.put_u8(config.ROSpecStopTriggerType);  // ROSpecStopTriggerType (0 - No stop trigger)
-          buffer.put_u32(0); // Null-field padding (Fields not required with ROSpecStoTriggerType=0)
-        }
-
-        LlrpParameterType::AISpec => {
-
-          // Antenna configuration
-          buffer.put_u16(config.antenna_count);
-
-          // AntennaID Array (Allocated before AISpecStopTrigger)
-          for antenna_id in &config.antennas {
-            buffer.put_u16(*antenna_id);
-          }
-
-          // AISpecStopTrigger
-          buffer.put_u16(LlrpParameterType::AISpecStopTrigger.value());
-          buffer.put_u16(9); // Length (dynamic)
-
-          /* Fields */
-          buffer.put_u8(config.AISpecStopTriggerType); // AISpecStopTriggerType
-          buffer.put_u32(0); // Null-field padding
-
-          // InventoryParamSpec
-          buffer.put_u16(LlrpParameterType::InventoryParameterSpec.value());
-          buffer.put_u16(7); // Length (static)
-
-          buffer.put_u16(config.InventoryParamSpecID); // InventoryParamSpec ID
-          buffer.put_u8(config.AIProtocol); // AiProcotol
-        }
-
-        LlrpParameterType::ROReportSpec => {
-
-          buffer.put_u8(config.ROReportTriggerType); // ROReportTriggerType
-          buffer.put_u16(config.ROReportTrigger_N);  // N
-
-          // TagReportContentSelector
-          buffer.put_u16(LlrpParameterType::TagReportContentSelector.value());
-          buffer.put_u16(6); // Length (static)
-
-          /* Fields */
-          buffer.put_u16(config.ReportContentSelector); // ReportContentSelector (TagInfo/EPC)
-        }
-
-        _ => {}
-      }
-
-      // Recursively encode nested parameters.
-      for sub_param in &param.payload {
-        encode_parameter(sub_param, buffer, config); 
-      }
-
-      let final_length_pos = buffer.len();
-      let actual_length = (final_length_pos - initial_length_pos) as u16;
-
-      buffer[initial_length_pos + 2..initial_length_pos + 4].copy_from_slice(&actual_length.to_be_bytes());
-    };
-
-    encode_parameter(&ro_spec, &mut payload, config);
+    LlrpMessage::new_add_built_rospec(message_id, &ROSpec::from_config(config, report_extensions))
+  }
 
-    LlrpMessage::new(LlrpMessageType::AddROSpec, message_id, payload.to_vec())
+  /// Constructs a new `AddROSpec` message carrying an ROSpec composed with `ROSpecBuilder`.
+  pub fn new_add_built_rospec(
+    message_id : u32,
+    rospec     : &ROSpec
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::AddROSpec, message_id, rospec.encode())
   }
 
   pub fn new_enable_rospec(
@@ -526,6 +823,17 @@ impl LlrpMessage {
     LlrpMessage::new(LlrpMessageType::EnableROSpec, message_id, payload.to_vec())
   }
 
+  pub fn new_disable_rospec(
+    message_id : u32, 
+    rospec_id  : u32
+  ) -> Self {
+
+    let mut payload = BytesMut::with_capacity(4);
+    payload.put_u32(rospec_id);
+    
+    LlrpMessage::new(LlrpMessageType::DisableROSpec, message_id, payload.to_vec())
+  }
+
   pub fn new_start_rospec(
     message_id : u32, 
     rospec_id  : u32
@@ -559,72 +867,345 @@ impl LlrpMessage {
     LlrpMessage::new(LlrpMessageType::DeleteROSpec, message_id, payload.to_vec())
   }
 
-  /// Encodes the LLRP message into a binary format.
+  /// Constructs a new `AddAccessSpec` message for the given AccessSpec.
   ///
-  /// This includes the LLRP header and the message payload.
-  pub fn encode(
-    &self
-  ) -> BytesMut {
+  /// The AccessSpec includes the following parameters:
+  /// - `AccessSpecStopTrigger`: Stops the AccessSpec after `operation_count` executions.
+  /// - `AccessCommand`: A C1G2TagSpec matching the configured target, or every tag
+  ///   without one, followed by the configured `C1G2Read`, `C1G2Write`, `C1G2BlockErase`,
+  ///   `C1G2BlockWrite`, `C1G2Kill` and `C1G2Lock` op specs, in that order.
+  /// - `AccessReportSpec`: Reports the op spec results with the ROSpec's tag reports.
+  ///
+  /// The AccessSpec is added in the disabled state and must be enabled separately.
+  pub fn new_add_access_spec(
+    message_id : u32,
+    config     : &AccessSpecConfig
+  ) -> Self {
 
-    let mut buffer = BytesMut::with_capacity(self.message_length as usize);
+    let access_spec_stop_trigger = Parameter {
+      param_type: LlrpParameterType::AccessSpecStopTrigger,
+      payload: vec![]
+    };
 
-    let padding = 0;
-    let version = 1;
+    let access_command = Parameter {
+      param_type: LlrpParameterType::AccessCommand,
+      payload: vec![]
+    };
 
-    let version_and_type = ((padding & 0x7) << 13) | ((version & 0x7) << 10) | ((self.message_type.value()) & 0x3FFF);
+    let access_report_spec = Parameter {
+      param_type: LlrpParameterType::AccessReportSpec,
+      payload: vec![]
+    };
 
-    buffer.put_u16(version_and_type as u16);
-    buffer.put_u32(self.message_length);
-    buffer.put_u32(self.message_id);
-    buffer.extend_from_slice(&self.payload);
+    let access_spec = Parameter {
+      param_type: LlrpParameterType::AccessSpec,
+      payload: vec![access_spec_stop_trigger, access_command, access_report_spec]
+    };
 
-    buffer
-  }
+    let mut payload = BytesMut::new();
 
-  /// Decodes an LLRP message from a binary buffer.
-  ///
-  /// Returns an `io::Result` with the decoded message or an error.
-  pub fn decode(
-    buf: &mut BytesMut
-  ) -> io::Result<Self> {
+    fn encode_parameter(
+      param  : &Parameter,
+      buffer : &mut BytesMut,
+      config : &AccessSpecConfig
+    ) {
 
-    if buf.len() < 10 {
-      return Err(Error::new(ErrorKind::InvalidData, "Buffer too short for LLRP header"));
+      let initial_length_pos = buffer.len();
+      buffer.put_u16(param.param_type.value());
+      buffer.put_u16(0); // Length (dynamic)
+
+      match param.param_type {
+
+        LlrpParameterType::AccessSpec => {
+          buffer.put_u32(config.access_spec_id);
+          buffer.put_u16(config.antenna_id); // AntennaID (0 - All)
+          buffer.put_u8(1);                  // ProtocolID (EPCGlobal Class 1 Gen 2)
+          buffer.put_u8(0);                  // CurrentState (Disabled)
+          buffer.put_u32(config.rospec_id);  // ROSpecID (0 - Any)
+        }
+
+        LlrpParameterType::AccessSpecStopTrigger => {
+          buffer.put_u8(if config.operation_count > 0 { 1 } else { 0 }); // AccessSpecStopTriggerType (0 - Null, 1 - Operation count)
+          buffer.put_u16(config.operation_count);                        // OperationCountValue
+        }
+
+        LlrpParameterType::AccessCommand => {
+
+          // C1G2TagSpec
+          let tag_spec_pos = buffer.len();
+          buffer.put_u16(LlrpParameterType::C1G2TagSpec.value());
+          buffer.put_u16(0); // Length (dynamic)
+
+          // C1G2TargetTag
+          let target_tag_pos = buffer.len();
+          buffer.put_u16(LlrpParameterType::C1G2TargetTag.value());
+          buffer.put_u16(0); // Length (dynamic)
+
+          /* Fields */
+          match &config.target {
+
+            Some(target) => {
+
+              // Hex is checked by `AccessSpecConfig::validate` before the spec is sent.
+              let tag_mask = decode_hex(&target.tag_mask).unwrap_or_default();
+              let tag_data = decode_hex(&target.tag_data).unwrap_or_default();
+
+              buffer.put_u8(((target.memory_bank & 0x03) << 6) | (1 << 5)); // MB, Match (Matching tags)
+              buffer.put_u16(target.pointer);
+              buffer.put_u16((target.tag_mask.len() * 4) as u16);           // TagMask bit count
+              buffer.put_slice(&tag_mask);
+              buffer.put_u16((target.tag_data.len() * 4) as u16);           // TagData bit count
+              buffer.put_slice(&tag_data);
+            }
+
+            None => {
+              buffer.put_u8((1 << 6) | (1 << 5)); // MB (EPC), Match (Matching tags)
+              buffer.put_u16(0);                  // Pointer
+              buffer.put_u16(0);                  // TagMask bit count (Empty mask matches every tag)
+              buffer.put_u16(0);                  // TagData bit count
+            }
+          }
+
+          patch_parameter_length(buffer, target_tag_pos);
+          patch_parameter_length(buffer, tag_spec_pos);
+
+          for read in &config.reads {
+
+            // C1G2Read
+            buffer.put_u16(LlrpParameterType::C1G2Read.value());
+            buffer.put_u16(15); // Length (static)
+
+            /* Fields */
+            buffer.put_u16(read.op_spec_id);
+            buffer.put_u32(read.access_password);
+            buffer.put_u8((read.memory_bank & 0x03) << 6); // MB (First two bits)
+            buffer.put_u16(read.word_pointer);
+            buffer.put_u16(read.word_count);                // WordCount (0 - Entire bank)
+          }
+
+          for write in &config.writes {
+
+            // C1G2Write
+            buffer.put_u16(LlrpParameterType::C1G2Write.value());
+            buffer.put_u16((15 + 2 * write.data.len()) as u16); // Length (dynamic)
+
+            /* Fields */
+            buffer.put_u16(write.op_spec_id);
+            buffer.put_u32(write.access_password);
+            buffer.put_u8((write.memory_bank & 0x03) << 6); // MB (First two bits)
+            buffer.put_u16(write.word_pointer);
+            buffer.put_u16(write.data.len() as u16);         // WriteData word count
+            for word in &write.data {
+              buffer.put_u16(*word);
+            }
+          }
+
+          for erase in &config.block_erases {
+
+            // C1G2BlockErase
+            buffer.put_u16(LlrpParameterType::C1G2BlockErase.value());
+            buffer.put_u16(15); // Length (static)
+
+            /* Fields */
+            buffer.put_u16(erase.op_spec_id);
+            buffer.put_u32(erase.access_password);
+            buffer.put_u8((erase.memory_bank & 0x03) << 6); // MB (First two bits)
+            buffer.put_u16(erase.word_pointer);
+            buffer.put_u16(erase.word_count);
+          }
+
+          for write in &config.block_writes {
+
+            // C1G2BlockWrite
+            buffer.put_u16(LlrpParameterType::C1G2BlockWrite.value());
+            buffer.put_u16((15 + 2 * write.data.len()) as u16); // Length (dynamic)
+
+            /* Fields */
+            buffer.put_u16(write.op_spec_id);
+            buffer.put_u32(write.access_password);
+            buffer.put_u8((write.memory_bank & 0x03) << 6); // MB (First two bits)
+            buffer.put_u16(write.word_pointer);
+            buffer.put_u16(write.data.len() as u16);         // WriteData word count
+            for word in &write.data {
+              buffer.put_u16(*word);
+            }
+          }
+
+          for kill in &config.kills {
+
+            // C1G2Kill
+            buffer.put_u16(LlrpParameterType::C1G2Kill.value());
+            buffer.put_u16(10); // Length (static)
+
+            /* Fields */
+            buffer.put_u16(kill.op_spec_id);
+            buffer.put_u32(kill.kill_password);
+          }
+
+          for lock in &config.locks {
+
+            // C1G2Lock
+            buffer.put_u16(LlrpParameterType::C1G2Lock.value());
+            buffer.put_u16((10 + 6 * lock.payloads.len()) as u16); // Length (dynamic)
+
+            /* Fields */
+            buffer.put_u16(lock.op_spec_id);
+            buffer.put_u32(lock.access_password);
+
+            for payload in &lock.payloads {
+
+              // C1G2LockPayload
+              buffer.put_u16(LlrpParameterType::C1G2LockPayload.value());
+              buffer.put_u16(6); // Length (static)
+
+              /* Fields */
+              buffer.put_u8(payload.privilege);
+              buffer.put_u8(payload.data_field);
+            }
+          }
+        }
+
+        LlrpParameterType::AccessReportSpec => {
+          buffer.put_u8(0); // AccessReportTrigger (Report with the ROSpec's tag reports)
+        }
+
+        _ => {}
+      }
+
+      for sub_param in &param.payload {
+        encode_parameter(sub_param, buffer, config);
+      }
+
+      let final_length_pos = buffer.len();
+      let actual_length = (final_length_pos - initial_length_pos) as u16;
+
+      buffer[initial_length_pos + 2..initial_length_pos + 4].copy_from_slice(&actual_length.to_be_bytes());
     }
 
-    let version_and_type = buf.get_u16();
-    let version = (version_and_type >> 10) & 0x7;
-    let message_type_value = version_and_type & 0x3FF;
-    let message_length = buf.get_u32();
-    let message_id = buf.get_u32();
+    encode_parameter(&access_spec, &mut payload, config);
+
+    LlrpMessage::new(LlrpMessageType::AddAccessSpec, message_id, payload.to_vec())
+  }
+
+  pub fn new_enable_access_spec(
+    message_id     : u32,
+    access_spec_id : u32
+  ) -> Self {
+
+    let mut payload = BytesMut::with_capacity(4);
+    payload.put_u32(access_spec_id);
+
+    LlrpMessage::new(LlrpMessageType::EnableAccessSpec, message_id, payload.to_vec())
+  }
+
+  pub fn new_delete_access_spec(
+    message_id     : u32,
+    access_spec_id : u32
+  ) -> Self {
+
+    let mut payload = BytesMut::with_capacity(4);
+    payload.put_u32(access_spec_id);
+
+    LlrpMessage::new(LlrpMessageType::DeleteAccessSpec, message_id, payload.to_vec())
+  }
 
-    if buf.len() < (message_length - 10) as usize {
+  /// Constructs a new `GetReport` message, asking the reader to send the tag
+  /// reports it is currently holding.
+  pub fn new_get_report(
+    message_id: u32
+  ) -> Self {
+    LlrpMessage::new(LlrpMessageType::GetReport, message_id, vec![])
+  }
+
+  /// Encodes the LLRP message into a binary format.
+  ///
+  /// This includes the LLRP header and the message payload.
+  pub fn encode(
+    &self
+  ) -> BytesMut {
+
+    let mut buffer = BytesMut::with_capacity(self.message_length as usize);
+
+    LlrpHeader {
+      version            : self.version,
+      message_type_value : self.message_type.value(),
+      message_length     : self.message_length,
+      message_id         : self.message_id
+    }.encode(&mut buffer);
+
+    buffer.extend_from_slice(&self.payload);
+
+    buffer
+  }
+
+  /// Decodes an LLRP message from a binary buffer.
+  ///
+  /// Returns an `io::Result` with the decoded message or an error.
+  pub fn decode(
+    buf: &mut BytesMut
+  ) -> io::Result<Self> {
+
+    let header = LlrpHeader::decode(buf)?;
+    let payload_length = header.message_length as usize - LLRP_HEADER_LENGTH;
+
+    if buf.len() - LLRP_HEADER_LENGTH < payload_length {
       return Err(Error::new(ErrorKind::InvalidData, "Buffer too short for payload"));
     }
 
-    let payload = buf.split_to((message_length - 10) as usize).to_vec();
+    buf.advance(LLRP_HEADER_LENGTH);
+    let payload = buf.split_to(payload_length).to_vec();
 
-    let message_type = LlrpMessageType::from_value(message_type_value)
+    let message_type = LlrpMessageType::from_value(header.message_type_value)
       .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Unknown LLRP message type"))?;
     
     Ok(LlrpMessage {
+      version        : header.version,
       message_type,
-      message_length,
-      message_id,
+      message_length : header.message_length,
+      message_id     : header.message_id,
       payload,
     })
   }
 }
 
+static MONOTONIC_EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Client-side time at which a message was received.
+///
+/// Fields:
+/// - `monotonic_us`: Microseconds since a process-wide reference point. Never goes
+///   backwards, so it orders messages even when the host or reader clock is adjusted.
+/// - `utc_us`: Host UTC time in microseconds since the Unix epoch, for comparison with
+///   reader timestamps.
+///
+/// Timestamps compare by `monotonic_us`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct ReceiveTimestamp {
+  pub monotonic_us : u64,
+  pub utc_us       : i64
+}
+
+impl ReceiveTimestamp {
+
+  pub fn now() -> Self {
+    ReceiveTimestamp {
+      monotonic_us : MONOTONIC_EPOCH.elapsed().as_micros() as u64,
+      utc_us       : Utc::now().timestamp_micros()
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct LlrpResponse {
   pub message_type : LlrpMessageType,
   pub message_id   : u32,
-  pub payload      : Vec<u8>
+  pub payload      : Vec<u8>,
+  pub received_at  : ReceiveTimestamp
 }
 
 impl LlrpResponse {
   
+  /// Wraps a message read from the reader, stamping it with the current time.
   pub fn from_message(
     message: LlrpMessage
   ) -> Self {
@@ -632,7 +1213,81 @@ impl LlrpResponse {
       message_type : message.message_type,
       message_id   : message.message_id,
       payload      : message.payload,
+      received_at  : ReceiveTimestamp::now()
+    }
+  }
+
+  /// The LLRPStatus following the message's fixed fields, e.g. of an ErrorMessage,
+  /// with the reader's ErrorDescription and any FieldError or ParameterError, read
+  /// without the logging of `decode`.
+  pub fn status(
+    &self,
+    fixed_length: usize
+  ) -> Option<LLRPStatus> {
+
+    let parameters = parse_parameters(self.payload.get(fixed_length..)?).ok()?;
+    let parameter = parameters.iter().find(|parameter| parameter.param_type == LlrpParameterType::LLRPStatus)?;
+
+    LLRPStatus::decode(&parameter.param_value).ok()
+  }
+
+  /// The StatusCode of the LLRPStatus following the message's fixed fields.
+  pub fn status_code(
+    &self,
+    fixed_length: usize
+  ) -> Option<u16> {
+    self.status(fixed_length).map(|status| status.status_code)
+  }
+
+  /// The UTCTimestamp of a ReaderEventNotification, read without the logging of `decode`.
+  pub fn event_timestamp_us(
+    &self
+  ) -> Option<u64> {
+    self.reader_event_data()?.timestamp_us
+  }
+
+  /// The GPIEvent of a ReaderEventNotification, read without the logging of `decode`.
+  pub fn gpi_event(
+    &self
+  ) -> Option<GPIEvent> {
+    self.reader_event_data()?.gpi_event
+  }
+
+  /// The decoded ReaderEventNotificationData of a READER_EVENT_NOTIFICATION, read
+  /// without the logging of `decode`.
+  pub fn reader_event_data(
+    &self
+  ) -> Option<ReaderEventNotificationData> {
+
+    if self.message_type != LlrpMessageType::ReaderEventNotification {
+      return None;
     }
+
+    let parameters = parse_parameters(&self.payload).ok()?;
+    let parameter = parameters.iter().find(|parameter| parameter.param_type == LlrpParameterType::ReaderEventNotificationData)?;
+
+    ReaderEventNotificationData::decode(&parameter.param_value).ok()
+  }
+
+  /// The Custom parameters in the ReaderEventNotificationData of a READER_EVENT_NOTIFICATION
+  /// or at the top level of a GET_READER_CONFIG_RESPONSE, where readers put vendor
+  /// events and vendor configuration. Parameters that fail to decode are skipped.
+  pub fn custom_parameters(
+    &self
+  ) -> Vec<CustomParameter> {
+
+    let parameters = match self.message_type {
+      LlrpMessageType::ReaderEventNotification => parse_parameters(&self.payload).ok()
+        .and_then(|parameters| parameters.into_iter().find(|parameter| parameter.param_type == LlrpParameterType::ReaderEventNotificationData))
+        .and_then(|parameter| parse_parameters(&parameter.param_value).ok()),
+      LlrpMessageType::GetReaderConfigResponse => parse_parameters(&self.payload).ok(),
+      _ => None
+    };
+
+    parameters.unwrap_or_default().iter()
+      .filter(|parameter| parameter.param_type == LlrpParameterType::Custom)
+      .filter_map(|parameter| CustomParameter::decode(&parameter.param_value).ok())
+      .collect()
   }
 
   pub fn decode(
@@ -644,42 +1299,48 @@ impl LlrpResponse {
 
       LlrpMessageType::GetReaderCapabilitiesResponse => {
 
-        let parameters = parse_parameters(&mut buf)?;
+        let parameters = parse_parameters(&mut buf).within("GetReaderCapabilitiesResponse")?;
         let mut parsed_params: Vec<LlrpParameterData> = Vec::new();
 
         for param in parameters {
           match param.param_type {
 
             LlrpParameterType::LLRPStatus => {
-              let llrp_status = LLRPStatus::decode(&param.param_value)?;
+              let llrp_status = LLRPStatus::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
               info!("[VAL] GetReaderCapabilitiesResponse->LLRPStatus: {:?}", llrp_status);
               parsed_params.push(LlrpParameterData::LLRPStatus(llrp_status));
             }
 
             LlrpParameterType::GeneralDeviceCapabilities => {
-              let gdc = GeneralDeviceCapabilities::decode(&param.param_value)?;
+              let gdc = GeneralDeviceCapabilities::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
               info!("[VAL] GetReaderCapabilitiesResponse->GeneralDeviceCapabilities: {:?}", gdc);
               parsed_params.push(LlrpParameterData::GeneralDeviceCapabilities(gdc));
             }
 
             LlrpParameterType::LLRPCapabilities => {
-              let llrp_caps = LLRPCapabilities::decode(&param.param_value)?;
+              let llrp_caps = LLRPCapabilities::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
               info!("[VAL] GetReaderCapabilitiesResponse->LLRPCapabilities: {:?}", llrp_caps);
               parsed_params.push(LlrpParameterData::LLRPCapabilities(llrp_caps));
             }
 
             LlrpParameterType::RegulatoryCapabilities => {
-              let reg_caps = RegulatoryCapabilities::decode(&param.param_value)?;
+              let reg_caps = RegulatoryCapabilities::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
               info!("[VAL] GetReaderCapabilitiesResponse->RegulatoryCapabilities: {:?}", reg_caps);
               parsed_params.push(LlrpParameterData::RegulatoryCapabilities(reg_caps));
             }
 
             LlrpParameterType::C1G2LLRPCapabilities=> {
-              let c1g2_llrp_caps = C1G2LLRPCapabilities::decode(&param.param_value)?;
+              let c1g2_llrp_caps = C1G2LLRPCapabilities::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
               info!("[VAL] GetReaderCapabilitiesResponse->C1G2LLRPCapabilities: {:?}", c1g2_llrp_caps);
               parsed_params.push(LlrpParameterData::C1G2LLRPCapabilities(c1g2_llrp_caps));
             }
 
+            LlrpParameterType::Custom => {
+              let custom = CustomParameter::decode(&param.param_value).within("GetReaderCapabilitiesResponse")?;
+              info!("[VAL] GetReaderCapabilitiesResponse->Custom: {:?}", custom);
+              parsed_params.push(LlrpParameterData::Custom(custom));
+            }
+
             _ => {
               warn!("Unhandled GetReaderCapabilitiesResponse parameter: {:?}", param.param_type);
             }
@@ -691,48 +1352,78 @@ impl LlrpResponse {
 
       LlrpMessageType::GetReaderConfigResponse => {
 
-        let parameters = parse_parameters(&mut buf)?;
+        let parameters = parse_parameter_tree(&buf).within("GetReaderConfigResponse")?;
         let mut parsed_params: Vec<LlrpParameterData> = Vec::new();
 
         for param in parameters {
           match param.param_type {
 
             LlrpParameterType::LLRPStatus => {
-              let var = LLRPStatus::decode(&param.param_value)?;
+              let var = LLRPStatus::decode(&param.param_value).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->LLRPStatus: {:?}", var);
               parsed_params.push(LlrpParameterData::LLRPStatus(var));
             }
 
             LlrpParameterType::Identification => {
-              let var = Identification::decode(&param.param_value)?;
+              let var = Identification::decode(&param.param_value).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->Identification: {:?}", var);
               parsed_params.push(LlrpParameterData::Identification(var));
             }
 
             LlrpParameterType::AntennaProperties => {
-              let var = AntennaProperties::decode(&param.param_value)?;
+              let var = AntennaProperties::decode(&param.param_value).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->AntennaProperties: {:?}", var);
               parsed_params.push(LlrpParameterData::AntennaProperties(var));
             }
 
             LlrpParameterType::AntennaConfiguration => {
-              let var = AntennaConfiguration::decode(&param.param_value)?;
+              let var = AntennaConfiguration::decode(&param).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->AntennaConfiguration: {:?}", var);
               parsed_params.push(LlrpParameterData::AntennaConfiguration(var));
             }
 
             LlrpParameterType::ReaderEventNotificationSpec => {
-              let var = ReaderEventNotificationSpec::decode(&param.param_value)?;
+              let var = ReaderEventNotificationSpec::decode(&param).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->ReaderEventNotificationSpec: {:?}", var);
               parsed_params.push(LlrpParameterData::ReaderEventNotificationSpec(var));
             }
 
             LlrpParameterType::ROReportSpec => {
-              let var = ROReportSpec::decode(&param.param_value)?;
+              let var = ROReportSpec::decode(&param).within("GetReaderConfigResponse")?;
               info!("[VAL] GetReaderConfigResponse->ROReportSpec: {:?}", var);
               parsed_params.push(LlrpParameterData::ROReportSpec(var));
             }
 
+            LlrpParameterType::KeepAliveSpec => {
+              let var = KeepaliveSpec::decode(&param.param_value).within("GetReaderConfigResponse")?;
+              info!("[VAL] GetReaderConfigResponse->KeepaliveSpec: {:?}", var);
+              parsed_params.push(LlrpParameterData::KeepaliveSpec(var));
+            }
+
+            LlrpParameterType::LLRPConfigurationStateValue => {
+              let var = LLRPConfigurationStateValue::decode(&param.param_value).within("GetReaderConfigResponse")?;
+              info!("[VAL] GetReaderConfigResponse->LLRPConfigurationStateValue: {:?}", var);
+              parsed_params.push(LlrpParameterData::LLRPConfigurationStateValue(var));
+            }
+
+            LlrpParameterType::GPIPortCurrentState => {
+              let var = GPIPortCurrentState::decode(&param.param_value).within("GetReaderConfigResponse")?;
+              info!("[VAL] GetReaderConfigResponse->GPIPortCurrentState: {:?}", var);
+              parsed_params.push(LlrpParameterData::GPIPortCurrentState(var));
+            }
+
+            LlrpParameterType::GPOWriteData => {
+              let var = GPOWriteData::decode(&param.param_value).within("GetReaderConfigResponse")?;
+              info!("[VAL] GetReaderConfigResponse->GPOWriteData: {:?}", var);
+              parsed_params.push(LlrpParameterData::GPOWriteData(var));
+            }
+
+            LlrpParameterType::Custom => {
+              let var = CustomParameter::decode(&param.param_value).within("GetReaderConfigResponse")?;
+              info!("[VAL] GetReaderConfigResponse->Custom: {:?}", var);
+              parsed_params.push(LlrpParameterData::Custom(var));
+            }
+
             _ => {
               warn!("Unhandled GetReaderConfigResponse parameter: {:?}", param.param_type);
             }
@@ -742,16 +1433,45 @@ impl LlrpResponse {
         Ok(LlrpResponseData::ReaderConfig(parsed_params))
       }
 
+      LlrpMessageType::GetAccessSpecsResponse => {
+
+        let parameters = parse_parameters(&mut buf).within("GetAccessSpecsResponse")?;
+        let mut parsed_params: Vec<LlrpParameterData> = Vec::new();
+
+        for param in parameters {
+          match param.param_type {
+
+            LlrpParameterType::LLRPStatus => {
+              let var = LLRPStatus::decode(&param.param_value).within("GetAccessSpecsResponse")?;
+              info!("[VAL] GetAccessSpecsResponse->LLRPStatus: {:?}", var);
+              parsed_params.push(LlrpParameterData::LLRPStatus(var));
+            }
+
+            LlrpParameterType::AccessSpec => {
+              let var = AccessSpec::decode(&param.param_value).within("GetAccessSpecsResponse")?;
+              info!("[VAL] GetAccessSpecsResponse->AccessSpec: {:?}", var);
+              parsed_params.push(LlrpParameterData::AccessSpec(var));
+            }
+
+            _ => {
+              warn!("Unhandled GetAccessSpecsResponse parameter: {:?}", param.param_type);
+            }
+          }
+        }
+
+        Ok(LlrpResponseData::AccessSpecs(parsed_params))
+      }
+
       LlrpMessageType::ROAccessReport => {
 
         let mut tag_reports = Vec::new();
-        let parameters = parse_parameters(&mut buf)?;
+        let parameters = parse_parameters(&mut buf).within("ROAccessReport")?;
 
         for parameter in parameters {
           match parameter.param_type {
 
             LlrpParameterType::TagReportData => {
-              let tag_report_data = TagReportData::decode(&parameter.param_value)?;
+              let tag_report_data = TagReportData::decode(&parameter.param_value, self.received_at).within("ROAccessReport")?;
               tag_reports.push(tag_report_data);
             }
 
@@ -764,6 +1484,59 @@ impl LlrpResponse {
         Ok(LlrpResponseData::TagReport(tag_reports))
       }
 
+      LlrpMessageType::ReaderEventNotification => {
+
+        let parameters = parse_parameters(&mut buf).within("ReaderEventNotification")?;
+
+        let Some(parameter) = parameters.iter().find(|parameter| parameter.param_type == LlrpParameterType::ReaderEventNotificationData) else {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "ReaderEventNotification without ReaderEventNotificationData"
+          ));
+        };
+
+        let event_data = ReaderEventNotificationData::decode(&parameter.param_value).within("ReaderEventNotification")?;
+        info!("[VAL] ReaderEventNotification->ReaderEventNotificationData: {:?}", event_data);
+
+        Ok(LlrpResponseData::ReaderEvent(event_data))
+      }
+
+      LlrpMessageType::CustomMessage => {
+        Ok(LlrpResponseData::CustomMessage(CustomMessage::decode(&self.payload).within("CustomMessage")?))
+      }
+
+      LlrpMessageType::ErrorMessage => {
+
+        let parameters = parse_parameters(&mut buf).within("ErrorMessage")?;
+
+        let Some(parameter) = parameters.iter().find(|parameter| parameter.param_type == LlrpParameterType::LLRPStatus) else {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "ErrorMessage without LLRPStatus"
+          ));
+        };
+
+        let status = LLRPStatus::decode(&parameter.param_value).within("ErrorMessage")?;
+        warn!("[VAL] ErrorMessage->LLRPStatus: {}", status);
+
+        Ok(LlrpResponseData::Error(status))
+      }
+
+      LlrpMessageType::GetSupportedVersionResponse => {
+
+        if buf.remaining() < 2 {
+          return Err(ParameterDecodeError::too_short("GetSupportedVersionResponse", 2, buf.remaining()).into());
+        }
+
+        let supported_version = SupportedVersion {
+          current_version   : buf.get_u8(),
+          supported_version : buf.get_u8()
+        };
+        info!("[VAL] GetSupportedVersionResponse: {:?}", supported_version);
+
+        Ok(LlrpResponseData::SupportedVersion(supported_version))
+      }
+
       _ => {
         Err(io::Error::new(
           io::ErrorKind::InvalidData,
@@ -774,17 +1547,583 @@ impl LlrpResponse {
   }
 }
 
+/// Decodes a hex string into bytes. An odd number of digits leaves the low half of
+/// the last byte zero.
+pub fn decode_hex(
+  hex: &str
+) -> Option<Vec<u8>> {
+
+  let hex = format!("{:0<width$}", hex, width = hex.len().div_ceil(2) * 2);
+
+  (0..hex.len())
+    .step_by(2)
+    .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+    .collect()
+}
+
+fn encode_keepalive_spec(
+  buffer         : &mut BytesMut,
+  keepalive_spec : &KeepaliveSpecConfig
+) {
+  buffer.put_u16(LlrpParameterType::KeepAliveSpec.value());
+  buffer.put_u16(9); // Length (static)
+  buffer.put_u8(keepalive_spec.trigger.value());
+  buffer.put_u32(keepalive_spec.interval); // PeriodicTriggerValue (ms)
+}
+
+fn encode_gpo_write_data(
+  buffer : &mut BytesMut,
+  port   : GpoPort,
+  state  : PinState
+) {
+  buffer.put_u16(LlrpParameterType::GPOWriteData.value());
+  buffer.put_u16(7); // Length (static)
+  buffer.put_u16(port.0);
+  buffer.put_u8(if state.is_high() { 0x80 } else { 0 }); // GPOData (First bit)
+}
+
+fn encode_gpi_port_current_state(
+  buffer  : &mut BytesMut,
+  port    : GpiPort,
+  enabled : bool
+) {
+  buffer.put_u16(LlrpParameterType::GPIPortCurrentState.value());
+  buffer.put_u16(8); // Length (static)
+  buffer.put_u16(port.0);
+  buffer.put_u8(if enabled { 0x80 } else { 0 }); // Config (First bit)
+  buffer.put_u8(PinState::Unknown.value());      // State (Ignored by the reader)
+}
+
+/// Writes the length of the TLV parameter starting at `start`, which extends to
+/// the end of `buffer`.
+pub(crate) fn patch_parameter_length(
+  buffer : &mut BytesMut,
+  start  : usize
+) {
+  let length = (buffer.len() - start) as u16;
+  buffer[start + 2..start + 4].copy_from_slice(&length.to_be_bytes());
+}
+
+/// Encodes an AntennaConfiguration for all antennas carrying the C1G2InventoryCommand
+/// of an InventoryParameterSpec.
+pub(crate) fn encode_inventory_command(
+  buffer  : &mut BytesMut,
+  command : &C1G2InventoryCommandConfig
+) {
+
+  // AntennaConfiguration
+  let antenna_configuration_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::AntennaConfiguration.value());
+  buffer.put_u16(0); // Length (dynamic)
+  buffer.put_u16(0); // Antenna ID (0 - All)
+
+  // C1G2InventoryCommand
+  let inventory_command_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::C1G2InventoryCommand.value());
+  buffer.put_u16(0); // Length (dynamic)
+  buffer.put_u8(if command.tag_inventory_state_aware { 0x80 } else { 0 }); // TagInventoryStateAware (First bit is boolean value)
+
+  for filter in &command.filters {
+
+    let mask = match decode_hex(&filter.mask) {
+      Some(mask) => mask,
+      None => {
+        warn!("Ignoring C1G2Filter with invalid hex mask: {}", filter.mask);
+        continue;
+      }
+    };
+
+    let mask_bit_count = filter.mask_bit_count.unwrap_or((filter.mask.len() * 4) as u16);
+
+    // C1G2Filter
+    let filter_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::C1G2Filter.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u8(filter.truncate.value() << 6); // T (First two bits)
+
+    // C1G2TagInventoryMask
+    let mask_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::C1G2TagInventoryMask.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u8((filter.memory_bank & 0x03) << 6); // MB (First two bits)
+    buffer.put_u16(filter.pointer);
+    buffer.put_u16(mask_bit_count);
+    buffer.put_slice(&mask[..(mask_bit_count as usize).div_ceil(8).min(mask.len())]);
+    patch_parameter_length(buffer, mask_pos);
+
+    if command.tag_inventory_state_aware {
+
+      // C1G2TagInventoryStateAwareFilterAction
+      buffer.put_u16(LlrpParameterType::C1G2TagInventoryStateAwareFilterAction.value());
+      buffer.put_u16(6); // Length (static)
+      buffer.put_u8(filter.target);
+      buffer.put_u8(filter.action);
+
+    } else {
+
+      // C1G2TagInventoryStateUnawareFilterAction
+      buffer.put_u16(LlrpParameterType::C1G2TagInventoryStateUnawareFilterAction.value());
+      buffer.put_u16(5); // Length (static)
+      buffer.put_u8(filter.action);
+    }
+
+    patch_parameter_length(buffer, filter_pos);
+  }
+
+  if let Some(rf_control) = &command.rf_control {
+
+    // C1G2RFControl
+    buffer.put_u16(LlrpParameterType::C1G2RFControl.value());
+    buffer.put_u16(8); // Length (static)
+    buffer.put_u16(rf_control.mode_index);
+    buffer.put_u16(rf_control.tari);
+  }
+
+  if let Some(singulation) = &command.singulation {
+
+    // C1G2SingulationControl
+    let singulation_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::C1G2SingulationControl.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u8((singulation.session & 0x03) << 6); // Session (First two bits)
+    buffer.put_u16(singulation.tag_population);
+    buffer.put_u32(singulation.tag_transit_time);
+
+    if let Some(action) = singulation.state_aware_action.as_ref().filter(|_| command.tag_inventory_state_aware) {
+
+      // C1G2TagInventoryStateAwareSingulationAction
+      buffer.put_u16(LlrpParameterType::C1G2TagInventoryStateAwareSingulationAction.value());
+      buffer.put_u16(5); // Length (static)
+      buffer.put_u8(((action.inventoried_state & 0x01) << 7) | ((action.sl & 0x01) << 6)); // I, S
+    }
+
+    patch_parameter_length(buffer, singulation_pos);
+  }
+
+  patch_parameter_length(buffer, inventory_command_pos);
+  patch_parameter_length(buffer, antenna_configuration_pos);
+}
+
+/// The decoded content of a message from the reader. New message types are added
+/// as variants, so matches outside this crate need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum LlrpResponseData {
   TagReport(Vec<TagReportData>),
   ReaderCapabilities(Vec<LlrpParameterData>),
   ReaderConfig(Vec<LlrpParameterData>),
+  AccessSpecs(Vec<LlrpParameterData>),
+  ReaderEvent(ReaderEventNotificationData),
+  CustomMessage(CustomMessage),
+  SupportedVersion(SupportedVersion),
+  Error(LLRPStatus),
 }
 
+/// A parameter as split from its enclosing buffer.
+///
+/// Fields:
+/// - `param_type` / `param_type_value`: The parameter type, `Unknown` for values the client does not know.
+/// - `param_length`: Encoded length of the parameter, header included.
+/// - `param_value`: The parameter after its header, sub-parameters included.
+/// - `sub_params`: The sub-parameters, for parameters parsed by `parse_parameter_tree`
+///   whose type has any.
 #[derive(Debug)]
 pub struct LlrpParameter {
-  pub param_type   : LlrpParameterType,
-  pub param_length : u16,
-  pub param_value  : Vec<u8>,
-  pub sub_params   : Option<Vec<LlrpParameter>>
+  pub param_type       : LlrpParameterType,
+  pub param_type_value : u16,
+  pub param_length     : u16,
+  pub param_value      : Vec<u8>,
+  pub sub_params       : Option<Vec<LlrpParameter>>
+}
+
+impl LlrpParameter {
+
+  /// The fields of the parameter ahead of its sub-parameters; all of `param_value`
+  /// when the sub-parameters were not parsed.
+  pub fn fields(
+    &self
+  ) -> &[u8] {
+    let sub_params_length: usize = self.children().iter().map(|child| child.param_length as usize).sum();
+    &self.param_value[..self.param_value.len() - sub_params_length]
+  }
+
+  /// The parsed sub-parameters, in the order the reader sent them.
+  pub fn children(
+    &self
+  ) -> &[LlrpParameter] {
+    self.sub_params.as_deref().unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::config::C1G2RFControlConfig;
+  use crate::params::{get_tv_param_length, C1G2UHFRFModeTable, FieldError, ParameterDecodeError, ROReportSpec, RFReceiver, RFTransmitter};
+
+  #[test]
+  fn header_encodes_spec_examples() {
+
+    // GET_READER_CAPABILITIES, LLRP 1.0.1: Rsvd=0, Ver=1, Type=1
+    let mut buf = BytesMut::new();
+    LlrpHeader { version: 1, message_type_value: 1, message_length: 11, message_id: 0x0000_04D2 }.encode(&mut buf);
+    assert_eq!(&buf[..], &[0x04, 0x01, 0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x04, 0xD2]);
+
+    // KEEPALIVE_ACK, LLRP 1.0.1: Type=72
+    let mut buf = BytesMut::new();
+    LlrpHeader { version: 1, message_type_value: 72, message_length: 10, message_id: 1 }.encode(&mut buf);
+    assert_eq!(&buf[..2], &[0x04, 0x48]);
+
+    // ERROR_MESSAGE, LLRP 1.1: Ver=2, Type=100
+    let mut buf = BytesMut::new();
+    LlrpHeader { version: 2, message_type_value: 100, message_length: 10, message_id: 1 }.encode(&mut buf);
+    assert_eq!(&buf[..2], &[0x08, 0x64]);
+
+    // CUSTOM_MESSAGE uses the full 10-bit type field: Type=1023
+    let mut buf = BytesMut::new();
+    LlrpHeader { version: 1, message_type_value: 1023, message_length: 10, message_id: 1 }.encode(&mut buf);
+    assert_eq!(&buf[..2], &[0x07, 0xFF]);
+  }
+
+  #[test]
+  fn header_encode_never_sets_reserved_bits() {
+
+    for version in 0..=7u8 {
+      for message_type_value in [0u16, 1, 100, 1023] {
+        let mut buf = BytesMut::new();
+        LlrpHeader { version, message_type_value, message_length: 10, message_id: 0 }.encode(&mut buf);
+        assert_eq!(buf[0] & 0xE0, 0, "reserved bits set for version {} type {}", version, message_type_value);
+      }
+    }
+
+    // Out-of-range values are masked rather than spilling into neighbouring fields.
+    let mut buf = BytesMut::new();
+    LlrpHeader { version: 0xFF, message_type_value: 0xFFFF, message_length: 10, message_id: 0 }.encode(&mut buf);
+    assert_eq!(&buf[..2], &[0x1F, 0xFF]);
+  }
+
+  #[test]
+  fn header_decode_ignores_reserved_bits() {
+
+    let bytes = [0xE4, 0x3D, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x07];
+    let header = LlrpHeader::decode(&bytes).unwrap();
+
+    assert_eq!(header.version, 1);
+    assert_eq!(header.message_type_value, LlrpMessageType::ROAccessReport.value());
+    assert_eq!(header.message_length, 10);
+    assert_eq!(header.message_id, 7);
+  }
+
+  #[test]
+  fn header_decode_rejects_short_buffer_and_length() {
+
+    assert!(LlrpHeader::decode(&[0x04, 0x01, 0x00, 0x00]).is_err());
+    assert!(LlrpHeader::decode(&[0x04, 0x01, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x01]).is_err());
+  }
+
+  #[test]
+  fn header_roundtrips_every_message_type_and_version() {
+
+    for message_type in LlrpMessageType::iter() {
+      for version in [LlrpVersion::V1_0_1, LlrpVersion::V1_1] {
+
+        let header = LlrpHeader {
+          version            : version.value(),
+          message_type_value : message_type.value(),
+          message_length     : 1234,
+          message_id         : 0xDEAD_BEEF
+        };
+
+        let mut buf = BytesMut::new();
+        header.encode(&mut buf);
+
+        assert_eq!(buf.len(), LLRP_HEADER_LENGTH);
+        assert_eq!(LlrpHeader::decode(&buf).unwrap(), header);
+      }
+    }
+  }
+
+  #[test]
+  fn message_encodes_configured_version() {
+
+    let mut message = LlrpMessage::new(LlrpMessageType::Keepalive, 5, vec![]);
+    assert_eq!(&message.encode()[..2], &[0x04, 0x3E]);
+
+    message.version = LlrpVersion::V1_1.value();
+    let mut encoded = message.encode();
+    assert_eq!(&encoded[..2], &[0x08, 0x3E]);
+
+    let decoded = LlrpMessage::decode(&mut encoded).unwrap();
+    assert_eq!(decoded.version, LlrpVersion::V1_1.value());
+    assert_eq!(decoded.message_type, LlrpMessageType::Keepalive);
+    assert_eq!(decoded.message_id, 5);
+    assert!(encoded.is_empty());
+  }
+
+  #[test]
+  fn tag_report_data_decodes_optional_tv_fields() {
+
+    let payload = vec![
+      0x00, 0xF0, 0x00, 0x3C,                                                   // TagReportData
+      0x8D, 0xE2, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00, 0x01, // EPC-96
+      0x89, 0x00, 0x00, 0x00, 0x07,                                             // ROSpecID
+      0x8A, 0x00, 0x03,                                                         // InventoryParameterSpecID
+      0x81, 0x00, 0x02,                                                         // AntennaID
+      0x86, 0xC4,                                                               // PeakRSSI (-60)
+      0x87, 0x00, 0x05,                                                         // ChannelIndex
+      0x82, 0x00, 0x06, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E, 0x6F,                     // FirstSeenTimestampUTC
+      0x84, 0x00, 0x06, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E, 0x70,                     // LastSeenTimestampUTC
+      0x88, 0x00, 0x04,                                                         // TagSeenCount
+      0x8C, 0x30, 0x00,                                                         // C1G2PC
+      0x8B, 0x5A, 0x3C                                                          // C1G2CRC
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ROAccessReport, 7, payload);
+    let Ok(LlrpResponseData::TagReport(tag_reports)) = LlrpResponse::from_message(message).decode() else {
+      panic!("expected a tag report");
+    };
+
+    let tag = &tag_reports[0];
+    assert_eq!(tag.to_string(), "e200123456789abcdef00001");
+    assert_eq!(tag.rospec_id, Some(7));
+    assert_eq!(tag.inventory_parameter_spec_id, Some(3));
+    assert_eq!(tag.antenna_id, Some(2));
+    assert_eq!(tag.peak_rssi, Some(-60));
+    assert_eq!(tag.channel_index, Some(5));
+    assert_eq!(tag.first_seen_us, Some(0x0006_1A2B_3C4D_5E6F));
+    assert_eq!(tag.last_seen_us, Some(0x0006_1A2B_3C4D_5E70));
+    assert_eq!(tag.tag_seen_count, Some(4));
+    assert_eq!((tag.pc_bits, tag.crc), (Some(0x3000), Some(0x5A3C)));
+  }
+
+  #[test]
+  fn every_tv_parameter_type_has_a_length() {
+
+    for param_type_value in 1..=20u16 {
+      let param_type = LlrpParameterType::from_value(param_type_value).unwrap();
+      assert!(get_tv_param_length(param_type).is_some(), "no TV length for {:?}", param_type);
+    }
+
+    let payload = vec![
+      0x00, 0xF0, 0x00, 0x19,                                                   // TagReportData
+      0x8D, 0xE2, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00, 0x01, // EPC-96
+      0x92, 0x00, 0x03, 0x00, 0x01,                                             // C1G2SingulationDetails
+      0x81, 0x00, 0x04                                                          // AntennaID
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ROAccessReport, 7, payload);
+    let Ok(LlrpResponseData::TagReport(tag_reports)) = LlrpResponse::from_message(message).decode() else {
+      panic!("expected a tag report");
+    };
+
+    assert_eq!(tag_reports[0].antenna_id, Some(4));
+  }
+
+  #[test]
+  fn tag_report_data_decodes_op_spec_results() {
+
+    let payload = vec![
+      0x00, 0xF0, 0x00, 0x2C,                                                   // TagReportData
+      0x8D, 0xE2, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00, 0x01, // EPC-96
+      0x90, 0x00, 0x00, 0x00, 0x2A,                                             // AccessSpecID
+      0x01, 0x5D, 0x00, 0x0D, 0x00, 0x00, 0x01, 0x00, 0x02, 0xAB, 0xCD, 0x12, 0x34, // C1G2ReadOpSpecResult
+      0x01, 0x5E, 0x00, 0x09, 0x03, 0x00, 0x02, 0x00, 0x00                      // C1G2WriteOpSpecResult (insufficient power)
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ROAccessReport, 7, payload);
+    let Ok(LlrpResponseData::TagReport(tag_reports)) = LlrpResponse::from_message(message).decode() else {
+      panic!("expected a tag report");
+    };
+
+    let tag = &tag_reports[0];
+    assert_eq!(tag.access_spec_id, Some(42));
+
+    let read = &tag.read_results[0];
+    assert!(read.succeeded());
+    assert_eq!(read.op_spec_id, 1);
+    assert_eq!(read.words(), vec![0xABCD, 0x1234]);
+
+    let write = &tag.write_results[0];
+    assert!(!write.succeeded());
+    assert_eq!((write.op_spec_id, write.num_words_written), (2, 0));
+  }
+
+  #[test]
+  fn error_message_decodes_status_with_parameter_and_field_errors() {
+
+    let payload = vec![
+      0x01, 0x1F, 0x00, 0x1B, 0x00, 0x64, 0x00, 0x03, b'b', b'a', b'd', // LLRPStatus (M_ParameterError)
+      0x01, 0x21, 0x00, 0x10, 0x00, 0xB1, 0x00, 0xC9,                   // ParameterError (ROSpec, P_FieldError)
+      0x01, 0x20, 0x00, 0x08, 0x00, 0x01, 0x01, 0x2D                    // FieldError (Priority, A_OutOfRange)
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ErrorMessage, 9, payload.clone());
+    let Ok(LlrpResponseData::Error(status)) = LlrpResponse::from_message(message).decode() else {
+      panic!("expected an error");
+    };
+
+    assert!(!status.succeeded());
+    assert_eq!(status.error_description, "bad");
+    assert_eq!(status.field_error, None);
+
+    let parameter_error = status.parameter_error.as_ref().unwrap();
+    assert_eq!((parameter_error.parameter_type, parameter_error.error_code), (177, 201));
+    assert_eq!(parameter_error.field_error, Some(FieldError { field_num: 1, error_code: 301 }));
+
+    assert_eq!(
+      status.to_string(),
+      "M_ParameterError (100): bad; parameter ROSpec: P_FieldError (201), field 1: A_OutOfRange (301)"
+    );
+
+    let response = LlrpResponse::from_message(LlrpMessage::new(LlrpMessageType::AddROspecResponse, 10, payload));
+    assert_eq!(response.status(0), Some(status));
+    assert_eq!(response.status_code(0), Some(100));
+  }
+
+  #[test]
+  fn custom_parameters_keep_their_vendor_and_use_registered_decoders() {
+
+    crate::custom::register_custom_parameter(0xFFFF_0001, 5, "TestPhase", |data| {
+      Ok(serde_json::json!({ "phase": u16::from_be_bytes([data[0], data[1]]) }))
+    });
+
+    let payload = vec![
+      0x00, 0xF0, 0x00, 0x2C,                                                   // TagReportData
+      0x8D, 0xE2, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x00, 0x01, // EPC-96
+      0x03, 0xFF, 0x00, 0x0E, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x0B, 0xB8, // Custom (registered)
+      0x03, 0xFF, 0x00, 0x0D, 0xFF, 0xFF, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x7F        // Custom (unregistered)
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ROAccessReport, 7, payload);
+    let Ok(LlrpResponseData::TagReport(tag_reports)) = LlrpResponse::from_message(message).decode() else {
+      panic!("expected a tag report");
+    };
+
+    let custom = &tag_reports[0].custom;
+    assert_eq!((custom[0].vendor_id, custom[0].subtype), (0xFFFF_0001, 5));
+    assert_eq!(custom[0].name.as_deref(), Some("TestPhase"));
+    assert_eq!(custom[0].decoded, Some(serde_json::json!({ "phase": 3000 })));
+    assert_eq!((custom[1].vendor_id, custom[1].subtype, custom[1].data.clone()), (0xFFFF_0002, 1, vec![0x7F]));
+    assert_eq!(custom[1].decoded, None);
+  }
+
+  #[test]
+  fn unknown_parameter_types_are_not_taken_for_custom() {
+
+    let parameters = parse_parameters(&[0x02, 0xFE, 0x00, 0x05, 0x01]).unwrap();
+
+    assert_eq!(parameters[0].param_type, LlrpParameterType::Unknown);
+    assert_eq!(parameters[0].param_type_value, 766);
+  }
+
+  #[test]
+  fn decode_errors_carry_the_parameter_path() {
+
+    // TagReportData { EPCData declaring 96 bits but carrying 4 bytes }
+    let payload = vec![
+      0x00, 0xF0, 0x00, 0x0E,
+      0x00, 0xF1, 0x00, 0x0A, 0x00, 0x60, 0xE2, 0x00, 0x12, 0x34
+    ];
+
+    let message = LlrpMessage::new(LlrpMessageType::ROAccessReport, 7, payload);
+    let error = LlrpResponse::from_message(message).decode().unwrap_err();
+    let error = error.get_ref().and_then(|inner| inner.downcast_ref::<ParameterDecodeError>()).unwrap();
+
+    assert_eq!(error, &ParameterDecodeError::TooShort {
+      path      : "ROAccessReport/TagReportData/EPCData.EPC".to_string(),
+      needed    : 12,
+      available : 4
+    });
+  }
+
+  #[test]
+  fn set_reader_config_rf_settings_roundtrip_through_the_decoders() {
+
+    let config = ReaderConfig {
+      hop_table_id         : 3,
+      channel_index        : 7,
+      tx_power_table_index : 81,
+      rx_power_table_index : 12,
+      gpi_ports            : vec![],
+      gpo_outputs          : vec![],
+      keepalive_spec       : None
+    };
+
+    let message = LlrpMessage::new_set_antenna_configuration(1, 2, &config, false);
+    let parameters = parse_parameter_tree(&message.payload[1..]).unwrap();
+    assert_eq!(parameters.len(), 1);
+
+    let antenna_configuration = AntennaConfiguration::decode(&parameters[0]).unwrap();
+    let rf_receiver = antenna_configuration.rf_receiver.unwrap();
+    let rf_transmitter = antenna_configuration.rf_transmitter.unwrap();
+
+    assert_eq!(antenna_configuration.antenna_id, 2);
+    assert_eq!(rf_receiver.receiver_sensitivity, 12);
+    assert_eq!((rf_transmitter.hop_table_id, rf_transmitter.channel_index, rf_transmitter.transmit_power_value), (3, 7, 81));
+
+    let sub_parameters = parameters[0].children();
+    assert_eq!(parameters[0].fields(), &[0x00, 0x02]);
+    assert_eq!(RFReceiver::decode(&sub_parameters[0].param_value).unwrap().receiver_sensitivity, 12);
+    assert_eq!(RFTransmitter::decode(&sub_parameters[1].param_value).unwrap().transmit_power_value, 81);
+
+    assert!(RFReceiver::decode(&[0x00]).is_err());
+    assert!(RFTransmitter::decode(&[0x00, 0x01, 0x00, 0x02]).is_err());
+  }
+
+  #[test]
+  fn parameter_trees_nest_the_sub_parameters_of_known_layouts() {
+
+    let payload = [
+      0x00, 0xED, 0x00, 0x12, 0x01, 0x00, 0x05,       // ROReportSpec, upon N tags or end of AISpec
+      0x00, 0xEE, 0x00, 0x0B, 0x90, 0x00,             // TagReportContentSelector, ROSpecID and AntennaID
+      0x01, 0x5C, 0x00, 0x05, 0xC0,                   // C1G2EPCMemorySelector, CRC and PC bits
+      0x00, 0xD9, 0x00, 0x08, 0x00, 0x00, 0x00, 0x2A  // LLRPConfigurationStateValue
+    ];
+
+    let parameters = parse_parameter_tree(&payload).unwrap();
+    assert_eq!(parameters.len(), 2);
+    assert!(parameters[1].sub_params.is_none());
+
+    let selector = &parameters[0].children()[0];
+    assert_eq!(parameters[0].fields(), &[0x01, 0x00, 0x05]);
+    assert_eq!((selector.fields(), selector.children()[0].param_type), (&[0x90, 0x00][..], LlrpParameterType::C1G2EPCMemorySelector));
+
+    let ro_report_spec = ROReportSpec::decode(&parameters[0]).unwrap();
+    let selector = ro_report_spec.tag_report_content_selector.unwrap();
+    assert!(selector.enable_rospec_id && selector.enable_antenna_id && !selector.enable_peak_rssi);
+    assert!(selector.c1g2_epc_memory_selector.unwrap().enable_pc_bits);
+
+    let truncated = [0x00, 0xED, 0x00, 0x06, 0x01, 0x00];
+    assert_eq!(parse_parameter_tree(&truncated).unwrap_err().to_string(), ParameterDecodeError::too_short("ROReportSpec", 3, 2).to_string());
+  }
+
+  #[test]
+  fn rf_control_is_checked_against_the_decoded_mode_table() {
+
+    let entry = |mode_identifier: u32, min_tari: u32, max_tari: u32, tari_step: u32| {
+      let mut entry = BytesMut::new();
+      entry.put_u16(LlrpParameterType::C1G2UHFRFModeTableEntry.value());
+      entry.put_u16(32);
+      entry.put_u32(mode_identifier);
+      entry.put_slice(&[0x80, 2, 0, 2]); // DR, M, FLM, SMI
+      entry.put_u32(320_000);             // BDR
+      entry.put_u32(1500);                // PIE
+      entry.put_u32(min_tari);
+      entry.put_u32(max_tari);
+      entry.put_u32(tari_step);
+      entry.to_vec()
+    };
+
+    let table = C1G2UHFRFModeTable::decode(&[entry(0, 25000, 25000, 0), entry(1002, 6250, 25000, 6250)].concat()).unwrap();
+    assert_eq!(table.entries[1].tari_step, 6250);
+
+    let check = |mode_index: u16, tari: u16| table.check_rf_control(&C1G2RFControlConfig { mode_index, tari }).map(|mode| mode.mode_identifier);
+
+    assert_eq!(check(1002, 0), Ok(1002));
+    assert_eq!(check(1002, 12500), Ok(1002));
+    assert!(check(1002, 10000).unwrap_err().contains("step"));
+    assert!(check(0, 12500).is_err());
+    assert!(check(1, 0).unwrap_err().contains("0, 1002"));
+  }
 }
\ No newline at end of file
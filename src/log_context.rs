@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+tokio::task_local! {
+  static READER_ID: String;
+}
+
+/// Returns the identifier of the reader whose operation is currently being
+/// executed, if any.
+pub fn current_reader_id() -> Option<String> {
+  READER_ID.try_with(|reader_id| reader_id.clone()).ok()
+}
+
+/// Runs `future` with `reader_id` attached to every log line it emits.
+pub async fn scope<F>(
+  reader_id : String,
+  future    : F
+) -> F::Output
+where
+  F: Future
+{
+  READER_ID.scope(reader_id, future).await
+}
+
+/// Synchronous counterpart of [`scope`].
+pub fn sync_scope<F, R>(
+  reader_id : String,
+  f         : F
+) -> R
+where
+  F: FnOnce() -> R
+{
+  READER_ID.sync_scope(reader_id, f)
+}
+
+/// Log sink writing every record to the system log and, optionally, records
+/// emitted in a reader context to a separate `<reader_id>.log` file in
+/// `reader_log_dir`.
+pub struct ReaderLogWriter {
+  system_log     : File,
+  reader_log_dir : Option<PathBuf>,
+  reader_logs    : HashMap<String, File>
+}
+
+impl ReaderLogWriter {
+
+  pub fn new(
+    system_log     : File,
+    reader_log_dir : Option<PathBuf>
+  ) -> Self {
+    ReaderLogWriter {
+      system_log,
+      reader_log_dir,
+      reader_logs: HashMap::new()
+    }
+  }
+
+  fn reader_log(
+    &mut self,
+    reader_log_dir : PathBuf,
+    reader_id      : String
+  ) -> io::Result<&mut File> {
+
+    if !self.reader_logs.contains_key(&reader_id) {
+
+      let file_name: String = reader_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+      let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(reader_log_dir.join(format!("{}.log", file_name)))?;
+
+      self.reader_logs.insert(reader_id.clone(), file);
+    }
+
+    Ok(self.reader_logs.get_mut(&reader_id).unwrap())
+  }
+}
+
+impl Write for ReaderLogWriter {
+
+  fn write(
+    &mut self,
+    buf: &[u8]
+  ) -> io::Result<usize> {
+
+    self.system_log.write_all(buf)?;
+
+    if let Some(reader_log_dir) = self.reader_log_dir.clone() {
+      if let Some(reader_id) = current_reader_id() {
+        self.reader_log(reader_log_dir, reader_id)?.write_all(buf)?;
+      }
+    }
+
+    Ok(buf.len())
+  }
+
+  fn flush(
+    &mut self
+  ) -> io::Result<()> {
+
+    self.system_log.flush()?;
+
+    for file in self.reader_logs.values_mut() {
+      file.flush()?;
+    }
+
+    Ok(())
+  }
+}
\ No newline at end of file
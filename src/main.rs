@@ -1,10 +1,33 @@
+mod alerts;
+mod channel;
+mod clock;
 mod config;
+mod custom;
+mod duration;
+mod error;
+mod gpio;
 mod params;
+mod region;
+mod rospec;
+mod secrets;
 mod llrp;
+mod setup;
+mod stream;
+mod stats;
+mod tag_stream;
+#[cfg(feature = "tls")]
+mod tls;
 mod client;
+mod history;
+mod introspect;
+mod journal;
+mod liveness;
+#[cfg(feature = "impinj")]
+mod impinj;
+mod log_context;
 
 use std::env;
-use llrp::LlrpResponseData;
+use llrp::{LlrpResponseData, ReaderConfigRequest};
 use log::{info, debug, warn, error};
 use tokio::{self};
 
@@ -19,7 +42,7 @@ async fn main() {
   let get_reader_capabilities  = true;
   let get_reader_config        = true;
 
-  match LlrpClient::initialize(config_file.to_str().unwrap()).await {
+  match LlrpClient::initialize(&config_file).await {
     Ok(mut client) => {
 
       /*
@@ -45,7 +68,7 @@ async fn main() {
       */
 
       if get_reader_config {
-        if let Err(e) = client.send_get_reader_config(| response_data | async move {
+        if let Err(e) = client.send_get_reader_config(ReaderConfigRequest::default(), | response_data | async move {
 
         }).await {
           error!("GetReaderConfig error: {}", e);
@@ -53,20 +76,8 @@ async fn main() {
       }
 
       /*
-      if let Err(e) = client.send_enable_events_and_reports().await {
-        error!("EnableEventsAndReports error: {}", e);
-      }
-
-      if let Err(e) = client.send_add_rospec().await {
-        error!("AddROSpec error: {}", e);
-      }
-
-      if let Err(e) = client.send_enable_rospec().await {
-        error!("EnableROSpec error: {}", e);
-      }
-
-      if let Err(e) = client.send_start_rospec().await {
-        error!("StartROSpec error: {}", e);
+      if let Err(e) = client.start_inventory().await {
+        error!("StartInventory error: {}", e);
       }
 
       if let Err(e) = client.await_ro_access_report( | response_data | async move {
@@ -86,8 +97,8 @@ async fn main() {
         error!("Error while attempting to receive ROAccessReport: {}", e)
       }
 
-      if let Err(e) = client.send_stop_rospec().await {
-        error!("StopROSpec error: {}", e);
+      if let Err(e) = client.stop_inventory().await {
+        error!("StopInventory error: {}", e);
       }
       
       if let Err(e) = client.send_close_connection().await {
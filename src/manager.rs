@@ -0,0 +1,760 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::sync::Arc;
+use futures::stream::{self, StreamExt};
+use log::{info, warn};
+use serde::Serialize;
+use tokio::sync::{broadcast, Mutex, OwnedMutexGuard};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::chain::SpecChain;
+use crate::client::LlrpClient;
+use crate::config::{Config, SinkConfig};
+use crate::error::LlrpError;
+use crate::llrp::{LlrpResponse, LlrpResponseData};
+use crate::log_context;
+use crate::params::LlrpParameterData;
+use crate::schedule::{EnableScheduler, PowerScheduler};
+use crate::tuning::ReportTriggerTuner;
+use crate::sinks::{self, SinkError, SinkMetrics, SinkRegistry, TagEventBatch, TagEventSink};
+use crate::stats::{TelemetryStats, TelemetryTracker};
+
+/// Default number of readers contacted at the same time by fleet operations.
+pub const DEFAULT_FLEET_CONCURRENCY: usize = 16;
+
+/// Outcome of a fleet operation for a single reader.
+///
+/// Fields:
+/// - `reader_id`: Identifier of the reader the operation ran against.
+/// - `duration_ms`: Time spent on this reader, including waiting for its client.
+/// - `outcome`: The operation's value, or the error message it failed with.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReaderResult<T> {
+  pub reader_id   : String,
+  pub duration_ms : u64,
+  pub outcome     : Result<T, String>
+}
+
+/// Per-reader results of an operation run across the fleet, ordered by reader id.
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetReport<T> {
+  pub results : Vec<ReaderResult<T>>
+}
+
+impl<T> FleetReport<T> {
+
+  pub fn succeeded(
+    &self
+  ) -> impl Iterator<Item = (&str, &T)> {
+    self.results.iter().filter_map(|result| match &result.outcome {
+      Ok(value) => Some((result.reader_id.as_str(), value)),
+      Err(_) => None
+    })
+  }
+
+  pub fn failed(
+    &self
+  ) -> impl Iterator<Item = (&str, &str)> {
+    self.results.iter().filter_map(|result| match &result.outcome {
+      Ok(_) => None,
+      Err(e) => Some((result.reader_id.as_str(), e.as_str()))
+    })
+  }
+
+  pub fn failure_count(
+    &self
+  ) -> usize {
+    self.failed().count()
+  }
+}
+
+/// Owns the clients of a fleet of readers, keyed by reader id.
+///
+/// Each client sits behind its own async mutex so that operations on different
+/// readers can run concurrently while operations on the same reader are serialized.
+/// Tag reports of every managed reader are published to the registered sinks.
+/// Readers can be put in named groups, e.g. `"dock-doors"`, to run operations on
+/// a part of the fleet; a reader joins the groups listed in its configuration.
+#[derive(Default)]
+pub struct ReaderManager {
+  readers           : BTreeMap<String, Arc<Mutex<LlrpClient>>>,
+  groups            : BTreeMap<String, BTreeSet<String>>,
+  dispatchers       : BTreeMap<String, JoinHandle<()>>,
+  schedulers        : BTreeMap<String, PowerScheduler>,
+  enable_schedulers : BTreeMap<String, EnableScheduler>,
+  tuners            : BTreeMap<String, ReportTriggerTuner>,
+  chains            : BTreeMap<String, SpecChain>,
+  telemetry         : BTreeMap<String, TelemetryTracker>,
+  sinks             : SinkRegistry
+}
+
+impl ReaderManager {
+
+  pub fn new() -> Self {
+    ReaderManager::default()
+  }
+
+  /// Adds an already connected client, replacing any client registered under the
+  /// same reader id as `remove_client` would. Returns the reader id.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub fn add_client(
+    &mut self,
+    client: LlrpClient
+  ) -> String {
+
+    let reader_id = client.config().reader_id();
+    let groups = client.config().groups.clone();
+
+    // The tasks driving a replaced client, and its groups, go with it.
+    if self.remove_client(&reader_id).is_some() {
+      warn!("Replaced existing client for reader {}", reader_id);
+    }
+
+    let dispatcher = spawn_sink_dispatcher(reader_id.clone(), client.subscribe_ro_reports(), self.sinks.clone());
+    self.telemetry.insert(reader_id.clone(), client.telemetry_tracker());
+    self.readers.insert(reader_id.clone(), Arc::new(Mutex::new(client)));
+    self.dispatchers.insert(reader_id.clone(), dispatcher);
+
+    for group in groups {
+      self.add_to_group(&group, &reader_id);
+    }
+
+    reader_id
+  }
+
+  pub fn remove_client(
+    &mut self,
+    reader_id: &str
+  ) -> Option<Arc<Mutex<LlrpClient>>> {
+
+    if let Some(dispatcher) = self.dispatchers.remove(reader_id) {
+      dispatcher.abort();
+    }
+
+    self.stop_power_schedules(reader_id);
+    self.stop_enable_schedule(reader_id);
+    self.stop_report_tuning(reader_id);
+
+    if let Some(chain) = self.chains.remove(reader_id) {
+      chain.stop();
+    }
+
+    self.telemetry.remove(reader_id);
+
+    self.groups.retain(|_, members| {
+      members.remove(reader_id);
+      !members.is_empty()
+    });
+
+    self.readers.remove(reader_id)
+  }
+
+  /// Adds a managed reader to `group`, creating the group if needed. Returns false
+  /// if the reader is not managed.
+  pub fn add_to_group(
+    &mut self,
+    group     : &str,
+    reader_id : &str
+  ) -> bool {
+
+    if !self.readers.contains_key(reader_id) {
+      return false;
+    }
+
+    self.groups.entry(group.to_string()).or_default().insert(reader_id.to_string());
+
+    true
+  }
+
+  /// Removes a reader from `group`, dropping the group once it is empty. Returns
+  /// false if the reader was not in the group.
+  pub fn remove_from_group(
+    &mut self,
+    group     : &str,
+    reader_id : &str
+  ) -> bool {
+
+    let Some(members) = self.groups.get_mut(group) else {
+      return false;
+    };
+
+    let removed = members.remove(reader_id);
+    if members.is_empty() {
+      self.groups.remove(group);
+    }
+
+    removed
+  }
+
+  pub fn group_names(
+    &self
+  ) -> Vec<String> {
+    self.groups.keys().cloned().collect()
+  }
+
+  /// Reader ids in `group`, empty if there is no such group.
+  pub fn group_members(
+    &self,
+    group: &str
+  ) -> Vec<String> {
+    self.groups.get(group).map(|members| members.iter().cloned().collect()).unwrap_or_default()
+  }
+
+  /// Starts the power schedules declared in the reader's configuration, replacing
+  /// any already running for it. Returns false if the reader is not managed.
+  pub async fn start_power_schedules(
+    &mut self,
+    reader_id: &str
+  ) -> bool {
+
+    let Some(client) = self.client(reader_id) else {
+      return false;
+    };
+
+    self.stop_power_schedules(reader_id);
+    self.schedulers.insert(reader_id.to_string(), PowerScheduler::start(client).await);
+
+    true
+  }
+
+  pub fn stop_power_schedules(
+    &mut self,
+    reader_id: &str
+  ) {
+    if let Some(scheduler) = self.schedulers.remove(reader_id) {
+      scheduler.stop();
+    }
+  }
+
+  /// Starts following the time-of-day windows of the reader's `enable_schedule`,
+  /// replacing any schedule already running for it. Returns false if the reader is
+  /// not managed or has no enable schedule.
+  pub async fn start_enable_schedule(
+    &mut self,
+    reader_id: &str
+  ) -> bool {
+
+    let Some(client) = self.client(reader_id) else {
+      return false;
+    };
+
+    self.stop_enable_schedule(reader_id);
+
+    match EnableScheduler::start(client).await {
+      Some(scheduler) => {
+        self.enable_schedulers.insert(reader_id.to_string(), scheduler);
+        true
+      }
+      None => false
+    }
+  }
+
+  pub fn stop_enable_schedule(
+    &mut self,
+    reader_id: &str
+  ) {
+    if let Some(scheduler) = self.enable_schedulers.remove(reader_id) {
+      scheduler.stop();
+    }
+  }
+
+  /// Starts adjusting the reader's report trigger N as configured by its
+  /// `report_tuning`, replacing any tuner already running for it. Returns false if
+  /// the reader is not managed or has no valid tuning configuration.
+  pub async fn start_report_tuning(
+    &mut self,
+    reader_id: &str
+  ) -> bool {
+
+    let Some(client) = self.client(reader_id) else {
+      return false;
+    };
+
+    self.stop_report_tuning(reader_id);
+
+    match ReportTriggerTuner::start(client).await {
+      Some(tuner) => {
+        self.tuners.insert(reader_id.to_string(), tuner);
+        true
+      }
+      None => false
+    }
+  }
+
+  pub fn stop_report_tuning(
+    &mut self,
+    reader_id: &str
+  ) {
+    if let Some(tuner) = self.tuners.remove(reader_id) {
+      tuner.stop();
+    }
+  }
+
+  /// Starts running the ROSpecs of the reader's `spec_chain` back to back,
+  /// aborting any chain already running for it. Returns false if the reader is
+  /// not managed or has no valid chain configuration.
+  pub async fn start_spec_chain(
+    &mut self,
+    reader_id: &str
+  ) -> bool {
+
+    let Some(client) = self.client(reader_id) else {
+      return false;
+    };
+
+    self.abort_spec_chain(reader_id).await;
+
+    match SpecChain::start(client).await {
+      Some(chain) => {
+        self.chains.insert(reader_id.to_string(), chain);
+        true
+      }
+      None => false
+    }
+  }
+
+  /// Ends the reader's spec chain and stops the ROSpec it is running.
+  pub async fn abort_spec_chain(
+    &mut self,
+    reader_id: &str
+  ) {
+    if let Some(chain) = self.chains.remove(reader_id) {
+      chain.abort().await;
+    }
+  }
+
+  /// Changes whether the reader's spec chain starts over after its last ROSpec.
+  /// Returns false if no chain is running for the reader.
+  pub fn set_spec_chain_repeat(
+    &self,
+    reader_id : &str,
+    repeat    : bool
+  ) -> bool {
+    match self.chains.get(reader_id) {
+      Some(chain) if !chain.is_finished() => {
+        chain.set_repeat(repeat);
+        true
+      }
+      _ => false
+    }
+  }
+
+  /// Registers a sink that receives the tag events of every managed reader.
+  pub fn register_sink(
+    &self,
+    sink: Arc<dyn TagEventSink>
+  ) {
+    info!("Registered tag event sink {}", sink.name());
+    self.sinks.register(sink);
+  }
+
+  /// Builds and registers the sinks declared in a configuration. Sinks whose name
+  /// is already registered are skipped, so readers sharing a sink declaration
+  /// publish to a single instance.
+  pub fn register_configured_sinks(
+    &self,
+    sink_configs: &[SinkConfig]
+  ) -> Result<(), SinkError> {
+
+    for sink_config in sink_configs {
+      if !self.sinks.contains(sink_config.name()) {
+        self.register_sink(sinks::build_sink(sink_config)?);
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn unregister_sink(
+    &self,
+    name: &str
+  ) -> bool {
+    self.sinks.unregister(name)
+  }
+
+  /// Returns the delivery metrics of every registered sink.
+  pub fn sink_metrics(
+    &self
+  ) -> Vec<SinkMetrics> {
+    self.sinks.metrics()
+  }
+
+  /// Returns the latest temperature and utilization of every managed reader, for
+  /// exporting to fleet monitoring. Does not wait for busy clients.
+  pub fn telemetry_stats(
+    &self
+  ) -> BTreeMap<String, TelemetryStats> {
+    self.telemetry.iter()
+      .map(|(reader_id, telemetry)| (reader_id.clone(), telemetry.snapshot()))
+      .collect()
+  }
+
+  pub fn client(
+    &self,
+    reader_id: &str
+  ) -> Option<Arc<Mutex<LlrpClient>>> {
+    self.readers.get(reader_id).cloned()
+  }
+
+  pub fn reader_ids(
+    &self
+  ) -> Vec<String> {
+    self.readers.keys().cloned().collect()
+  }
+
+  pub fn len(
+    &self
+  ) -> usize {
+    self.readers.len()
+  }
+
+  pub fn is_empty(
+    &self
+  ) -> bool {
+    self.readers.is_empty()
+  }
+
+  /// Connects to every reader in `configs`, at most `max_concurrency` at a time.
+  /// Readers that connect are added to the manager and the sinks their configuration
+  /// declares are registered; the report lists every reader, including the ones that
+  /// could not be reached.
+  pub async fn connect_all(
+    &mut self,
+    configs         : Vec<Config>,
+    max_concurrency : usize
+  ) -> FleetReport<()> {
+
+    let attempts: Vec<(String, u64, Result<LlrpClient, String>)> = stream::iter(configs)
+      .map(|config| async move {
+        let reader_id = config.reader_id();
+        let start_time = Instant::now();
+        let result = LlrpClient::initialize_with_config(config).await.map_err(|e| e.to_string());
+        (reader_id, start_time.elapsed().as_millis() as u64, result)
+      })
+      .buffer_unordered(max_concurrency.max(1))
+      .collect()
+      .await;
+
+    let mut results = Vec::with_capacity(attempts.len());
+
+    for (reader_id, duration_ms, result) in attempts {
+
+      let outcome = result.and_then(|client| {
+        let sink_result = self.register_configured_sinks(&client.config().sinks).map_err(|e| e.to_string());
+        self.add_client(client);
+        sink_result
+      });
+
+      results.push(ReaderResult { reader_id, duration_ms, outcome });
+    }
+
+    results.sort_by(|a, b| a.reader_id.cmp(&b.reader_id));
+
+    FleetReport { results }
+  }
+
+  /// Runs `operation` against every managed reader, at most `max_concurrency` at a
+  /// time, and collects each reader's result. A failing reader does not affect the
+  /// others.
+  pub async fn for_each<T, F, Fut>(
+    &self,
+    max_concurrency : usize,
+    operation       : F
+  ) -> FleetReport<T>
+  where
+    F   : Fn(OwnedMutexGuard<LlrpClient>) -> Fut,
+    Fut : Future<Output = Result<T, LlrpError>>
+  {
+    let report = self.run_on(self.readers.iter(), max_concurrency, operation).await;
+    info!(
+      "Fleet operation completed on {} readers ({} failed)",
+      report.results.len(),
+      report.failure_count()
+    );
+    report
+  }
+
+  /// Runs `operation` against every reader in `group` like `for_each`, so one
+  /// failing reader is reported without stopping the rest. Returns `None` if there
+  /// is no such group.
+  pub async fn for_each_in_group<T, F, Fut>(
+    &self,
+    group           : &str,
+    max_concurrency : usize,
+    operation       : F
+  ) -> Option<FleetReport<T>>
+  where
+    F   : Fn(OwnedMutexGuard<LlrpClient>) -> Fut,
+    Fut : Future<Output = Result<T, LlrpError>>
+  {
+
+    let members = self.groups.get(group)?;
+    let readers = self.readers.iter().filter(|(reader_id, _)| members.contains(*reader_id));
+
+    let report = self.run_on(readers, max_concurrency, operation).await;
+    info!(
+      "Operation on group {} completed on {} readers ({} failed)",
+      group,
+      report.results.len(),
+      report.failure_count()
+    );
+
+    Some(report)
+  }
+
+  /// Starts the configured ROSpec on every reader in `group`. Returns `None` if
+  /// there is no such group.
+  pub async fn start_inventory_on_group(
+    &self,
+    group           : &str,
+    max_concurrency : usize
+  ) -> Option<FleetReport<()>> {
+    self.for_each_in_group(group, max_concurrency, |mut client| async move {
+      client.start_inventory().await
+    }).await
+  }
+
+  /// Stops the configured ROSpec on every reader in `group`. Returns `None` if
+  /// there is no such group.
+  pub async fn stop_inventory_on_group(
+    &self,
+    group           : &str,
+    max_concurrency : usize
+  ) -> Option<FleetReport<()>> {
+    self.for_each_in_group(group, max_concurrency, |mut client| async move {
+      client.stop_inventory().await
+    }).await
+  }
+
+  async fn run_on<'a, T, F, Fut>(
+    &self,
+    readers         : impl Iterator<Item = (&'a String, &'a Arc<Mutex<LlrpClient>>)>,
+    max_concurrency : usize,
+    operation       : F
+  ) -> FleetReport<T>
+  where
+    F   : Fn(OwnedMutexGuard<LlrpClient>) -> Fut,
+    Fut : Future<Output = Result<T, LlrpError>>
+  {
+
+    let operation = &operation;
+
+    let mut results: Vec<ReaderResult<T>> = stream::iter(readers)
+      .map(|(reader_id, client)| async move {
+        let start_time = Instant::now();
+        let outcome = operation(client.clone().lock_owned().await).await.map_err(|e| e.to_string());
+        ReaderResult {
+          reader_id   : reader_id.clone(),
+          duration_ms : start_time.elapsed().as_millis() as u64,
+          outcome
+        }
+      })
+      .buffer_unordered(max_concurrency.max(1))
+      .collect()
+      .await;
+
+    results.sort_by(|a, b| a.reader_id.cmp(&b.reader_id));
+
+    FleetReport { results }
+  }
+
+  /// Fetches the reader capabilities of every managed reader.
+  pub async fn fetch_capabilities(
+    &self,
+    max_concurrency: usize
+  ) -> FleetReport<Vec<LlrpParameterData>> {
+    self.for_each(max_concurrency, |mut client| async move {
+      let mut capabilities = Vec::new();
+      client.send_get_reader_capabilities(|response_data| {
+        if let LlrpResponseData::ReaderCapabilities(parameters) = response_data {
+          capabilities = parameters;
+        }
+        async {}
+      }).await?;
+      Ok(capabilities)
+    }).await
+  }
+
+  /// Fetches the firmware version reported in GeneralDeviceCapabilities by every
+  /// managed reader.
+  pub async fn fetch_firmware_versions(
+    &self,
+    max_concurrency: usize
+  ) -> FleetReport<String> {
+
+    let capabilities = self.fetch_capabilities(max_concurrency).await;
+
+    let results = capabilities.results.into_iter().map(|result| ReaderResult {
+      reader_id   : result.reader_id,
+      duration_ms : result.duration_ms,
+      outcome     : result.outcome.and_then(|parameters| {
+        parameters.into_iter()
+          .find_map(|parameter| match parameter {
+            LlrpParameterData::GeneralDeviceCapabilities(gdc) => Some(gdc.reader_firmware_version),
+            _ => None
+          })
+          .ok_or_else(|| "GeneralDeviceCapabilities missing from response".to_string())
+      })
+    }).collect();
+
+    FleetReport { results }
+  }
+
+  /// Shuts down the session with every managed reader and removes them from the manager.
+  pub async fn close_all(
+    &mut self,
+    max_concurrency: usize
+  ) -> FleetReport<()> {
+
+    let report = self.for_each(max_concurrency, |mut client| async move {
+      client.shutdown().await
+    }).await;
+
+    for dispatcher in self.dispatchers.values() {
+      dispatcher.abort();
+    }
+
+    for scheduler in std::mem::take(&mut self.schedulers).into_values() {
+      scheduler.stop();
+    }
+
+    for scheduler in std::mem::take(&mut self.enable_schedulers).into_values() {
+      scheduler.stop();
+    }
+
+    for tuner in std::mem::take(&mut self.tuners).into_values() {
+      tuner.stop();
+    }
+
+    for chain in std::mem::take(&mut self.chains).into_values() {
+      chain.stop();
+    }
+
+    self.dispatchers.clear();
+    self.groups.clear();
+    self.telemetry.clear();
+    self.readers.clear();
+
+    report
+  }
+}
+
+/// Decodes the reader's ROAccessReports and publishes them to the sinks.
+fn spawn_sink_dispatcher(
+  reader_id        : String,
+  mut ro_report_rx : broadcast::Receiver<LlrpResponse>,
+  sinks            : SinkRegistry
+) -> JoinHandle<()> {
+
+  tokio::spawn(log_context::scope(reader_id.clone(), async move {
+    loop {
+      match ro_report_rx.recv().await {
+
+        Ok(response) => {
+          match response.decode() {
+            Ok(LlrpResponseData::TagReport(tag_reports)) => {
+              sinks.publish(&TagEventBatch::from_tag_reports(&reader_id, &tag_reports)).await;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to decode ROAccessReport for sinks: {}", e)
+          }
+        }
+
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+          warn!("Sink dispatch skipped {} ROAccessReports due to buffer overflow", skipped);
+        }
+
+        Err(broadcast::error::RecvError::Closed) => break
+      }
+    }
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::scripted_reader::{test_config, ScriptedReader};
+
+  /// Connects a client to a scripted reader that only greets it, registered as
+  /// `reader_id` in `groups`.
+  async fn grouped_client(
+    reader_id : &str,
+    groups    : &[&str]
+  ) -> LlrpClient {
+
+    let reader = ScriptedReader::start(|_| async { Ok(()) }).await;
+
+    let mut config = test_config(&reader.host, 1000);
+    config.reader_id = Some(reader_id.to_string());
+    config.groups = groups.iter().map(|group| group.to_string()).collect();
+
+    let client = LlrpClient::initialize_with_config(config).await.unwrap();
+    reader.finish().await;
+    client
+  }
+
+  #[tokio::test]
+  async fn added_clients_join_their_configured_groups() {
+
+    let mut manager = ReaderManager::new();
+    manager.add_client(grouped_client("dock-1", &["docks", "north"]).await);
+    manager.add_client(grouped_client("dock-2", &["docks"]).await);
+
+    assert_eq!(manager.group_names(), vec!["docks", "north"]);
+    assert_eq!(manager.group_members("docks"), vec!["dock-1", "dock-2"]);
+    assert_eq!(manager.group_members("north"), vec!["dock-1"]);
+    assert!(!manager.add_to_group("docks", "dock-3"));
+  }
+
+  #[tokio::test]
+  async fn replaced_clients_leave_the_groups_of_their_predecessor() {
+
+    let mut manager = ReaderManager::new();
+    manager.add_client(grouped_client("dock-1", &["docks", "north"]).await);
+    manager.add_client(grouped_client("dock-1", &["south"]).await);
+
+    assert_eq!(manager.len(), 1);
+    assert_eq!(manager.group_names(), vec!["south"]);
+    assert_eq!(manager.group_members("south"), vec!["dock-1"]);
+    assert!(manager.group_members("docks").is_empty());
+  }
+
+  #[tokio::test]
+  async fn removed_clients_leave_their_groups_and_empty_groups_are_dropped() {
+
+    let mut manager = ReaderManager::new();
+    manager.add_client(grouped_client("dock-1", &["docks", "north"]).await);
+    manager.add_client(grouped_client("dock-2", &["docks"]).await);
+
+    assert!(manager.remove_client("dock-1").is_some());
+
+    assert_eq!(manager.group_names(), vec!["docks"]);
+    assert_eq!(manager.group_members("docks"), vec!["dock-2"]);
+    assert!(manager.remove_client("dock-1").is_none());
+  }
+
+  #[tokio::test]
+  async fn group_operations_run_on_members_only_and_skip_unknown_groups() {
+
+    let mut manager = ReaderManager::new();
+    manager.add_client(grouped_client("dock-1", &["docks"]).await);
+    manager.add_client(grouped_client("gate-1", &["gates"]).await);
+
+    let report = manager.for_each_in_group("docks", 2, |client| async move {
+      Ok(client.config().reader_id())
+    }).await.unwrap();
+
+    let succeeded: Vec<(&str, &String)> = report.succeeded().collect();
+    assert_eq!(succeeded, vec![("dock-1", &"dock-1".to_string())]);
+    assert_eq!(report.failure_count(), 0);
+
+    let unknown = manager.for_each_in_group("yard", 2, |client| async move {
+      Ok(client.config().reader_id())
+    }).await;
+
+    assert!(unknown.is_none());
+  }
+}
\ No newline at end of file
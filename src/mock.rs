@@ -0,0 +1,618 @@
+use bytes::{BufMut, BytesMut};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::time::{Duration, Instant};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::interval;
+use log::{info, debug, warn};
+
+use crate::llrp::{LlrpHeader, LlrpMessage, LlrpMessageType, LlrpParameterType, LLRP_HEADER_LENGTH};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MockReaderConfig {
+  pub listen_address  : String,
+  pub report_interval : u64,
+  pub tag_populations : Vec<TagPopulation>,
+  #[serde(default)]
+  pub scripted_events : Vec<ScriptedEvent>,
+  /// Time in milliseconds after which a started ROSpec ends on its own, as with a
+  /// duration stop trigger. Without it ROSpecs run until stopped.
+  #[serde(default)]
+  pub rospec_duration : Option<u64>,
+  /// RegulatoryCapabilities CommunicationsStandard to report. FCC (1) readers
+  /// report a hop table, other regions four fixed ETSI channels.
+  #[serde(default)]
+  pub communications_standard : Option<u16>,
+  /// Number of connections refused as if another client held the reader, before
+  /// connections are accepted.
+  #[serde(default)]
+  pub refused_connections : u32
+}
+
+/// A contiguous range of simulated EPC-96 tags.
+///
+/// Fields:
+/// - `epc_start`: First EPC of the range as a 24-digit hex string.
+/// - `count`: Number of tags in the range (EPCs are assigned sequentially).
+/// - `read_rate`: Average reads per second for each tag.
+/// - `rssi_min` / `rssi_max`: Bounds of the uniform PeakRSSI distribution (dBm).
+/// - `antennas`: Antenna IDs the tags may be read on.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagPopulation {
+  pub epc_start : String,
+  pub count     : u32,
+  pub read_rate : f64,
+  pub rssi_min  : i8,
+  pub rssi_max  : i8,
+  pub antennas  : Vec<u16>
+}
+
+/// An event injected at a fixed offset (ms) from the start of the session.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScriptedEvent {
+  pub at_ms : u64,
+  pub event : ScriptedEventKind
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScriptedEventKind {
+  AntennaDisconnect { antenna_id: u16 },
+  AntennaConnect    { antenna_id: u16 }
+}
+
+pub fn load_mock_config(file_path: &str) -> Result<MockReaderConfig, Box<dyn Error>> {
+
+  let config_data = fs::read_to_string(file_path)?;
+  let config: MockReaderConfig = serde_json::from_str(&config_data)?;
+
+  Ok(config)
+}
+
+/// Minimal LLRP reader emulator.
+///
+/// Acknowledges every request with a successful LLRPStatus and, while an
+/// ROSpec is started, emits ROAccessReports generated from the configured
+/// tag populations. Scripted events and ROSpec starts and ends are delivered as
+/// ReaderEventNotifications.
+pub struct MockReader {
+  config : MockReaderConfig,
+  rng    : XorShift
+}
+
+struct Session {
+  started_at             : Instant,
+  running_rospec         : Option<(u32, Instant)>,
+  pending_events         : Vec<LlrpMessage>,
+  disconnected_antennas  : HashSet<u16>,
+  next_event             : usize,
+  read_credit            : Vec<f64>,
+  message_id             : u32,
+  access_specs           : Vec<Vec<u8>>
+}
+
+impl MockReader {
+
+  pub fn new(
+    config: MockReaderConfig
+  ) -> Self {
+    MockReader {
+      config,
+      rng: XorShift::from_time()
+    }
+  }
+
+  pub async fn run(
+    &mut self
+  ) -> io::Result<()> {
+
+    let listener = TcpListener::bind(&self.config.listen_address).await?;
+    info!("Mock reader listening on {}", self.config.listen_address);
+
+    let mut refused = 0;
+
+    loop {
+      let (mut stream, peer) = listener.accept().await?;
+
+      if refused < self.config.refused_connections {
+        refused += 1;
+        info!("Mock reader refusing connection from {} ({} of {})", peer, refused, self.config.refused_connections);
+        stream.write_all(&connection_attempt_event(1, 2).encode()).await?; // Failed_A_Client_Initiated_Connection_Already_Exists
+        continue;
+      }
+
+      info!("Mock reader accepted connection from {}", peer);
+
+      if let Err(e) = self.serve(stream).await {
+        warn!("Mock reader session ended: {}", e);
+      }
+    }
+  }
+
+  async fn serve(
+    &mut self,
+    mut stream: TcpStream
+  ) -> io::Result<()> {
+
+    let mut session = Session {
+      started_at            : Instant::now(),
+      running_rospec        : None,
+      pending_events        : Vec::new(),
+      disconnected_antennas : HashSet::new(),
+      next_event            : 0,
+      read_credit           : vec![0.0; self.config.tag_populations.len()],
+      message_id            : 1,
+      access_specs          : Vec::new()
+    };
+
+    let mut buf = BytesMut::with_capacity(1024);
+    let mut ticker = interval(Duration::from_millis(self.config.report_interval.max(1)));
+
+    stream.write_all(&connection_attempt_event(session.next_message_id(), 0).encode()).await?;
+
+    loop {
+      tokio::select! {
+
+        read = stream.read_buf(&mut buf) => {
+
+          if read? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Client closed connection"));
+          }
+
+          while let Some(message) = take_message(&mut buf)? {
+            let close = message.message_type == LlrpMessageType::CloseConnection;
+            let response = self.handle_request(&mut session, message);
+
+            if let Some(response) = response {
+              stream.write_all(&response.encode()).await?;
+            }
+
+            if close {
+              info!("Mock reader closing connection on client request");
+              return Ok(());
+            }
+          }
+        }
+
+        _ = ticker.tick() => {
+
+          for event in self.due_events(&mut session) {
+            stream.write_all(&event.encode()).await?;
+          }
+
+          if let Some(duration) = self.config.rospec_duration {
+            if let Some((rospec_id, _)) = session.running_rospec.filter(|(_, started_at)| started_at.elapsed().as_millis() as u64 >= duration) {
+              session.running_rospec = None;
+              let message_id = session.next_message_id();
+              stream.write_all(&rospec_event(message_id, 1, rospec_id).encode()).await?;
+            }
+          }
+
+          if session.running_rospec.is_some() {
+            if let Some(report) = self.generate_report(&mut session) {
+              stream.write_all(&report.encode()).await?;
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn handle_request(
+    &mut self,
+    session : &mut Session,
+    request : LlrpMessage
+  ) -> Option<LlrpMessage> {
+
+    debug!("Mock reader received {:?} (ID {})", request.message_type, request.message_id);
+
+    let response_type = match request.message_type {
+      LlrpMessageType::GetReaderCapabilities  => LlrpMessageType::GetReaderCapabilitiesResponse,
+      LlrpMessageType::GetReaderConfig        => LlrpMessageType::GetReaderConfigResponse,
+      LlrpMessageType::SetReaderConfig        => LlrpMessageType::SetReaderConfigResponse,
+      LlrpMessageType::CloseConnection        => LlrpMessageType::CloseConnectionResponse,
+      LlrpMessageType::AddROSpec              => LlrpMessageType::AddROspecResponse,
+      LlrpMessageType::DeleteROSpec           => LlrpMessageType::DeleteROSpecResponse,
+      LlrpMessageType::EnableROSpec           => LlrpMessageType::EnableROSpecResponse,
+      LlrpMessageType::GetROSpecs             => LlrpMessageType::GetROSpecsResponse,
+      LlrpMessageType::GetAccessSpecs         => LlrpMessageType::GetAccessSpecsResponse,
+
+      // The AccessSpec parameter is kept as sent, so GetAccessSpecs echoes it back.
+      LlrpMessageType::AddAccessSpec => {
+        if request.payload.len() >= 12 {
+          session.access_specs.push(request.payload.clone());
+        }
+        LlrpMessageType::AddAccessSpecResponse
+      }
+
+      LlrpMessageType::DeleteAccessSpec => {
+        let access_spec_id = request_spec_id(&request);
+        session.access_specs.retain(|spec| access_spec_id != 0 && spec[4..8] != access_spec_id.to_be_bytes());
+        LlrpMessageType::DeleteAccessSpecResponse
+      }
+
+      LlrpMessageType::EnableAccessSpec => {
+        let access_spec_id = request_spec_id(&request);
+        for spec in session.access_specs.iter_mut().filter(|spec| access_spec_id == 0 || spec[4..8] == access_spec_id.to_be_bytes()) {
+          spec[11] |= 0x80; // CurrentState (Active)
+        }
+        LlrpMessageType::EnableAccessSpecResponse
+      }
+
+      LlrpMessageType::StartROSpec => {
+        let rospec_id = request_spec_id(&request);
+        session.running_rospec = Some((rospec_id, Instant::now()));
+        let message_id = session.next_message_id();
+        session.pending_events.push(rospec_event(message_id, 0, rospec_id));
+        LlrpMessageType::StartROSpecResponse
+      }
+
+      LlrpMessageType::StopROSpec => {
+        if let Some((rospec_id, _)) = session.running_rospec.take() {
+          let message_id = session.next_message_id();
+          session.pending_events.push(rospec_event(message_id, 1, rospec_id));
+        }
+        LlrpMessageType::StopROSpecResponse
+      }
+
+      LlrpMessageType::DisableROSpec => {
+        let rospec_id = request_spec_id(&request);
+        if let Some((running_id, _)) = session.running_rospec.filter(|(running_id, _)| rospec_id == 0 || *running_id == rospec_id) {
+          session.running_rospec = None;
+          let message_id = session.next_message_id();
+          session.pending_events.push(rospec_event(message_id, 1, running_id));
+        }
+        LlrpMessageType::DisableROSpecResponse
+      }
+
+      LlrpMessageType::GetReport => {
+        return self.generate_report(session);
+      }
+
+      LlrpMessageType::Keepalive => {
+        return Some(LlrpMessage::new(LlrpMessageType::KeepaliveAck, request.message_id, vec![]));
+      }
+
+      _ => return None
+    };
+
+    let mut payload = BytesMut::new();
+    put_llrp_status_success(&mut payload);
+
+    if response_type == LlrpMessageType::GetReaderCapabilitiesResponse {
+      put_general_device_capabilities(&mut payload, self.max_antenna_id());
+      if let Some(communications_standard) = self.config.communications_standard {
+        put_regulatory_capabilities(&mut payload, communications_standard);
+      }
+    }
+
+    if response_type == LlrpMessageType::GetAccessSpecsResponse {
+      for spec in &session.access_specs {
+        payload.extend_from_slice(spec);
+      }
+    }
+
+    Some(LlrpMessage::new(response_type, request.message_id, payload.to_vec()))
+  }
+
+  fn due_events(
+    &mut self,
+    session: &mut Session
+  ) -> Vec<LlrpMessage> {
+
+    let elapsed_ms = session.started_at.elapsed().as_millis() as u64;
+    let mut notifications = std::mem::take(&mut session.pending_events);
+
+    while let Some(scripted) = self.config.scripted_events.get(session.next_event) {
+
+      if scripted.at_ms > elapsed_ms {
+        break;
+      }
+
+      let (event_type, antenna_id) = match scripted.event {
+        ScriptedEventKind::AntennaDisconnect { antenna_id } => {
+          session.disconnected_antennas.insert(antenna_id);
+          (0, antenna_id)
+        }
+        ScriptedEventKind::AntennaConnect { antenna_id } => {
+          session.disconnected_antennas.remove(&antenna_id);
+          (1, antenna_id)
+        }
+      };
+
+      info!("Mock reader scripted event at {} ms: {:?}", scripted.at_ms, scripted.event);
+
+      let mut payload = BytesMut::new();
+      put_tlv(&mut payload, LlrpParameterType::ReaderEventNotificationData, |buf| {
+        put_utc_timestamp(buf);
+        put_tlv(buf, LlrpParameterType::AntennaEvent, |buf| {
+          buf.put_u8(event_type);
+          buf.put_u16(antenna_id);
+        });
+      });
+
+      notifications.push(LlrpMessage::new(
+        LlrpMessageType::ReaderEventNotification,
+        session.next_message_id(),
+        payload.to_vec()
+      ));
+
+      session.next_event += 1;
+    }
+
+    notifications
+  }
+
+  /// Highest antenna ID used by any tag population, reported as the number of
+  /// supported antennas.
+  fn max_antenna_id(
+    &self
+  ) -> u16 {
+    self.config.tag_populations.iter()
+      .flat_map(|population| population.antennas.iter().copied())
+      .max()
+      .unwrap_or(1)
+  }
+
+  fn generate_report(
+    &mut self,
+    session: &mut Session
+  ) -> Option<LlrpMessage> {
+
+    let interval_secs = self.config.report_interval as f64 / 1000.0;
+    let mut payload = BytesMut::new();
+    let mut tag_count = 0;
+
+    for (index, population) in self.config.tag_populations.iter().enumerate() {
+
+      let antennas: Vec<u16> = population.antennas.iter()
+        .copied()
+        .filter(|antenna_id| !session.disconnected_antennas.contains(antenna_id))
+        .collect();
+
+      if antennas.is_empty() {
+        continue;
+      }
+
+      let epc_start = match u128::from_str_radix(&population.epc_start, 16) {
+        Ok(epc_start) => epc_start,
+        Err(e) => {
+          warn!("Invalid EPC range start {}: {}", population.epc_start, e);
+          continue;
+        }
+      };
+
+      session.read_credit[index] += population.read_rate * population.count as f64 * interval_secs;
+      let reads = session.read_credit[index].floor() as u64;
+      session.read_credit[index] -= reads as f64;
+
+      for _ in 0..reads {
+
+        let epc = epc_start.wrapping_add(self.rng.below(population.count.max(1) as u64) as u128);
+        let antenna_id = antennas[self.rng.below(antennas.len() as u64) as usize];
+        let rssi = self.rng.between(population.rssi_min as i64, population.rssi_max as i64) as i8;
+        let seen_at = Utc::now().timestamp_micros() as u64;
+
+        put_tlv(&mut payload, LlrpParameterType::TagReportData, |buf| {
+          buf.put_u8(0x80 | LlrpParameterType::EPC96.value() as u8);
+          buf.put_slice(&epc.to_be_bytes()[4..]);
+          buf.put_u8(0x80 | LlrpParameterType::AntennaID.value() as u8);
+          buf.put_u16(antenna_id);
+          buf.put_u8(0x80 | LlrpParameterType::PeakRSSI.value() as u8);
+          buf.put_i8(rssi);
+          buf.put_u8(0x80 | LlrpParameterType::FirstSeenTimestampUTC.value() as u8);
+          buf.put_u64(seen_at);
+          buf.put_u8(0x80 | LlrpParameterType::LastSeenTimestampUTC.value() as u8);
+          buf.put_u64(seen_at);
+          buf.put_u8(0x80 | LlrpParameterType::TagSeenCount.value() as u8);
+          buf.put_u16(1);
+        });
+
+        tag_count += 1;
+      }
+    }
+
+    if tag_count == 0 {
+      return None;
+    }
+
+    debug!("Mock reader generated ROAccessReport with {} tag reads", tag_count);
+
+    Some(LlrpMessage::new(LlrpMessageType::ROAccessReport, session.next_message_id(), payload.to_vec()))
+  }
+}
+
+impl Session {
+
+  fn next_message_id(
+    &mut self
+  ) -> u32 {
+
+    let current_id = self.message_id;
+    self.message_id += 1;
+
+    current_id
+  }
+}
+
+fn connection_attempt_event(
+  message_id : u32,
+  status     : u16
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  put_tlv(&mut payload, LlrpParameterType::ReaderEventNotificationData, |buf| {
+    put_utc_timestamp(buf);
+    put_tlv(buf, LlrpParameterType::ConnectionAttemptEvent, |buf| {
+      buf.put_u16(status);
+    });
+  });
+
+  LlrpMessage::new(LlrpMessageType::ReaderEventNotification, message_id, payload.to_vec())
+}
+
+/// An ROSpecEvent notification; `event_type` 0 is Start_of_ROSpec, 1 is End_of_ROSpec.
+fn rospec_event(
+  message_id : u32,
+  event_type : u8,
+  rospec_id  : u32
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  put_tlv(&mut payload, LlrpParameterType::ReaderEventNotificationData, |buf| {
+    put_utc_timestamp(buf);
+    put_tlv(buf, LlrpParameterType::ROSpecEvent, |buf| {
+      buf.put_u8(event_type);
+      buf.put_u32(rospec_id);
+      buf.put_u32(0); // PreemptingROSpecID
+    });
+  });
+
+  LlrpMessage::new(LlrpMessageType::ReaderEventNotification, message_id, payload.to_vec())
+}
+
+fn take_message(
+  buf: &mut BytesMut
+) -> io::Result<Option<LlrpMessage>> {
+
+  if buf.len() < LLRP_HEADER_LENGTH {
+    return Ok(None);
+  }
+
+  let header = LlrpHeader::decode(buf)?;
+
+  if buf.len() < header.message_length as usize {
+    return Ok(None);
+  }
+
+  LlrpMessage::decode(buf).map(Some)
+}
+
+fn put_tlv<F>(
+  buffer     : &mut BytesMut,
+  param_type : LlrpParameterType,
+  encode     : F
+)
+where
+  F: FnOnce(&mut BytesMut)
+{
+
+  let initial_length_pos = buffer.len();
+
+  buffer.put_u16(param_type.value());
+  buffer.put_u16(0); // Length (dynamic)
+
+  encode(buffer);
+
+  let actual_length = (buffer.len() - initial_length_pos) as u16;
+  buffer[initial_length_pos + 2..initial_length_pos + 4].copy_from_slice(&actual_length.to_be_bytes());
+}
+
+/// The ROSpecID or AccessSpecID leading the payload of a request addressing a
+/// single spec (0 - All).
+fn request_spec_id(
+  request: &LlrpMessage
+) -> u32 {
+  request.payload.get(..4).map_or(0, |id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+}
+
+fn put_llrp_status_success(
+  buffer: &mut BytesMut
+) {
+  put_tlv(buffer, LlrpParameterType::LLRPStatus, |buf| {
+    buf.put_u16(0); // StatusCode (M_Success)
+    buf.put_u16(0); // ErrorDescription length
+  });
+}
+
+fn put_general_device_capabilities(
+  buffer       : &mut BytesMut,
+  max_antennas : u16
+) {
+  put_tlv(buffer, LlrpParameterType::GeneralDeviceCapabilities, |buf| {
+    buf.put_u16(max_antennas);
+    buf.put_u16(0); // CanSetAntennaProperties, HasUTCClockCapability
+    buf.put_u32(0); // DeviceManufacturerName
+    buf.put_u32(0); // ModelName
+    let firmware = format!("mock-{}", env!("CARGO_PKG_VERSION"));
+    buf.put_u16(firmware.len() as u16);
+    buf.put_slice(firmware.as_bytes());
+  });
+}
+
+fn put_regulatory_capabilities(
+  buffer                  : &mut BytesMut,
+  communications_standard : u16
+) {
+  put_tlv(buffer, LlrpParameterType::RegulatoryCapabilities, |buf| {
+    buf.put_u16(0); // CountryCode
+    buf.put_u16(communications_standard);
+    put_tlv(buf, LlrpParameterType::UHFBandCapabilities, |buf| {
+      put_tlv(buf, LlrpParameterType::FrequencyInformation, |buf| {
+        if communications_standard == 1 {
+          buf.put_u8(0x80); // Hopping
+          put_tlv(buf, LlrpParameterType::FrequencyHopTable, |buf| {
+            buf.put_u8(1);  // HopTableID
+            buf.put_u8(0);  // Reserved
+            buf.put_u16(50);
+            (0..50).for_each(|channel| buf.put_u32(902_750 + channel * 500));
+          });
+        } else {
+          buf.put_u8(0);
+          put_tlv(buf, LlrpParameterType::FixedFrequencyTable, |buf| {
+            buf.put_u16(4);
+            [865_700, 866_300, 866_900, 867_500].into_iter().for_each(|frequency| buf.put_u32(frequency));
+          });
+        }
+      });
+    });
+  });
+}
+
+fn put_utc_timestamp(
+  buffer: &mut BytesMut
+) {
+  put_tlv(buffer, LlrpParameterType::UTCTimeStamp, |buf| {
+    buf.put_u64(Utc::now().timestamp_micros() as u64);
+  });
+}
+
+/// Small xorshift PRNG, sufficient for generating simulated traffic.
+struct XorShift(u64);
+
+impl XorShift {
+
+  fn from_time() -> Self {
+    let seed = Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64;
+    XorShift(seed | 1)
+  }
+
+  fn next(
+    &mut self
+  ) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  fn below(
+    &mut self,
+    bound: u64
+  ) -> u64 {
+    self.next() % bound.max(1)
+  }
+
+  fn between(
+    &mut self,
+    min : i64,
+    max : i64
+  ) -> i64 {
+    let (low, high) = if min <= max { (min, max) } else { (max, min) };
+    low + self.below((high - low + 1) as u64) as i64
+  }
+}
\ No newline at end of file
@@ -0,0 +1,40 @@
+#![allow(dead_code)]
+
+mod config;
+mod custom;
+mod duration;
+mod gpio;
+mod params;
+mod region;
+mod rospec;
+mod secrets;
+mod llrp;
+mod mock;
+
+use std::env;
+use log::error;
+
+use mock::{load_mock_config, MockReader};
+
+#[tokio::main]
+async fn main() {
+
+  env_logger::Builder::from_default_env()
+    .filter(None, log::LevelFilter::Info)
+    .init();
+
+  let config_path = env::args().nth(1).unwrap_or_else(|| "mock_reader.json".to_string());
+
+  let config = match load_mock_config(&config_path) {
+    Ok(config) => config,
+    Err(e) => {
+      error!("Failed to load mock reader configuration {}: {}", config_path, e);
+      std::process::exit(1);
+    }
+  };
+
+  if let Err(e) = MockReader::new(config).run().await {
+    error!("Mock reader error: {}", e);
+    std::process::exit(1);
+  }
+}
\ No newline at end of file
@@ -1,10 +1,125 @@
-use std::{fmt, io::{self, Error, ErrorKind}};
+use std::{error, fmt, io};
 use bytes::{Buf, BytesMut};
 use log::{debug, warn};
+use serde::Serialize;
+
+use crate::config::{C1G2InventoryCommandConfig, C1G2RFControlConfig, ReaderConfig};
+use crate::custom::CustomParameter;
+use crate::gpio::{GpiPort, GpoPort, PinState};
+use crate::llrp::{LlrpParameter, LlrpParameterType, ReceiveTimestamp};
+
+/// Why a parameter failed to decode.
+///
+/// Every variant carries the `path` of the offending parameter: the names of the
+/// parameters enclosing it, outermost first, separated by `/`, with the field that
+/// failed after a `.` (e.g. `ROAccessReport/TagReportData/EPCData.EPC`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParameterDecodeError {
+  /// The parameter or field needs more bytes than the buffer holds.
+  TooShort    { path: String, needed: usize, available: usize },
+  /// A parameter length that is below its header size, overruns the enclosing
+  /// buffer, or does not match the parameter's fixed size.
+  BadLength   { path: String, length: usize },
+  /// A TV parameter whose type, and so whose length, is unknown.
+  UnknownType { path: String, param_type: u16 },
+  /// A length or count field whose value overflows when converted to bytes.
+  Overflow    { path: String },
+  /// A string field that is not valid UTF-8.
+  Utf8        { path: String }
+}
+
+impl ParameterDecodeError {
+
+  pub fn too_short(
+    path      : impl Into<String>,
+    needed    : usize,
+    available : usize
+  ) -> Self {
+    ParameterDecodeError::TooShort { path: path.into(), needed, available }
+  }
+
+  pub fn path(
+    &self
+  ) -> &str {
+    match self {
+      ParameterDecodeError::TooShort    { path, .. }
+      | ParameterDecodeError::BadLength   { path, .. }
+      | ParameterDecodeError::UnknownType { path, .. }
+      | ParameterDecodeError::Overflow    { path }
+      | ParameterDecodeError::Utf8        { path } => path
+    }
+  }
+
+  /// Prefixes the path with the parameter (or message) the error occurred in.
+  pub fn within(
+    mut self,
+    parent: &str
+  ) -> Self {
+
+    let path = match &mut self {
+      ParameterDecodeError::TooShort    { path, .. }
+      | ParameterDecodeError::BadLength   { path, .. }
+      | ParameterDecodeError::UnknownType { path, .. }
+      | ParameterDecodeError::Overflow    { path }
+      | ParameterDecodeError::Utf8        { path } => path
+    };
+
+    *path = if path.is_empty() || path.starts_with('.') { format!("{}{}", parent, path) } else { format!("{}/{}", parent, path) };
+    self
+  }
+}
+
+impl fmt::Display for ParameterDecodeError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    match self {
+      ParameterDecodeError::TooShort { path, needed, available } =>
+        write!(f, "{}: too short, needs {} bytes but {} remain", path, needed, available),
+      ParameterDecodeError::BadLength { path, length } =>
+        write!(f, "{}: bad length {}", path, length),
+      ParameterDecodeError::UnknownType { path, param_type } =>
+        write!(f, "{}: unknown TV parameter type {}", path, param_type),
+      ParameterDecodeError::Overflow { path } =>
+        write!(f, "{}: length overflows", path),
+      ParameterDecodeError::Utf8 { path } =>
+        write!(f, "{}: invalid UTF-8", path)
+    }
+  }
+}
 
-use crate::llrp::{LlrpParameter, LlrpParameterType};
+impl error::Error for ParameterDecodeError {}
+
+impl From<ParameterDecodeError> for io::Error {
+  fn from(
+    e: ParameterDecodeError
+  ) -> Self {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+  }
+}
+
+/// Adds the enclosing parameter to the path of a failed decode, so `?` can
+/// propagate a child's error as `decode(..).within("Parent")?`.
+pub trait DecodeContext<T> {
+  fn within(
+    self,
+    parent: &str
+  ) -> Result<T, ParameterDecodeError>;
+}
+
+impl<T> DecodeContext<T> for Result<T, ParameterDecodeError> {
+  fn within(
+    self,
+    parent: &str
+  ) -> Result<T, ParameterDecodeError> {
+    self.map_err(|e| e.within(parent))
+  }
+}
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum LlrpParameterData {
   LLRPStatus                  (LLRPStatus),
   GeneralDeviceCapabilities   (GeneralDeviceCapabilities),
@@ -16,11 +131,62 @@ pub enum LlrpParameterData {
   AntennaConfiguration        (AntennaConfiguration),
   ReaderEventNotificationSpec (ReaderEventNotificationSpec),
   ROReportSpec                (ROReportSpec),
+  AccessSpec                  (AccessSpec),
+  GPIPortCurrentState         (GPIPortCurrentState),
+  GPOWriteData                (GPOWriteData),
+  KeepaliveSpec               (KeepaliveSpec),
+  LLRPConfigurationStateValue (LLRPConfigurationStateValue),
+  Custom                      (CustomParameter),
 }
 
+/// A tag observation from an ROAccessReport.
+///
+/// Fields:
+/// - `epc`: The tag EPC.
+/// - `received_at`: When the client received the report carrying this tag.
+/// - `antenna_id`: Antenna that last saw the tag.
+/// - `peak_rssi`: Highest RSSI of the tag's reads, in dBm.
+/// - `tag_seen_count`: Number of times the tag was read since the last report.
+/// - `first_seen_us` / `last_seen_us`: UTC time the tag was first and last read, in
+///   microseconds since the Unix epoch.
+/// - `channel_index`: 1-based index of the channel the tag was last read on.
+/// - `pc_bits` / `crc`: The tag's PC bits and CRC, when the C1G2EPCMemorySelector enables them.
+/// - `xpc_w1` / `xpc_w2`: The tag's XPC words, when enabled and the tag has them.
+/// - `rospec_id`: ROSpec whose inventory read the tag.
+/// - `inventory_parameter_spec_id`: InventoryParameterSpec whose inventory read the tag.
+/// - `access_spec_id`: AccessSpec whose operations produced the `*_results` below.
+/// - `read_results`: Results of the AccessSpec C1G2Read operations performed on the tag.
+/// - `write_results`: Results of the AccessSpec C1G2Write operations performed on the tag.
+/// - `block_erase_results`: Results of the AccessSpec C1G2BlockErase operations performed on the tag.
+/// - `block_write_results`: Results of the AccessSpec C1G2BlockWrite operations performed on the tag.
+/// - `kill_results`: Results of the AccessSpec C1G2Kill operations performed on the tag.
+/// - `lock_results`: Results of the AccessSpec C1G2Lock operations performed on the tag.
+/// - `custom`: Vendor Custom parameters reported with the tag.
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct TagReportData {
-  pub epc: Vec<u8>
+  pub epc                         : Vec<u8>,
+  pub received_at                 : ReceiveTimestamp,
+  pub antenna_id                  : Option<u16>,
+  pub peak_rssi                   : Option<i8>,
+  pub tag_seen_count              : Option<u16>,
+  pub first_seen_us               : Option<u64>,
+  pub last_seen_us                : Option<u64>,
+  pub channel_index               : Option<u16>,
+  pub pc_bits                     : Option<u16>,
+  pub crc                         : Option<u16>,
+  pub xpc_w1                      : Option<u16>,
+  pub xpc_w2                      : Option<u16>,
+  pub rospec_id                   : Option<u32>,
+  pub inventory_parameter_spec_id : Option<u16>,
+  pub access_spec_id              : Option<u32>,
+  pub read_results                : Vec<C1G2ReadOpSpecResult>,
+  pub write_results               : Vec<C1G2WriteOpSpecResult>,
+  pub block_erase_results         : Vec<C1G2BlockEraseOpSpecResult>,
+  pub block_write_results         : Vec<C1G2WriteOpSpecResult>,
+  pub kill_results                : Vec<C1G2KillOpSpecResult>,
+  pub lock_results                : Vec<C1G2LockOpSpecResult>,
+  pub custom                      : Vec<CustomParameter>
 }
 
 impl fmt::Display for TagReportData {
@@ -41,34 +207,671 @@ impl fmt::Display for TagReportData {
 impl TagReportData {
   
   pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
+    buf         : &[u8],
+    received_at : ReceiveTimestamp
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
     let mut epc = Vec::new();
-
-    let parameters = parse_parameters(&mut buf)?;
+    let mut antenna_id = None;
+    let mut peak_rssi = None;
+    let mut tag_seen_count = None;
+    let mut first_seen_us = None;
+    let mut last_seen_us = None;
+    let mut channel_index = None;
+    let mut pc_bits = None;
+    let mut crc = None;
+    let mut xpc_w1 = None;
+    let mut xpc_w2 = None;
+    let mut rospec_id = None;
+    let mut inventory_parameter_spec_id = None;
+    let mut access_spec_id = None;
+    let mut read_results = Vec::new();
+    let mut write_results = Vec::new();
+    let mut block_erase_results = Vec::new();
+    let mut block_write_results = Vec::new();
+    let mut kill_results = Vec::new();
+    let mut lock_results = Vec::new();
+    let mut custom = Vec::new();
+
+    let parameters = parse_parameters(&mut buf).within("TagReportData")?;
 
     for parameter in parameters {
       match parameter.param_type {
 
         LlrpParameterType::EPCData => {
-          let epc_data = EPCData::decode(&parameter.param_value)?;
+          let epc_data = EPCData::decode(&parameter.param_value).within("TagReportData")?;
           epc = epc_data.epc;
         }
 
         LlrpParameterType::EPC96 => {
-          let epc_data = EPCData::decode_epc96(&parameter.param_value)?;
+          let epc_data = EPCData::decode_epc96(&parameter.param_value).within("TagReportData")?;
           epc = epc_data.epc;
         }
 
+        LlrpParameterType::AntennaID => {
+          antenna_id = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::PeakRSSI => {
+          peak_rssi = Some(parameter.param_value[0] as i8);
+        }
+
+        LlrpParameterType::TagSeenCount => {
+          tag_seen_count = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::FirstSeenTimestampUTC => {
+          first_seen_us = Some(BytesMut::from(&parameter.param_value[..]).get_u64());
+        }
+
+        LlrpParameterType::LastSeenTimestampUTC => {
+          last_seen_us = Some(BytesMut::from(&parameter.param_value[..]).get_u64());
+        }
+
+        LlrpParameterType::ChannelIndex => {
+          channel_index = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::C1G2PC => {
+          pc_bits = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::C1G2CRC => {
+          crc = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::C1G2XPCW1 => {
+          xpc_w1 = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::C1G2XPCW2 => {
+          xpc_w2 = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::ROSpecID => {
+          rospec_id = Some(BytesMut::from(&parameter.param_value[..]).get_u32());
+        }
+
+        LlrpParameterType::InventoryParameterSpecID => {
+          inventory_parameter_spec_id = Some(u16::from_be_bytes([parameter.param_value[0], parameter.param_value[1]]));
+        }
+
+        LlrpParameterType::AccessSpecID => {
+          access_spec_id = Some(BytesMut::from(&parameter.param_value[..]).get_u32());
+        }
+
+        // Report content this client does not use.
+        LlrpParameterType::SpecIndex
+        | LlrpParameterType::FirstSeenTimestampUptime
+        | LlrpParameterType::LastSeenTimestampUptime => {}
+
+        LlrpParameterType::C1G2ReadOpSpecResult => {
+          read_results.push(C1G2ReadOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        LlrpParameterType::C1G2WriteOpSpecResult => {
+          write_results.push(C1G2WriteOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        LlrpParameterType::C1G2BlockEraseOpSpecResult => {
+          block_erase_results.push(C1G2BlockEraseOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        // Same fields as C1G2WriteOpSpecResult
+        LlrpParameterType::C1G2BlockWriteOpSpecResult => {
+          block_write_results.push(C1G2WriteOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        LlrpParameterType::C1G2KillOpSpecResult => {
+          kill_results.push(C1G2KillOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        LlrpParameterType::C1G2LockOpSpecResult => {
+          lock_results.push(C1G2LockOpSpecResult::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
+        LlrpParameterType::Custom => {
+          custom.push(CustomParameter::decode(&parameter.param_value).within("TagReportData")?);
+        }
+
         _ => {
           warn!("Unhandled sub-parameter type: {:?}", parameter.param_type);
         }
       }
     }
 
-    Ok(TagReportData { epc })
+    Ok(TagReportData {
+      epc,
+      received_at,
+      antenna_id,
+      peak_rssi,
+      tag_seen_count,
+      first_seen_us,
+      last_seen_us,
+      channel_index,
+      pc_bits,
+      crc,
+      xpc_w1,
+      xpc_w2,
+      rospec_id,
+      inventory_parameter_spec_id,
+      access_spec_id,
+      read_results,
+      write_results,
+      block_erase_results,
+      block_write_results,
+      kill_results,
+      lock_results,
+      custom
+    })
+  }
+}
+
+/// The outcome of a C1G2Read operation.
+///
+/// Fields:
+/// - `result`: 0 on success, otherwise the LLRP C1G2Read result code.
+/// - `op_spec_id`: Identifier of the C1G2Read operation.
+/// - `read_data`: The words read, as big-endian bytes.
+#[derive(Debug)]
+pub struct C1G2ReadOpSpecResult {
+  pub result     : u8,
+  pub op_spec_id : u16,
+  pub read_data  : Vec<u8>
+}
+
+impl C1G2ReadOpSpecResult {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 5 {
+      return Err(ParameterDecodeError::too_short("C1G2ReadOpSpecResult", 5, buf.remaining()));
+    }
+
+    let result = buf.get_u8();
+    let op_spec_id = buf.get_u16();
+    let word_count = buf.get_u16() as usize;
+
+    if buf.remaining() < word_count * 2 {
+      return Err(ParameterDecodeError::too_short("C1G2ReadOpSpecResult.ReadData", word_count * 2, buf.remaining()));
+    }
+
+    let read_data = buf.split_to(word_count * 2).to_vec();
+
+    Ok(C1G2ReadOpSpecResult { result, op_spec_id, read_data })
+  }
+
+  /// The words read, in tag memory order.
+  pub fn words(
+    &self
+  ) -> Vec<u16> {
+    self.read_data.chunks_exact(2).map(|word| u16::from_be_bytes([word[0], word[1]])).collect()
+  }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.result == 0
+  }
+}
+
+/// The outcome of a C1G2Write operation.
+///
+/// Fields:
+/// - `result`: 0 on success, otherwise the LLRP C1G2Write result code.
+/// - `op_spec_id`: Identifier of the C1G2Write operation.
+/// - `num_words_written`: Number of words the tag accepted.
+#[derive(Debug)]
+pub struct C1G2WriteOpSpecResult {
+  pub result            : u8,
+  pub op_spec_id        : u16,
+  pub num_words_written : u16
+}
+
+impl C1G2WriteOpSpecResult {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 5 {
+      return Err(ParameterDecodeError::too_short("C1G2WriteOpSpecResult", 5, buf.remaining()));
+    }
+
+    Ok(C1G2WriteOpSpecResult {
+      result            : buf.get_u8(),
+      op_spec_id        : buf.get_u16(),
+      num_words_written : buf.get_u16()
+    })
+  }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.result == 0
+  }
+}
+
+/// The outcome of a C1G2BlockErase operation.
+///
+/// Fields:
+/// - `result`: 0 on success, otherwise the LLRP C1G2BlockErase result code.
+/// - `op_spec_id`: Identifier of the C1G2BlockErase operation.
+#[derive(Debug)]
+pub struct C1G2BlockEraseOpSpecResult {
+  pub result     : u8,
+  pub op_spec_id : u16
+}
+
+impl C1G2BlockEraseOpSpecResult {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("C1G2BlockEraseOpSpecResult", 3, buf.remaining()));
+    }
+
+    Ok(C1G2BlockEraseOpSpecResult {
+      result     : buf.get_u8(),
+      op_spec_id : buf.get_u16()
+    })
+  }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.result == 0
+  }
+}
+
+/// The outcome of a C1G2Kill operation.
+///
+/// Fields:
+/// - `result`: 0 - Success, 1 - Zero kill password, 2 - Insufficient power, 3 - Non-specific
+///   tag error, 4 - No response from tag, 5 - Non-specific reader error.
+/// - `op_spec_id`: Identifier of the C1G2Kill operation.
+#[derive(Debug)]
+pub struct C1G2KillOpSpecResult {
+  pub result     : u8,
+  pub op_spec_id : u16
+}
+
+impl C1G2KillOpSpecResult {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("C1G2KillOpSpecResult", 3, buf.remaining()));
+    }
+
+    Ok(C1G2KillOpSpecResult {
+      result     : buf.get_u8(),
+      op_spec_id : buf.get_u16()
+    })
+  }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.result == 0
+  }
+}
+
+/// The outcome of a C1G2Lock operation.
+///
+/// Fields:
+/// - `result`: 0 on success, otherwise the LLRP C1G2Lock result code.
+/// - `op_spec_id`: Identifier of the C1G2Lock operation.
+#[derive(Debug)]
+pub struct C1G2LockOpSpecResult {
+  pub result     : u8,
+  pub op_spec_id : u16
+}
+
+impl C1G2LockOpSpecResult {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("C1G2LockOpSpecResult", 3, buf.remaining()));
+    }
+
+    Ok(C1G2LockOpSpecResult {
+      result     : buf.get_u8(),
+      op_spec_id : buf.get_u16()
+    })
+  }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.result == 0
+  }
+}
+
+/// An AccessSpec loaded on the reader, as reported by GET_ACCESSSPECS.
+///
+/// Fields:
+/// - `access_spec_id`: Identifier of the AccessSpec.
+/// - `antenna_id`: Antenna the AccessSpec applies to (0 - All).
+/// - `protocol_id`: Air protocol (1 - EPCGlobal Class 1 Gen 2).
+/// - `enabled`: Whether the AccessSpec is in the active state.
+/// - `rospec_id`: ROSpec the AccessSpec applies to (0 - Any).
+/// - `stop_trigger`: When the reader deletes the AccessSpec.
+/// - `access_command`: The tags the AccessSpec applies to and the operations performed on them.
+/// - `access_report_trigger`: 0 - Report with the ROSpec's tag reports, 1 - Report at the end
+///   of the access operations. `None` when the reader's default applies.
+#[derive(Debug)]
+pub struct AccessSpec {
+  pub access_spec_id        : u32,
+  pub antenna_id            : u16,
+  pub protocol_id           : u8,
+  pub enabled               : bool,
+  pub rospec_id             : u32,
+  pub stop_trigger          : Option<AccessSpecStopTrigger>,
+  pub access_command        : Option<AccessCommand>,
+  pub access_report_trigger : Option<u8>
+}
+
+impl AccessSpec {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 12 {
+      return Err(ParameterDecodeError::too_short("AccessSpec", 12, buf.remaining()));
+    }
+
+    let access_spec_id = buf.get_u32();
+    let antenna_id = buf.get_u16();
+    let protocol_id = buf.get_u8();
+    let enabled = (buf.get_u8() & 0x80) != 0;
+    let rospec_id = buf.get_u32();
+
+    let mut stop_trigger = None;
+    let mut access_command = None;
+    let mut access_report_trigger = None;
+
+    for param in parse_parameters(buf.chunk()).within("AccessSpec")? {
+      match param.param_type {
+
+        LlrpParameterType::AccessSpecStopTrigger => {
+          stop_trigger = Some(AccessSpecStopTrigger::decode(&param.param_value).within("AccessSpec")?);
+        }
+
+        LlrpParameterType::AccessCommand => {
+          access_command = Some(AccessCommand::decode(&param.param_value).within("AccessSpec")?);
+        }
+
+        LlrpParameterType::AccessReportSpec => {
+          access_report_trigger = param.param_value.first().copied();
+        }
+
+        _ => {
+          warn!("Unhandled sub-parameter type in AccessSpec: {:?}", param.param_type);
+        }
+      }
+    }
+
+    Ok(AccessSpec {
+      access_spec_id,
+      antenna_id,
+      protocol_id,
+      enabled,
+      rospec_id,
+      stop_trigger,
+      access_command,
+      access_report_trigger
+    })
+  }
+}
+
+/// Fields:
+/// - `trigger_type`: 0 - Null (never stops), 1 - Operation count.
+/// - `operation_count`: Executions after which the reader deletes the AccessSpec.
+#[derive(Debug)]
+pub struct AccessSpecStopTrigger {
+  pub trigger_type    : u8,
+  pub operation_count : u16
+}
+
+impl AccessSpecStopTrigger {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("AccessSpecStopTrigger", 3, buf.remaining()));
+    }
+
+    Ok(AccessSpecStopTrigger {
+      trigger_type    : buf.get_u8(),
+      operation_count : buf.get_u16()
+    })
+  }
+}
+
+/// Fields:
+/// - `target_tags`: The C1G2TargetTag filters of the C1G2TagSpec; a tag must match all of them.
+/// - `op_specs`: The operations performed on each matching tag, in execution order.
+#[derive(Debug)]
+pub struct AccessCommand {
+  pub target_tags : Vec<C1G2TargetTag>,
+  pub op_specs    : Vec<AccessOpSpec>
+}
+
+impl AccessCommand {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut target_tags = Vec::new();
+    let mut op_specs = Vec::new();
+
+    for param in parse_parameters(buf).within("AccessCommand")? {
+      match param.param_type {
+
+        LlrpParameterType::C1G2TagSpec => {
+          for target_tag in parse_parameters(&param.param_value).within("AccessCommand/C1G2TagSpec")? {
+            if target_tag.param_type == LlrpParameterType::C1G2TargetTag {
+              target_tags.push(C1G2TargetTag::decode(&target_tag.param_value).within("AccessCommand/C1G2TagSpec")?);
+            }
+          }
+        }
+
+        _ => op_specs.push(AccessOpSpec::decode(&param).within("AccessCommand")?)
+      }
+    }
+
+    Ok(AccessCommand { target_tags, op_specs })
+  }
+}
+
+/// Fields:
+/// - `memory_bank`: 0 - Reserved, 1 - EPC, 2 - TID, 3 - User.
+/// - `matching`: Whether the filter selects tags that match (`true`) or that do not.
+/// - `pointer`: First bit compared.
+/// - `tag_mask` / `tag_data`: The compared bits, padded to whole bytes.
+/// - `mask_bit_count` / `data_bit_count`: Number of significant bits in the mask and data.
+#[derive(Debug)]
+pub struct C1G2TargetTag {
+  pub memory_bank    : u8,
+  pub matching       : bool,
+  pub pointer        : u16,
+  pub mask_bit_count : u16,
+  pub tag_mask       : Vec<u8>,
+  pub data_bit_count : u16,
+  pub tag_data       : Vec<u8>
+}
+
+impl C1G2TargetTag {
+
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 5 {
+      return Err(ParameterDecodeError::too_short("C1G2TargetTag", 5, buf.remaining()));
+    }
+
+    let flags = buf.get_u8();
+    let pointer = buf.get_u16();
+
+    let mask_bit_count = buf.get_u16();
+    let tag_mask = take_bits(&mut buf, mask_bit_count, "C1G2TargetTag.TagMask")?;
+
+    if buf.remaining() < 2 {
+      return Err(ParameterDecodeError::too_short("C1G2TargetTag.DataBitCount", 2, buf.remaining()));
+    }
+
+    let data_bit_count = buf.get_u16();
+    let tag_data = take_bits(&mut buf, data_bit_count, "C1G2TargetTag.TagData")?;
+
+    Ok(C1G2TargetTag {
+      memory_bank : flags >> 6,
+      matching    : (flags & 0x20) != 0,
+      pointer,
+      mask_bit_count,
+      tag_mask,
+      data_bit_count,
+      tag_data
+    })
+  }
+}
+
+/// Reads a bit field padded to whole bytes.
+fn take_bits(
+  buf       : &mut BytesMut,
+  bit_count : u16,
+  field     : &str
+) -> Result<Vec<u8>, ParameterDecodeError> {
+
+  let byte_count = (bit_count as usize).div_ceil(8);
+
+  if buf.remaining() < byte_count {
+    return Err(ParameterDecodeError::too_short(field, byte_count, buf.remaining()));
+  }
+
+  Ok(buf.split_to(byte_count).to_vec())
+}
+
+/// A single operation of an AccessCommand. Op specs this client does not
+/// decode are kept as `Other` with their parameter type.
+#[derive(Debug)]
+pub enum AccessOpSpec {
+  Read       { op_spec_id: u16, access_password: u32, memory_bank: u8, word_pointer: u16, word_count: u16 },
+  Write      { op_spec_id: u16, access_password: u32, memory_bank: u8, word_pointer: u16, data: Vec<u16> },
+  BlockErase { op_spec_id: u16, access_password: u32, memory_bank: u8, word_pointer: u16, word_count: u16 },
+  BlockWrite { op_spec_id: u16, access_password: u32, memory_bank: u8, word_pointer: u16, data: Vec<u16> },
+  Kill       { op_spec_id: u16, kill_password: u32 },
+  Lock       { op_spec_id: u16, access_password: u32, payloads: Vec<(u8, u8)> },
+  Other      (LlrpParameterType)
+}
+
+impl AccessOpSpec {
+
+  pub fn decode(
+    param: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(&param.param_value[..]);
+
+    let min_length = match param.param_type {
+      LlrpParameterType::C1G2Read
+      | LlrpParameterType::C1G2Write
+      | LlrpParameterType::C1G2BlockErase
+      | LlrpParameterType::C1G2BlockWrite => 11,
+      LlrpParameterType::C1G2Kill
+      | LlrpParameterType::C1G2Lock => 6,
+      other => return Ok(AccessOpSpec::Other(other))
+    };
+
+    if buf.remaining() < min_length {
+      return Err(ParameterDecodeError::too_short(format!("{:?}", param.param_type), min_length, buf.remaining()));
+    }
+
+    let op_spec_id = buf.get_u16();
+    let password = buf.get_u32();
+
+    let op_spec = match param.param_type {
+
+      LlrpParameterType::C1G2Kill => AccessOpSpec::Kill { op_spec_id, kill_password: password },
+
+      LlrpParameterType::C1G2Lock => {
+
+        let mut payloads = Vec::new();
+        for payload in parse_parameters(buf.chunk()).within(&format!("{:?}", param.param_type))? {
+          if payload.param_type == LlrpParameterType::C1G2LockPayload && payload.param_value.len() >= 2 {
+            payloads.push((payload.param_value[0], payload.param_value[1]));
+          }
+        }
+
+        AccessOpSpec::Lock { op_spec_id, access_password: password, payloads }
+      }
+
+      param_type => {
+
+        let memory_bank = buf.get_u8() >> 6;
+        let word_pointer = buf.get_u16();
+        let word_count = buf.get_u16();
+
+        match param_type {
+
+          LlrpParameterType::C1G2Read => AccessOpSpec::Read {
+            op_spec_id, access_password: password, memory_bank, word_pointer, word_count
+          },
+
+          LlrpParameterType::C1G2BlockErase => AccessOpSpec::BlockErase {
+            op_spec_id, access_password: password, memory_bank, word_pointer, word_count
+          },
+
+          _ => {
+
+            if buf.remaining() < 2 * word_count as usize {
+              return Err(ParameterDecodeError::too_short(format!("{:?}.WriteData", param_type), 2 * word_count as usize, buf.remaining()));
+            }
+
+            let data = (0..word_count).map(|_| buf.get_u16()).collect();
+
+            if param_type == LlrpParameterType::C1G2Write {
+              AccessOpSpec::Write { op_spec_id, access_password: password, memory_bank, word_pointer, data }
+            } else {
+              AccessOpSpec::BlockWrite { op_spec_id, access_password: password, memory_bank, word_pointer, data }
+            }
+          }
+        }
+      }
+    };
+
+    Ok(op_spec)
   }
 }
 
@@ -95,25 +898,20 @@ impl fmt::Display for EPCData {
 impl EPCData {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 2 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for EPCData Bit Field Length"
-      ));
+      return Err(ParameterDecodeError::too_short("EPCData.EPCLengthBits", 2, buf.remaining()));
     }
 
     let bit_field_length = buf.get_u16();
-    let epc_byte_length = ((bit_field_length + 7) / 8) as usize;
+    let epc_byte_length = (bit_field_length.checked_add(7)
+      .ok_or_else(|| ParameterDecodeError::Overflow { path: "EPCData.EPCLengthBits".to_string() })? / 8) as usize;
 
     if buf.remaining() < epc_byte_length {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for EPCData EPC field"
-      ));
+      return Err(ParameterDecodeError::too_short("EPCData.EPC", epc_byte_length, buf.remaining()));
     }
 
     let epc = buf.split_to(epc_byte_length).to_vec();
@@ -123,13 +921,10 @@ impl EPCData {
 
   pub fn decode_epc96(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     if buf.len() != 12 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "EPC96 data must be 12 bytes",
-      ));
+      return Err(ParameterDecodeError::BadLength { path: "EPC-96".to_string(), length: buf.len() });
     }
 
     let epc = buf.to_vec();
@@ -137,37 +932,255 @@ impl EPCData {
   }
 }
 
-#[derive(Debug)]
+/// The outcome a reader reports for a request.
+///
+/// Fields:
+/// - `status_code`: 0 on success, otherwise the LLRP StatusCode of the failure.
+/// - `error_description`: The reader's description of the failure.
+/// - `field_error`: The field of the message that caused the failure.
+/// - `parameter_error`: The parameter of the message that caused the failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LLRPStatus {
-  pub status_code : u16,
-  pub error_desc  : u16
+  pub status_code       : u16,
+  pub error_description : String,
+  pub field_error       : Option<FieldError>,
+  pub parameter_error   : Option<ParameterError>
 }
 
 impl LLRPStatus {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for LLRPStatus"
-      ));
+      return Err(ParameterDecodeError::too_short("LLRPStatus", 4, buf.remaining()));
     }
 
     let status_code = buf.get_u16();
-    let error_desc = buf.get_u16();
+    let description_length = buf.get_u16() as usize;
+
+    if buf.remaining() < description_length {
+      return Err(ParameterDecodeError::too_short("LLRPStatus.ErrorDescription", description_length, buf.remaining()));
+    }
+
+    let error_description = String::from_utf8(buf.split_to(description_length).to_vec())
+      .map_err(|_| ParameterDecodeError::Utf8 { path: "LLRPStatus.ErrorDescription".to_string() })?;
+
+    let mut field_error = None;
+    let mut parameter_error = None;
+
+    for param in parse_parameters(buf.chunk()).within("LLRPStatus")? {
+      match param.param_type {
+
+        LlrpParameterType::FieldError => {
+          field_error = Some(FieldError::decode(&param.param_value).within("LLRPStatus")?);
+        }
+
+        LlrpParameterType::ParameterError => {
+          parameter_error = Some(ParameterError::decode(&param.param_value).within("LLRPStatus")?);
+        }
+
+        _ => {
+          warn!("Unhandled sub-parameter type in LLRPStatus: {:?}", param.param_type);
+        }
+      }
+    }
 
-    Ok(LLRPStatus { 
-      status_code, 
-      error_desc
+    Ok(LLRPStatus {
+      status_code,
+      error_description,
+      field_error,
+      parameter_error
     })
   }
+
+  pub fn succeeded(
+    &self
+  ) -> bool {
+    self.status_code == 0
+  }
 }
 
-#[derive(Debug)]
+impl fmt::Display for LLRPStatus {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+
+    write!(f, "{} ({})", status_code_name(self.status_code), self.status_code)?;
+
+    if !self.error_description.is_empty() {
+      write!(f, ": {}", self.error_description)?;
+    }
+    if let Some(field_error) = &self.field_error {
+      write!(f, "; {}", field_error)?;
+    }
+    if let Some(parameter_error) = &self.parameter_error {
+      write!(f, "; {}", parameter_error)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// The name the LLRP specification gives a StatusCode, e.g. `M_FieldError`.
+pub fn status_code_name(
+  status_code: u16
+) -> &'static str {
+  match status_code {
+    0   => "M_Success",
+    100 => "M_ParameterError",
+    101 => "M_FieldError",
+    102 => "M_UnexpectedParameter",
+    103 => "M_MissingParameter",
+    104 => "M_DuplicateParameter",
+    105 => "M_OverflowParameter",
+    106 => "M_OverflowField",
+    107 => "M_UnknownParameter",
+    108 => "M_UnknownField",
+    109 => "M_UnsupportedMessage",
+    110 => "M_UnsupportedVersion",
+    111 => "M_UnsupportedParameter",
+    112 => "M_UnexpectedMessage",
+    200 => "P_ParameterError",
+    201 => "P_FieldError",
+    202 => "P_UnexpectedParameter",
+    203 => "P_MissingParameter",
+    204 => "P_DuplicateParameter",
+    205 => "P_OverflowParameter",
+    206 => "P_OverflowField",
+    207 => "P_UnknownParameter",
+    208 => "P_UnknownField",
+    209 => "P_UnsupportedParameter",
+    300 => "A_Invalid",
+    301 => "A_OutOfRange",
+    401 => "R_DeviceError",
+    _   => "Unknown status"
+  }
+}
+
+/// A field that caused a request to fail.
+///
+/// Fields:
+/// - `field_num`: Position of the field in its message or parameter, counting from 0.
+/// - `error_code`: StatusCode describing what is wrong with the field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+  pub field_num  : u16,
+  pub error_code : u16
+}
+
+impl FieldError {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 4 {
+      return Err(ParameterDecodeError::too_short("FieldError", 4, buf.remaining()));
+    }
+
+    Ok(FieldError {
+      field_num  : buf.get_u16(),
+      error_code : buf.get_u16()
+    })
+  }
+}
+
+impl fmt::Display for FieldError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    write!(f, "field {}: {} ({})", self.field_num, status_code_name(self.error_code), self.error_code)
+  }
+}
+
+/// A parameter that caused a request to fail, pointing further into the parameter
+/// at the field or sub-parameter at fault.
+///
+/// Fields:
+/// - `parameter_type`: Type of the parameter.
+/// - `error_code`: StatusCode describing what is wrong with the parameter.
+/// - `field_error`: The field of the parameter at fault.
+/// - `parameter_error`: The sub-parameter at fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterError {
+  pub parameter_type  : u16,
+  pub error_code      : u16,
+  pub field_error     : Option<FieldError>,
+  pub parameter_error : Option<Box<ParameterError>>
+}
+
+impl ParameterError {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 4 {
+      return Err(ParameterDecodeError::too_short("ParameterError", 4, buf.remaining()));
+    }
+
+    let parameter_type = buf.get_u16();
+    let error_code = buf.get_u16();
+
+    let mut field_error = None;
+    let mut parameter_error = None;
+
+    for param in parse_parameters(buf.chunk()).within("ParameterError")? {
+      match param.param_type {
+
+        LlrpParameterType::FieldError => {
+          field_error = Some(FieldError::decode(&param.param_value).within("ParameterError")?);
+        }
+
+        LlrpParameterType::ParameterError => {
+          parameter_error = Some(Box::new(ParameterError::decode(&param.param_value).within("ParameterError")?));
+        }
+
+        _ => {
+          warn!("Unhandled sub-parameter type in ParameterError: {:?}", param.param_type);
+        }
+      }
+    }
+
+    Ok(ParameterError {
+      parameter_type,
+      error_code,
+      field_error,
+      parameter_error
+    })
+  }
+}
+
+impl fmt::Display for ParameterError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+
+    let parameter_type = LlrpParameterType::from_value(self.parameter_type)
+      .map_or(self.parameter_type.to_string(), |parameter_type| format!("{:?}", parameter_type));
+
+    write!(f, "parameter {}: {} ({})", parameter_type, status_code_name(self.error_code), self.error_code)?;
+
+    if let Some(field_error) = &self.field_error {
+      write!(f, ", {}", field_error)?;
+    }
+    if let Some(parameter_error) = &self.parameter_error {
+      write!(f, ", {}", parameter_error)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Serialize)]
 pub struct GeneralDeviceCapabilities {
   pub max_number_of_antennas_supported  : u16,
   pub general_device_capabilities       : u16,
@@ -182,15 +1195,12 @@ pub struct GeneralDeviceCapabilities {
 impl GeneralDeviceCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 12 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for GeneralDeviceCapabilities"
-      ));
+      return Err(ParameterDecodeError::too_short("GeneralDeviceCapabilities", 12, buf.remaining()));
     }
 
     let max_number_of_antennas_supported = buf.get_u16();
@@ -199,27 +1209,21 @@ impl GeneralDeviceCapabilities {
     let model_name = buf.get_u32();
 
     if buf.remaining() < 2 {
-      return Err(io::Error::new(
-        io::ErrorKind::InvalidData,
-        "Buffer too short for firmware length prefix",
-      ));
+      return Err(ParameterDecodeError::too_short("GeneralDeviceCapabilities.ReaderFirmwareVersion", 2, buf.remaining()));
     }
 
     let firmware_length = buf.get_u16() as usize;
 
     if buf.remaining() < firmware_length {
-      return Err(io::Error::new(
-        io::ErrorKind::InvalidData,
-        "Buffer too short for firmware version string",
-      ));
+      return Err(ParameterDecodeError::too_short("GeneralDeviceCapabilities.ReaderFirmwareVersion", firmware_length, buf.remaining()));
     }
 
     let firmware_bytes = buf.split_to(firmware_length);
     let reader_firmware_version = String::from_utf8(firmware_bytes.to_vec())
-      .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+      .map_err(|_| ParameterDecodeError::Utf8 { path: "GeneralDeviceCapabilities.ReaderFirmwareVersion".to_string() })?;
 
     let sub_param_slice = buf.chunk();
-    let sub_parameters = parse_parameters(sub_param_slice)?;
+    let sub_parameters = parse_parameters(sub_param_slice).within("GeneralDeviceCapabilities")?;
 
     let mut receive_sensitivity_table_entries = Vec::new();
     let mut gpio_capabilities = None;
@@ -229,17 +1233,17 @@ impl GeneralDeviceCapabilities {
       match param.param_type {
 
         LlrpParameterType::ReceiveSensitivityTableEntry => {
-          let entry = ReceiveSensitivityTableEntry::decode(&param.param_value)?;
+          let entry = ReceiveSensitivityTableEntry::decode(&param.param_value).within("GeneralDeviceCapabilities")?;
           receive_sensitivity_table_entries.push(entry);
         }
 
         LlrpParameterType::GPIOCapabilities => {
-          let gpio_caps = GPIOCapabilities::decode(&param.param_value)?;
+          let gpio_caps = GPIOCapabilities::decode(&param.param_value).within("GeneralDeviceCapabilities")?;
           gpio_capabilities = Some(gpio_caps);
         }
 
         LlrpParameterType::PerAntennaAirProtocol => {
-          let antenna_protocol = AntennaAirProtocol::decode(&param.param_value)?;
+          let antenna_protocol = AntennaAirProtocol::decode(&param.param_value).within("GeneralDeviceCapabilities")?;
           antenna_air_protocols.push(antenna_protocol);
         }
 
@@ -262,7 +1266,7 @@ impl GeneralDeviceCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct GPIOCapabilities {
   pub num_gpi_ports : u16,
   pub num_gpo_ports : u16 
@@ -271,14 +1275,11 @@ pub struct GPIOCapabilities {
 impl GPIOCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for GPIOCapabilities"
-      ));
+      return Err(ParameterDecodeError::too_short("GPIOCapabilities", 4, buf.remaining()));
     }
 
     let num_gpi_ports = buf.get_u16();
@@ -291,7 +1292,7 @@ impl GPIOCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct AntennaAirProtocol {
   pub antenna_id   : u16,
   pub protocol_ids : Vec<u8>
@@ -300,15 +1301,12 @@ pub struct AntennaAirProtocol {
 impl AntennaAirProtocol {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 3 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for AntennaAirProtocol"
-      ));
+      return Err(ParameterDecodeError::too_short("PerAntennaAirProtocol", 3, buf.remaining()));
     }
 
     let antenna_id = buf.get_u16();
@@ -317,10 +1315,7 @@ impl AntennaAirProtocol {
     let mut protocol_ids = Vec::new();
     for _ in 0..num_protocols {
       if buf.remaining() < 1 {
-        return Err(Error::new(
-          ErrorKind::InvalidData,
-          "Buffer too short for antenna air protocol IDs"
-        ));
+        return Err(ParameterDecodeError::too_short("PerAntennaAirProtocol.ProtocolID", 1, buf.remaining()));
       }
 
       let protocol_id = buf.get_u8();
@@ -334,7 +1329,7 @@ impl AntennaAirProtocol {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct LLRPCapabilities {
   pub can_do_rfsurvey                               : bool,
   pub can_report_buffer_fill_warning                : bool,
@@ -353,15 +1348,12 @@ pub struct LLRPCapabilities {
 impl LLRPCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 24 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for LLRPCapabilities"
-      ));
+      return Err(ParameterDecodeError::too_short("LLRPCapabilities", 24, buf.remaining()));
     }
 
     let capabilities = buf.get_u8();
@@ -396,7 +1388,7 @@ impl LLRPCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct RegulatoryCapabilities {
   pub country_code            : u16,
   pub communications_standard : u16,
@@ -406,22 +1398,19 @@ pub struct RegulatoryCapabilities {
 impl RegulatoryCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for RegulatoryCapabilities"
-      ));
+      return Err(ParameterDecodeError::too_short("RegulatoryCapabilities", 4, buf.remaining()));
     }
 
     let country_code = buf.get_u16();
     let communications_standard = buf.get_u16();
 
     let param_slice = buf.chunk();
-    let sub_parameters = parse_parameters(param_slice)?;
+    let sub_parameters = parse_parameters(param_slice).within("RegulatoryCapabilities")?;
 
     let mut uhf_band_capabilities = None;
  
@@ -429,7 +1418,7 @@ impl RegulatoryCapabilities {
       match param.param_type {
         
         LlrpParameterType::UHFBandCapabilities => {
-          let uhf_caps = UHFBandCapabilities::decode(&param.param_value)?;
+          let uhf_caps = UHFBandCapabilities::decode(&param.param_value).within("RegulatoryCapabilities")?;
           uhf_band_capabilities = Some(uhf_caps);
         }
 
@@ -448,7 +1437,7 @@ impl RegulatoryCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct UHFBandCapabilities {
   pub transmit_power_levels  : Vec<TransmitPowerLevelTableEntry>,
   pub frequency_information  : Option<FrequencyInformation>,
@@ -458,9 +1447,9 @@ pub struct UHFBandCapabilities {
 impl UHFBandCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
-    let sub_parameters = parse_parameters(&mut buf)?;
+    let sub_parameters = parse_parameters(&mut buf).within("UHFBandCapabilities")?;
 
     let mut transmit_power_levels = Vec::new();
     let mut frequency_information = None;
@@ -470,17 +1459,17 @@ impl UHFBandCapabilities {
       match param.param_type {
         
         LlrpParameterType::TransmitPowerLevelTableEntry => {
-          let entry = TransmitPowerLevelTableEntry::decode(&param.param_value)?;
+          let entry = TransmitPowerLevelTableEntry::decode(&param.param_value).within("UHFBandCapabilities")?;
           transmit_power_levels.push(entry);
         }
 
         LlrpParameterType::FrequencyInformation => {
-          let freq_info = FrequencyInformation::decode(&param.param_value)?;
+          let freq_info = FrequencyInformation::decode(&param.param_value).within("UHFBandCapabilities")?;
           frequency_information = Some(freq_info)
         }
 
         LlrpParameterType::C1G2UHFRFModeTable => {
-          let c1g2_table = C1G2UHFRFModeTable::decode(&param.param_value)?;
+          let c1g2_table = C1G2UHFRFModeTable::decode(&param.param_value).within("UHFBandCapabilities")?;
           c1g2_uhf_rf_mode_table = Some(c1g2_table);
         }
 
@@ -498,7 +1487,7 @@ impl UHFBandCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct TransmitPowerLevelTableEntry {
   pub index                : u16,
   pub transmit_power_value : u16
@@ -507,14 +1496,11 @@ pub struct TransmitPowerLevelTableEntry {
 impl TransmitPowerLevelTableEntry {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for TransmitPowerLevelTableEntry"
-      ));
+      return Err(ParameterDecodeError::too_short("TransmitPowerLevelTableEntry", 4, buf.remaining()));
     }
 
     let index = buf.get_u16();
@@ -527,7 +1513,7 @@ impl TransmitPowerLevelTableEntry {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ReceiveSensitivityTableEntry {
   pub index                     : u16,
   pub receive_sensitivity_value : i16
@@ -536,14 +1522,11 @@ pub struct ReceiveSensitivityTableEntry {
 impl ReceiveSensitivityTableEntry {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for ReceiveSensitivityTableEntry"
-      ));
+      return Err(ParameterDecodeError::too_short("ReceiveSensitivityTableEntry", 4, buf.remaining()));
     }
 
     let index = buf.get_u16();
@@ -556,7 +1539,7 @@ impl ReceiveSensitivityTableEntry {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FrequencyInformation {
   pub hopping               : bool,
   pub frequency_hop_tables  : Vec<FrequencyHopTable>,
@@ -566,21 +1549,18 @@ pub struct FrequencyInformation {
 impl FrequencyInformation {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for FrequencyInformation"
-      ));
+      return Err(ParameterDecodeError::too_short("FrequencyInformation", 1, buf.remaining()));
     }
 
     let hop_flag = buf.get_u8();
     let hopping = hop_flag != 0;
 
-    let sub_parameters = parse_parameters(&mut buf)?;
+    let sub_parameters = parse_parameters(&mut buf).within("FrequencyInformation")?;
 
     let mut frequency_hop_tables = Vec::new();
     let mut fixed_frequency_table = None;
@@ -589,12 +1569,12 @@ impl FrequencyInformation {
       match param.param_type {
 
         LlrpParameterType::FrequencyHopTable => {
-          let hop_table = FrequencyHopTable::decode(&param.param_value)?;
+          let hop_table = FrequencyHopTable::decode(&param.param_value).within("FrequencyInformation")?;
           frequency_hop_tables.push(hop_table);
         }
 
         LlrpParameterType::FixedFrequencyTable => {
-          fixed_frequency_table = Some(FixedFrequencyTable::decode(&param.param_value)?);
+          fixed_frequency_table = Some(FixedFrequencyTable::decode(&param.param_value).within("FrequencyInformation")?);
         }
 
         _ => {
@@ -611,7 +1591,7 @@ impl FrequencyInformation {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FrequencyHopTable {
   pub hop_table_id   : u16,
   pub number_of_hops : u16,
@@ -621,31 +1601,25 @@ pub struct FrequencyHopTable {
 impl FrequencyHopTable {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for FrequencyHopTable header"
-      ));
+      return Err(ParameterDecodeError::too_short("FrequencyHopTable", 4, buf.remaining()));
     }
 
-    let hop_table_id = buf.get_u16();
+    let hop_table_id = buf.get_u8() as u16;
+    buf.advance(1); // Reserved
     let number_of_hops = buf.get_u16();
-    let num_frequencies = buf.get_u16();
 
-    let frequencies_size = num_frequencies as usize * 4;
+    let frequencies_size = number_of_hops as usize * 4;
 
     if buf.remaining() < frequencies_size {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for FrequencyHopTable frequencies"
-      ));
+      return Err(ParameterDecodeError::too_short("FrequencyHopTable.Frequency", frequencies_size, buf.remaining()));
     }
 
-    let mut frequencies = Vec::with_capacity(num_frequencies as usize);
-    for _ in 0..num_frequencies {
+    let mut frequencies = Vec::with_capacity(number_of_hops as usize);
+    for _ in 0..number_of_hops {
       let frequency = buf.get_u32();
       frequencies.push(frequency);
     }
@@ -658,7 +1632,7 @@ impl FrequencyHopTable {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FixedFrequencyTable {
   pub frequencies: Vec<u32>
 }
@@ -666,24 +1640,18 @@ pub struct FixedFrequencyTable {
 impl FixedFrequencyTable {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 2 {
-      return Err(Error::new(
-        ErrorKind::InvalidData, 
-        "Buffer too short for FixedFrequencyTable header"
-      ));
+      return Err(ParameterDecodeError::too_short("FixedFrequencyTable", 2, buf.remaining()));
     }
 
     let num_frequencies = buf.get_u16();
     let frequencies_size = num_frequencies as usize * 4;
 
     if buf.remaining() < frequencies_size {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for FixedFrequencyTable frequencies"
-      ));
+      return Err(ParameterDecodeError::too_short("FixedFrequencyTable.Frequency", frequencies_size, buf.remaining()));
     }
 
     let mut frequencies = Vec::with_capacity(num_frequencies as usize);
@@ -696,7 +1664,7 @@ impl FixedFrequencyTable {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct C1G2UHFRFModeTable {
   pub entries: Vec<C1G2UHFRFModeTableEntry>
 }
@@ -704,27 +1672,59 @@ pub struct C1G2UHFRFModeTable {
 impl C1G2UHFRFModeTable {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
-    let sub_parameters = parse_parameters(&buf)?;
+    let sub_parameters = parse_parameters(&buf).within("C1G2UHFRFModeTable")?;
 
     let mut entries = Vec::new();
 
     for param in sub_parameters {
       if param.param_type == LlrpParameterType::C1G2UHFRFModeTableEntry {
-        let entry = C1G2UHFRFModeTableEntry::decode(&param.param_value)?;
+        let entry = C1G2UHFRFModeTableEntry::decode(&param.param_value).within("C1G2UHFRFModeTable")?;
         entries.push(entry);
       } else {
         warn!("Unexpected parameter type in C1G2UHFRFModeTable: {:?}", param.param_type);
       }
     }
 
-    Ok(C1G2UHFRFModeTable { entries })
+    Ok(C1G2UHFRFModeTable { entries })
+  }
+
+  /// Finds the mode `rf_control` selects and checks its Tari against the mode's
+  /// range. Readers number their modes by `mode_identifier`, which is what the
+  /// ModeIndex of C1G2RFControl refers to.
+  pub fn check_rf_control(
+    &self,
+    rf_control: &C1G2RFControlConfig
+  ) -> Result<&C1G2UHFRFModeTableEntry, String> {
+
+    let mode = self.entries.iter()
+      .find(|entry| entry.mode_identifier == rf_control.mode_index as u32)
+      .ok_or_else(|| format!(
+        "RF mode {} is not in the reader's mode table ({})",
+        rf_control.mode_index,
+        self.entries.iter().map(|entry| entry.mode_identifier.to_string()).collect::<Vec<String>>().join(", ")
+      ))?;
+
+    let tari = rf_control.tari as u32;
+    if tari == 0 {
+      return Ok(mode);
+    }
+
+    if tari < mode.min_tari || tari > mode.max_tari {
+      return Err(format!("Tari {} ns is outside the {}-{} ns of RF mode {}", tari, mode.min_tari, mode.max_tari, mode.mode_identifier));
+    }
+
+    if mode.tari_step > 0 && !(tari - mode.min_tari).is_multiple_of(mode.tari_step) {
+      return Err(format!("Tari {} ns is not a step of {} ns from {} ns in RF mode {}", tari, mode.tari_step, mode.min_tari, mode.mode_identifier));
+    }
+
+    Ok(mode)
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct C1G2UHFRFModeTableEntry {
   pub mode_identifier             : u32,
   pub dr                          : bool,
@@ -742,15 +1742,12 @@ pub struct C1G2UHFRFModeTableEntry {
 impl C1G2UHFRFModeTableEntry {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
-    if buf.remaining() < 2 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for C1G2UHFRFModeTableEntry header"
-      ));
+    if buf.remaining() < 24 {
+      return Err(ParameterDecodeError::too_short("C1G2UHFRFModeTableEntry", 24, buf.remaining()));
     }
 
     let mode_identifier = buf.get_u32();
@@ -766,7 +1763,7 @@ impl C1G2UHFRFModeTableEntry {
     let pie = buf.get_u32();
     let min_tari = buf.get_u32();
     let max_tari = buf.get_u32();
-    let tari_step = 0;
+    let tari_step = if buf.remaining() >= 4 { buf.get_u32() } else { 0 };
 
     Ok(C1G2UHFRFModeTableEntry {
       mode_identifier,
@@ -784,7 +1781,7 @@ impl C1G2UHFRFModeTableEntry {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct C1G2LLRPCapabilities {
   pub supports_block_erase                : bool,
   pub supports_block_write                : bool,
@@ -798,15 +1795,12 @@ pub struct C1G2LLRPCapabilities {
 impl C1G2LLRPCapabilities {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
-    if buf.remaining() < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for C1G2LLRPCapabilities"
-      ));
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("C1G2LLRPCapabilities", 3, buf.remaining()));
     }
 
     let flags = buf.get_u8();
@@ -831,7 +1825,7 @@ impl C1G2LLRPCapabilities {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Identification {
   pub id_type   : u8,
   pub reader_id : Vec<u8>
@@ -840,13 +1834,10 @@ pub struct Identification {
 impl Identification {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     if buf.len() < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for Identification parameter, missing IDType"
-      ));
+      return Err(ParameterDecodeError::too_short("Identification", 1, buf.len()));
     }
 
     let length      = buf.len();
@@ -858,13 +1849,7 @@ impl Identification {
       0 => {
 
         if reader_id.len() < 8 {
-          return Err(Error::new(
-            ErrorKind::InvalidData,
-            format!(
-              "Identification parameter: Expected 8-byte MAX address, received {} bytes",
-              reader_id.len()
-            )
-          ));
+          return Err(ParameterDecodeError::too_short("Identification.ReaderID", 8, reader_id.len()));
         };
 
         if reader_id.len() > 8 {
@@ -898,32 +1883,36 @@ impl Identification {
   }
 }
 
-#[derive(Debug)]
+/// An antenna port of the reader, as reported by GET_READER_CONFIG.
+///
+/// Fields:
+/// - `antenna_connected`: Whether the reader detects an antenna on the port. Readers
+///   that cannot detect antennas report every port as connected.
+/// - `antenna_id`: The antenna port.
+/// - `antenna_gain`: Gain of the antenna in hundredths of a dBi, including cable loss.
+#[derive(Debug, Serialize)]
 pub struct AntennaProperties {
   pub antenna_connected : bool,
   pub antenna_id        : u16,
-  pub antenna_gain      : u16
+  pub antenna_gain      : i16
 }
 
 impl AntennaProperties {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 5 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for AntennaProperties"
-      ));
+      return Err(ParameterDecodeError::too_short("AntennaProperties", 5, buf.remaining()));
     }
 
     let flags = buf.get_u8();
     let antenna_connected = (flags & 0x80) != 0;
 
     let antenna_id = buf.get_u16();
-    let antenna_gain = buf.get_u16();
+    let antenna_gain = buf.get_i16();
 
     Ok(AntennaProperties {
       antenna_connected,
@@ -933,7 +1922,14 @@ impl AntennaProperties {
   }
 }
 
-#[derive(Debug)]
+/// The RF and inventory settings of an antenna, as reported by GET_READER_CONFIG.
+///
+/// Fields:
+/// - `antenna_id`: The antenna port.
+/// - `rf_receiver`: Receive sensitivity of the antenna, if reported.
+/// - `rf_transmitter`: RF channel and transmit power of the antenna, if reported.
+/// - `c1g2_inventory_commands`: The reader's default C1G2 inventory settings for the antenna.
+#[derive(Debug, Serialize)]
 pub struct AntennaConfiguration {
   pub antenna_id              : u16,
   pub rf_receiver             : Option<RFReceiver>,
@@ -942,39 +1938,37 @@ pub struct AntennaConfiguration {
 }
 
 impl AntennaConfiguration {
+
+  /// Decodes an AntennaConfiguration parsed by `parse_parameter_tree`.
   pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
+    parameter: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
 
-    let mut buf = BytesMut::from(buf);
+    let mut fields = parameter.fields();
 
-    if buf.remaining() < 2 {
-      return Err(Error::new(
-        ErrorKind::InvalidData, 
-        "Buffer too short for AntennaConfiguration"
-      ));
+    if fields.remaining() < 2 {
+      return Err(ParameterDecodeError::too_short("AntennaConfiguration", 2, fields.remaining()));
     }
 
-    let antenna_id = buf.get_u16();
-    let sub_parameters = parse_parameters(buf.chunk())?;
+    let antenna_id = fields.get_u16();
 
     let mut rf_receiver = None;
     let mut rf_transmitter = None;
     let mut c1g2_inventory_commands = Vec::new();
 
-    for param in sub_parameters {
+    for param in parameter.children() {
       match param.param_type {
 
         LlrpParameterType::RFReceiver => {
-          rf_receiver = Some(RFReceiver::decode(&param.param_value)?);
+          rf_receiver = Some(RFReceiver::decode(&param.param_value).within("AntennaConfiguration")?);
         }
 
         LlrpParameterType::RFTransmitter => {
-          rf_transmitter = Some(RFTransmitter::decode(&param.param_value)?);
+          rf_transmitter = Some(RFTransmitter::decode(&param.param_value).within("AntennaConfiguration")?);
         }
 
         LlrpParameterType::C1G2InventoryCommand => {
-          let inventory_command = C1G2InventoryCommand::decode(&param.param_value)?;
+          let inventory_command = C1G2InventoryCommand::decode(param).within("AntennaConfiguration")?;
           c1g2_inventory_commands.push(inventory_command);
         }
 
@@ -991,8 +1985,56 @@ impl AntennaConfiguration {
       c1g2_inventory_commands
     })
   }
+
+  /// Lists the settings of `desired`, and the RF mode and singulation settings of
+  /// `inventory_command`, that the antenna does not currently have, as their paths
+  /// in the configuration, e.g. `"reader_config.tx_power_table_index"`. Settings
+  /// the reader did not report count as differing; a Tari of 0 is not compared.
+  pub fn differences(
+    &self,
+    desired           : &ReaderConfig,
+    inventory_command : Option<&C1G2InventoryCommandConfig>
+  ) -> Vec<String> {
+
+    let mut differences = Vec::new();
+    let mut compare = |path: &str, actual: Option<u32>, desired: u32| {
+      if actual != Some(desired) {
+        differences.push(path.to_string());
+      }
+    };
+
+    let rf_transmitter = self.rf_transmitter.as_ref();
+    compare("reader_config.hop_table_id", rf_transmitter.map(|rf| rf.hop_table_id.into()), desired.hop_table_id.into());
+    compare("reader_config.channel_index", rf_transmitter.map(|rf| rf.channel_index.into()), desired.channel_index.into());
+    compare("reader_config.tx_power_table_index", rf_transmitter.map(|rf| rf.transmit_power_value.into()), desired.tx_power_table_index.into());
+    compare("reader_config.rx_power_table_index", self.rf_receiver.as_ref().map(|rf| rf.receiver_sensitivity.into()), desired.rx_power_table_index.into());
+
+    let reported = self.c1g2_inventory_commands.first();
+
+    if let Some(rf_control) = inventory_command.and_then(|command| command.rf_control.as_ref()) {
+      let actual = reported.and_then(|command| command.c1g2_rf_control.as_ref());
+      compare("rospec.inventory_command.rf_control.mode_index", actual.map(|rf| rf.mode_index.into()), rf_control.mode_index.into());
+      if rf_control.tari != 0 {
+        compare("rospec.inventory_command.rf_control.tari", actual.map(|rf| rf.tari.into()), rf_control.tari.into());
+      }
+    }
+
+    if let Some(singulation) = inventory_command.and_then(|command| command.singulation.as_ref()) {
+      let actual = reported.and_then(|command| command.c1g2_singulation_control.as_ref());
+      compare("rospec.inventory_command.singulation.session", actual.map(|control| control.session.into()), singulation.session.into());
+      compare("rospec.inventory_command.singulation.tag_population", actual.map(|control| control.tag_population.into()), singulation.tag_population.into());
+      compare("rospec.inventory_command.singulation.tag_transit_time", actual.map(|control| control.tag_transit_time), singulation.tag_transit_time);
+    }
+
+    differences
+  }
 }
-#[derive(Debug)]
+/// Receive settings of an antenna, as sent in SET_READER_CONFIG and reported by
+/// GET_READER_CONFIG.
+///
+/// Fields:
+/// - `receiver_sensitivity`: Index into the reader's ReceiveSensitivityTable.
+#[derive(Debug, Serialize)]
 pub struct RFReceiver {
   pub receiver_sensitivity: u16
 }
@@ -1000,15 +2042,12 @@ pub struct RFReceiver {
 impl RFReceiver {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 2 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for RFReceiver"
-      ));
+      return Err(ParameterDecodeError::too_short("RFReceiver", 2, buf.remaining()));
     }
 
     let receiver_sensitivity = buf.get_u16();
@@ -1017,7 +2056,15 @@ impl RFReceiver {
   }
 }
 
-#[derive(Debug)]
+/// Transmit settings of an antenna, as sent in SET_READER_CONFIG and reported by
+/// GET_READER_CONFIG.
+///
+/// Fields:
+/// - `hop_table_id`: Frequency hop table in use, for readers in hopping regions.
+/// - `channel_index`: Index into the reader's fixed frequency table, for readers in
+///   non-hopping regions.
+/// - `transmit_power_value`: Index into the reader's TransmitPowerLevelTable.
+#[derive(Debug, Serialize)]
 pub struct RFTransmitter {
   pub hop_table_id         : u16,
   pub channel_index        : u16,
@@ -1027,15 +2074,12 @@ pub struct RFTransmitter {
 impl RFTransmitter {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 6 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for RFTransmitter"
-      ));
+      return Err(ParameterDecodeError::too_short("RFTransmitter", 6, buf.remaining()));
     }
 
     let hop_table_id         = buf.get_u16();
@@ -1050,7 +2094,13 @@ impl RFTransmitter {
   }
 }
 
-#[derive(Debug)]
+/// C1G2 inventory settings, as reported within an AntennaConfiguration.
+///
+/// Fields:
+/// - `tag_inventory_state_aware`: Whether filters and singulation are state aware.
+/// - `c1g2_rf_control`: RF mode and Tari, if reported.
+/// - `c1g2_singulation_control`: Session, expected population and transit time, if reported.
+#[derive(Debug, Serialize)]
 pub struct C1G2InventoryCommand {
   pub tag_inventory_state_aware : bool,
   pub c1g2_rf_control           : Option<C1G2RFControl>,
@@ -1058,35 +2108,33 @@ pub struct C1G2InventoryCommand {
 }
 
 impl C1G2InventoryCommand {
+
+  /// Decodes a C1G2InventoryCommand parsed by `parse_parameter_tree`.
   pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
-    
-    let mut buf = BytesMut::from(buf);
+    parameter: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
 
-    if buf.remaining() < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for C1G2InventoryCommand"
-      ));
+    let mut fields = parameter.fields();
+
+    if fields.remaining() < 1 {
+      return Err(ParameterDecodeError::too_short("C1G2InventoryCommand", 1, fields.remaining()));
     }
 
-    let flags = buf.get_u8();
+    let flags = fields.get_u8();
     let tag_inventory_state_aware = (flags & 0x80) != 0;
 
-    let sub_parameters = parse_parameters(buf.chunk())?;
     let mut c1g2_rf_control = None;
     let mut c1g2_singulation_control = None;
 
-    for param in sub_parameters {
+    for param in parameter.children() {
       match param.param_type {
 
         LlrpParameterType::C1G2RFControl => {
-          c1g2_rf_control = Some(C1G2RFControl::decode(&param.param_value)?);
+          c1g2_rf_control = Some(C1G2RFControl::decode(&param.param_value).within("C1G2InventoryCommand")?);
         }
 
         LlrpParameterType::C1G2SingulationControl => {
-          c1g2_singulation_control = Some(C1G2SingulationControl::decode(&param.param_value)?);
+          c1g2_singulation_control = Some(C1G2SingulationControl::decode(&param.param_value).within("C1G2InventoryCommand")?);
         }
 
         _ => {
@@ -1095,15 +2143,18 @@ impl C1G2InventoryCommand {
       }
     }
 
-      Ok(C1G2InventoryCommand {
-        tag_inventory_state_aware,
-        c1g2_rf_control,
-        c1g2_singulation_control
-      })
+    Ok(C1G2InventoryCommand {
+      tag_inventory_state_aware,
+      c1g2_rf_control,
+      c1g2_singulation_control
+    })
   }
 }
 
-#[derive(Debug)]
+/// Fields:
+/// - `mode_index`: Mode identifier of the reader's C1G2UHFRFModeTable entry in use.
+/// - `tari`: Tari in nanoseconds, 0 for the mode's own.
+#[derive(Debug, Serialize)]
 pub struct C1G2RFControl {
   pub mode_index : u16,
   pub tari       : u16
@@ -1112,15 +2163,12 @@ pub struct C1G2RFControl {
 impl C1G2RFControl {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
     
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 4 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for C1G2RFControl"
-      ));
+      return Err(ParameterDecodeError::too_short("C1G2RFControl", 4, buf.remaining()));
     }
 
     let mode_index = buf.get_u16();
@@ -1133,7 +2181,11 @@ impl C1G2RFControl {
   }
 }
 
-#[derive(Debug)]
+/// Fields:
+/// - `session`: Gen2 session (0-3).
+/// - `tag_population`: Expected number of tags in the field.
+/// - `tag_transit_time`: Expected time in milliseconds a tag stays in the field.
+#[derive(Debug, Serialize)]
 pub struct C1G2SingulationControl {
   pub session          : u8,
   pub tag_population   : u16,
@@ -1143,18 +2195,15 @@ pub struct C1G2SingulationControl {
 impl C1G2SingulationControl {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 7 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for C1G2SingulationControl"
-      ));
+      return Err(ParameterDecodeError::too_short("C1G2SingulationControl", 7, buf.remaining()));
     }
 
-    let session = buf.get_u8();
+    let session = buf.get_u8() >> 6; // Session (First two bits)
     let tag_population = buf.get_u16();
     let tag_transit_time = buf.get_u32();
 
@@ -1166,27 +2215,25 @@ impl C1G2SingulationControl {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ReaderEventNotificationSpec {
   pub event_notification_states: Vec<EventNotificationState>
 }
 
 impl ReaderEventNotificationSpec {
-  pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
 
-    let buf = BytesMut::from(buf);
-
-    let sub_parameters = parse_parameters(buf.chunk())?;
+  /// Decodes a ReaderEventNotificationSpec parsed by `parse_parameter_tree`.
+  pub fn decode(
+    parameter: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut event_notification_states = Vec::new();
 
-    for param in sub_parameters {
+    for param in parameter.children() {
       match param.param_type {
 
         LlrpParameterType::EventNotificationState => {
-          let event_notification_state = EventNotificationState::decode(&param.param_value)?;
+          let event_notification_state = EventNotificationState::decode(&param.param_value).within("ReaderEventNotificationSpec")?;
           event_notification_states.push(event_notification_state);
         }
 
@@ -1200,7 +2247,7 @@ impl ReaderEventNotificationSpec {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct EventNotificationState {
   pub event_type         : u16,
   pub notification_state : bool
@@ -1209,15 +2256,12 @@ pub struct EventNotificationState {
 impl EventNotificationState {
   pub fn decode(
     buf: &[u8]
-  ) -> io::Result<Self> {
+  ) -> Result<Self, ParameterDecodeError> {
 
     let mut buf = BytesMut::from(buf);
 
     if buf.remaining() < 3 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for EventNotificationState"
-      ));
+      return Err(ParameterDecodeError::too_short("EventNotificationState", 3, buf.remaining()));
     }
 
     let event_type = buf.get_u16();
@@ -1232,7 +2276,436 @@ impl EventNotificationState {
   }
 }
 
-#[derive(Debug)]
+/// The configuration and level of a GPI port, as reported by GET_READER_CONFIG.
+///
+/// Fields:
+/// - `port`: The GPI port.
+/// - `enabled`: Whether the reader monitors the port and reports its events.
+/// - `state`: The port's current level.
+#[derive(Debug, Serialize)]
+pub struct GPIPortCurrentState {
+  pub port    : GpiPort,
+  pub enabled : bool,
+  pub state   : PinState
+}
+
+impl GPIPortCurrentState {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 4 {
+      return Err(ParameterDecodeError::too_short("GPIPortCurrentState", 4, buf.remaining()));
+    }
+
+    Ok(GPIPortCurrentState {
+      port    : GpiPort(buf.get_u16()),
+      enabled : (buf.get_u8() & 0x80) != 0,
+      state   : PinState::from_value(buf.get_u8())
+    })
+  }
+}
+
+/// The level a GPO port is driven to, as reported by GET_READER_CONFIG.
+///
+/// Fields:
+/// - `port`: The GPO port.
+/// - `state`: `Low` or `High`.
+#[derive(Debug, Serialize)]
+pub struct GPOWriteData {
+  pub port  : GpoPort,
+  pub state : PinState
+}
+
+impl GPOWriteData {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("GPOWriteData", 3, buf.remaining()));
+    }
+
+    let port = GpoPort(buf.get_u16());
+    let state = if (buf.get_u8() & 0x80) != 0 { PinState::High } else { PinState::Low };
+
+    Ok(GPOWriteData { port, state })
+  }
+}
+
+/// When the reader sends KEEPALIVEs, as reported by GET_READER_CONFIG.
+///
+/// Fields:
+/// - `trigger_type`: 0 - Null (no KEEPALIVEs), 1 - Periodic.
+/// - `periodic_trigger_value`: Time in milliseconds between KEEPALIVEs of a periodic trigger.
+#[derive(Debug, Serialize)]
+pub struct KeepaliveSpec {
+  pub trigger_type           : u8,
+  pub periodic_trigger_value : u32
+}
+
+impl KeepaliveSpec {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 5 {
+      return Err(ParameterDecodeError::too_short("KeepaliveSpec", 5, buf.remaining()));
+    }
+
+    Ok(KeepaliveSpec {
+      trigger_type           : buf.get_u8(),
+      periodic_trigger_value : buf.get_u32()
+    })
+  }
+}
+
+/// A value the reader changes whenever its configuration changes, as reported by
+/// GET_READER_CONFIG. Readers are free to choose the values, so only equality is
+/// meaningful.
+///
+/// Fields:
+/// - `state_value`: The reader's current configuration state.
+#[derive(Debug, Serialize)]
+pub struct LLRPConfigurationStateValue {
+  pub state_value : u32
+}
+
+impl LLRPConfigurationStateValue {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 4 {
+      return Err(ParameterDecodeError::too_short("LLRPConfigurationStateValue", 4, buf.remaining()));
+    }
+
+    Ok(LLRPConfigurationStateValue {
+      state_value : buf.get_u32()
+    })
+  }
+}
+
+/// The ROSpecEvent `EventType` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ROSpecEventType {
+  Start,
+  End,
+  Preempted,
+  Unknown(u8)
+}
+
+impl ROSpecEventType {
+  pub fn from_value(
+    value: u8
+  ) -> Self {
+    match value {
+      0 => ROSpecEventType::Start,
+      1 => ROSpecEventType::End,
+      2 => ROSpecEventType::Preempted,
+      _ => ROSpecEventType::Unknown(value)
+    }
+  }
+}
+
+/// Start, end or preemption of an ROSpec.
+///
+/// Fields:
+/// - `event_type`: What happened to the ROSpec.
+/// - `rospec_id`: The ROSpec the event is about.
+/// - `preempting_rospec_id`: For `Preempted`, the ROSpec that took over; otherwise 0.
+#[derive(Debug, Clone)]
+pub struct ROSpecEvent {
+  pub event_type           : ROSpecEventType,
+  pub rospec_id            : u32,
+  pub preempting_rospec_id : u32
+}
+
+impl ROSpecEvent {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 9 {
+      return Err(ParameterDecodeError::too_short("ROSpecEvent", 9, buf.remaining()));
+    }
+
+    Ok(ROSpecEvent {
+      event_type           : ROSpecEventType::from_value(buf.get_u8()),
+      rospec_id            : buf.get_u32(),
+      preempting_rospec_id : buf.get_u32()
+    })
+  }
+}
+
+/// A change of level on a GPI port, reported in a ReaderEventNotification when
+/// the port is enabled.
+///
+/// Fields:
+/// - `port`: The GPI port.
+/// - `state`: The port's new level, `Low` or `High`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GPIEvent {
+  pub port  : GpiPort,
+  pub state : PinState
+}
+
+impl GPIEvent {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("GPIEvent", 3, buf.remaining()));
+    }
+
+    Ok(GPIEvent {
+      port  : GpiPort(buf.get_u16()),
+      state : if buf.get_u8() & 0x80 != 0 { PinState::High } else { PinState::Low } // GPIEvent (First bit)
+    })
+  }
+}
+
+/// The AntennaEvent `EventType` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntennaEventType {
+  Disconnected,
+  Connected,
+  Unknown(u8)
+}
+
+impl AntennaEventType {
+  pub fn from_value(
+    value: u8
+  ) -> Self {
+    match value {
+      0 => AntennaEventType::Disconnected,
+      1 => AntennaEventType::Connected,
+      _ => AntennaEventType::Unknown(value)
+    }
+  }
+}
+
+/// An antenna being attached to or removed from the reader.
+///
+/// Fields:
+/// - `event_type`: Whether the antenna was connected or disconnected.
+/// - `antenna_id`: The antenna's port.
+#[derive(Debug, Clone)]
+pub struct AntennaEvent {
+  pub event_type : AntennaEventType,
+  pub antenna_id : u16
+}
+
+impl AntennaEvent {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("AntennaEvent", 3, buf.remaining()));
+    }
+
+    Ok(AntennaEvent {
+      event_type : AntennaEventType::from_value(buf.get_u8()),
+      antenna_id : buf.get_u16()
+    })
+  }
+}
+
+/// An unexpected condition the reader reports, e.g. a hardware fault. The
+/// sub-parameters naming the affected spec or antenna are skipped.
+///
+/// Fields:
+/// - `message`: The reader's description of the exception.
+#[derive(Debug, Clone)]
+pub struct ReaderExceptionEvent {
+  pub message: String
+}
+
+impl ReaderExceptionEvent {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut buf = BytesMut::from(buf);
+
+    if buf.remaining() < 2 {
+      return Err(ParameterDecodeError::too_short("ReaderExceptionEvent", 2, buf.remaining()));
+    }
+
+    let message_length = buf.get_u16() as usize;
+
+    if buf.remaining() < message_length {
+      return Err(ParameterDecodeError::too_short("ReaderExceptionEvent.Message", message_length, buf.remaining()));
+    }
+
+    let message = String::from_utf8(buf.split_to(message_length).to_vec())
+      .map_err(|_| ParameterDecodeError::Utf8 { path: "ReaderExceptionEvent.Message".to_string() })?;
+
+    Ok(ReaderExceptionEvent { message })
+  }
+}
+
+/// The ConnectionAttemptEvent `Status` field, sent by the reader when a client
+/// connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionAttemptStatus {
+  Success,
+  ReaderInitiatedConnectionExists,
+  ClientInitiatedConnectionExists,
+  Failed,
+  AnotherConnectionAttempted,
+  Unknown(u16)
+}
+
+impl ConnectionAttemptStatus {
+
+  pub fn from_value(
+    value: u16
+  ) -> Self {
+    match value {
+      0 => ConnectionAttemptStatus::Success,
+      1 => ConnectionAttemptStatus::ReaderInitiatedConnectionExists,
+      2 => ConnectionAttemptStatus::ClientInitiatedConnectionExists,
+      3 => ConnectionAttemptStatus::Failed,
+      4 => ConnectionAttemptStatus::AnotherConnectionAttempted,
+      _ => ConnectionAttemptStatus::Unknown(value)
+    }
+  }
+
+  /// Whether the reader refused the connection because it already has one.
+  pub fn is_duplicate_connection(
+    &self
+  ) -> bool {
+    matches!(self, ConnectionAttemptStatus::ReaderInitiatedConnectionExists | ConnectionAttemptStatus::ClientInitiatedConnectionExists)
+  }
+}
+
+/// The content of a READER_EVENT_NOTIFICATION. Events without a decoder are
+/// skipped.
+///
+/// Fields:
+/// - `timestamp_us`: UTC time of the event, in microseconds since the Unix epoch.
+/// - `rospec_event`: The ROSpecEvent, if the notification carries one.
+/// - `gpi_event`: The GPIEvent, if the notification carries one.
+/// - `connection_attempt`: The ConnectionAttemptEvent status, if the notification carries one.
+/// - `antenna_event`: The AntennaEvent, if the notification carries one.
+/// - `reader_exception`: The ReaderExceptionEvent, if the notification carries one.
+/// - `report_buffer_level`: The fill level of the reader's report buffer in percent,
+///   if the notification carries a ReportBufferLevelWarningEvent.
+/// - `report_buffer_overflow`: Whether the reader's report buffer overflowed and reports were lost.
+/// - `connection_close`: Whether the reader is about to close the connection.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReaderEventNotificationData {
+  pub timestamp_us           : Option<u64>,
+  pub rospec_event           : Option<ROSpecEvent>,
+  pub gpi_event              : Option<GPIEvent>,
+  pub connection_attempt     : Option<ConnectionAttemptStatus>,
+  pub antenna_event          : Option<AntennaEvent>,
+  pub reader_exception       : Option<ReaderExceptionEvent>,
+  pub report_buffer_level    : Option<u8>,
+  pub report_buffer_overflow : bool,
+  pub connection_close       : bool
+}
+
+impl ReaderEventNotificationData {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let mut timestamp_us = None;
+    let mut rospec_event = None;
+    let mut gpi_event = None;
+    let mut connection_attempt = None;
+    let mut antenna_event = None;
+    let mut reader_exception = None;
+    let mut report_buffer_level = None;
+    let mut report_buffer_overflow = false;
+    let mut connection_close = false;
+
+    for param in parse_parameters(buf).within("ReaderEventNotificationData")? {
+      match param.param_type {
+
+        LlrpParameterType::UTCTimeStamp => {
+          let mut value = BytesMut::from(&param.param_value[..]);
+          if value.remaining() < 8 {
+            return Err(ParameterDecodeError::too_short("ReaderEventNotificationData/UTCTimeStamp", 8, value.remaining()));
+          }
+          timestamp_us = Some(value.get_u64());
+        }
+
+        LlrpParameterType::ROSpecEvent => {
+          rospec_event = Some(ROSpecEvent::decode(&param.param_value).within("ReaderEventNotificationData")?);
+        }
+
+        LlrpParameterType::GPIEvent => {
+          gpi_event = Some(GPIEvent::decode(&param.param_value).within("ReaderEventNotificationData")?);
+        }
+
+        LlrpParameterType::ConnectionAttemptEvent => {
+          let mut value = BytesMut::from(&param.param_value[..]);
+          if value.remaining() < 2 {
+            return Err(ParameterDecodeError::too_short("ReaderEventNotificationData/ConnectionAttemptEvent", 2, value.remaining()));
+          }
+          connection_attempt = Some(ConnectionAttemptStatus::from_value(value.get_u16()));
+        }
+
+        LlrpParameterType::AntennaEvent => {
+          antenna_event = Some(AntennaEvent::decode(&param.param_value).within("ReaderEventNotificationData")?);
+        }
+
+        LlrpParameterType::ReaderExceptionEvent => {
+          reader_exception = Some(ReaderExceptionEvent::decode(&param.param_value).within("ReaderEventNotificationData")?);
+        }
+
+        LlrpParameterType::ReportBufferLevelWarningEvent => {
+          let Some(&level) = param.param_value.first() else {
+            return Err(ParameterDecodeError::too_short("ReaderEventNotificationData/ReportBufferLevelWarningEvent", 1, 0));
+          };
+          report_buffer_level = Some(level);
+        }
+
+        LlrpParameterType::ReportBufferOverflowErrorEvent => report_buffer_overflow = true,
+
+        LlrpParameterType::ConnectionCloseEvent => connection_close = true,
+
+        _ => {
+          debug!("Skipping event type in ReaderEventNotificationData: {:?}", param.param_type);
+        }
+      }
+    }
+
+    Ok(ReaderEventNotificationData {
+      timestamp_us,
+      rospec_event,
+      gpi_event,
+      connection_attempt,
+      antenna_event,
+      reader_exception,
+      report_buffer_level,
+      report_buffer_overflow,
+      connection_close
+    })
+  }
+}
+
+#[derive(Debug, Serialize)]
 pub struct ROReportSpec {
   pub ro_report_trigger: u8,
   pub n: u16,
@@ -1240,31 +2713,28 @@ pub struct ROReportSpec {
 }
 
 impl ROReportSpec {
+
+  /// Decodes an ROReportSpec parsed by `parse_parameter_tree`.
   pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
+    parameter: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
 
-    let mut buf = BytesMut::from(buf);
+    let mut fields = parameter.fields();
 
-    if buf.remaining() < 3 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for ROReportSpec"
-      ));
+    if fields.remaining() < 3 {
+      return Err(ParameterDecodeError::too_short("ROReportSpec", 3, fields.remaining()));
     }
 
-    let ro_report_trigger = buf.get_u8();
-    let n = buf.get_u16();
+    let ro_report_trigger = fields.get_u8();
+    let n = fields.get_u16();
 
-    // Decode sub-parameters
-    let sub_parameters = parse_parameters(buf.chunk())?;
     let mut tag_report_content_selector = None;
 
-    for param in sub_parameters {
+    for param in parameter.children() {
       match param.param_type {
 
         LlrpParameterType::TagReportContentSelector => {
-          tag_report_content_selector = Some(TagReportContentSelector::decode(&param.param_value)?);
+          tag_report_content_selector = Some(TagReportContentSelector::decode(param).within("ROReportSpec")?);
         }
 
         LlrpParameterType::Custom => {
@@ -1288,7 +2758,8 @@ impl ROReportSpec {
   }
 }
 
-#[derive(Debug)]
+/// The TagReportContentSelector of an ROReportSpec, as reported by GET_READER_CONFIG.
+#[derive(Debug, Serialize)]
 pub struct TagReportContentSelector {
   pub enable_rospec_id: bool,
   pub enable_spec_index: bool,
@@ -1300,23 +2771,23 @@ pub struct TagReportContentSelector {
   pub enable_last_seen_timestamp: bool,
   pub enable_tag_seen_count: bool,
   pub enable_access_spec_id: bool,
+  pub c1g2_epc_memory_selector: Option<C1G2EPCMemorySelector>,
 }
 
 impl TagReportContentSelector {
+
+  /// Decodes a TagReportContentSelector parsed by `parse_parameter_tree`.
   pub fn decode(
-    buf: &[u8]
-  ) -> io::Result<Self> {
+    parameter: &LlrpParameter
+  ) -> Result<Self, ParameterDecodeError> {
 
-    let mut buf = BytesMut::from(buf);
+    let mut fields = parameter.fields();
 
-    if buf.remaining() < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Buffer too short for TagReportContentSelector"
-      ));
+    if fields.remaining() < 2 {
+      return Err(ParameterDecodeError::too_short("TagReportContentSelector", 2, fields.remaining()));
     }
 
-    let flags = buf.get_u16();
+    let flags = fields.get_u16();
     let enable_rospec_id            = (flags & 0x8000) != 0;
     let enable_spec_index           = (flags & 0x4000) != 0;
     let enable_inventory_spec_id    = (flags & 0x2000) != 0;
@@ -1328,6 +2799,13 @@ impl TagReportContentSelector {
     let enable_tag_seen_count       = (flags & 0x0080) != 0;
     let enable_access_spec_id       = (flags & 0x0040) != 0;
 
+    let mut c1g2_epc_memory_selector = None;
+    for param in parameter.children() {
+      if param.param_type == LlrpParameterType::C1G2EPCMemorySelector {
+        c1g2_epc_memory_selector = Some(C1G2EPCMemorySelector::decode(&param.param_value).within("TagReportContentSelector")?);
+      }
+    }
+
     Ok(TagReportContentSelector {
       enable_rospec_id,
       enable_spec_index,
@@ -1338,12 +2816,40 @@ impl TagReportContentSelector {
       enable_first_seen_timestamp,
       enable_last_seen_timestamp,
       enable_tag_seen_count,
-      enable_access_spec_id
+      enable_access_spec_id,
+      c1g2_epc_memory_selector
+    })
+  }
+}
+
+/// Which C1G2 memory the reader reports with each tag.
+///
+/// Fields:
+/// - `enable_crc` / `enable_pc_bits`: The tag's CRC and PC bits.
+/// - `enable_xpc_bits`: The tag's XPC words; always false before LLRP 1.1.
+#[derive(Debug, Serialize)]
+pub struct C1G2EPCMemorySelector {
+  pub enable_crc      : bool,
+  pub enable_pc_bits  : bool,
+  pub enable_xpc_bits : bool
+}
+
+impl C1G2EPCMemorySelector {
+  pub fn decode(
+    buf: &[u8]
+  ) -> Result<Self, ParameterDecodeError> {
+
+    let flags = *buf.first().ok_or_else(|| ParameterDecodeError::too_short("C1G2EPCMemorySelector", 1, 0))?;
+
+    Ok(C1G2EPCMemorySelector {
+      enable_crc      : flags & 0x80 != 0,
+      enable_pc_bits  : flags & 0x40 != 0,
+      enable_xpc_bits : flags & 0x20 != 0
     })
   }
 }
 
-pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
+pub fn parse_parameters(buf: &[u8]) -> Result<Vec<LlrpParameter>, ParameterDecodeError> {
 
   let mut parameters = Vec::new();
   let mut index = 0;
@@ -1352,10 +2858,7 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
   while index < buf_len {
 
     if buf_len - index < 1 {
-      return Err(Error::new(
-        ErrorKind::InvalidData,
-        "Insufficient data for parameter parsing",
-      ));
+      return Err(ParameterDecodeError::too_short("", 1, buf_len - index));
     }
 
     let first_byte = buf[index];
@@ -1365,22 +2868,20 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
       index += 1;
 
       let param_type = LlrpParameterType::from_value(param_type_value as u16);
-      let param_value_length = get_tv_param_length(param_type.unwrap_or(LlrpParameterType::Custom));
+      let param_value_length = get_tv_param_length(param_type.unwrap_or(LlrpParameterType::Unknown));
       
       if let Some(param_value_length) = param_value_length {
 
         if buf_len - index < param_value_length {
-          return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Buffer too short for TV parameter value",
-          ));
+          return Err(ParameterDecodeError::too_short(parameter_name(param_type_value as u16), param_value_length, buf_len - index));
         }
 
         let param_value = buf[index..index + param_value_length].to_vec();
         index += param_value_length;
 
         let parameter = LlrpParameter {
-          param_type: param_type.unwrap_or(LlrpParameterType::Custom),
+          param_type: param_type.unwrap_or(LlrpParameterType::Unknown),
+          param_type_value: param_type_value as u16,
           param_length: (1 + param_value_length) as u16,
           param_value,
           sub_params: None,
@@ -1389,19 +2890,14 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
         parameters.push(parameter);
 
       } else {
-        return Err(Error::new(
-          ErrorKind::InvalidData,
-          format!("Unknown TV parameter length for parameter type {:?}", param_type),
-        ));
+        // Without its length the rest of the buffer cannot be split into parameters.
+        return Err(ParameterDecodeError::UnknownType { path: String::new(), param_type: param_type_value as u16 });
       }
 
     } else {
 
       if buf_len - index < 4 {
-        return Err(Error::new(
-          ErrorKind::InvalidData,
-          "Buffer too short for TLV parameter header",
-        ));
+        return Err(ParameterDecodeError::too_short("", 4, buf_len - index));
       }
 
       let param_type_value = ((buf[index] as u16) << 8) | buf[index + 1] as u16;
@@ -1411,10 +2907,7 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
       index += 2;
 
       if param_length < 4 || (param_length - 4) as usize > (buf_len - index) {
-        return Err(Error::new(
-          ErrorKind::InvalidData,
-          "Invalid TLV parameter length",
-        ));
+        return Err(ParameterDecodeError::BadLength { path: parameter_name(param_type_value), length: param_length as usize });
       }
 
       let param_value_length = (param_length - 4) as usize;
@@ -1423,7 +2916,8 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
 
       let param_type = LlrpParameterType::from_value(param_type_value);
       let parameter = LlrpParameter {
-        param_type: param_type.unwrap_or(LlrpParameterType::Custom),
+        param_type: param_type.unwrap_or(LlrpParameterType::Unknown),
+        param_type_value,
         param_length,
         param_value,
         sub_params: None,
@@ -1436,9 +2930,78 @@ pub fn parse_parameters(buf: &[u8]) -> io::Result<Vec<LlrpParameter>> {
   Ok(parameters)
 }
 
+/// Parses `buf` like `parse_parameters`, and also the sub-parameters of every
+/// parameter whose type has a known layout, recursively, into `sub_params`.
+pub fn parse_parameter_tree(
+  buf: &[u8]
+) -> Result<Vec<LlrpParameter>, ParameterDecodeError> {
+
+  let mut parameters = parse_parameters(buf)?;
+
+  for parameter in &mut parameters {
+    if let Some(fields_length) = sub_parameter_offset(parameter.param_type) {
+
+      let name = parameter_name(parameter.param_type_value);
+      if parameter.param_value.len() < fields_length {
+        return Err(ParameterDecodeError::too_short(&name, fields_length, parameter.param_value.len()));
+      }
+
+      parameter.sub_params = Some(parse_parameter_tree(&parameter.param_value[fields_length..]).within(&name)?);
+    }
+  }
+
+  Ok(parameters)
+}
+
+/// Length of the fields ahead of the sub-parameters, for the parameter types
+/// `parse_parameter_tree` descends into.
+fn sub_parameter_offset(
+  param_type: LlrpParameterType
+) -> Option<usize> {
+  match param_type {
+    LlrpParameterType::AntennaConfiguration        => Some(2),
+    LlrpParameterType::C1G2InventoryCommand        => Some(1),
+    LlrpParameterType::ReaderEventNotificationSpec => Some(0),
+    LlrpParameterType::ROReportSpec                => Some(3),
+    LlrpParameterType::TagReportContentSelector    => Some(2),
+    _ => None
+  }
+}
+
+/// The name of a parameter type for decode error paths.
+fn parameter_name(
+  param_type_value: u16
+) -> String {
+  match LlrpParameterType::from_value(param_type_value) {
+    Some(param_type) => format!("{:?}", param_type),
+    None             => format!("Parameter{}", param_type_value)
+  }
+}
+
+/// Length of the value of a TV-encoded parameter, which TV parameters do not carry
+/// themselves. Covers every TV parameter LLRP 1.0.1 and 1.1 define (types 1-20).
 pub fn get_tv_param_length(param_type: LlrpParameterType) -> Option<usize> {
   match param_type {
-    LlrpParameterType::EPC96 => Some(12),
+    LlrpParameterType::AntennaID                 => Some(2),
+    LlrpParameterType::FirstSeenTimestampUTC     => Some(8),
+    LlrpParameterType::FirstSeenTimestampUptime  => Some(8),
+    LlrpParameterType::LastSeenTimestampUTC      => Some(8),
+    LlrpParameterType::LastSeenTimestampUptime   => Some(8),
+    LlrpParameterType::PeakRSSI                  => Some(1),
+    LlrpParameterType::ChannelIndex              => Some(2),
+    LlrpParameterType::TagSeenCount              => Some(2),
+    LlrpParameterType::ROSpecID                  => Some(4),
+    LlrpParameterType::InventoryParameterSpecID  => Some(2),
+    LlrpParameterType::C1G2CRC                   => Some(2),
+    LlrpParameterType::C1G2PC                    => Some(2),
+    LlrpParameterType::EPC96                     => Some(12),
+    LlrpParameterType::SpecIndex                 => Some(2),
+    LlrpParameterType::ClientRequestOpSpecResult => Some(2),
+    LlrpParameterType::AccessSpecID              => Some(4),
+    LlrpParameterType::OpSpecID                  => Some(2),
+    LlrpParameterType::C1G2SingulationDetails    => Some(4),
+    LlrpParameterType::C1G2XPCW1                 => Some(2),
+    LlrpParameterType::C1G2XPCW2                 => Some(2),
     _ => None
   }
 }
\ No newline at end of file
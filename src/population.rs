@@ -0,0 +1,395 @@
+//! Deduplication of tag reads for counts of very large tag populations.
+//!
+//! The set of EPCs seen grows with the population, so past `memory_limit` it moves
+//! to a sled store in a temporary directory and stays there for the rest of the
+//! count. The directory is removed when the count is dropped. The store needs the
+//! `spill` feature; without it the set stays in memory.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serde::Serialize;
+
+use crate::config::LargePopulationConfig;
+use crate::params::TagReportData;
+
+/// Page cache of the on-disk store, kept small since lookups are random.
+#[cfg(feature = "spill")]
+const SPILL_CACHE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The running population count of a client, if any, replaced when a new count starts.
+pub type SharedPopulationCount = Arc<Mutex<Option<PopulationCount>>>;
+
+/// The set of EPCs seen, held in memory up to a limit and on disk beyond it.
+pub struct UniqueTagSet {
+  memory_limit : usize,
+  spill_dir    : PathBuf,
+  in_memory    : HashSet<Vec<u8>>,
+  spilled      : Option<SpillStore>,
+  len          : u64
+}
+
+impl UniqueTagSet {
+
+  pub fn new(
+    memory_limit : usize,
+    spill_dir    : Option<&Path>
+  ) -> Self {
+    UniqueTagSet {
+      memory_limit : memory_limit.max(1),
+      spill_dir    : spill_dir.map(Path::to_path_buf).unwrap_or_else(std::env::temp_dir),
+      in_memory    : HashSet::new(),
+      spilled      : None,
+      len          : 0
+    }
+  }
+
+  pub fn contains(
+    &self,
+    epc: &[u8]
+  ) -> io::Result<bool> {
+    match &self.spilled {
+      Some(store) => store.contains(epc),
+      None => Ok(self.in_memory.contains(epc))
+    }
+  }
+
+  /// Adds `epcs`, none of which may be in the set yet. Either all are added or,
+  /// on error, none.
+  pub fn insert_all(
+    &mut self,
+    epcs: Vec<Vec<u8>>
+  ) -> io::Result<()> {
+
+    let count = epcs.len() as u64;
+
+    match &self.spilled {
+
+      Some(store) => store.insert_all(&epcs)?,
+
+      None if self.in_memory.len() + epcs.len() > self.memory_limit => {
+        match SpillStore::create(&self.spill_dir, self.in_memory.iter().chain(&epcs))? {
+          Some(store) => {
+            log::info!("Unique tag set passed {} EPCs, spilled to {}", self.memory_limit, store.path().display());
+            self.in_memory = HashSet::new();
+            self.spilled = Some(store);
+          }
+          None => {
+            if self.in_memory.len() <= self.memory_limit {
+              log::warn!("Unique tag set passed {} EPCs and stays in memory, as this build has no \"spill\" feature", self.memory_limit);
+            }
+            self.in_memory.extend(epcs);
+          }
+        }
+      }
+
+      None => self.in_memory.extend(epcs)
+    }
+
+    self.len += count;
+
+    Ok(())
+  }
+
+  pub fn len(
+    &self
+  ) -> u64 {
+    self.len
+  }
+
+  pub fn is_empty(
+    &self
+  ) -> bool {
+    self.len == 0
+  }
+
+  /// Whether the set has moved to disk.
+  pub fn is_spilled(
+    &self
+  ) -> bool {
+    self.spilled.is_some()
+  }
+}
+
+/// The on-disk store a `UniqueTagSet` moves to past its memory limit. Builds
+/// without the `spill` feature have none, and their sets stay in memory.
+#[cfg(feature = "spill")]
+struct SpillStore {
+  db   : sled::Db,
+  path : PathBuf
+}
+
+#[cfg(feature = "spill")]
+impl SpillStore {
+
+  /// Creates a store in a new directory of `dir` holding `epcs`.
+  fn create<'a>(
+    dir  : &Path,
+    epcs : impl Iterator<Item = &'a Vec<u8>>
+  ) -> io::Result<Option<Self>> {
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static SPILL_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    let path = dir.join(format!(
+      "llrp-unique-tags-{}-{}",
+      std::process::id(),
+      SPILL_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let db = sled::Config::new()
+      .path(&path)
+      .temporary(true)
+      .cache_capacity(SPILL_CACHE_BYTES)
+      .open()?;
+
+    // Constructed before it is filled, so a failed fill removes the directory.
+    let store = SpillStore { db, path };
+    store.insert_all(epcs)?;
+
+    Ok(Some(store))
+  }
+
+  fn path(
+    &self
+  ) -> &Path {
+    &self.path
+  }
+
+  fn contains(
+    &self,
+    epc: &[u8]
+  ) -> io::Result<bool> {
+    Ok(self.db.contains_key(epc)?)
+  }
+
+  /// Adds `epcs` in one atomic batch.
+  fn insert_all<'a>(
+    &self,
+    epcs: impl IntoIterator<Item = &'a Vec<u8>>
+  ) -> io::Result<()> {
+
+    let mut batch = sled::Batch::default();
+    for epc in epcs {
+      batch.insert(epc.as_slice(), &[]);
+    }
+
+    Ok(self.db.apply_batch(batch)?)
+  }
+}
+
+#[cfg(feature = "spill")]
+impl Drop for SpillStore {
+  /// Sled removes a temporary store once its background threads let go of it, which
+  /// may be after the store is dropped, so the directory is removed here as well.
+  fn drop(
+    &mut self
+  ) {
+    if let Err(e) = std::fs::remove_dir_all(&self.path) {
+      if e.kind() != io::ErrorKind::NotFound {
+        log::warn!("Failed to remove unique tag store {}: {}", self.path.display(), e);
+      }
+    }
+  }
+}
+
+#[cfg(not(feature = "spill"))]
+enum SpillStore {}
+
+#[cfg(not(feature = "spill"))]
+impl SpillStore {
+
+  fn create<'a>(
+    _dir  : &Path,
+    _epcs : impl Iterator<Item = &'a Vec<u8>>
+  ) -> io::Result<Option<Self>> {
+    Ok(None)
+  }
+
+  fn path(
+    &self
+  ) -> &Path {
+    match *self {}
+  }
+
+  fn contains(
+    &self,
+    _epc: &[u8]
+  ) -> io::Result<bool> {
+    match *self {}
+  }
+
+  fn insert_all<'a>(
+    &self,
+    _epcs: impl IntoIterator<Item = &'a Vec<u8>>
+  ) -> io::Result<()> {
+    match *self {}
+  }
+}
+
+/// Progress of a population count.
+///
+/// Fields:
+/// - `unique_tags`: Number of distinct EPCs seen.
+/// - `tag_reads`: Number of tag reads counted, repeats included.
+/// - `spilled`: Whether the EPCs seen are held on disk.
+/// - `elapsed_ms`: Time since the count started.
+#[derive(Debug, Clone, Serialize)]
+pub struct PopulationProgress {
+  pub unique_tags : u64,
+  pub tag_reads   : u64,
+  pub spilled     : bool,
+  pub elapsed_ms  : u64
+}
+
+/// A count of the distinct tags in the field, reporting progress each time the
+/// unique count reaches a multiple of `milestone_interval`.
+pub struct PopulationCount {
+  unique_tags        : UniqueTagSet,
+  milestone_interval : u64,
+  tag_reads          : u64,
+  started_at         : Instant
+}
+
+impl PopulationCount {
+
+  pub fn new(
+    config: &LargePopulationConfig
+  ) -> Self {
+    PopulationCount {
+      unique_tags        : UniqueTagSet::new(config.memory_limit, config.spill_dir.as_deref()),
+      milestone_interval : config.milestone_interval.max(1),
+      tag_reads          : 0,
+      started_at         : Instant::now()
+    }
+  }
+
+  /// Keeps the tags of `tag_reports` read for the first time in this count, and
+  /// returns the progress at each milestone they reach, in order. On error neither
+  /// `tag_reports` nor the count changes.
+  pub fn retain_new(
+    &mut self,
+    tag_reports: &mut Vec<TagReportData>
+  ) -> io::Result<Vec<PopulationProgress>> {
+
+    let mut new_epcs = Vec::new();
+    let mut new_in_batch = HashSet::new();
+    // The tag reads counted up to each new EPC, for the milestones it reaches.
+    let mut reads_at_new = Vec::new();
+    let mut is_new = Vec::with_capacity(tag_reports.len());
+    let mut tag_reads = self.tag_reads;
+
+    for tag_report in tag_reports.iter() {
+
+      tag_reads += tag_report.tag_seen_count.unwrap_or(1).max(1) as u64;
+
+      let new = !new_in_batch.contains(&tag_report.epc) && !self.unique_tags.contains(&tag_report.epc)?;
+      if new {
+        new_in_batch.insert(&tag_report.epc);
+        new_epcs.push(tag_report.epc.clone());
+        reads_at_new.push(tag_reads);
+      }
+
+      is_new.push(new);
+    }
+
+    let unique_before = self.unique_tags.len();
+    self.unique_tags.insert_all(new_epcs)?;
+    self.tag_reads = tag_reads;
+
+    let elapsed_ms = self.started_at.elapsed().as_millis() as u64;
+    let milestones = (unique_before + 1..).zip(reads_at_new)
+      .filter(|(unique_tags, _)| unique_tags.is_multiple_of(self.milestone_interval))
+      .map(|(unique_tags, tag_reads)| PopulationProgress {
+        unique_tags,
+        tag_reads,
+        spilled : self.unique_tags.is_spilled(),
+        elapsed_ms
+      })
+      .collect();
+
+    let mut is_new = is_new.into_iter();
+    tag_reports.retain(|_| is_new.next().unwrap_or(false));
+
+    Ok(milestones)
+  }
+
+  pub fn progress(
+    &self
+  ) -> PopulationProgress {
+    PopulationProgress {
+      unique_tags : self.unique_tags.len(),
+      tag_reads   : self.tag_reads,
+      spilled     : self.unique_tags.is_spilled(),
+      elapsed_ms  : self.started_at.elapsed().as_millis() as u64
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::llrp::ReceiveTimestamp;
+
+  /// TagReportData holding only an EPC-96 whose last byte is `serial`.
+  fn tag(
+    serial: u8
+  ) -> TagReportData {
+
+    let mut buf = vec![0x8D, 0xE2, 0x80, 0x11, 0x60, 0, 0, 0, 0, 0, 0, 0];
+    buf.push(serial);
+
+    TagReportData::decode(&buf, ReceiveTimestamp::now()).unwrap()
+  }
+
+  #[test]
+  fn milestones_carry_the_counts_at_the_tag_reaching_them() {
+
+    let config = LargePopulationConfig { memory_limit: 10, milestone_interval: 2, spill_dir: None };
+    let mut count = PopulationCount::new(&config);
+
+    let mut tag_reports = vec![tag(1), tag(1), tag(2), tag(3), tag(4), tag(4)];
+    let milestones = count.retain_new(&mut tag_reports).unwrap();
+    assert_eq!(tag_reports.iter().map(|tag| tag.epc[11]).collect::<Vec<u8>>(), vec![1, 2, 3, 4]);
+    assert_eq!(
+      milestones.iter().map(|progress| (progress.unique_tags, progress.tag_reads)).collect::<Vec<(u64, u64)>>(),
+      vec![(2, 3), (4, 5)]
+    );
+    assert_eq!((count.progress().unique_tags, count.progress().tag_reads), (4, 6));
+  }
+
+  #[cfg(feature = "spill")]
+  #[test]
+  fn count_spills_to_disk_and_reports_milestones() {
+
+    let spill_dir = std::env::temp_dir().join(format!("llrp-population-test-{}", std::process::id()));
+    std::fs::create_dir_all(&spill_dir).unwrap();
+
+    let config = LargePopulationConfig { memory_limit: 3, milestone_interval: 2, spill_dir: Some(spill_dir.clone()) };
+    let mut count = PopulationCount::new(&config);
+
+    let mut tag_reports = vec![tag(1), tag(2), tag(1), tag(3)];
+    let milestones = count.retain_new(&mut tag_reports).unwrap();
+    assert_eq!(tag_reports.iter().map(|tag| tag.epc[11]).collect::<Vec<u8>>(), vec![1, 2, 3]);
+    assert_eq!(milestones.iter().map(|progress| progress.unique_tags).collect::<Vec<u64>>(), vec![2]);
+    assert!(!count.progress().spilled);
+
+    // The fourth EPC passes the memory limit; those seen before must still be known.
+    let mut tag_reports = vec![tag(4), tag(2), tag(5), tag(3), tag(5)];
+    let milestones = count.retain_new(&mut tag_reports).unwrap();
+    assert_eq!(tag_reports.iter().map(|tag| tag.epc[11]).collect::<Vec<u8>>(), vec![4, 5]);
+    assert_eq!(milestones.iter().map(|progress| progress.unique_tags).collect::<Vec<u64>>(), vec![4]);
+
+    let progress = count.progress();
+    assert!(progress.spilled);
+    assert_eq!((progress.unique_tags, progress.tag_reads), (5, 9));
+
+    drop(count);
+    assert_eq!(std::fs::read_dir(&spill_dir).unwrap().count(), 0);
+    std::fs::remove_dir(&spill_dir).unwrap();
+  }
+}
\ No newline at end of file
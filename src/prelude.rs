@@ -0,0 +1,29 @@
+//! The types most programs built on the client need, for a single glob import:
+//!
+//! ```
+//! use llrp_lib::prelude::*;
+//!
+//! let rospec = ROSpecBuilder::new(1)
+//!   .boundary_spec(ROBoundarySpec::periodic(0, 10_000, 2_000))
+//!   .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]))
+//!   .report_spec(ROReportSpec::end_of_rospec(TagReportContentConfig::default()))
+//!   .build();
+//! ```
+//!
+//! Everything exported here is part of the stable API. Enums and structs that
+//! grow with the protocol, such as [`LlrpResponseData`] and [`TagReportData`], are
+//! `#[non_exhaustive]`, so adding a message type, parameter or field is not a
+//! breaking change: match them with a wildcard arm and read their fields rather
+//! than constructing them.
+
+pub use crate::alerts::{Alert, AlertKind};
+pub use crate::client::{ClientOptions, FrameDirection, LlrpClient};
+pub use crate::config::{load_config, Config, ROReportTrigger, ROSpecConfig, ReaderConfig, TagReportContentConfig};
+pub use crate::custom::{CustomMessage, CustomParameter};
+pub use crate::error::{LlrpError, LlrpStatusError};
+pub use crate::gpio::{GpiPort, GpoPort, PinState};
+pub use crate::history::{ConnectionEvent, ConnectionEventKind};
+pub use crate::llrp::{LlrpMessageType, LlrpResponse, LlrpResponseData, LlrpVersion, ReaderConfigData, ReaderConfigRequest, ReceiveTimestamp};
+pub use crate::params::{AntennaConfiguration, AntennaEvent, AntennaEventType, AntennaProperties, GPIEvent, GPIPortCurrentState, LlrpParameterData, ParameterDecodeError, ROSpecEvent, ROSpecEventType, ReaderEventNotificationData, ReaderExceptionEvent, TagReportData};
+pub use crate::rospec::{AISpec, InventoryParameterSpec, ROBoundarySpec, ROReportSpec, ROSpec, ROSpecBuilder};
+pub use crate::tag_stream::TagReportStream;
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::config::RegionPreset;
+use crate::params::RegulatoryCapabilities;
+
+/// A regulatory region, as identified by the `CommunicationsStandard` a reader
+/// reports in RegulatoryCapabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Region {
+  Fcc,
+  Etsi,
+  HongKong,
+  Taiwan,
+  Korea
+}
+
+impl Region {
+
+  /// Maps a CommunicationsStandard value to its region. All three ETSI standards
+  /// (EN 300 220, EN 302 208 with and without LBT) map to `Etsi`; 0 (Unspecified)
+  /// and unknown values map to `None`.
+  pub fn from_communications_standard(
+    value: u16
+  ) -> Option<Self> {
+    match value {
+      1     => Some(Region::Fcc),
+      2..=4 => Some(Region::Etsi),
+      5     => Some(Region::HongKong),
+      6     => Some(Region::Taiwan),
+      7     => Some(Region::Korea),
+      _     => None
+    }
+  }
+}
+
+impl fmt::Display for Region {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    let name = match self {
+      Region::Fcc      => "FCC",
+      Region::Etsi     => "ETSI",
+      Region::HongKong => "Hong Kong",
+      Region::Taiwan   => "Taiwan",
+      Region::Korea    => "Korea"
+    };
+    write!(f, "{}", name)
+  }
+}
+
+/// The RF settings a region preset resolves to for a particular reader.
+///
+/// Fields:
+/// - `region`: The reader's regulatory region.
+/// - `hop_table_id` / `channel_index`: RF channel for `ReaderConfig`.
+/// - `tx_power_table_index`: Transmit power override from the preset, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionSettings {
+  pub region               : Region,
+  pub hop_table_id         : u16,
+  pub channel_index        : u16,
+  pub tx_power_table_index : Option<u16>
+}
+
+/// Picks the preset matching the reader's regulatory region and resolves it
+/// against the frequencies the reader reports.
+///
+/// Hopping readers use the preset's `hop_table_id`, or their first hop table, with
+/// channel index 0. Fixed-frequency readers use the 1-based index of the preset's
+/// `frequency_khz` in their FixedFrequencyTable, or its first channel, with hop
+/// table 0. Returns `Ok(None)` when the reader's region is unspecified or no
+/// preset covers it, and an error when the preset asks for a channel the reader
+/// does not offer.
+pub fn resolve_region_preset(
+  presets      : &[RegionPreset],
+  capabilities : &RegulatoryCapabilities
+) -> Result<Option<RegionSettings>, String> {
+
+  let Some((region, preset)) = Region::from_communications_standard(capabilities.communications_standard)
+    .and_then(|region| Some((region, presets.iter().find(|preset| preset.region == region)?))) else {
+    return Ok(None);
+  };
+
+  let frequency_information = capabilities.uhf_band_capabilities.as_ref()
+    .and_then(|uhf| uhf.frequency_information.as_ref())
+    .ok_or_else(|| format!("reader in region {} reports no FrequencyInformation", region))?;
+
+  let (hop_table_id, channel_index) = if frequency_information.hopping {
+
+    let hop_tables = &frequency_information.frequency_hop_tables;
+    let hop_table = match preset.hop_table_id {
+      Some(hop_table_id) => hop_tables.iter().find(|table| table.hop_table_id == hop_table_id)
+        .ok_or_else(|| format!("reader in region {} has no hop table {}", region, hop_table_id))?,
+      None => hop_tables.first()
+        .ok_or_else(|| format!("reader in region {} reports no hop tables", region))?
+    };
+
+    (hop_table.hop_table_id, 0)
+
+  } else {
+
+    let frequencies = frequency_information.fixed_frequency_table.as_ref()
+      .map(|table| table.frequencies.as_slice())
+      .unwrap_or_default();
+
+    let position = match preset.frequency_khz {
+      Some(frequency_khz) => frequencies.iter().position(|frequency| *frequency == frequency_khz)
+        .ok_or_else(|| format!("reader in region {} has no fixed channel at {} kHz", region, frequency_khz))?,
+      None if !frequencies.is_empty() => 0,
+      None => return Err(format!("reader in region {} reports no fixed frequencies", region))
+    };
+
+    (0, position as u16 + 1)
+  };
+
+  Ok(Some(RegionSettings {
+    region,
+    hop_table_id,
+    channel_index,
+    tx_power_table_index: preset.tx_power_table_index
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::params::{FixedFrequencyTable, FrequencyHopTable, FrequencyInformation, UHFBandCapabilities};
+
+  fn capabilities(
+    communications_standard : u16,
+    frequency_information   : FrequencyInformation
+  ) -> RegulatoryCapabilities {
+    RegulatoryCapabilities {
+      country_code          : 0,
+      communications_standard,
+      uhf_band_capabilities : Some(UHFBandCapabilities {
+        transmit_power_levels  : Vec::new(),
+        frequency_information  : Some(frequency_information),
+        c1g2_uhf_rf_mode_table : None
+      })
+    }
+  }
+
+  fn preset(
+    region        : Region,
+    frequency_khz : Option<u32>
+  ) -> RegionPreset {
+    RegionPreset { region, hop_table_id: None, frequency_khz, tx_power_table_index: None }
+  }
+
+  #[test]
+  fn hopping_region_uses_first_hop_table() {
+
+    let capabilities = capabilities(1, FrequencyInformation {
+      hopping               : true,
+      frequency_hop_tables  : vec![
+        FrequencyHopTable { hop_table_id: 3, number_of_hops: 2, frequencies: vec![902750, 903250] },
+        FrequencyHopTable { hop_table_id: 4, number_of_hops: 1, frequencies: vec![915250] }
+      ],
+      fixed_frequency_table : None
+    });
+
+    let presets = [preset(Region::Etsi, Some(866900)), preset(Region::Fcc, None)];
+    let settings = resolve_region_preset(&presets, &capabilities).unwrap().unwrap();
+
+    assert_eq!(settings.region, Region::Fcc);
+    assert_eq!((settings.hop_table_id, settings.channel_index), (3, 0));
+  }
+
+  #[test]
+  fn fixed_frequency_region_uses_preset_channel() {
+
+    let capabilities = capabilities(3, FrequencyInformation {
+      hopping               : false,
+      frequency_hop_tables  : Vec::new(),
+      fixed_frequency_table : Some(FixedFrequencyTable { frequencies: vec![865700, 866300, 866900, 867500] })
+    });
+
+    let settings = resolve_region_preset(&[preset(Region::Etsi, Some(866900))], &capabilities).unwrap().unwrap();
+    assert_eq!((settings.hop_table_id, settings.channel_index), (0, 3));
+
+    assert!(resolve_region_preset(&[preset(Region::Etsi, Some(868000))], &capabilities).is_err());
+    assert!(resolve_region_preset(&[preset(Region::Fcc, None)], &capabilities).unwrap().is_none());
+  }
+}
\ No newline at end of file
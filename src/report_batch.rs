@@ -0,0 +1,105 @@
+//! C layout of the tag report batches passed to the tag report batch callback.
+//!
+//! The layout is versioned so host-side marshaling stays valid as fields are
+//! added: fields are only ever appended, `struct_version` is bumped with each
+//! addition, and hosts step through `records` by `record_size` rather than their
+//! own idea of the record size. A host built against version N can read any batch
+//! with `struct_version >= N`.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use crate::params::TagReportData;
+
+/// Current version of `LlrpTagReportBatch` and `LlrpTagRecord`.
+///
+/// Version history:
+/// - 1: EPC, antenna, peak RSSI, seen count and first/last seen timestamps.
+/// - 2: ROSpec ID, channel index and InventoryParameterSpec ID.
+pub const TAG_REPORT_BATCH_VERSION: u32 = 2;
+
+/// A single tag observation. Fields the reader did not report are zero.
+///
+/// Fields:
+/// - `epc` / `epc_length`: The tag EPC bytes.
+/// - `antenna_id`: Antenna that last saw the tag.
+/// - `tag_seen_count`: Number of times the tag was read since the last report.
+/// - `peak_rssi`: Highest RSSI of the tag's reads, in dBm, valid when `has_peak_rssi` is 1.
+/// - `first_seen_us` / `last_seen_us`: UTC time the tag was first and last read, in
+///   microseconds since the Unix epoch.
+/// - `rospec_id`: ROSpec whose inventory read the tag (version 2).
+/// - `channel_index`: 1-based index of the channel the tag was last read on (version 2).
+/// - `inventory_parameter_spec_id`: InventoryParameterSpec whose inventory read the tag (version 2).
+#[repr(C)]
+pub struct LlrpTagRecord {
+  pub epc                         : *const u8,
+  pub epc_length                  : u32,
+  pub antenna_id                  : u16,
+  pub tag_seen_count              : u16,
+  pub peak_rssi                   : i8,
+  pub has_peak_rssi               : u8,
+  pub reserved                    : [u8; 6],
+  pub first_seen_us               : u64,
+  pub last_seen_us                : u64,
+  pub rospec_id                   : u32,
+  pub channel_index               : u16,
+  pub inventory_parameter_spec_id : u16
+}
+
+/// The tags of one ROAccessReport.
+///
+/// Fields:
+/// - `struct_version`: `TAG_REPORT_BATCH_VERSION` of the library that built the batch.
+/// - `struct_size` / `record_size`: Size in bytes of the batch and of each record.
+/// - `record_count`: Number of records at `records`.
+/// - `reader_id`: Identifier of the reader that sent the report, NUL-terminated.
+/// - `received_at_us`: UTC time the client received the report, in microseconds since the Unix epoch.
+#[repr(C)]
+pub struct LlrpTagReportBatch {
+  pub struct_version : u32,
+  pub struct_size    : u32,
+  pub record_size    : u32,
+  pub record_count   : u32,
+  pub reader_id      : *const c_char,
+  pub received_at_us : u64,
+  pub records        : *const LlrpTagRecord
+}
+
+/// Lays out `tag_reports` as a batch and passes it to `deliver`. The batch and
+/// everything it points to are only valid for the duration of the call.
+pub fn with_tag_report_batch<F>(
+  reader_id   : &CStr,
+  tag_reports : &[TagReportData],
+  deliver     : F
+)
+where
+  F: FnOnce(*const LlrpTagReportBatch)
+{
+
+  let records: Vec<LlrpTagRecord> = tag_reports.iter().map(|tag_report| LlrpTagRecord {
+    epc                         : tag_report.epc.as_ptr(),
+    epc_length                  : tag_report.epc.len() as u32,
+    antenna_id                  : tag_report.antenna_id.unwrap_or(0),
+    tag_seen_count              : tag_report.tag_seen_count.unwrap_or(0),
+    peak_rssi                   : tag_report.peak_rssi.unwrap_or(0),
+    has_peak_rssi               : tag_report.peak_rssi.is_some() as u8,
+    reserved                    : [0; 6],
+    first_seen_us               : tag_report.first_seen_us.unwrap_or(0),
+    last_seen_us                : tag_report.last_seen_us.unwrap_or(0),
+    rospec_id                   : tag_report.rospec_id.unwrap_or(0),
+    channel_index               : tag_report.channel_index.unwrap_or(0),
+    inventory_parameter_spec_id : tag_report.inventory_parameter_spec_id.unwrap_or(0)
+  }).collect();
+
+  let batch = LlrpTagReportBatch {
+    struct_version : TAG_REPORT_BATCH_VERSION,
+    struct_size    : std::mem::size_of::<LlrpTagReportBatch>() as u32,
+    record_size    : std::mem::size_of::<LlrpTagRecord>() as u32,
+    record_count   : records.len() as u32,
+    reader_id      : reader_id.as_ptr(),
+    received_at_us : tag_reports.first().map_or(0, |tag_report| tag_report.received_at.utc_us as u64),
+    records        : records.as_ptr()
+  };
+
+  deliver(&batch);
+}
\ No newline at end of file
@@ -0,0 +1,796 @@
+//! ROSpecs composed in code, for library users defining inventories programmatically
+//! rather than through the `rospec` section of the configuration file.
+//!
+//! ```no_run
+//! # use llrp_lib::config::TagReportContentConfig;
+//! # use llrp_lib::rospec::{AISpec, InventoryParameterSpec, ROReportSpec, ROSpecBuilder};
+//! let content = TagReportContentConfig { antenna_id: true, peak_rssi: true, ..TagReportContentConfig::default() };
+//! let rospec = ROSpecBuilder::new(1)
+//!   .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]))
+//!   .ai_spec(AISpec::new(vec![2], vec![InventoryParameterSpec::new(2)]))
+//!   .report_spec(ROReportSpec::end_of_rospec(content))
+//!   .build()
+//!   .unwrap();
+//! ```
+
+use bytes::{BufMut, BytesMut};
+
+use crate::config::{C1G2InventoryCommandConfig, C1G2SingulationConfig, GpiTriggerConfig, PeriodicTriggerConfig, ROReportTrigger, ROSpecConfig, TagReportContentConfig};
+use crate::gpio::PinState;
+use crate::custom::CustomParameter;
+use crate::llrp::{encode_inventory_command, patch_parameter_length, LlrpParameterType};
+
+/// The AirProtocolID of EPCglobal Class 1 Gen 2.
+pub const AIR_PROTOCOL_EPC_C1G2: u8 = 1;
+
+/// When the ROSpec starts and stops.
+///
+/// Fields:
+/// - `start_trigger_type`: 0 - Null (started by START_ROSPEC), 1 - Immediate (once enabled), 2 - Periodic, 3 - GPI.
+/// - `periodic_trigger`: Offset and period of a periodic start trigger.
+/// - `start_gpi_trigger`: GPI port and event of a GPI start trigger.
+/// - `stop_trigger_type`: 0 - Null (stopped by STOP_ROSPEC), 1 - Duration, 2 - GPI with timeout.
+/// - `stop_duration_ms`: Time the ROSpec runs for with a duration stop trigger.
+/// - `stop_gpi_trigger`: GPI port, event and timeout of a GPI stop trigger.
+#[derive(Debug, Clone, Default)]
+pub struct ROBoundarySpec {
+  pub start_trigger_type : u8,
+  pub periodic_trigger   : Option<PeriodicTriggerConfig>,
+  pub start_gpi_trigger  : Option<GpiTriggerConfig>,
+  pub stop_trigger_type  : u8,
+  pub stop_duration_ms   : u32,
+  pub stop_gpi_trigger   : Option<GpiTriggerConfig>
+}
+
+impl ROBoundarySpec {
+
+  /// Starts the ROSpec every `period_ms`, the first time `offset_ms` after it is
+  /// enabled, and runs it for `duration_ms` each time.
+  pub fn periodic(
+    offset_ms   : u32,
+    period_ms   : u32,
+    duration_ms : u32
+  ) -> Self {
+    ROBoundarySpec {
+      start_trigger_type : 2,
+      periodic_trigger   : Some(PeriodicTriggerConfig { offset_ms, period_ms, utc_timestamp_us: None }),
+      stop_trigger_type  : 1,
+      stop_duration_ms   : duration_ms,
+      ..ROBoundarySpec::default()
+    }
+  }
+
+  /// Starts the ROSpec on the `start` GPI event and stops it on the `stop` one, or
+  /// once the stop trigger's timeout passes.
+  pub fn gpi(
+    start : GpiTriggerConfig,
+    stop  : GpiTriggerConfig
+  ) -> Self {
+    ROBoundarySpec {
+      start_trigger_type : 3,
+      start_gpi_trigger  : Some(start),
+      stop_trigger_type  : 2,
+      stop_gpi_trigger   : Some(stop),
+      ..ROBoundarySpec::default()
+    }
+  }
+}
+
+/// An antenna inventory: the antennas it runs on, when it ends and the inventories
+/// carried out on them. An ROSpec runs its AISpecs in turn, so one AISpec per
+/// antenna gives each antenna its own settings.
+///
+/// Fields:
+/// - `antennas`: Antennas to inventory (0 - All).
+/// - `stop_trigger_type`: 0 - Null (runs until the ROSpec stops), 1 - Duration.
+/// - `stop_duration_ms`: Time the AISpec runs for with a duration stop trigger.
+/// - `inventory_parameter_specs`: The inventories run, each with its own ID and C1G2 settings.
+#[derive(Debug, Clone)]
+pub struct AISpec {
+  pub antennas                  : Vec<u16>,
+  pub stop_trigger_type         : u8,
+  pub stop_duration_ms          : u32,
+  pub inventory_parameter_specs : Vec<InventoryParameterSpec>
+}
+
+impl AISpec {
+
+  /// An AISpec running until the ROSpec stops.
+  pub fn new(
+    antennas                  : Vec<u16>,
+    inventory_parameter_specs : Vec<InventoryParameterSpec>
+  ) -> Self {
+    AISpec {
+      antennas,
+      stop_trigger_type : 0,
+      stop_duration_ms  : 0,
+      inventory_parameter_specs
+    }
+  }
+
+  /// Ends the AISpec after `duration_ms`, moving on to the next one.
+  pub fn stop_after(
+    mut self,
+    duration_ms: u32
+  ) -> Self {
+    self.stop_trigger_type = 1;
+    self.stop_duration_ms = duration_ms;
+    self
+  }
+}
+
+/// Fields:
+/// - `inventory_parameter_spec_id`: Identifier reported with the tags the inventory reads.
+/// - `protocol_id`: Air protocol of the inventory (1 - EPC C1G2).
+/// - `inventory_command`: C1G2 filters and singulation settings of the inventory.
+#[derive(Debug, Clone)]
+pub struct InventoryParameterSpec {
+  pub inventory_parameter_spec_id : u16,
+  pub protocol_id                 : u8,
+  pub inventory_command           : Option<C1G2InventoryCommandConfig>
+}
+
+impl InventoryParameterSpec {
+
+  /// A C1G2 inventory with the reader's default settings.
+  pub fn new(
+    inventory_parameter_spec_id: u16
+  ) -> Self {
+    InventoryParameterSpec {
+      inventory_parameter_spec_id,
+      protocol_id       : AIR_PROTOCOL_EPC_C1G2,
+      inventory_command : None
+    }
+  }
+
+  pub fn inventory_command(
+    mut self,
+    inventory_command: C1G2InventoryCommandConfig
+  ) -> Self {
+    self.inventory_command = Some(inventory_command);
+    self
+  }
+
+  /// Sets the session, tag population and transit time of the inventory, keeping
+  /// its filters. A state-aware singulation action makes the inventory state aware.
+  pub fn singulation(
+    mut self,
+    singulation: C1G2SingulationConfig
+  ) -> Self {
+    let inventory_command = self.inventory_command.get_or_insert_with(C1G2InventoryCommandConfig::default);
+    inventory_command.tag_inventory_state_aware |= singulation.state_aware_action.is_some();
+    inventory_command.singulation = Some(singulation);
+    self
+  }
+}
+
+/// When the reader sends ROAccessReports and what they contain.
+///
+/// Fields:
+/// - `trigger_type`: When to report; see [`ROReportTrigger`].
+/// - `n`: Tags, seconds or milliseconds after which to report, by trigger (0 - Only at
+///   the end of the AISpec or ROSpec, for the tag-count triggers).
+/// - `content_selector`: What the reader reports with each tag.
+/// - `extensions`: Vendor Custom parameters selecting additional report content.
+#[derive(Debug, Clone)]
+pub struct ROReportSpec {
+  pub trigger_type     : ROReportTrigger,
+  pub n                : u16,
+  pub content_selector : TagReportContentConfig,
+  pub extensions       : Vec<CustomParameter>
+}
+
+impl ROReportSpec {
+
+  pub fn new(
+    trigger_type     : ROReportTrigger,
+    n                : u16,
+    content_selector : TagReportContentConfig
+  ) -> Self {
+    ROReportSpec {
+      trigger_type,
+      n,
+      content_selector,
+      extensions: Vec::new()
+    }
+  }
+
+  /// Reports once at the end of each AISpec.
+  pub fn end_of_ai_spec(
+    content_selector: TagReportContentConfig
+  ) -> Self {
+    ROReportSpec::new(ROReportTrigger::NTagsOrEndOfAISpec, 0, content_selector)
+  }
+
+  /// Reports once at the end of the ROSpec.
+  pub fn end_of_rospec(
+    content_selector: TagReportContentConfig
+  ) -> Self {
+    ROReportSpec::new(ROReportTrigger::NTagsOrEndOfROSpec, 0, content_selector)
+  }
+}
+
+/// A complete ROSpec, as built by [`ROSpecBuilder`] or read from the configuration.
+///
+/// Fields:
+/// - `rospec_id`: Identifier of the ROSpec on the reader.
+/// - `priority`: 0 (highest) to 7.
+/// - `boundary_spec`: Start and stop triggers.
+/// - `ai_specs`: The antenna inventories, run in turn.
+/// - `loop_count`: Runs the AISpecs again this many times through a LoopSpec (0 - Until
+///   the ROSpec stops); LLRP 1.1 only.
+/// - `report_spec`: Reporting settings; absent leaves them to the reader's defaults.
+#[derive(Debug, Clone)]
+pub struct ROSpec {
+  pub rospec_id     : u32,
+  pub priority      : u8,
+  pub boundary_spec : ROBoundarySpec,
+  pub ai_specs      : Vec<AISpec>,
+  pub loop_count    : Option<u32>,
+  pub report_spec   : Option<ROReportSpec>
+}
+
+impl ROSpec {
+
+  /// The ROSpec described by the `rospec` section of the configuration.
+  pub fn from_config(
+    config            : &ROSpecConfig,
+    report_extensions : &[CustomParameter]
+  ) -> Self {
+
+    let inventory_parameter_spec = InventoryParameterSpec {
+      inventory_parameter_spec_id : config.InventoryParamSpecID,
+      protocol_id                 : config.AIProtocol,
+      inventory_command           : config.inventory_command.clone()
+    };
+
+    ROSpec {
+      rospec_id     : config.rospec_id,
+      priority      : config.priority,
+      boundary_spec : ROBoundarySpec {
+        start_trigger_type : config.ROSpecStartTriggerType,
+        periodic_trigger   : config.periodic_trigger.clone(),
+        start_gpi_trigger  : config.start_gpi_trigger.clone(),
+        stop_trigger_type  : config.ROSpecStopTriggerType,
+        stop_duration_ms   : config.rospec_stop_duration_ms.unwrap_or(0),
+        stop_gpi_trigger   : config.stop_gpi_trigger.clone()
+      },
+      ai_specs      : vec![AISpec {
+        antennas                  : config.antennas.clone(),
+        stop_trigger_type         : config.AISpecStopTriggerType,
+        stop_duration_ms          : config.aispec_stop_duration_ms.unwrap_or(0),
+        inventory_parameter_specs : vec![inventory_parameter_spec]
+      }],
+      loop_count    : None,
+      report_spec   : Some(ROReportSpec {
+        trigger_type     : config.ROReportTriggerType,
+        n                : config.ROReportTrigger_N,
+        content_selector : config.ReportContentSelector,
+        extensions       : report_extensions.to_vec()
+      })
+    }
+  }
+
+  /// Encodes the ROSpec parameter, the payload of an ADD_ROSPEC message.
+  pub fn encode(
+    &self
+  ) -> Vec<u8> {
+
+    let mut buffer = BytesMut::new();
+
+    // ROSpec
+    buffer.put_u16(LlrpParameterType::ROSpec.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u32(self.rospec_id);
+    buffer.put_u8(self.priority); // Priority
+    buffer.put_u8(0);             // CurrentState (Disabled)
+
+    // ROBoundarySpec
+    let boundary_spec_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::ROBoundarySpec.value());
+    buffer.put_u16(0); // Length (dynamic)
+
+    // ROSpecStartTrigger
+    let start_trigger_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::ROSpecStartTrigger.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u8(self.boundary_spec.start_trigger_type);
+
+    match (self.boundary_spec.start_trigger_type, &self.boundary_spec.periodic_trigger, &self.boundary_spec.start_gpi_trigger) {
+      (2, Some(periodic_trigger), _) => encode_periodic_trigger_value(&mut buffer, periodic_trigger),
+      (3, _, Some(gpi_trigger)) => encode_gpi_trigger_value(&mut buffer, gpi_trigger),
+      _ => {}
+    }
+
+    patch_parameter_length(&mut buffer, start_trigger_pos);
+
+    // ROSpecStopTrigger
+    let stop_trigger_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::ROSpecStopTrigger.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u8(self.boundary_spec.stop_trigger_type);
+    buffer.put_u32(self.boundary_spec.stop_duration_ms); // DurationTriggerValue
+
+    if let Some(gpi_trigger) = self.boundary_spec.stop_gpi_trigger.as_ref().filter(|_| self.boundary_spec.stop_trigger_type == 2) {
+      encode_gpi_trigger_value(&mut buffer, gpi_trigger);
+    }
+
+    patch_parameter_length(&mut buffer, stop_trigger_pos);
+
+    patch_parameter_length(&mut buffer, boundary_spec_pos);
+
+    for ai_spec in &self.ai_specs {
+      encode_ai_spec(&mut buffer, ai_spec);
+    }
+
+    if let Some(loop_count) = self.loop_count {
+
+      // LoopSpec
+      buffer.put_u16(LlrpParameterType::LoopSpec.value());
+      buffer.put_u16(8); // Length (static)
+      buffer.put_u32(loop_count);
+    }
+
+    if let Some(report_spec) = &self.report_spec {
+      encode_ro_report_spec(&mut buffer, report_spec);
+    }
+
+    patch_parameter_length(&mut buffer, 0);
+
+    buffer.to_vec()
+  }
+}
+
+fn encode_periodic_trigger_value(
+  buffer           : &mut BytesMut,
+  periodic_trigger : &PeriodicTriggerConfig
+) {
+
+  let periodic_trigger_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::PeriodicTriggerValue.value());
+  buffer.put_u16(0); // Length (dynamic)
+  buffer.put_u32(periodic_trigger.offset_ms);
+  buffer.put_u32(periodic_trigger.period_ms);
+
+  if let Some(utc_timestamp_us) = periodic_trigger.utc_timestamp_us {
+
+    // UTCTimestamp
+    buffer.put_u16(LlrpParameterType::UTCTimeStamp.value());
+    buffer.put_u16(12); // Length (static)
+    buffer.put_u64(utc_timestamp_us);
+  }
+
+  patch_parameter_length(buffer, periodic_trigger_pos);
+}
+
+fn encode_gpi_trigger_value(
+  buffer      : &mut BytesMut,
+  gpi_trigger : &GpiTriggerConfig
+) {
+  buffer.put_u16(LlrpParameterType::GPITriggerValue.value());
+  buffer.put_u16(11); // Length (static)
+  buffer.put_u16(gpi_trigger.port.0);
+  buffer.put_u8(if gpi_trigger.event == PinState::High { 0x80 } else { 0 }); // GPIEvent (First bit is boolean value)
+  buffer.put_u32(gpi_trigger.timeout_ms);
+}
+
+fn encode_ai_spec(
+  buffer  : &mut BytesMut,
+  ai_spec : &AISpec
+) {
+
+  let ai_spec_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::AISpec.value());
+  buffer.put_u16(0); // Length (dynamic)
+
+  // AntennaID array
+  buffer.put_u16(ai_spec.antennas.len() as u16);
+  for antenna_id in &ai_spec.antennas {
+    buffer.put_u16(*antenna_id);
+  }
+
+  // AISpecStopTrigger
+  buffer.put_u16(LlrpParameterType::AISpecStopTrigger.value());
+  buffer.put_u16(9); // Length (static)
+  buffer.put_u8(ai_spec.stop_trigger_type);
+  buffer.put_u32(ai_spec.stop_duration_ms); // DurationTrigger
+
+  for inventory_parameter_spec in &ai_spec.inventory_parameter_specs {
+
+    // InventoryParameterSpec
+    let inventory_parameter_spec_pos = buffer.len();
+    buffer.put_u16(LlrpParameterType::InventoryParameterSpec.value());
+    buffer.put_u16(0); // Length (dynamic)
+    buffer.put_u16(inventory_parameter_spec.inventory_parameter_spec_id);
+    buffer.put_u8(inventory_parameter_spec.protocol_id);
+
+    if let Some(inventory_command) = &inventory_parameter_spec.inventory_command {
+      encode_inventory_command(buffer, inventory_command);
+    }
+
+    patch_parameter_length(buffer, inventory_parameter_spec_pos);
+  }
+
+  patch_parameter_length(buffer, ai_spec_pos);
+}
+
+fn encode_ro_report_spec(
+  buffer      : &mut BytesMut,
+  report_spec : &ROReportSpec
+) {
+
+  let report_spec_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::ROReportSpec.value());
+  buffer.put_u16(0); // Length (dynamic)
+  buffer.put_u8(report_spec.trigger_type.value());
+  buffer.put_u16(report_spec.n);
+
+  let content_selector_pos = buffer.len();
+  buffer.put_u16(LlrpParameterType::TagReportContentSelector.value());
+  buffer.put_u16(0); // Length (dynamic)
+  buffer.put_u16(report_spec.content_selector.flags());
+
+  if let Some(epc_memory_flags) = report_spec.content_selector.epc_memory_flags() {
+    buffer.put_u16(LlrpParameterType::C1G2EPCMemorySelector.value());
+    buffer.put_u16(5); // Length (static)
+    buffer.put_u8(epc_memory_flags);
+  }
+
+  patch_parameter_length(buffer, content_selector_pos);
+
+  for extension in &report_spec.extensions {
+    buffer.put_slice(&extension.encode());
+  }
+
+  patch_parameter_length(buffer, report_spec_pos);
+}
+
+/// Composes an ROSpec in code. The ROSpec starts and stops on START_ROSPEC and
+/// STOP_ROSPEC, at priority 0, unless set otherwise.
+#[derive(Debug, Clone)]
+pub struct ROSpecBuilder {
+  rospec: ROSpec
+}
+
+impl ROSpecBuilder {
+
+  pub fn new(
+    rospec_id: u32
+  ) -> Self {
+    ROSpecBuilder {
+      rospec: ROSpec {
+        rospec_id,
+        priority      : 0,
+        boundary_spec : ROBoundarySpec::default(),
+        ai_specs      : Vec::new(),
+        loop_count    : None,
+        report_spec   : None
+      }
+    }
+  }
+
+  pub fn priority(
+    mut self,
+    priority: u8
+  ) -> Self {
+    self.rospec.priority = priority;
+    self
+  }
+
+  pub fn boundary_spec(
+    mut self,
+    boundary_spec: ROBoundarySpec
+  ) -> Self {
+    self.rospec.boundary_spec = boundary_spec;
+    self
+  }
+
+  /// Appends an AISpec, run after those added before it.
+  pub fn ai_spec(
+    mut self,
+    ai_spec: AISpec
+  ) -> Self {
+    self.rospec.ai_specs.push(ai_spec);
+    self
+  }
+
+  /// Loops back to the first AISpec after the last, `loop_count` more times, or
+  /// until the ROSpec stops with 0. Every AISpec needs a duration stop trigger so
+  /// the loop advances. Requires a reader speaking LLRP 1.1.
+  pub fn loop_count(
+    mut self,
+    loop_count: u32
+  ) -> Self {
+    self.rospec.loop_count = Some(loop_count);
+    self
+  }
+
+  pub fn report_spec(
+    mut self,
+    report_spec: ROReportSpec
+  ) -> Self {
+    self.rospec.report_spec = Some(report_spec);
+    self
+  }
+
+  /// Checks the ROSpec against the constraints LLRP places on it, which a reader
+  /// would otherwise reject it for.
+  pub fn build(
+    self
+  ) -> Result<ROSpec, String> {
+
+    let rospec = self.rospec;
+
+    if rospec.rospec_id == 0 {
+      return Err("ROSpec ID must not be 0".to_string());
+    }
+
+    if rospec.priority > 7 {
+      return Err(format!("ROSpec priority {} is above the lowest priority, 7", rospec.priority));
+    }
+
+    if rospec.boundary_spec.start_trigger_type == 2 && rospec.boundary_spec.periodic_trigger.is_none() {
+      return Err("A periodic start trigger requires a PeriodicTriggerValue".to_string());
+    }
+
+    if rospec.boundary_spec.start_trigger_type == 3 && rospec.boundary_spec.start_gpi_trigger.is_none() {
+      return Err("A GPI start trigger requires a GPITriggerValue".to_string());
+    }
+
+    if rospec.boundary_spec.stop_trigger_type == 2 && rospec.boundary_spec.stop_gpi_trigger.is_none() {
+      return Err("A GPI stop trigger requires a GPITriggerValue".to_string());
+    }
+
+    let gpi_triggers = [&rospec.boundary_spec.start_gpi_trigger, &rospec.boundary_spec.stop_gpi_trigger];
+    if gpi_triggers.into_iter().flatten().any(|gpi_trigger| gpi_trigger.event == PinState::Unknown) {
+      return Err("A GPI trigger event must be low or high".to_string());
+    }
+
+    if rospec.ai_specs.is_empty() {
+      return Err("ROSpec must have at least one AISpec".to_string());
+    }
+
+    if let Some(report_spec) = &rospec.report_spec {
+      report_spec.trigger_type.check_n(report_spec.n)?;
+    }
+
+    for (index, ai_spec) in rospec.ai_specs.iter().enumerate() {
+
+      if ai_spec.antennas.is_empty() {
+        return Err(format!("AISpec {} lists no antennas", index + 1));
+      }
+
+      if ai_spec.inventory_parameter_specs.is_empty() {
+        return Err(format!("AISpec {} has no InventoryParameterSpec", index + 1));
+      }
+
+      if let Some(spec) = ai_spec.inventory_parameter_specs.iter().find(|spec| spec.inventory_parameter_spec_id == 0) {
+        return Err(format!("AISpec {} has an InventoryParameterSpec with ID {}, which must not be 0", index + 1, spec.inventory_parameter_spec_id));
+      }
+
+      for spec in &ai_spec.inventory_parameter_specs {
+        if let Some(Err(e)) = spec.inventory_command.as_ref().map(C1G2InventoryCommandConfig::validate) {
+          return Err(format!("AISpec {} InventoryParameterSpec {}: {}", index + 1, spec.inventory_parameter_spec_id, e));
+        }
+      }
+
+      if rospec.loop_count.is_some() && ai_spec.stop_trigger_type == 0 {
+        return Err(format!("AISpec {} has no stop trigger, so the LoopSpec would never advance past it", index + 1));
+      }
+    }
+
+    Ok(rospec)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::config::{C1G2FilterConfig, C1G2FilterTruncate};
+  use crate::gpio::GpiPort;
+  use crate::llrp::LlrpMessage;
+
+  fn rospec_config() -> ROSpecConfig {
+    serde_json::from_value(serde_json::json!({
+      "rospec_id": 7,
+      "priority": 1,
+      "antenna_count": 2,
+      "antennas": [1, 2],
+      "ROSpecStartTriggerType": 0,
+      "ROSpecStopTriggerType": 0,
+      "AISpecStopTriggerType": 0,
+      "InventoryParamSpecID": 1,
+      "AIProtocol": 1,
+      "ROReportTriggerType": 1,
+      "ROReportTrigger_N": 1,
+      "ReportContentSelector": 1
+    })).unwrap()
+  }
+
+  #[test]
+  fn builder_encodes_the_configured_rospec_identically() {
+
+    let built = ROSpecBuilder::new(7)
+      .priority(1)
+      .ai_spec(AISpec::new(vec![1, 2], vec![InventoryParameterSpec::new(1)]))
+      .report_spec(ROReportSpec::new(ROReportTrigger::NTagsOrEndOfAISpec, 1, TagReportContentConfig::default()))
+      .build()
+      .unwrap();
+
+    assert_eq!(built.encode(), LlrpMessage::new_add_rospec(1, &rospec_config(), &[]).payload);
+  }
+
+  #[test]
+  fn build_rejects_ai_specs_without_antennas_or_inventories() {
+
+    assert!(ROSpecBuilder::new(1).build().is_err());
+    assert!(ROSpecBuilder::new(1).ai_spec(AISpec::new(vec![], vec![InventoryParameterSpec::new(1)])).build().is_err());
+    assert!(ROSpecBuilder::new(1).ai_spec(AISpec::new(vec![1], vec![])).build().is_err());
+
+    let rospec = ROSpecBuilder::new(1)
+      .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]).stop_after(500))
+      .ai_spec(AISpec::new(vec![2], vec![InventoryParameterSpec::new(2), InventoryParameterSpec::new(3)]))
+      .build()
+      .unwrap();
+
+    assert_eq!(rospec.ai_specs.len(), 2);
+    assert!(rospec.report_spec.is_none());
+  }
+
+  #[test]
+  fn periodic_start_trigger_carries_its_trigger_value() {
+
+    let periodic = ROBoundarySpec { start_trigger_type: 2, ..ROBoundarySpec::default() };
+    assert!(ROSpecBuilder::new(1).boundary_spec(periodic).ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)])).build().is_err());
+
+    let mut boundary_spec = ROBoundarySpec::periodic(1000, 60_000, 5000);
+    boundary_spec.periodic_trigger.as_mut().unwrap().utc_timestamp_us = Some(1_700_000_000_000_000);
+
+    let rospec = ROSpecBuilder::new(1)
+      .boundary_spec(boundary_spec)
+      .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]))
+      .build()
+      .unwrap();
+
+    let encoded = rospec.encode();
+
+    // ROSpec header and fields, then the ROBoundarySpec header.
+    let start_trigger = &encoded[14..];
+    assert_eq!(&start_trigger[..5], &[0x00, 0xB3, 0x00, 0x1D, 0x02]);
+    assert_eq!(&start_trigger[5..17], &[0x00, 0xB4, 0x00, 0x18, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00, 0xEA, 0x60]);
+    assert_eq!(&start_trigger[17..21], &[0x00, 0x80, 0x00, 0x0C]);
+    assert_eq!(&start_trigger[21..29], &1_700_000_000_000_000u64.to_be_bytes());
+
+    // ROSpecStopTrigger with its duration follows.
+    assert_eq!(&start_trigger[29..38], &[0x00, 0xB6, 0x00, 0x09, 0x01, 0x00, 0x00, 0x13, 0x88]);
+  }
+
+  #[test]
+  fn gpi_triggers_carry_port_event_and_timeout() {
+
+    let start = GpiTriggerConfig { port: GpiPort(1), event: PinState::High, timeout_ms: 0 };
+    let stop = GpiTriggerConfig { port: GpiPort(1), event: PinState::Low, timeout_ms: 30_000 };
+
+    let rospec = ROSpecBuilder::new(1)
+      .boundary_spec(ROBoundarySpec::gpi(start, stop))
+      .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]))
+      .build()
+      .unwrap();
+
+    let encoded = rospec.encode();
+
+    let boundary_spec = &encoded[10..];
+    assert_eq!(&boundary_spec[..4], &[0x00, 0xB2, 0x00, 0x28]);
+    assert_eq!(&boundary_spec[4..20], &[0x00, 0xB3, 0x00, 0x10, 0x03, 0x00, 0xB5, 0x00, 0x0B, 0x00, 0x01, 0x80, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(&boundary_spec[20..40], &[0x00, 0xB6, 0x00, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB5, 0x00, 0x0B, 0x00, 0x01, 0x00, 0x00, 0x00, 0x75, 0x30]);
+
+    let unknown = GpiTriggerConfig { port: GpiPort(1), event: PinState::Unknown, timeout_ms: 0 };
+    let missing_stop = ROBoundarySpec { stop_trigger_type: 2, ..ROBoundarySpec::default() };
+    for boundary_spec in [ROBoundarySpec::gpi(unknown.clone(), unknown), missing_stop] {
+      assert!(ROSpecBuilder::new(1).boundary_spec(boundary_spec).ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)])).build().is_err());
+    }
+  }
+
+  #[test]
+  fn loop_spec_follows_the_ai_specs_it_repeats() {
+
+    assert!(ROSpecBuilder::new(1).ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)])).loop_count(3).build().is_err());
+
+    let rospec = ROSpecBuilder::new(1)
+      .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]).stop_after(500))
+      .ai_spec(AISpec::new(vec![2], vec![InventoryParameterSpec::new(2)]).stop_after(500))
+      .loop_count(3)
+      .report_spec(ROReportSpec::new(ROReportTrigger::NTagsOrEndOfAISpec, 1, TagReportContentConfig::default()))
+      .build()
+      .unwrap();
+
+    let encoded = rospec.encode();
+    let without_loop = ROSpec { loop_count: None, ..rospec }.encode();
+
+    // The LoopSpec sits between the AISpecs and the ROReportSpec.
+    let loop_spec_pos = encoded.windows(4).position(|header| header == [0x01, 0x63, 0x00, 0x08]).unwrap();
+    assert_eq!(encoded.len(), without_loop.len() + 8);
+    assert_eq!(&encoded[loop_spec_pos + 4..loop_spec_pos + 10], &[0x00, 0x00, 0x00, 0x03, 0x00, 0xED]);
+    assert_eq!(&encoded[loop_spec_pos + 8..], &without_loop[loop_spec_pos..]);
+  }
+
+  #[test]
+  fn report_triggers_need_n_only_when_periodic() {
+
+    let build = |report_spec: ROReportSpec| ROSpecBuilder::new(1)
+      .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1)]))
+      .report_spec(report_spec)
+      .build();
+
+    let rospec = build(ROReportSpec::end_of_ai_spec(TagReportContentConfig::default())).unwrap();
+    let report_spec = rospec.report_spec.unwrap();
+    assert_eq!((report_spec.trigger_type.value(), report_spec.n), (1, 0));
+
+    assert!(build(ROReportSpec::new(ROReportTrigger::None, 0, TagReportContentConfig::default())).is_ok());
+    assert!(build(ROReportSpec::new(ROReportTrigger::NMillisecondsOrEndOfROSpec, 0, TagReportContentConfig::default())).is_err());
+
+    let rospec = build(ROReportSpec::new(ROReportTrigger::NSecondsOrEndOfAISpec, 30, TagReportContentConfig::default())).unwrap();
+    let encoded = rospec.encode();
+    let report_spec_pos = encoded.windows(2).position(|header| header == [0x00, 0xED]).unwrap();
+    assert_eq!(&encoded[report_spec_pos + 4..report_spec_pos + 7], &[0x03, 0x00, 0x1E]);
+
+    // The configuration names the trigger or gives its value, and may leave out N.
+    let mut config = serde_json::to_value(rospec_config()).unwrap();
+    config["ROReportTriggerType"] = serde_json::json!("end_of_rospec");
+    config.as_object_mut().unwrap().remove("ROReportTrigger_N");
+    let config: ROSpecConfig = serde_json::from_value(config).unwrap();
+    assert_eq!((config.ROReportTriggerType, config.ROReportTrigger_N), (ROReportTrigger::NTagsOrEndOfROSpec, 0));
+
+    let mut config = serde_json::to_value(rospec_config()).unwrap();
+    config["ROReportTriggerType"] = serde_json::json!(7);
+    assert!(serde_json::from_value::<ROSpecConfig>(config).is_err());
+  }
+
+  #[test]
+  fn build_rejects_filters_the_reader_would_refuse() {
+
+    let build = |filters: Vec<C1G2FilterConfig>| {
+      let command = C1G2InventoryCommandConfig { filters, ..C1G2InventoryCommandConfig::default() };
+      ROSpecBuilder::new(1)
+        .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1).inventory_command(command)]))
+        .build()
+    };
+
+    let truncated = C1G2FilterConfig { truncate: C1G2FilterTruncate::Truncate, ..C1G2FilterConfig::epc_prefix("3008") };
+    let tid = C1G2FilterConfig { memory_bank: 2, pointer: 0, ..C1G2FilterConfig::epc_prefix("E280") };
+
+    assert!(build(vec![tid.clone(), truncated.clone()]).is_ok());
+    assert!(build(vec![truncated.clone(), tid.clone()]).is_err());
+    assert!(build(vec![C1G2FilterConfig { truncate: C1G2FilterTruncate::Truncate, ..tid.clone() }]).is_err());
+    assert!(build(vec![C1G2FilterConfig::epc_prefix("30G8")]).is_err());
+    assert!(build(vec![C1G2FilterConfig { mask_bit_count: Some(17), ..tid }]).is_err());
+  }
+
+  #[test]
+  fn singulation_is_encoded_in_the_inventory_command() {
+
+    let build = |singulation: C1G2SingulationConfig| {
+      ROSpecBuilder::new(1)
+        .ai_spec(AISpec::new(vec![1], vec![InventoryParameterSpec::new(1).singulation(singulation)]))
+        .build()
+    };
+
+    let encoded = build(C1G2SingulationConfig::new(2, 64, 500).state_aware(1, 0)).unwrap().encode();
+
+    // C1G2InventoryCommand, state aware, holding only the C1G2SingulationControl.
+    let command_pos = encoded.windows(2).position(|header| header == [0x01, 0x4A]).unwrap();
+    assert_eq!(&encoded[command_pos..command_pos + 5], &[0x01, 0x4A, 0x00, 0x15, 0x80]);
+    assert_eq!(&encoded[command_pos + 5..command_pos + 16], &[0x01, 0x50, 0x00, 0x10, 0x80, 0x00, 0x40, 0x00, 0x00, 0x01, 0xF4]);
+    assert_eq!(&encoded[command_pos + 16..command_pos + 21], &[0x01, 0x51, 0x00, 0x05, 0x80]);
+
+    assert!(build(C1G2SingulationConfig::new(4, 64, 500)).is_err());
+    assert!(build(C1G2SingulationConfig::new(1, 64, 500).state_aware(2, 0)).is_err());
+
+    let state_unaware = C1G2InventoryCommandConfig {
+      singulation: Some(C1G2SingulationConfig::new(1, 64, 500).state_aware(0, 0)),
+      ..C1G2InventoryCommandConfig::default()
+    };
+    assert!(state_unaware.validate().is_err());
+  }
+}
\ No newline at end of file
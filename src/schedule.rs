@@ -0,0 +1,266 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{Local, NaiveTime};
+use log::{info, warn};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::client::LlrpClient;
+use crate::config::{EnableSchedule, EnableWindow, PowerSchedule};
+use crate::history::{ConnectionEvent, ConnectionEventKind};
+use crate::log_context;
+
+/// Drives the power schedules of one reader, sending a per-antenna
+/// SET_READER_CONFIG whenever a schedule moves to its next power level.
+///
+/// Hop table, channel and receive sensitivity are taken from the reader's
+/// `reader_config`; only the transmit power index changes between steps.
+pub struct PowerScheduler {
+  tasks: Vec<JoinHandle<()>>
+}
+
+impl PowerScheduler {
+
+  /// Starts one task per schedule in the client's configuration.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub async fn start(
+    client: Arc<Mutex<LlrpClient>>
+  ) -> Self {
+
+    let (reader_id, schedules) = {
+      let client = client.lock().await;
+      (client.config().reader_id(), client.config().power_schedules.clone())
+    };
+
+    let tasks = schedules.into_iter()
+      .filter(|schedule| {
+        if schedule.tx_power_table_indices.is_empty() || schedule.interval == 0 {
+          warn!("Ignoring power schedule for antenna {} without power levels or interval", schedule.antenna_id);
+          return false;
+        }
+        true
+      })
+      .map(|schedule| tokio::spawn(log_context::scope(reader_id.clone(), run_schedule(client.clone(), schedule))))
+      .collect();
+
+    PowerScheduler { tasks }
+  }
+
+  pub fn stop(
+    self
+  ) {
+    for task in self.tasks {
+      task.abort();
+    }
+  }
+}
+
+async fn run_schedule(
+  client   : Arc<Mutex<LlrpClient>>,
+  schedule : PowerSchedule
+) {
+
+  info!(
+    "Starting power schedule for antenna {}: indices {:?} every {} ms",
+    schedule.antenna_id, schedule.tx_power_table_indices, schedule.interval
+  );
+
+  let mut ticker = tokio::time::interval(Duration::from_millis(schedule.interval));
+
+  for tx_power_table_index in schedule.tx_power_table_indices.iter().cycle() {
+
+    ticker.tick().await;
+
+    let mut client = client.lock().await;
+
+    let mut reader_config = client.config().reader_config.clone();
+    reader_config.tx_power_table_index = *tx_power_table_index;
+
+    if let Err(e) = client.send_antenna_config(schedule.antenna_id, &reader_config).await {
+      warn!(
+        "Failed to set transmit power index {} on antenna {}: {}",
+        tx_power_table_index, schedule.antenna_id, e
+      );
+    }
+  }
+}
+
+/// Follows the time-of-day windows of a reader's `enable_schedule`, disabling its
+/// ROSpec while no window is open and re-adding it with the antennas of the open
+/// windows when they change.
+pub struct EnableScheduler {
+  task: JoinHandle<()>
+}
+
+impl EnableScheduler {
+
+  /// Starts following the client's schedule. Returns `None` when the configuration
+  /// has no `enable_schedule`.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub async fn start(
+    client: Arc<Mutex<LlrpClient>>
+  ) -> Option<Self> {
+
+    let (reader_id, schedule, all_antennas, connection_events) = {
+      let client = client.lock().await;
+      let config = client.config();
+      (config.reader_id(), config.enable_schedule.clone()?, config.rospec.antennas.clone(), client.subscribe_connection_events())
+    };
+
+    let task = tokio::spawn(log_context::scope(reader_id, run_enable_schedule(client, schedule, all_antennas, connection_events)));
+
+    Some(EnableScheduler { task })
+  }
+
+  pub fn stop(
+    self
+  ) {
+    self.task.abort();
+  }
+}
+
+/// The antennas to read with at `time`, in the order of `all_antennas`, or `None`
+/// when no window is open. A window without antennas opens all of them.
+fn open_antennas(
+  windows      : &[EnableWindow],
+  all_antennas : &[u16],
+  time         : NaiveTime
+) -> Option<Vec<u16>> {
+
+  let open: Vec<&EnableWindow> = windows.iter().filter(|window| window.contains(time)).collect();
+
+  if open.is_empty() {
+    return None;
+  }
+
+  if open.iter().any(|window| window.antennas.is_empty()) {
+    return Some(all_antennas.to_vec());
+  }
+
+  Some(all_antennas.iter().copied().filter(|antenna_id| open.iter().any(|window| window.antennas.contains(antenna_id))).collect())
+}
+
+/// Whether the client connected again since the last call, draining the events
+/// received so far. Missed events count as a reconnect.
+fn reconnected(
+  connection_events: &mut broadcast::Receiver<ConnectionEvent>
+) -> bool {
+
+  let mut reconnected = false;
+
+  loop {
+    match connection_events.try_recv() {
+      Ok(event) => reconnected |= matches!(event.kind, ConnectionEventKind::Connected { .. }),
+      Err(TryRecvError::Lagged(_)) => reconnected = true,
+      Err(TryRecvError::Empty | TryRecvError::Closed) => return reconnected
+    }
+  }
+}
+
+async fn run_enable_schedule(
+  client                : Arc<Mutex<LlrpClient>>,
+  schedule              : EnableSchedule,
+  all_antennas          : Vec<u16>,
+  mut connection_events : broadcast::Receiver<ConnectionEvent>
+) {
+
+  info!("Starting enable schedule with {} windows", schedule.windows.len());
+
+  let mut ticker = tokio::time::interval(Duration::from_secs(schedule.check_interval));
+
+  // What the reader was last set to; unset until the first check succeeds, and
+  // again after a reconnect, so the reader is brought in line with the schedule on
+  // start and whenever its session begins anew.
+  let mut applied: Option<Option<Vec<u16>>> = None;
+
+  loop {
+
+    ticker.tick().await;
+
+    if reconnected(&mut connection_events) {
+      applied = None;
+    }
+
+    let antennas = open_antennas(&schedule.windows, &all_antennas, Local::now().time());
+    if applied.as_ref() == Some(&antennas) {
+      continue;
+    }
+
+    let mut client = client.lock().await;
+    let rospec_id = client.config().rospec.rospec_id;
+
+    let result = match &antennas {
+      None => {
+        info!("No enable window open, disabling ROSpec {}", rospec_id);
+        client.send_disable_rospec(rospec_id).await
+      }
+      Some(antennas) => {
+        info!("Enable window open, reading with antennas {:?}", antennas);
+        client.set_rospec_antennas(antennas.clone()).await
+      }
+    };
+
+    match result {
+      Ok(()) => applied = Some(antennas),
+      Err(e) => warn!("Failed to apply enable schedule: {}", e)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn window(
+    start    : &str,
+    end      : &str,
+    antennas : Vec<u16>
+  ) -> EnableWindow {
+    EnableWindow { start: start.to_string(), end: end.to_string(), antennas }
+  }
+
+  fn at(
+    time: &str
+  ) -> NaiveTime {
+    NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+  }
+
+  #[test]
+  fn open_windows_select_their_antennas() {
+
+    let windows = vec![window("08:00", "18:00", vec![]), window("22:00", "06:00", vec![3, 1])];
+    let all_antennas = [1, 2, 3, 4];
+
+    assert_eq!(open_antennas(&windows, &all_antennas, at("12:00")), Some(vec![1, 2, 3, 4]));
+    assert_eq!(open_antennas(&windows, &all_antennas, at("23:30")), Some(vec![1, 3]));
+    assert_eq!(open_antennas(&windows, &all_antennas, at("05:59")), Some(vec![1, 3]));
+    assert_eq!(open_antennas(&windows, &all_antennas, at("06:00")), None);
+    assert_eq!(open_antennas(&windows, &all_antennas, at("18:00")), None);
+  }
+
+  #[test]
+  fn connects_and_missed_events_count_as_reconnects() {
+
+    let event = |kind| ConnectionEvent { timestamp_ms: 0, kind };
+    let (event_tx, mut event_rx) = broadcast::channel(2);
+
+    assert!(!reconnected(&mut event_rx));
+
+    event_tx.send(event(ConnectionEventKind::Disconnected { reason: "Connection closed".to_string(), session_duration_ms: 0 })).unwrap();
+    assert!(!reconnected(&mut event_rx));
+
+    event_tx.send(event(ConnectionEventKind::ReconnectAttempt { attempt: 1 })).unwrap();
+    event_tx.send(event(ConnectionEventKind::Connected { host: "reader".to_string(), connect_duration_ms: 0 })).unwrap();
+    assert!(reconnected(&mut event_rx));
+    assert!(!reconnected(&mut event_rx));
+
+    for attempt in 1..=3 {
+      event_tx.send(event(ConnectionEventKind::ReconnectAttempt { attempt })).unwrap();
+    }
+    assert!(reconnected(&mut event_rx));
+  }
+}
\ No newline at end of file
@@ -0,0 +1,245 @@
+//! A scripted LLRP peer on a loopback socket, shared by the tests that drive a
+//! client against message sequences a real reader may produce.
+
+use std::future::Future;
+use std::time::Duration;
+use bytes::{BufMut, BytesMut};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::client::LlrpClient;
+use crate::config::Config;
+use crate::llrp::{LlrpHeader, LlrpMessage, LlrpMessageType, LlrpParameterType, LLRP_HEADER_LENGTH};
+
+/// Accepts a single client connection, greets it with a successful
+/// ConnectionAttemptEvent as a reader does, and runs `script` against it.
+pub(crate) struct ScriptedReader {
+  pub(crate) host : String,
+  task            : JoinHandle<io::Result<()>>
+}
+
+impl ScriptedReader {
+
+  pub(crate) async fn start<F, Fut>(
+    script: F
+  ) -> Self
+  where
+    F   : FnOnce(ScriptedConnection) -> Fut + Send + 'static,
+    Fut : Future<Output = io::Result<()>> + Send
+  {
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let host = listener.local_addr().unwrap().to_string();
+
+    let task = tokio::spawn(async move {
+      let (mut stream, _) = listener.accept().await?;
+      stream.write_all(&connection_attempt_event().encode()).await?;
+      script(ScriptedConnection::new(stream)).await
+    });
+
+    ScriptedReader { host, task }
+  }
+
+  pub(crate) async fn finish(
+    self
+  ) {
+    self.task.await.unwrap().unwrap();
+  }
+}
+
+pub(crate) struct ScriptedConnection {
+  pub(crate) stream : TcpStream,
+  buf               : BytesMut
+}
+
+impl ScriptedConnection {
+
+  /// Wraps a connection the test accepted itself, e.g. behind a gateway.
+  pub(crate) fn new(
+    stream: TcpStream
+  ) -> Self {
+    ScriptedConnection { stream, buf: BytesMut::new() }
+  }
+
+  /// Reads the next request from the client and checks its type.
+  pub(crate) async fn expect(
+    &mut self,
+    message_type: LlrpMessageType
+  ) -> io::Result<LlrpMessage> {
+
+    loop {
+
+      if self.buf.len() >= LLRP_HEADER_LENGTH {
+        let header = LlrpHeader::decode(&self.buf)?;
+        if self.buf.len() >= header.message_length as usize {
+          let message = LlrpMessage::decode(&mut self.buf)?;
+          assert_eq!(message.message_type, message_type);
+          return Ok(message);
+        }
+      }
+
+      if self.stream.read_buf(&mut self.buf).await? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Client closed the connection"));
+      }
+    }
+  }
+
+  /// Writes all messages back to back in a single write, so the client reads
+  /// them from one segment.
+  pub(crate) async fn send(
+    &mut self,
+    messages: &[LlrpMessage]
+  ) -> io::Result<()> {
+
+    let mut bytes = BytesMut::new();
+    for message in messages {
+      bytes.extend_from_slice(&message.encode());
+    }
+
+    self.stream.write_all(&bytes).await
+  }
+
+  /// Writes a message in pieces split at the given offsets, pausing between
+  /// them so the client sees partial frames.
+  pub(crate) async fn send_split(
+    &mut self,
+    message : &LlrpMessage,
+    splits  : &[usize]
+  ) -> io::Result<()> {
+
+    let bytes = message.encode();
+    let mut start = 0;
+
+    for end in splits.iter().copied().chain([bytes.len()]) {
+      self.stream.write_all(&bytes[start..end]).await?;
+      self.stream.flush().await?;
+      tokio::time::sleep(Duration::from_millis(20)).await;
+      start = end;
+    }
+
+    Ok(())
+  }
+}
+
+pub(crate) fn status_response(
+  message_type : LlrpMessageType,
+  message_id   : u32
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  payload.put_u16(LlrpParameterType::LLRPStatus.value());
+  payload.put_u16(8);
+  payload.put_u16(0);
+  payload.put_u16(0);
+
+  LlrpMessage::new(message_type, message_id, payload.to_vec())
+}
+
+pub(crate) fn failed_status_response(
+  message_type : LlrpMessageType,
+  message_id   : u32,
+  status_code  : u16
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  payload.put_u16(LlrpParameterType::LLRPStatus.value());
+  payload.put_u16(8);
+  payload.put_u16(status_code);
+  payload.put_u16(0);
+
+  LlrpMessage::new(message_type, message_id, payload.to_vec())
+}
+
+pub(crate) fn keepalive_ack(
+  message_id: u32
+) -> LlrpMessage {
+  LlrpMessage::new(LlrpMessageType::KeepaliveAck, message_id, vec![])
+}
+
+pub(crate) fn connection_attempt_event() -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  payload.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+  payload.put_u16(10);
+  payload.put_u16(LlrpParameterType::ConnectionAttemptEvent.value());
+  payload.put_u16(6);
+  payload.put_u16(0); // Success
+
+  LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, payload.to_vec())
+}
+
+pub(crate) fn reader_event_notification() -> LlrpMessage {
+  LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, vec![])
+}
+
+pub(crate) fn timestamped_reader_event(
+  utc_us: u64
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  payload.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+  payload.put_u16(16);
+  payload.put_u16(LlrpParameterType::UTCTimeStamp.value());
+  payload.put_u16(12);
+  payload.put_u64(utc_us);
+
+  LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, payload.to_vec())
+}
+
+pub(crate) fn ro_access_report(
+  epc: u128
+) -> LlrpMessage {
+
+  let mut payload = BytesMut::new();
+  payload.put_u16(LlrpParameterType::TagReportData.value());
+  payload.put_u16(17);
+  payload.put_u8(0x80 | LlrpParameterType::EPC96.value() as u8);
+  payload.put_slice(&epc.to_be_bytes()[4..]);
+
+  LlrpMessage::new(LlrpMessageType::ROAccessReport, 0, payload.to_vec())
+}
+
+pub(crate) fn test_config(
+  host             : &str,
+  response_timeout : u64
+) -> Config {
+
+  let log_file = std::env::temp_dir().join("llrp-client-tests.log");
+
+  serde_json::from_value(serde_json::json!({
+    "host": host,
+    "log_level": "debug",
+    "log_file": log_file,
+    "per_reader_log_files": false,
+    "log_response_ack": false,
+    "response_timeout": response_timeout,
+    "reader_config": {
+      "hop_table_id": 1,
+      "channel_index": 1,
+      "tx_power_table_index": 1,
+      "rx_power_table_index": 1
+    },
+    "rospec": {
+      "rospec_id": 1,
+      "priority": 0,
+      "antenna_count": 1,
+      "antennas": [1],
+      "ROSpecStartTriggerType": 0,
+      "ROSpecStopTriggerType": 0,
+      "AISpecStopTriggerType": 0,
+      "InventoryParamSpecID": 1,
+      "AIProtocol": 1,
+      "ROReportTriggerType": 1,
+      "ROReportTrigger_N": 1,
+      "ReportContentSelector": 1
+    }
+  })).unwrap()
+}
+
+pub(crate) async fn connect(
+  host             : &str,
+  response_timeout : u64
+) -> LlrpClient {
+  LlrpClient::initialize_with_config(test_config(host, response_timeout)).await.unwrap()
+}
\ No newline at end of file
@@ -0,0 +1,255 @@
+//! End-to-end protection of sink payloads. A sink with a `protection` section
+//! writes each payload as a [`SealedPayload`] envelope instead of plain JSON:
+//! encrypted with AES-256-GCM, signed with HMAC-SHA256, or both, so tag data
+//! passing through shared brokers or storage can only be read, or trusted, by
+//! holders of the keys.
+//!
+//! The signature covers the key ID, the nonce and the payload, each preceded by
+//! its length as a big-endian u32. The key ID is also the associated data of the
+//! encryption, so an envelope cannot be passed off under another key's ID.
+//!
+//! Sealing needs the `sealing` feature. Without it a sink with a `protection`
+//! section fails to build rather than write unprotected payloads.
+
+#[cfg(feature = "sealing")]
+use base64::engine::general_purpose::STANDARD;
+#[cfg(feature = "sealing")]
+use base64::Engine;
+#[cfg(feature = "sealing")]
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+#[cfg(feature = "sealing")]
+use ring::hmac;
+#[cfg(feature = "sealing")]
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::config::PayloadProtectionConfig;
+#[cfg(feature = "sealing")]
+use crate::secrets::Secret;
+use crate::sinks::SinkError;
+
+/// A protected payload as written by a sink.
+///
+/// Fields:
+/// - `key_id`: The configured `key_id`, if any.
+/// - `nonce`: The AES-GCM nonce as base64, present when the payload is encrypted.
+/// - `payload`: The ciphertext and tag as base64 when encrypted, else the JSON text.
+/// - `signature`: The HMAC-SHA256 signature as base64, present when signed.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SealedPayload {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub key_id    : Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub nonce     : Option<String>,
+  pub payload   : String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signature : Option<String>
+}
+
+/// Seals payloads with the keys of a sink's `protection` section, and opens them
+/// again on the consuming side.
+#[cfg(feature = "sealing")]
+pub struct PayloadSealer {
+  key_id     : Option<String>,
+  encryption : Option<LessSafeKey>,
+  signing    : Option<hmac::Key>,
+  random     : SystemRandom
+}
+
+#[cfg(feature = "sealing")]
+impl PayloadSealer {
+
+  /// Resolves and decodes the configured keys. Fails when neither key is given or
+  /// the encryption key is not 32 bytes.
+  pub fn new(
+    config: &PayloadProtectionConfig
+  ) -> Result<Self, SinkError> {
+
+    if config.encryption_key.is_none() && config.signing_key.is_none() {
+      return Err("Payload protection requires an encryption_key, a signing_key or both".into());
+    }
+
+    let decode_key = |name: &str, secret: &Secret| -> Result<Vec<u8>, SinkError> {
+      STANDARD.decode(secret.resolve()?.trim())
+        .map_err(|e| format!("Payload protection {} is not valid base64: {}", name, e).into())
+    };
+
+    let encryption = config.encryption_key.as_ref().map(|secret| {
+      let key = decode_key("encryption_key", secret)?;
+      UnboundKey::new(&AES_256_GCM, &key)
+        .map(LessSafeKey::new)
+        .map_err(|_| SinkError::from(format!("Payload protection encryption_key must be 32 bytes, got {}", key.len())))
+    }).transpose()?;
+
+    let signing = config.signing_key.as_ref()
+      .map(|secret| decode_key("signing_key", secret).map(|key| hmac::Key::new(hmac::HMAC_SHA256, &key)))
+      .transpose()?;
+
+    Ok(PayloadSealer {
+      key_id : config.key_id.clone(),
+      encryption,
+      signing,
+      random : SystemRandom::new()
+    })
+  }
+
+  pub fn seal(
+    &self,
+    plaintext: &[u8]
+  ) -> Result<SealedPayload, SinkError> {
+
+    let (nonce, payload) = match &self.encryption {
+      Some(key) => {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.random.fill(&mut nonce).map_err(|_| "Failed to generate a nonce for payload encryption")?;
+
+        let mut in_out = plaintext.to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), self.aad(), &mut in_out)
+          .map_err(|_| "Failed to encrypt payload")?;
+
+        (Some(STANDARD.encode(nonce)), STANDARD.encode(in_out))
+      }
+      None => (None, String::from_utf8(plaintext.to_vec())?)
+    };
+
+    let signature = self.signing.as_ref().map(|key| {
+      let signed = signing_input(self.key_id.as_deref(), nonce.as_deref(), &payload);
+      STANDARD.encode(hmac::sign(key, &signed))
+    });
+
+    Ok(SealedPayload {
+      key_id : self.key_id.clone(),
+      nonce,
+      payload,
+      signature
+    })
+  }
+
+  /// Verifies and decrypts an envelope sealed with the same keys.
+  pub fn open(
+    &self,
+    sealed: &SealedPayload
+  ) -> Result<Vec<u8>, SinkError> {
+
+    if sealed.key_id != self.key_id {
+      return Err(format!("Payload was sealed with key {:?}, expected {:?}", sealed.key_id, self.key_id).into());
+    }
+
+    if let Some(key) = &self.signing {
+      let signature = STANDARD.decode(sealed.signature.as_deref().ok_or("Payload is not signed")?)?;
+      let signed = signing_input(sealed.key_id.as_deref(), sealed.nonce.as_deref(), &sealed.payload);
+      hmac::verify(key, &signed, &signature).map_err(|_| "Payload signature does not match")?;
+    }
+
+    match &self.encryption {
+      Some(key) => {
+        let nonce: [u8; NONCE_LEN] = STANDARD.decode(sealed.nonce.as_deref().ok_or("Payload is not encrypted")?)?
+          .try_into()
+          .map_err(|_| "Payload nonce has the wrong length")?;
+
+        let mut in_out = STANDARD.decode(&sealed.payload)?;
+        let plaintext = key.open_in_place(Nonce::assume_unique_for_key(nonce), self.aad(), &mut in_out)
+          .map_err(|_| "Failed to decrypt payload")?;
+
+        Ok(plaintext.to_vec())
+      }
+      None => Ok(sealed.payload.clone().into_bytes())
+    }
+  }
+
+  fn aad(
+    &self
+  ) -> Aad<Vec<u8>> {
+    Aad::from(self.key_id.clone().unwrap_or_default().into_bytes())
+  }
+}
+
+#[cfg(feature = "sealing")]
+fn signing_input(
+  key_id  : Option<&str>,
+  nonce   : Option<&str>,
+  payload : &str
+) -> Vec<u8> {
+
+  let mut input = Vec::new();
+  for part in [key_id.unwrap_or(""), nonce.unwrap_or(""), payload] {
+    input.extend_from_slice(&(part.len() as u32).to_be_bytes());
+    input.extend_from_slice(part.as_bytes());
+  }
+  input
+}
+
+#[cfg(not(feature = "sealing"))]
+pub enum PayloadSealer {}
+
+#[cfg(not(feature = "sealing"))]
+impl PayloadSealer {
+
+  pub fn new(
+    _config: &PayloadProtectionConfig
+  ) -> Result<Self, SinkError> {
+    Err("Payload protection requires the \"sealing\" feature, which this build does not include".into())
+  }
+
+  pub fn seal(
+    &self,
+    _plaintext: &[u8]
+  ) -> Result<SealedPayload, SinkError> {
+    match *self {}
+  }
+
+  pub fn open(
+    &self,
+    _sealed: &SealedPayload
+  ) -> Result<Vec<u8>, SinkError> {
+    match *self {}
+  }
+}
+
+#[cfg(all(test, feature = "sealing"))]
+mod tests {
+
+  use super::*;
+
+  fn protection(
+    encryption_key : Option<&str>,
+    signing_key    : Option<&str>
+  ) -> PayloadProtectionConfig {
+    PayloadProtectionConfig {
+      encryption_key : encryption_key.map(|key| Secret::Inline(key.to_string())),
+      signing_key    : signing_key.map(|key| Secret::Inline(key.to_string())),
+      key_id         : Some("dock-2026".to_string())
+    }
+  }
+
+  #[test]
+  fn sealed_payloads_open_only_with_the_same_keys() {
+
+    let encryption_key = STANDARD.encode([7u8; 32]);
+    let signing_key = STANDARD.encode(b"shared broker signing key");
+    let sealer = PayloadSealer::new(&protection(Some(&encryption_key), Some(&signing_key))).unwrap();
+
+    let event = br#"{"reader_id":"dock-1","epc":"300833b2ddd9014000000000"}"#;
+    let sealed = sealer.seal(event).unwrap();
+
+    assert!(!sealed.payload.contains("300833b2"));
+    assert_eq!(sealer.open(&sealed).unwrap(), event);
+    assert_ne!(sealer.seal(event).unwrap().nonce, sealed.nonce);
+
+    let mut tampered = sealed.clone();
+    tampered.key_id = Some("dock-2025".to_string());
+    assert!(sealer.open(&tampered).is_err());
+
+    let other = PayloadSealer::new(&protection(Some(&STANDARD.encode([8u8; 32])), None)).unwrap();
+    assert!(other.open(&sealed).is_err());
+
+    let signer = PayloadSealer::new(&protection(None, Some(&signing_key))).unwrap();
+    let mut signed = signer.seal(event).unwrap();
+    assert_eq!(signed.payload.as_bytes(), event);
+    signed.payload = signed.payload.replace("dock-1", "dock-9");
+    assert!(signer.open(&signed).is_err());
+
+    assert!(PayloadSealer::new(&protection(None, None)).is_err());
+    assert!(PayloadSealer::new(&protection(Some(&STANDARD.encode([7u8; 16])), None)).is_err());
+  }
+}
\ No newline at end of file
@@ -0,0 +1,141 @@
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize, Serializer};
+
+const REDACTED: &str = "***";
+
+thread_local! {
+  static SERIALIZE_RESOLVED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with secrets serializing as their resolved values instead of as given,
+/// to compare configurations by what their secrets hold. A secret that does not
+/// resolve still serializes as given.
+pub fn with_resolved_secrets<F, R>(
+  f: F
+) -> R
+where
+  F: FnOnce() -> R
+{
+  let previous = SERIALIZE_RESOLVED.with(|resolved| resolved.replace(true));
+  let result = f();
+  SERIALIZE_RESOLVED.with(|resolved| resolved.set(previous));
+  result
+}
+
+/// A credential referenced from the configuration.
+///
+/// In JSON a secret is written as `{"env": "VAR_NAME"}` to read it from an
+/// environment variable, `{"file": "/run/secrets/token"}` to read it from a file,
+/// or as a plain string for an inline value. Secrets never appear in logs, as the
+/// `Debug` output only names the reference. Serializing writes the secret the way
+/// it was given, so a saved configuration keeps its references and inline values.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+  Env    { env: String },
+  File   { file: PathBuf },
+  Inline (String)
+}
+
+impl Secret {
+
+  /// Returns the secret value. File contents are stripped of trailing line breaks.
+  pub fn resolve(
+    &self
+  ) -> io::Result<String> {
+    match self {
+
+      Secret::Env { env } => env::var(env).map_err(|_| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Environment variable {} referenced by secret is not set", env)
+      )),
+
+      Secret::File { file } => fs::read_to_string(file)
+        .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| io::Error::new(
+          e.kind(),
+          format!("Failed to read secret file {}: {}", file.display(), e)
+        )),
+
+      Secret::Inline(value) => Ok(value.clone())
+    }
+  }
+}
+
+impl fmt::Debug for Secret {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>
+  ) -> fmt::Result {
+    match self {
+      Secret::Env { env } => write!(f, "Secret(env:{})", env),
+      Secret::File { file } => write!(f, "Secret(file:{})", file.display()),
+      Secret::Inline(_) => write!(f, "Secret({})", REDACTED)
+    }
+  }
+}
+
+impl Serialize for Secret {
+  fn serialize<S>(
+    &self,
+    serializer: S
+  ) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer
+  {
+
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum Reference<'a> {
+      Env  { env: &'a str },
+      File { file: &'a PathBuf }
+    }
+
+    if SERIALIZE_RESOLVED.with(Cell::get) {
+      if let Ok(value) = self.resolve() {
+        return serializer.serialize_str(&value);
+      }
+    }
+
+    match self {
+      Secret::Env { env } => Reference::Env { env }.serialize(serializer),
+      Secret::File { file } => Reference::File { file }.serialize(serializer),
+      Secret::Inline(value) => serializer.serialize_str(value)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  #[test]
+  fn secrets_serialize_as_given_and_debug_without_their_value() {
+
+    for json in [r#"{"env":"READER_TOKEN"}"#, r#"{"file":"/run/secrets/token"}"#, r#""hunter2""#] {
+      let secret: Secret = serde_json::from_str(json).unwrap();
+      assert_eq!(serde_json::to_string(&secret).unwrap(), json);
+    }
+
+    let inline: Secret = serde_json::from_str(r#""hunter2""#).unwrap();
+    assert_eq!(format!("{:?}", inline), "Secret(***)");
+  }
+
+  #[test]
+  fn secrets_serialize_resolved_only_when_asked() {
+
+    env::set_var("LLRP_TEST_RESOLVED_SECRET", "hunter2");
+    let secret = Secret::Env { env: "LLRP_TEST_RESOLVED_SECRET".to_string() };
+    let missing = Secret::Env { env: "LLRP_TEST_MISSING_SECRET".to_string() };
+
+    assert_eq!(with_resolved_secrets(|| serde_json::to_string(&secret).unwrap()), r#""hunter2""#);
+    assert_eq!(with_resolved_secrets(|| serde_json::to_string(&missing).unwrap()), r#"{"env":"LLRP_TEST_MISSING_SECRET"}"#);
+    assert_eq!(serde_json::to_string(&secret).unwrap(), r#"{"env":"LLRP_TEST_RESOLVED_SECRET"}"#);
+  }
+}
\ No newline at end of file
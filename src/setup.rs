@@ -0,0 +1,207 @@
+use log::{info, warn, error};
+
+use crate::client::LlrpClient;
+use crate::config::{GpiPortConfig, GpoOutputConfig, KeepaliveSpecConfig, KeepaliveTrigger, ReaderConfig};
+use crate::error::LlrpError;
+use crate::llrp::{LlrpResponseData, ReaderConfigRequest};
+use crate::params::LlrpParameterData;
+
+/// A reader-side change made as part of a setup sequence.
+#[derive(Debug, Clone)]
+pub enum AppliedStep {
+  ReaderConfig  { previous: Option<ReaderConfig> },
+  ROSpecAdded   { rospec_id: u32 },
+  ROSpecEnabled { rospec_id: u32 },
+  ROSpecStarted { rospec_id: u32 }
+}
+
+/// Tracks the steps applied to a reader during setup so that they can be
+/// undone if a later step fails.
+///
+/// Each helper performs the corresponding LLRP request and records it only
+/// once the reader has acknowledged it. `rollback` reverts the recorded steps
+/// in reverse order: started ROSpecs are stopped, added ROSpecs are deleted and
+/// the reader configuration captured before SET_READER_CONFIG is re-applied.
+#[derive(Debug, Default)]
+pub struct SetupTransaction {
+  applied: Vec<AppliedStep>
+}
+
+impl SetupTransaction {
+
+  pub fn new() -> Self {
+    SetupTransaction { applied: Vec::new() }
+  }
+
+  /// Captures the current RF configuration and applies `reader_config`.
+  pub async fn set_reader_config(
+    &mut self,
+    client        : &mut LlrpClient,
+    reader_config : &ReaderConfig
+  ) -> Result<(), LlrpError> {
+
+    let previous = match capture_reader_config(client).await {
+      Ok(previous) => previous,
+      Err(e) => {
+        warn!("Unable to capture reader configuration before SetReaderConfig: {}", e);
+        None
+      }
+    };
+
+    client.send_reader_config(reader_config).await?;
+    self.applied.push(AppliedStep::ReaderConfig { previous });
+
+    Ok(())
+  }
+
+  pub async fn add_rospec(
+    &mut self,
+    client: &mut LlrpClient
+  ) -> Result<(), LlrpError> {
+
+    client.send_add_rospec().await?;
+    self.applied.push(AppliedStep::ROSpecAdded { rospec_id: client.config().rospec.rospec_id });
+
+    Ok(())
+  }
+
+  pub async fn enable_rospec(
+    &mut self,
+    client: &mut LlrpClient
+  ) -> Result<(), LlrpError> {
+
+    client.send_enable_rospec().await?;
+    self.applied.push(AppliedStep::ROSpecEnabled { rospec_id: client.config().rospec.rospec_id });
+
+    Ok(())
+  }
+
+  pub async fn start_rospec(
+    &mut self,
+    client: &mut LlrpClient
+  ) -> Result<(), LlrpError> {
+
+    client.send_start_rospec().await?;
+    self.applied.push(AppliedStep::ROSpecStarted { rospec_id: client.config().rospec.rospec_id });
+
+    Ok(())
+  }
+
+  /// Keeps the applied changes.
+  pub fn commit(
+    self
+  ) {
+    info!("Setup committed ({} steps applied)", self.applied.len());
+  }
+
+  /// Reverts the applied steps in reverse order. Errors are logged and the
+  /// remaining steps are still attempted; the failed steps are returned.
+  pub async fn rollback(
+    mut self,
+    client: &mut LlrpClient
+  ) -> Vec<AppliedStep> {
+
+    let mut failed = Vec::new();
+
+    while let Some(step) = self.applied.pop() {
+
+      let result = match &step {
+
+        AppliedStep::ROSpecStarted { rospec_id } => {
+          info!("Rollback: stopping ROSpec {}", rospec_id);
+          client.send_stop_rospec_with_id(*rospec_id).await
+        }
+
+        // Deleting the ROSpec below also disables it.
+        AppliedStep::ROSpecEnabled { rospec_id } => {
+          info!("Rollback: ROSpec {} will be disabled by its deletion", rospec_id);
+          Ok(())
+        }
+
+        AppliedStep::ROSpecAdded { rospec_id } => {
+          info!("Rollback: deleting ROSpec {}", rospec_id);
+          client.send_delete_rospec(*rospec_id).await
+        }
+
+        AppliedStep::ReaderConfig { previous: Some(previous) } => {
+          info!("Rollback: restoring previous reader configuration");
+          client.send_reader_config(previous).await
+        }
+
+        AppliedStep::ReaderConfig { previous: None } => {
+          warn!("Rollback: previous reader configuration unknown, leaving current configuration");
+          Ok(())
+        }
+      };
+
+      if let Err(e) = result {
+        error!("Rollback step {:?} failed: {}", step, e);
+        failed.push(step);
+      }
+    }
+
+    failed
+  }
+}
+
+/// Reads the current RF settings of the first configured antenna, and the GPI port
+/// and GPO levels, back into a `ReaderConfig`, if the reader reports the RF settings.
+async fn capture_reader_config(
+  client: &mut LlrpClient
+) -> Result<Option<ReaderConfig>, LlrpError> {
+
+  let mut captured = None;
+
+  client.send_get_reader_config(ReaderConfigRequest::default(), |response_data| {
+
+    if let LlrpResponseData::ReaderConfig(parameters) = response_data {
+
+      let mut gpi_ports = Vec::new();
+      let mut gpo_outputs = Vec::new();
+      let mut keepalive_spec = None;
+      let mut rf_settings = None;
+
+      for parameter in parameters {
+        match parameter {
+
+          LlrpParameterData::AntennaConfiguration(antenna_configuration) if rf_settings.is_none() => {
+            if let (Some(rf_receiver), Some(rf_transmitter)) = (antenna_configuration.rf_receiver, antenna_configuration.rf_transmitter) {
+              rf_settings = Some((rf_receiver, rf_transmitter));
+            }
+          }
+
+          LlrpParameterData::GPIPortCurrentState(gpi_state) => {
+            gpi_ports.push(GpiPortConfig { port: gpi_state.port, enabled: gpi_state.enabled });
+          }
+
+          LlrpParameterData::GPOWriteData(gpo_data) => {
+            gpo_outputs.push(GpoOutputConfig { port: gpo_data.port, state: gpo_data.state });
+          }
+
+          LlrpParameterData::KeepaliveSpec(spec) => {
+            keepalive_spec = Some(KeepaliveSpecConfig {
+              trigger  : if spec.trigger_type == 1 { KeepaliveTrigger::Periodic } else { KeepaliveTrigger::Null },
+              interval : spec.periodic_trigger_value
+            });
+          }
+
+          _ => {}
+        }
+      }
+
+      captured = rf_settings.map(|(rf_receiver, rf_transmitter)| ReaderConfig {
+        hop_table_id         : rf_transmitter.hop_table_id,
+        channel_index        : rf_transmitter.channel_index,
+        tx_power_table_index : rf_transmitter.transmit_power_value,
+        rx_power_table_index : rf_receiver.receiver_sensitivity,
+        gpi_ports,
+        gpo_outputs,
+        keepalive_spec
+      });
+    }
+
+    async {}
+  }).await?;
+
+  Ok(captured)
+}
\ No newline at end of file
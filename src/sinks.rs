@@ -0,0 +1,529 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use async_trait::async_trait;
+use log::warn;
+use serde::Serialize;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
+
+use crate::config::SinkConfig;
+use crate::llrp::ReceiveTimestamp;
+use crate::params::TagReportData;
+use crate::sealing::PayloadSealer;
+
+pub type SinkError = Box<dyn Error + Send + Sync>;
+
+/// A single tag observation handed to sinks.
+///
+/// Fields:
+/// - `reader_id`: Identifier of the reader that reported the tag.
+/// - `epc`: The tag EPC as a lowercase hex string.
+/// - `received_at`: When the client received the report carrying the tag.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagEvent {
+  pub reader_id   : String,
+  pub epc         : String,
+  pub received_at : ReceiveTimestamp
+}
+
+/// The tag events of one ROAccessReport.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagEventBatch {
+  pub reader_id : String,
+  pub events    : Vec<TagEvent>
+}
+
+impl TagEventBatch {
+
+  pub fn from_tag_reports(
+    reader_id   : &str,
+    tag_reports : &[TagReportData]
+  ) -> Self {
+
+    TagEventBatch {
+      reader_id : reader_id.to_string(),
+      events    : tag_reports.iter().map(|tag_report| TagEvent {
+        reader_id   : reader_id.to_string(),
+        epc         : tag_report.to_string(),
+        received_at : tag_report.received_at
+      }).collect()
+    }
+  }
+}
+
+/// Destination for tag events, e.g. a message broker, a file or an HTTP endpoint.
+///
+/// The crate provides file, webhook (`webhook` feature) and MQTT (`mqtt` feature)
+/// sinks. Kafka is not built in, as its client library needs the native
+/// librdkafka; implement this trait around a Kafka producer instead, as for any
+/// proprietary system, and register the sink on the `ReaderManager`. A failed
+/// `publish` is counted in the sink's metrics; the batch is not retried.
+#[async_trait]
+pub trait TagEventSink: Send + Sync {
+
+  /// Short name used in logs and metrics.
+  fn name(&self) -> &str;
+
+  async fn publish(&self, batch: &TagEventBatch) -> Result<(), SinkError>;
+}
+
+/// Delivery counters of a registered sink.
+///
+/// Fields:
+/// - `sink`: Name of the sink.
+/// - `batches_delivered` / `events_delivered`: Successfully published batches and the tag events they held.
+/// - `failures`: Number of batches the sink failed to publish.
+/// - `last_error`: Message of the most recent failure, if any.
+/// - `last_latency_ms`: Duration of the most recent `publish` call.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SinkMetrics {
+  pub sink              : String,
+  pub batches_delivered : u64,
+  pub events_delivered  : u64,
+  pub failures          : u64,
+  pub last_error        : Option<String>,
+  pub last_latency_ms   : u64
+}
+
+struct RegisteredSink {
+  sink    : Arc<dyn TagEventSink>,
+  metrics : Mutex<SinkMetrics>
+}
+
+/// The set of sinks tag events are published to, shared between the manager and
+/// its per-reader dispatch tasks.
+#[derive(Clone, Default)]
+pub struct SinkRegistry {
+  sinks: Arc<RwLock<Vec<Arc<RegisteredSink>>>>
+}
+
+impl SinkRegistry {
+
+  pub fn register(
+    &self,
+    sink: Arc<dyn TagEventSink>
+  ) {
+
+    let metrics = SinkMetrics {
+      sink: sink.name().to_string(),
+      ..SinkMetrics::default()
+    };
+
+    self.sinks.write().unwrap().push(Arc::new(RegisteredSink {
+      sink,
+      metrics: Mutex::new(metrics)
+    }));
+  }
+
+  /// Removes every sink registered under `name`. Returns whether any was removed.
+  pub fn unregister(
+    &self,
+    name: &str
+  ) -> bool {
+
+    let mut sinks = self.sinks.write().unwrap();
+    let count = sinks.len();
+    sinks.retain(|registered| registered.sink.name() != name);
+
+    sinks.len() != count
+  }
+
+  pub fn contains(
+    &self,
+    name: &str
+  ) -> bool {
+    self.sinks.read().unwrap().iter().any(|registered| registered.sink.name() == name)
+  }
+
+  pub fn metrics(
+    &self
+  ) -> Vec<SinkMetrics> {
+    self.sinks.read().unwrap().iter()
+      .map(|registered| registered.metrics.lock().unwrap().clone())
+      .collect()
+  }
+
+  /// Publishes `batch` to every registered sink concurrently.
+  pub async fn publish(
+    &self,
+    batch: &TagEventBatch
+  ) {
+
+    let sinks: Vec<Arc<RegisteredSink>> = self.sinks.read().unwrap().clone();
+
+    futures::future::join_all(sinks.iter().map(|registered| async move {
+
+      let start_time = Instant::now();
+      let result = registered.sink.publish(batch).await;
+
+      let mut metrics = registered.metrics.lock().unwrap();
+      metrics.last_latency_ms = start_time.elapsed().as_millis() as u64;
+
+      match result {
+        Ok(()) => {
+          metrics.batches_delivered += 1;
+          metrics.events_delivered += batch.events.len() as u64;
+        }
+        Err(e) => {
+          warn!("Sink {} failed to publish {} tag events: {}", metrics.sink, batch.events.len(), e);
+          metrics.failures += 1;
+          metrics.last_error = Some(e.to_string());
+        }
+      }
+    })).await;
+  }
+}
+
+/// Appends tag events to a file, one JSON object per line. With a sealer, each
+/// line is the event's sealed envelope instead.
+pub struct FileSink {
+  name   : String,
+  path   : PathBuf,
+  sealer : Option<PayloadSealer>
+}
+
+impl FileSink {
+
+  pub fn new(
+    name : &str,
+    path : impl Into<PathBuf>
+  ) -> Self {
+    FileSink {
+      name   : name.to_string(),
+      path   : path.into(),
+      sealer : None
+    }
+  }
+
+  /// Encrypts and/or signs every event written.
+  pub fn with_sealer(
+    mut self,
+    sealer: PayloadSealer
+  ) -> Self {
+    self.sealer = Some(sealer);
+    self
+  }
+}
+
+#[async_trait]
+impl TagEventSink for FileSink {
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  async fn publish(&self, batch: &TagEventBatch) -> Result<(), SinkError> {
+
+    let mut lines = String::new();
+    for event in &batch.events {
+      let line = serde_json::to_string(event)?;
+      match &self.sealer {
+        Some(sealer) => lines.push_str(&serde_json::to_string(&sealer.seal(line.as_bytes())?)?),
+        None => lines.push_str(&line)
+      }
+      lines.push('\n');
+    }
+
+    let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)
+      .await?;
+
+    // tokio writes in the background; flush so the batch is on disk once delivered.
+    file.write_all(lines.as_bytes()).await?;
+    file.flush().await?;
+
+    Ok(())
+  }
+}
+
+/// Builds the sink described by a configuration entry, resolving its secrets.
+pub fn build_sink(
+  config: &SinkConfig
+) -> Result<Arc<dyn TagEventSink>, SinkError> {
+  match config {
+
+    SinkConfig::File { name, path, protection } => {
+      let sink = FileSink::new(name, path);
+      Ok(Arc::new(match protection {
+        Some(protection) => sink.with_sealer(PayloadSealer::new(protection)?),
+        None => sink
+      }))
+    }
+
+    #[cfg(feature = "webhook")]
+    SinkConfig::Webhook { name, url, token, protection } => {
+      let token = token.as_ref().map(|token| token.resolve()).transpose()?;
+      let sink = WebhookSink::new(name, url, token)?;
+      Ok(Arc::new(match protection {
+        Some(protection) => sink.with_sealer(PayloadSealer::new(protection)?),
+        None => sink
+      }))
+    }
+
+    #[cfg(not(feature = "webhook"))]
+    SinkConfig::Webhook { name, .. } => Err(format!(
+      "Sink {} requires the \"webhook\" feature, which this build does not include",
+      name
+    ).into()),
+
+    #[cfg(feature = "mqtt")]
+    SinkConfig::Mqtt { name, broker, topic, client_id, username, password, protection } => {
+      let credentials = match (username, password) {
+        (Some(username), Some(password)) => Some((username.clone(), password.resolve()?)),
+        (Some(username), None) => Some((username.clone(), String::new())),
+        (None, _) => None
+      };
+      let client_id = client_id.clone().unwrap_or_else(|| format!("llrp-{}", name));
+      let sink = MqttSink::new(name, broker, topic, &client_id, credentials)?;
+      Ok(Arc::new(match protection {
+        Some(protection) => sink.with_sealer(PayloadSealer::new(protection)?),
+        None => sink
+      }))
+    }
+
+    #[cfg(not(feature = "mqtt"))]
+    SinkConfig::Mqtt { name, .. } => Err(format!(
+      "Sink {} requires the \"mqtt\" feature, which this build does not include",
+      name
+    ).into())
+  }
+}
+
+/// POSTs each batch as JSON to an HTTP endpoint, optionally with a bearer token.
+/// With a sealer, the body is the batch's sealed envelope instead.
+#[cfg(feature = "webhook")]
+pub struct WebhookSink {
+  name   : String,
+  url    : String,
+  token  : Option<String>,
+  sealer : Option<PayloadSealer>,
+  client : reqwest::Client
+}
+
+#[cfg(feature = "webhook")]
+impl WebhookSink {
+
+  pub fn new(
+    name  : &str,
+    url   : &str,
+    token : Option<String>
+  ) -> Result<Self, SinkError> {
+    Ok(WebhookSink {
+      name   : name.to_string(),
+      url    : url.to_string(),
+      token,
+      sealer : None,
+      client : reqwest::Client::builder().build()?
+    })
+  }
+
+  /// Encrypts and/or signs every batch posted.
+  pub fn with_sealer(
+    mut self,
+    sealer: PayloadSealer
+  ) -> Self {
+    self.sealer = Some(sealer);
+    self
+  }
+}
+
+#[cfg(feature = "webhook")]
+#[async_trait]
+impl TagEventSink for WebhookSink {
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  async fn publish(&self, batch: &TagEventBatch) -> Result<(), SinkError> {
+
+    let mut request = match &self.sealer {
+      Some(sealer) => self.client.post(&self.url).json(&sealer.seal(&serde_json::to_vec(batch)?)?),
+      None => self.client.post(&self.url).json(batch)
+    };
+    if let Some(token) = &self.token {
+      request = request.bearer_auth(token);
+    }
+
+    request.send().await?.error_for_status()?;
+
+    Ok(())
+  }
+}
+
+/// Publishes each batch as one JSON message to an MQTT broker, at least once. With
+/// a sealer, the message is the batch's sealed envelope instead.
+#[cfg(feature = "mqtt")]
+pub struct MqttSink {
+  name       : String,
+  topic      : String,
+  sealer     : Option<PayloadSealer>,
+  client     : rumqttc::AsyncClient,
+  event_loop : tokio::task::JoinHandle<()>
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttSink {
+
+  /// Connects to `broker` (`host:port`) as `client_id`. A background task carries
+  /// the published messages to the broker and reconnects after errors, for as long
+  /// as the sink lives. `{reader_id}` in `topic` is replaced by the batch's reader.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub fn new(
+    name        : &str,
+    broker      : &str,
+    topic       : &str,
+    client_id   : &str,
+    credentials : Option<(String, String)>
+  ) -> Result<Self, SinkError> {
+
+    let (host, port) = broker.rsplit_once(':')
+      .ok_or_else(|| format!("MQTT broker {:?} is not of the form <host>:<port>", broker))?;
+
+    let mut options = rumqttc::MqttOptions::new(client_id, host, port.parse::<u16>()?);
+    options.set_keep_alive(std::time::Duration::from_secs(30));
+    if let Some((username, password)) = credentials {
+      options.set_credentials(username, password);
+    }
+
+    let (client, mut event_loop) = rumqttc::AsyncClient::new(options, 256);
+
+    let sink_name = name.to_string();
+    let event_loop = tokio::spawn(async move {
+      loop {
+        if let Err(e) = event_loop.poll().await {
+          warn!("MQTT sink {}: {}", sink_name, e);
+          tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+      }
+    });
+
+    Ok(MqttSink {
+      name   : name.to_string(),
+      topic  : topic.to_string(),
+      sealer : None,
+      client,
+      event_loop
+    })
+  }
+
+  /// Encrypts and/or signs every batch published.
+  pub fn with_sealer(
+    mut self,
+    sealer: PayloadSealer
+  ) -> Self {
+    self.sealer = Some(sealer);
+    self
+  }
+}
+
+#[cfg(feature = "mqtt")]
+impl Drop for MqttSink {
+  fn drop(
+    &mut self
+  ) {
+    self.event_loop.abort();
+  }
+}
+
+#[cfg(feature = "mqtt")]
+#[async_trait]
+impl TagEventSink for MqttSink {
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  async fn publish(&self, batch: &TagEventBatch) -> Result<(), SinkError> {
+
+    let payload = match &self.sealer {
+      Some(sealer) => serde_json::to_vec(&sealer.seal(&serde_json::to_vec(batch)?)?)?,
+      None => serde_json::to_vec(batch)?
+    };
+    let topic = self.topic.replace("{reader_id}", &batch.reader_id);
+
+    self.client.publish(topic, rumqttc::QoS::AtLeastOnce, false, payload).await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  struct FailingSink;
+
+  #[async_trait]
+  impl TagEventSink for FailingSink {
+
+    fn name(&self) -> &str {
+      "failing"
+    }
+
+    async fn publish(&self, _batch: &TagEventBatch) -> Result<(), SinkError> {
+      Err("broker unreachable".into())
+    }
+  }
+
+  fn batch(
+    epcs: &[&str]
+  ) -> TagEventBatch {
+    TagEventBatch {
+      reader_id : "dock-1".to_string(),
+      events    : epcs.iter().map(|epc| TagEvent {
+        reader_id   : "dock-1".to_string(),
+        epc         : epc.to_string(),
+        received_at : ReceiveTimestamp { monotonic_us: 1, utc_us: 1_700_000_000_000_000 }
+      }).collect()
+    }
+  }
+
+  #[tokio::test]
+  async fn a_failing_sink_does_not_keep_batches_from_the_others() {
+
+    let dir = std::env::temp_dir().join(format!("llrp-sinks-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tags.jsonl");
+
+    let registry = SinkRegistry::default();
+    registry.register(Arc::new(FailingSink));
+    registry.register(Arc::new(FileSink::new("file", &path)));
+
+    registry.publish(&batch(&["e2000001", "e2000002"])).await;
+    registry.publish(&batch(&["e2000003"])).await;
+
+    let metrics = registry.metrics();
+
+    assert_eq!(metrics[0].sink, "failing");
+    assert_eq!(metrics[0].batches_delivered, 0);
+    assert_eq!(metrics[0].failures, 2);
+    assert_eq!(metrics[0].last_error.as_deref(), Some("broker unreachable"));
+
+    assert_eq!(metrics[1].sink, "file");
+    assert_eq!(metrics[1].batches_delivered, 2);
+    assert_eq!(metrics[1].events_delivered, 3);
+    assert_eq!(metrics[1].failures, 0);
+    assert_eq!(metrics[1].last_error, None);
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path).unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["reader_id"], "dock-1");
+    assert_eq!(lines[0]["epc"], "e2000001");
+    assert_eq!(lines[2]["epc"], "e2000003");
+    assert_eq!(lines[2]["received_at"]["utc_us"], 1_700_000_000_000_000i64);
+
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::custom::CustomParameter;
+use crate::llrp::LlrpMessageType;
+
+const TELEMETRY_CHANNEL_CAPACITY: usize = 16;
+
+/// Number of messages of each type exchanged with the reader since the client
+/// was initialized, keyed by message type name.
+///
+/// Fields:
+/// - `sent`: Messages written to the reader.
+/// - `received`: Messages read from the reader, including unsolicited events and reports.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProtocolStats {
+  pub sent     : BTreeMap<String, u64>,
+  pub received : BTreeMap<String, u64>
+}
+
+/// Message counters shared between the client and its receive loop.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolCounters {
+  stats: Arc<Mutex<ProtocolStats>>
+}
+
+impl ProtocolCounters {
+
+  pub fn record_sent(
+    &self,
+    message_type: LlrpMessageType
+  ) {
+    *self.stats.lock().unwrap().sent.entry(format!("{:?}", message_type)).or_insert(0) += 1;
+  }
+
+  pub fn record_received(
+    &self,
+    message_type: LlrpMessageType
+  ) {
+    *self.stats.lock().unwrap().received.entry(format!("{:?}", message_type)).or_insert(0) += 1;
+  }
+
+  pub fn snapshot(
+    &self
+  ) -> ProtocolStats {
+    self.stats.lock().unwrap().clone()
+  }
+}
+
+/// Messages the report and event channels could not deliver to every subscriber,
+/// keyed by channel name: `ro_reports`, `reader_events`, `events` and `gpi_events`.
+///
+/// Fields:
+/// - `dropped`: Messages lost by at least one subscriber that had fallen too far behind.
+/// - `blocked_ms`: Time the receive loop waited for slow subscribers, with the `block` strategy.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelStats {
+  pub dropped    : BTreeMap<String, u64>,
+  pub blocked_ms : BTreeMap<String, u64>
+}
+
+/// Channel counters shared between the client and its receive loop.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelCounters {
+  stats: Arc<Mutex<ChannelStats>>
+}
+
+impl ChannelCounters {
+
+  pub fn record_dropped(
+    &self,
+    channel: &str
+  ) {
+    *self.stats.lock().unwrap().dropped.entry(channel.to_string()).or_insert(0) += 1;
+  }
+
+  pub fn record_blocked(
+    &self,
+    channel  : &str,
+    duration : Duration
+  ) {
+    *self.stats.lock().unwrap().blocked_ms.entry(channel.to_string()).or_insert(0) += duration.as_millis() as u64;
+  }
+
+  pub fn snapshot(
+    &self
+  ) -> ChannelStats {
+    self.stats.lock().unwrap().clone()
+  }
+}
+
+/// Offset of the reader's UTC clock from the host's.
+///
+/// Fields:
+/// - `drift_ms`: Reader time minus host time at the latest sample, positive when the reader is ahead.
+/// - `max_abs_drift_ms`: Largest drift seen in either direction.
+/// - `samples`: Number of reader timestamps compared with host time.
+/// - `sampled_at_ms`: Host UTC time of the latest sample, in milliseconds since the Unix epoch.
+/// - `resyncs`: Number of times the reader clock was set to host time.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClockDriftStats {
+  pub drift_ms         : Option<i64>,
+  pub max_abs_drift_ms : u64,
+  pub samples          : u64,
+  pub sampled_at_ms    : Option<i64>,
+  pub resyncs          : u64
+}
+
+#[derive(Debug, Default)]
+struct ClockDriftState {
+  stats       : ClockDriftStats,
+  last_resync : Option<Instant>
+}
+
+/// Clock drift samples shared between the client and its receive loop.
+#[derive(Debug, Clone, Default)]
+pub struct ClockDriftTracker {
+  state: Arc<Mutex<ClockDriftState>>
+}
+
+impl ClockDriftTracker {
+
+  /// Records a reader timestamp against the host time the message carrying it was
+  /// received, both in microseconds since the Unix epoch. Returns the drift in milliseconds.
+  pub fn record(
+    &self,
+    reader_utc_us : u64,
+    host_utc_us   : i64
+  ) -> i64 {
+
+    let drift_us = reader_utc_us as i128 - host_utc_us as i128;
+    let drift_ms = (drift_us / 1000).clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+
+    let stats = &mut self.state.lock().unwrap().stats;
+    stats.drift_ms = Some(drift_ms);
+    stats.max_abs_drift_ms = stats.max_abs_drift_ms.max(drift_ms.unsigned_abs());
+    stats.samples += 1;
+    stats.sampled_at_ms = Some(host_utc_us / 1000);
+
+    drift_ms
+  }
+
+  /// Whether the reader clock may be set, i.e. it was not within `interval` of `now`.
+  /// Claims the resync when it is due; `record_resync` counts it once sent.
+  pub fn resync_due(
+    &self,
+    now      : Instant,
+    interval : Duration
+  ) -> bool {
+    claim_if_due(&mut self.state.lock().unwrap().last_resync, now, interval)
+  }
+
+  pub fn record_resync(
+    &self
+  ) {
+    self.state.lock().unwrap().stats.resyncs += 1;
+  }
+
+  pub fn snapshot(
+    &self
+  ) -> ClockDriftStats {
+    self.state.lock().unwrap().stats.clone()
+  }
+}
+
+/// Temperature and utilization values reported by the reader in one message.
+///
+/// LLRP has no standard parameter for these, so they are read from the Custom
+/// parameters of reader events and GET_READER_CONFIG responses: a decoder
+/// registered with `register_custom_parameter` that decodes to a JSON object with a
+/// numeric `temperature_c`, `cpu_utilization_pct` or `rf_utilization_pct` field
+/// reports that value, whichever vendor defines the parameter.
+///
+/// Fields:
+/// - `timestamp_ms`: Host UTC time the message was received, in milliseconds since the Unix epoch.
+/// - `temperature_c`: Reader temperature in degrees Celsius.
+/// - `cpu_utilization_pct`: CPU load of the reader, 0-100.
+/// - `rf_utilization_pct`: Share of time the reader transmitted, 0-100.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TelemetrySample {
+  pub timestamp_ms        : i64,
+  pub temperature_c       : Option<f64>,
+  pub cpu_utilization_pct : Option<f64>,
+  pub rf_utilization_pct  : Option<f64>
+}
+
+impl TelemetrySample {
+
+  /// Collects the telemetry values of `parameters`, or `None` if none reports any.
+  pub fn from_custom_parameters(
+    parameters   : &[CustomParameter],
+    timestamp_ms : i64
+  ) -> Option<Self> {
+
+    let mut sample = TelemetrySample { timestamp_ms, ..TelemetrySample::default() };
+
+    for decoded in parameters.iter().filter_map(|parameter| parameter.decoded.as_ref()) {
+      let field = |name: &str| decoded.get(name).and_then(serde_json::Value::as_f64);
+      sample.temperature_c = field("temperature_c").or(sample.temperature_c);
+      sample.cpu_utilization_pct = field("cpu_utilization_pct").or(sample.cpu_utilization_pct);
+      sample.rf_utilization_pct = field("rf_utilization_pct").or(sample.rf_utilization_pct);
+    }
+
+    let reported = sample.temperature_c.is_some() || sample.cpu_utilization_pct.is_some() || sample.rf_utilization_pct.is_some();
+    reported.then_some(sample)
+  }
+}
+
+/// The latest telemetry of a reader. Each value is the last one reported, as a
+/// message may carry only some of them.
+///
+/// Fields:
+/// - `temperature_c`: Last reported temperature in degrees Celsius.
+/// - `max_temperature_c`: Highest temperature reported.
+/// - `cpu_utilization_pct`: Last reported CPU load, 0-100.
+/// - `rf_utilization_pct`: Last reported transmit duty, 0-100.
+/// - `samples`: Number of messages that reported telemetry.
+/// - `sampled_at_ms`: Host UTC time of the latest sample, in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TelemetryStats {
+  pub temperature_c       : Option<f64>,
+  pub max_temperature_c   : Option<f64>,
+  pub cpu_utilization_pct : Option<f64>,
+  pub rf_utilization_pct  : Option<f64>,
+  pub samples             : u64,
+  pub sampled_at_ms       : Option<i64>
+}
+
+/// Telemetry shared between the client and its receive loop, which publishes each
+/// sample to subscribers.
+#[derive(Debug, Clone)]
+pub struct TelemetryTracker {
+  stats     : Arc<Mutex<TelemetryStats>>,
+  sample_tx : broadcast::Sender<TelemetrySample>
+}
+
+impl Default for TelemetryTracker {
+
+  fn default() -> Self {
+    let (sample_tx, _) = broadcast::channel(TELEMETRY_CHANNEL_CAPACITY);
+    TelemetryTracker { stats: Arc::default(), sample_tx }
+  }
+}
+
+impl TelemetryTracker {
+
+  pub fn record(
+    &self,
+    sample: TelemetrySample
+  ) {
+
+    {
+      let mut stats = self.stats.lock().unwrap();
+      stats.temperature_c = sample.temperature_c.or(stats.temperature_c);
+      stats.max_temperature_c = match (stats.max_temperature_c, sample.temperature_c) {
+        (Some(max), Some(temperature_c)) => Some(max.max(temperature_c)),
+        (max, temperature_c) => max.or(temperature_c)
+      };
+      stats.cpu_utilization_pct = sample.cpu_utilization_pct.or(stats.cpu_utilization_pct);
+      stats.rf_utilization_pct = sample.rf_utilization_pct.or(stats.rf_utilization_pct);
+      stats.samples += 1;
+      stats.sampled_at_ms = Some(sample.timestamp_ms);
+    }
+
+    let _ = self.sample_tx.send(sample);
+  }
+
+  pub fn subscribe(
+    &self
+  ) -> broadcast::Receiver<TelemetrySample> {
+    self.sample_tx.subscribe()
+  }
+
+  pub fn snapshot(
+    &self
+  ) -> TelemetryStats {
+    self.stats.lock().unwrap().clone()
+  }
+}
+
+fn claim_if_due(
+  last     : &mut Option<Instant>,
+  now      : Instant,
+  interval : Duration
+) -> bool {
+
+  if last.is_some_and(|last| now.saturating_duration_since(last) < interval) {
+    return false;
+  }
+
+  *last = Some(now);
+  true
+}
\ No newline at end of file
@@ -0,0 +1,111 @@
+//! The connection to a reader: plain TCP, or TLS over TCP with the `tls` feature.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+#[cfg(feature = "tls")]
+use bytes::{Buf, BytesMut};
+#[cfg(feature = "tls")]
+use tokio::io::AsyncReadExt;
+#[cfg(feature = "tls")]
+use tokio_rustls::client::TlsStream;
+
+/// Variants:
+/// - `Tcp`: A plain TCP connection.
+/// - `Tls`: A TLS session, with the bytes read by `peek` and not yet consumed.
+pub enum LlrpStream {
+  Tcp(TcpStream),
+  #[cfg(feature = "tls")]
+  Tls { stream: Box<TlsStream<TcpStream>>, peeked: BytesMut }
+}
+
+impl LlrpStream {
+
+  /// Reads into `buf` without consuming the bytes, like `TcpStream::peek`. Returns
+  /// 0 once the connection is closed. A TLS session decrypts what it peeks, so
+  /// it may return fewer bytes than are on the wire; call again for more.
+  pub async fn peek(
+    &mut self,
+    buf: &mut [u8]
+  ) -> io::Result<usize> {
+    match self {
+
+      LlrpStream::Tcp(stream) => stream.peek(buf).await,
+
+      #[cfg(feature = "tls")]
+      LlrpStream::Tls { stream, peeked } => {
+        if peeked.len() < buf.len() && stream.read_buf(peeked).await? == 0 {
+          return Ok(0);
+        }
+        let n = peeked.len().min(buf.len());
+        buf[..n].copy_from_slice(&peeked[..n]);
+        Ok(n)
+      }
+    }
+  }
+}
+
+impl AsyncRead for LlrpStream {
+
+  fn poll_read(
+    self : Pin<&mut Self>,
+    cx   : &mut Context<'_>,
+    buf  : &mut ReadBuf<'_>
+  ) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+
+      LlrpStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+
+      #[cfg(feature = "tls")]
+      LlrpStream::Tls { stream, peeked } => {
+        if peeked.is_empty() {
+          return Pin::new(stream).poll_read(cx, buf);
+        }
+        let n = peeked.len().min(buf.remaining());
+        buf.put_slice(&peeked[..n]);
+        peeked.advance(n);
+        Poll::Ready(Ok(()))
+      }
+    }
+  }
+}
+
+impl AsyncWrite for LlrpStream {
+
+  fn poll_write(
+    self : Pin<&mut Self>,
+    cx   : &mut Context<'_>,
+    buf  : &[u8]
+  ) -> Poll<io::Result<usize>> {
+    match self.get_mut() {
+      LlrpStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+      #[cfg(feature = "tls")]
+      LlrpStream::Tls { stream, .. } => Pin::new(stream).poll_write(cx, buf)
+    }
+  }
+
+  fn poll_flush(
+    self : Pin<&mut Self>,
+    cx   : &mut Context<'_>
+  ) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      LlrpStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+      #[cfg(feature = "tls")]
+      LlrpStream::Tls { stream, .. } => Pin::new(stream).poll_flush(cx)
+    }
+  }
+
+  fn poll_shutdown(
+    self : Pin<&mut Self>,
+    cx   : &mut Context<'_>
+  ) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      LlrpStream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+      #[cfg(feature = "tls")]
+      LlrpStream::Tls { stream, .. } => Pin::new(stream).poll_shutdown(cx)
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,78 @@
+//! A continuous stream of the tags in the reader's ROAccessReports, for
+//! applications consuming an inventory as it runs rather than one report at a time.
+
+use std::collections::VecDeque;
+use futures::stream::{self, Stream};
+use log::warn;
+use tokio::sync::broadcast;
+
+use crate::llrp::{LlrpResponse, LlrpResponseData};
+use crate::params::TagReportData;
+
+/// The tags of every ROAccessReport received after `LlrpClient::tag_report_stream`,
+/// in the order the reader reported them. The stream carries over reconnects and
+/// ends once the client is dropped and its connection closed.
+pub struct TagReportStream {
+  ro_report_rx   : broadcast::Receiver<LlrpResponse>,
+  pending        : VecDeque<TagReportData>,
+  missed_reports : u64
+}
+
+impl TagReportStream {
+
+  pub(crate) fn new(
+    ro_report_rx: broadcast::Receiver<LlrpResponse>
+  ) -> Self {
+    TagReportStream {
+      ro_report_rx,
+      pending        : VecDeque::new(),
+      missed_reports : 0
+    }
+  }
+
+  /// Waits for the next tag, or returns `None` once the stream has ended. Reports
+  /// that fail to decode are logged and skipped.
+  pub async fn recv(
+    &mut self
+  ) -> Option<TagReportData> {
+
+    loop {
+
+      if let Some(tag) = self.pending.pop_front() {
+        return Some(tag);
+      }
+
+      match self.ro_report_rx.recv().await {
+
+        Ok(response) => match response.decode() {
+          Ok(LlrpResponseData::TagReport(tags)) => self.pending.extend(tags),
+          Ok(_) => {}
+          Err(e) => warn!("Failed to decode ROAccessReport for the tag report stream: {}", e)
+        },
+
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+          self.missed_reports += skipped;
+          warn!("Tag report stream fell behind, {} ROAccessReports missed", skipped);
+        }
+
+        Err(broadcast::error::RecvError::Closed) => return None
+      }
+    }
+  }
+
+  /// Number of ROAccessReports missed because the consumer fell behind the reader.
+  pub fn missed_reports(
+    &self
+  ) -> u64 {
+    self.missed_reports
+  }
+
+  /// Turns the handle into a `Stream` of tags, for use with stream combinators.
+  pub fn into_stream(
+    self
+  ) -> impl Stream<Item = TagReportData> {
+    stream::unfold(self, |mut tags| async move {
+      tags.recv().await.map(|tag| (tag, tags))
+    })
+  }
+}
\ No newline at end of file
@@ -0,0 +1,1360 @@
+//! Tests driving the client against a scripted LLRP peer, exercising its response
+//! matching against message sequences a real reader may produce.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::{BufMut, BytesMut};
+use futures::{FutureExt, StreamExt};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::client::{ClientOptions, ConnectHook, FrameDirection, LlrpClient};
+use crate::clock::ReplayClock;
+use crate::config::{C1G2InventoryCommandConfig, C1G2RFControlConfig, C1G2SingulationConfig, GpiPortConfig, GpoOutputConfig, ListenConfig, LivenessConfig, ReaderConfig, StartupAction};
+use crate::gpio::{GpiPort, GpoPort, PinState};
+use crate::custom::CustomMessage;
+use crate::error::LlrpError;
+use crate::history::ConnectionEventKind;
+use crate::llrp::{LlrpHeader, LlrpMessage, LlrpMessageType, LlrpParameterType, LlrpResponseData, LlrpVersion, ReaderConfigData, ReaderConfigRequest, LLRP_HEADER_LENGTH};
+use crate::params::AntennaEventType;
+use crate::scripted_reader::{connect, connection_attempt_event, failed_status_response, keepalive_ack, reader_event_notification, ro_access_report, status_response, test_config, timestamped_reader_event, ScriptedConnection, ScriptedReader};
+
+#[tokio::test]
+async fn unsolicited_messages_between_request_and_response_are_routed_aside() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[
+      reader_event_notification(),
+      ro_access_report(0xE200_0000_0000_0000_0000_0001),
+      status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)
+    ]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut ro_reports = client.subscribe_ro_reports();
+
+  let callbacks = Arc::new(AtomicUsize::new(0));
+  let callback_count = callbacks.clone();
+
+  client.send_get_reader_config(ReaderConfigRequest::default(), move |_| {
+    let callback_count = callback_count.clone();
+    async move { callback_count.fetch_add(1, Ordering::SeqCst); }
+  }).await.unwrap();
+
+  assert_eq!(callbacks.load(Ordering::SeqCst), 1);
+
+  let report = ro_reports.try_recv().unwrap();
+  assert_eq!(report.message_type, LlrpMessageType::ROAccessReport);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn responses_split_across_segments_are_reassembled() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    let response = status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id);
+    connection.send_split(&reader_event_notification(), &[3]).await?;
+    connection.send_split(&response, &[1, LLRP_HEADER_LENGTH, LLRP_HEADER_LENGTH + 5]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  client.send_get_reader_config(ReaderConfigRequest::default(), |_| async {}).await.unwrap();
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn response_with_another_message_id_is_not_accepted() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[keepalive_ack(request.message_id.wrapping_add(100))]).await?;
+    connection.expect(LlrpMessageType::Keepalive).await?;
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 200).await;
+
+  let error = client.send_keep_alive().await.unwrap_err();
+  assert!(matches!(error, LlrpError::Timeout(_)));
+
+  // Release the scripted reader, which waits for a second request before closing.
+  let _ = client.send_keep_alive().await;
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn responses_arriving_out_of_request_order_are_matched_by_message_id() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+
+    // The first request is answered only after the client has given up on it and
+    // sent the next one; the late answer arrives after the current one.
+    let first = connection.expect(LlrpMessageType::Keepalive).await?;
+    let second = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[keepalive_ack(second.message_id), keepalive_ack(first.message_id)]).await?;
+
+    // The late answer to the first request must not satisfy the third one.
+    let third = connection.expect(LlrpMessageType::Keepalive).await?;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    connection.send(&[keepalive_ack(first.message_id), keepalive_ack(third.message_id)]).await?;
+
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 200).await;
+
+  assert!(client.send_keep_alive().await.is_err());
+  client.send_keep_alive().await.unwrap();
+  client.send_keep_alive().await.unwrap();
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn requests_waiting_on_a_closed_connection_fail_without_timing_out() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    connection.expect(LlrpMessageType::Keepalive).await?;
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 10_000).await;
+
+  let started = std::time::Instant::now();
+  let error = client.send_keep_alive().await.unwrap_err();
+  assert!(matches!(error, LlrpError::ConnectionClosed(_)));
+  assert!(started.elapsed() < Duration::from_secs(5));
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn reader_keepalives_are_acknowledged() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    // Wait for a client request so the client has subscribed before the KEEPALIVE.
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[
+      keepalive_ack(request.message_id),
+      LlrpMessage::new(LlrpMessageType::Keepalive, 7001, vec![])
+    ]).await?;
+    let ack = connection.expect(LlrpMessageType::KeepaliveAck).await?;
+    assert_eq!(ack.message_id, 7001);
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut keepalives = client.subscribe_reader_keepalives();
+
+  client.send_keep_alive().await.unwrap();
+  reader.finish().await;
+
+  assert_eq!(keepalives.recv().await.unwrap().message_id, 7001);
+}
+
+#[tokio::test]
+async fn oversized_frames_are_rejected_before_their_body_is_read() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    connection.expect(LlrpMessageType::Keepalive).await?;
+    let mut header = BytesMut::new();
+    LlrpHeader { version: 1, message_type_value: LlrpMessageType::ROAccessReport.value(), message_length: u32::MAX, message_id: 0 }.encode(&mut header);
+    connection.stream.write_all(&header).await
+  }).await;
+
+  let mut client = connect(&reader.host, 200).await;
+
+  assert!(client.send_keep_alive().await.is_err());
+  reader.finish().await;
+
+  let disconnect_reason = client.connection_history().into_iter().find_map(|event| match event.kind {
+    ConnectionEventKind::Disconnected { reason, .. } => Some(reason),
+    _ => None
+  });
+  assert!(disconnect_reason.unwrap().contains("exceeds max_message_length"));
+}
+
+#[tokio::test]
+async fn startup_actions_run_in_order_on_connect() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+
+    let reset = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    assert_eq!(reset.payload, vec![0x80]);
+    connection.send(&[status_response(LlrpMessageType::SetReaderConfigResponse, reset.message_id)]).await?;
+
+    for (request_type, response_type) in [
+      (LlrpMessageType::SetReaderConfig, LlrpMessageType::SetReaderConfigResponse),
+      (LlrpMessageType::AddROSpec, LlrpMessageType::AddROspecResponse),
+      (LlrpMessageType::EnableROSpec, LlrpMessageType::EnableROSpecResponse),
+      (LlrpMessageType::StartROSpec, LlrpMessageType::StartROSpecResponse)
+    ] {
+      let request = connection.expect(request_type).await?;
+      connection.send(&[status_response(response_type, request.message_id)]).await?;
+    }
+
+    Ok(())
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.startup_actions = vec![
+    StartupAction::FactoryReset,
+    StartupAction::SetConfig,
+    StartupAction::AddRospec,
+    StartupAction::Enable,
+    StartupAction::Start
+  ];
+
+  LlrpClient::initialize_with_config(config).await.unwrap();
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn reader_clock_is_set_when_drift_exceeds_the_resync_threshold() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    let reader_utc_us = chrono::Utc::now().timestamp_micros() as u64 + 10_000_000;
+    connection.send(&[keepalive_ack(request.message_id), timestamped_reader_event(reader_utc_us)]).await?;
+
+    let set_clock = connection.expect(LlrpMessageType::CustomMessage).await?;
+    assert_eq!(&set_clock.payload[..5], &[0x00, 0x00, 0x62, 0xA6, 0x07]);
+    Ok(())
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.alerts.max_clock_drift_ms = Some(5000);
+  config.clock_drift = Some(serde_json::from_value(serde_json::json!({
+    "resync": { "threshold_ms": 1000, "vendor_id": 25254, "message_subtype": 7 }
+  })).unwrap());
+
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+
+  client.send_keep_alive().await.unwrap();
+  reader.finish().await;
+
+  let drift = client.clock_drift_stats();
+  assert!((9000..=10_000).contains(&drift.drift_ms.unwrap()));
+  assert_eq!((drift.samples, drift.resyncs), (1, 1));
+  assert_eq!(client.recent_alerts().len(), 1);
+}
+
+#[tokio::test]
+async fn custom_messages_are_answered_with_the_vendor_reply() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::CustomMessage).await?;
+    assert_eq!(request.payload, vec![0x00, 0x00, 0x65, 0x1A, 0x15, 0xAB]);
+    connection.send(&[
+      LlrpMessage::new(LlrpMessageType::CustomMessage, request.message_id, vec![0x00, 0x00, 0x65, 0x1A, 0x16, 0x01, 0x02])
+    ]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let reply = client.send_custom_message(&CustomMessage { vendor_id: 25882, message_subtype: 21, data: vec![0xAB] }).await.unwrap();
+  assert_eq!(reply, CustomMessage { vendor_id: 25882, message_subtype: 22, data: vec![0x01, 0x02] });
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn protocol_version_is_negotiated_up_to_llrp_1_1() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+
+    let request = connection.expect(LlrpMessageType::GetSupportedVersion).await?;
+    assert_eq!(request.version, LlrpVersion::V1_1.value());
+
+    let mut response = status_response(LlrpMessageType::GetSupportedVersionResponse, request.message_id);
+    response.payload.splice(0..0, [1, 3]);
+    response.message_length += 2;
+    response.version = LlrpVersion::V1_1.value();
+    connection.send(&[response]).await?;
+
+    let request = connection.expect(LlrpMessageType::SetProtocolVersion).await?;
+    assert_eq!(request.version, LlrpVersion::V1_1.value());
+    assert_eq!(request.payload, vec![LlrpVersion::V1_1.value()]);
+    connection.send(&[status_response(LlrpMessageType::SetProtocolVersionResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    assert_eq!(request.version, LlrpVersion::V1_1.value());
+    connection.send(&[keepalive_ack(request.message_id)]).await
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.negotiate_protocol_version = true;
+
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+  assert_eq!(client.protocol_version(), LlrpVersion::V1_1);
+
+  client.send_keep_alive().await.unwrap();
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn readers_answering_get_supported_version_with_an_error_stay_at_llrp_1_0_1() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+
+    let request = connection.expect(LlrpMessageType::GetSupportedVersion).await?;
+    connection.send(&[status_response(LlrpMessageType::ErrorMessage, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    assert_eq!(request.version, LlrpVersion::V1_0_1.value());
+    connection.send(&[keepalive_ack(request.message_id)]).await
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.negotiate_protocol_version = true;
+
+  let started = std::time::Instant::now();
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+  assert_eq!(client.protocol_version(), LlrpVersion::V1_0_1);
+  assert!(started.elapsed() < Duration::from_millis(1000));
+
+  client.send_keep_alive().await.unwrap();
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn connect_hook_runs_its_handshake_before_the_llrp_session() {
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let host = listener.local_addr().unwrap().to_string();
+
+  // A gateway that wants a token line before it passes the reader's traffic through.
+  let gateway = tokio::spawn(async move {
+    let (mut stream, _) = listener.accept().await?;
+    let mut token = [0u8; 10];
+    stream.read_exact(&mut token).await?;
+    assert_eq!(&token, b"TOKEN abc\n");
+    stream.write_all(b"OK\n").await?;
+    stream.write_all(&connection_attempt_event().encode()).await?;
+
+    let mut connection = ScriptedConnection::new(stream);
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await
+  });
+
+  let connect_hook: ConnectHook = Arc::new(|stream: &mut TcpStream| async move {
+    stream.write_all(b"TOKEN abc\n").await?;
+    let mut reply = [0u8; 3];
+    stream.read_exact(&mut reply).await?;
+    assert_eq!(&reply, b"OK\n");
+    Ok(())
+  }.boxed());
+
+  let mut client = LlrpClient::initialize_with_connect_hook(test_config(&host, 1000), connect_hook).await.unwrap();
+
+  client.send_get_reader_config(ReaderConfigRequest::default(), |_| async {}).await.unwrap();
+
+  gateway.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn connections_go_through_the_configured_socks5_proxy() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await
+  }).await;
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let proxy_port = listener.local_addr().unwrap().port();
+  let reader_addr: std::net::SocketAddr = reader.host.parse().unwrap();
+
+  // A SOCKS5 proxy accepting only username/password authentication, which checks
+  // the client asks for the reader and then relays between the two.
+  let proxy = tokio::spawn(async move {
+    let (mut client, _) = listener.accept().await?;
+
+    let mut greeting = [0u8; 2];
+    client.read_exact(&mut greeting).await?;
+    let mut methods = vec![0u8; greeting[1] as usize];
+    client.read_exact(&mut methods).await?;
+    assert!(methods.contains(&0x02));
+    client.write_all(&[0x05, 0x02]).await?;
+
+    let mut auth = [0u8; 2];
+    client.read_exact(&mut auth).await?;
+    let mut username = vec![0u8; auth[1] as usize];
+    client.read_exact(&mut username).await?;
+    let mut password = vec![0u8; client.read_u8().await? as usize];
+    client.read_exact(&mut password).await?;
+    assert_eq!((username.as_slice(), password.as_slice()), (&b"gateway"[..], &b"hunter2"[..]));
+    client.write_all(&[0x01, 0x00]).await?;
+
+    let mut request = [0u8; 10];
+    client.read_exact(&mut request).await?;
+    assert_eq!(request[..4], [0x05, 0x01, 0x00, 0x01]);
+    assert_eq!(u16::from_be_bytes([request[8], request[9]]), reader_addr.port());
+    client.write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await?;
+
+    let mut upstream = TcpStream::connect(reader_addr).await?;
+    let _ = io::copy_bidirectional(&mut client, &mut upstream).await;
+    Ok::<(), io::Error>(())
+  });
+
+  let mut config = test_config(&reader.host, 1000);
+  config.proxy = serde_json::from_value(serde_json::json!({
+    "host": "127.0.0.1",
+    "port": proxy_port,
+    "credentials": { "username": "gateway", "password": "hunter2" }
+  })).unwrap();
+
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+
+  client.send_get_reader_config(ReaderConfigRequest::default(), |_| async {}).await.unwrap();
+
+  reader.finish().await;
+  drop(client);
+  proxy.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn second_client_for_the_same_reader_is_refused_unless_shared() {
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let host = listener.local_addr().unwrap().to_string();
+
+  let reader = tokio::spawn(async move {
+    let mut connections = Vec::new();
+    for _ in 0..3 {
+      let (mut stream, _) = listener.accept().await?;
+      stream.write_all(&connection_attempt_event().encode()).await?;
+      connections.push(stream);
+    }
+    io::Result::Ok(connections)
+  });
+
+  let shared_config = || {
+    let mut config = test_config(&host, 1000);
+    config.allow_shared_connection = true;
+    config
+  };
+
+  let first = connect(&host, 1000).await;
+
+  let error = LlrpClient::initialize_with_config(test_config(&host, 1000)).await.err().unwrap();
+  assert!(matches!(error, LlrpError::InvalidState(_)));
+  assert!(!error.is_transient());
+  assert!(LlrpClient::initialize_with_config(shared_config()).await.is_err());
+
+  drop(first);
+
+  let shared = LlrpClient::initialize_with_config(shared_config()).await.unwrap();
+  let also_shared = LlrpClient::initialize_with_config(shared_config()).await.unwrap();
+
+  // The refused attempts never reached the reader.
+  assert_eq!(reader.await.unwrap().unwrap().len(), 3);
+
+  drop((shared, also_shared));
+}
+
+#[tokio::test]
+async fn vendor_telemetry_in_reader_events_is_tracked_and_alerted() {
+
+  // A made-up vendor event carrying the temperature (s16) and CPU load (u8).
+  crate::custom::register_custom_parameter(1, 9, "TestReaderHealth", |data| {
+    Ok(serde_json::json!({ "temperature_c": i16::from_be_bytes([data[0], data[1]]), "cpu_utilization_pct": data[2] }))
+  });
+
+  let health_event = |temperature_c: i16| {
+    let mut payload = BytesMut::new();
+    payload.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+    payload.put_u16(19);
+    payload.put_u16(LlrpParameterType::Custom.value());
+    payload.put_u16(15);
+    payload.put_u32(1);
+    payload.put_u32(9);
+    payload.put_i16(temperature_c);
+    payload.put_u8(35);
+    LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, payload.to_vec())
+  };
+
+  let reader = ScriptedReader::start(move |mut connection| async move {
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[health_event(58), health_event(71), health_event(72), health_event(60), health_event(70), keepalive_ack(request.message_id)]).await
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.alerts.max_temperature_c = Some(65.0);
+
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+  let mut samples = client.subscribe_telemetry();
+
+  client.send_keep_alive().await.unwrap();
+  reader.finish().await;
+
+  assert_eq!(samples.try_recv().unwrap().temperature_c, Some(58.0));
+  assert_eq!(samples.try_recv().unwrap().cpu_utilization_pct, Some(35.0));
+
+  let telemetry = client.telemetry_stats();
+  assert_eq!((telemetry.temperature_c, telemetry.max_temperature_c, telemetry.samples), (Some(70.0), Some(72.0), 5));
+
+  // One alert per episode above the limit, re-armed once the reader cooled down.
+  assert_eq!(client.recent_alerts().len(), 2);
+}
+
+#[tokio::test]
+async fn requested_telemetry_is_read_from_the_reader_config() {
+
+  crate::custom::register_custom_parameter(1, 10, "TestReaderTemperature", |data| {
+    Ok(serde_json::json!({ "temperature_c": i16::from_be_bytes([data[0], data[1]]) }))
+  });
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+
+    let mut payload = BytesMut::new();
+    payload.put_u16(LlrpParameterType::LLRPStatus.value());
+    payload.put_u16(8);
+    payload.put_u16(0);
+    payload.put_u16(0);
+    payload.put_u16(LlrpParameterType::Custom.value());
+    payload.put_u16(14);
+    payload.put_u32(1);
+    payload.put_u32(10);
+    payload.put_i16(44);
+
+    connection.send(&[LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, request.message_id, payload.to_vec())]).await
+  }).await;
+
+  let mut client = LlrpClient::initialize_with_config(test_config(&reader.host, 1000)).await.unwrap();
+
+  let telemetry = client.request_telemetry().await.unwrap();
+  reader.finish().await;
+
+  assert_eq!((telemetry.temperature_c, telemetry.samples), (Some(44.0), 1));
+}
+
+#[tokio::test]
+async fn sessions_replay_byte_identically_with_a_replay_clock_and_message_ids() {
+
+  const HOST_UTC_US: i64 = 1_700_000_000_000_000;
+
+  async fn run_session() -> Vec<Vec<u8>> {
+
+    let reader = ScriptedReader::start(|mut connection| async move {
+      let request = connection.expect(LlrpMessageType::Keepalive).await?;
+      connection.send(&[timestamped_reader_event(HOST_UTC_US as u64 + 10_000_000), keepalive_ack(request.message_id)]).await?;
+      connection.expect(LlrpMessageType::CustomMessage).await?;
+      let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+      connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await
+    }).await;
+
+    let mut config = test_config(&reader.host, 1000);
+    config.clock_drift = Some(serde_json::from_value(serde_json::json!({
+      "resync": { "threshold_ms": 1000, "vendor_id": 25254, "message_subtype": 7 }
+    })).unwrap());
+
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let observed = sent.clone();
+    let mut next_id = 0;
+
+    let options = ClientOptions {
+      clock          : Arc::new(ReplayClock::new(HOST_UTC_US)),
+      message_ids    : Some(Box::new(move || { next_id += 7; next_id })),
+      frame_observer : Some(Arc::new(move |direction, frame: &[u8]| {
+        if direction == FrameDirection::Sent {
+          observed.lock().unwrap().push(frame.to_vec());
+        }
+      })),
+      ..ClientOptions::default()
+    };
+
+    let mut client = LlrpClient::initialize_with_options(config, options).await.unwrap();
+
+    client.send_keep_alive().await.unwrap();
+    client.send_get_reader_config(ReaderConfigRequest::default(), |_| async {}).await.unwrap();
+    reader.finish().await;
+
+    let frames = sent.lock().unwrap().clone();
+    frames
+  }
+
+  let frames = run_session().await;
+  assert_eq!(frames, run_session().await);
+
+  let message_ids: Vec<u32> = frames.iter().map(|frame| LlrpHeader::decode(frame).unwrap().message_id).collect();
+  assert_eq!(message_ids, vec![7, 0, 14]);
+
+  // The resync carries the replay clock's UTC time.
+  assert_eq!(&frames[1][frames[1].len() - 8..], &(HOST_UTC_US as u64).to_be_bytes());
+}
+
+#[tokio::test]
+async fn gpo_ports_are_driven_together_in_one_set_reader_config() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    assert_eq!(request.payload, vec![
+      0x00,
+      0x00, 0xDB, 0x00, 0x07, 0x00, 0x01, 0x00,
+      0x00, 0xDB, 0x00, 0x07, 0x00, 0x03, 0x80
+    ]);
+    connection.send(&[status_response(LlrpMessageType::SetReaderConfigResponse, request.message_id)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let outputs = vec![
+    GpoOutputConfig { port: GpoPort(1), state: PinState::Low },
+    GpoOutputConfig { port: GpoPort(3), state: PinState::High }
+  ];
+  client.set_gpos(&outputs).await.unwrap();
+
+  let unknown = vec![GpoOutputConfig { port: GpoPort(2), state: PinState::Unknown }];
+  assert!(client.set_gpos(&unknown).await.is_err());
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn gpi_events_of_enabled_ports_reach_subscribers() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    assert_eq!(request.payload, vec![0x00, 0x00, 0xE1, 0x00, 0x08, 0x00, 0x02, 0x80, 0x02]);
+
+    let mut event = BytesMut::new();
+    event.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+    event.put_u16(11);
+    event.put_u16(LlrpParameterType::GPIEvent.value());
+    event.put_u16(7);
+    event.put_u16(2);
+    event.put_u8(0x80);
+
+    connection.send(&[
+      status_response(LlrpMessageType::SetReaderConfigResponse, request.message_id),
+      LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, event.to_vec())
+    ]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut gpi_events = client.subscribe_gpi_events();
+
+  client.set_gpi_ports(&[GpiPortConfig { port: GpiPort(2), enabled: true }]).await.unwrap();
+
+  let gpi_event = tokio::time::timeout(Duration::from_secs(1), gpi_events.recv()).await.unwrap().unwrap();
+  assert_eq!((gpi_event.port, gpi_event.state), (GpiPort(2), PinState::High));
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn configuration_changes_by_other_controllers_are_detected() {
+
+  fn configuration_state(
+    message_id  : u32,
+    state_value : u32
+  ) -> LlrpMessage {
+    let mut payload = status_response(LlrpMessageType::GetReaderConfigResponse, message_id).payload;
+    payload.extend_from_slice(&[0x00, 0xD9, 0x00, 0x08]);
+    payload.extend_from_slice(&state_value.to_be_bytes());
+    LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, message_id, payload)
+  }
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    for state_value in [5, 5, 9] {
+      let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+      assert_eq!(request.payload, vec![0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+      connection.send(&[configuration_state(request.message_id, state_value)]).await?;
+    }
+
+    let request = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    connection.send(&[status_response(LlrpMessageType::SetReaderConfigResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[configuration_state(request.message_id, 12)]).await?;
+
+    let request = connection.expect(LlrpMessageType::DeleteROSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::DeleteROSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[configuration_state(request.message_id, 13)]).await?;
+
+    let request = connection.expect(LlrpMessageType::DeleteAccessSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::DeleteAccessSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    connection.send(&[configuration_state(request.message_id, 14)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  assert!(!client.check_configuration_changed().await.unwrap());
+  assert!(!client.check_configuration_changed().await.unwrap());
+  assert!(client.check_configuration_changed().await.unwrap());
+  assert_eq!(client.configuration_state(), Some(9));
+
+  client.set_gpo(GpoPort(1), PinState::High).await.unwrap();
+  assert!(!client.check_configuration_changed().await.unwrap());
+  assert_eq!(client.configuration_state(), Some(12));
+
+  client.send_delete_rospec(1).await.unwrap();
+  assert!(!client.check_configuration_changed().await.unwrap());
+  assert_eq!(client.configuration_state(), Some(13));
+
+  client.send_delete_access_spec(1).await.unwrap();
+  assert!(!client.check_configuration_changed().await.unwrap());
+  assert_eq!(client.configuration_state(), Some(14));
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn antenna_properties_tell_which_ports_have_antennas_attached() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    for _ in 0..2 {
+      let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+      assert_eq!(request.payload, vec![0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00]);
+
+      let mut payload = status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id).payload;
+      for (connected, antenna_id, gain) in [(0x80u8, 1u16, 600i16), (0x00, 2, 0), (0x80, 3, -150)] {
+        payload.extend_from_slice(&[0x00, 0xDD, 0x00, 0x09, connected]);
+        payload.extend_from_slice(&antenna_id.to_be_bytes());
+        payload.extend_from_slice(&gain.to_be_bytes());
+      }
+
+      connection.send(&[LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, request.message_id, payload)]).await?;
+    }
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let antennas = client.get_antenna_properties().await.unwrap();
+  let antennas: Vec<_> = antennas.iter().map(|antenna| (antenna.antenna_id, antenna.antenna_connected, antenna.antenna_gain)).collect();
+  assert_eq!(antennas, vec![(1, true, 600), (2, false, 0), (3, true, -150)]);
+
+  assert_eq!(client.get_connected_antennas().await.unwrap(), vec![1, 3]);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn antenna_configurations_are_read_back_and_compared_with_the_desired_settings() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    assert_eq!(request.payload, vec![0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00]);
+
+    let mut payload = status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id).payload;
+    payload.extend_from_slice(&[
+      0x00, 0xDE, 0x00, 0x2E, 0x00, 0x01,                               // AntennaConfiguration, antenna 1
+      0x00, 0xDF, 0x00, 0x06, 0x00, 0x01,                               // RFReceiver
+      0x00, 0xE0, 0x00, 0x0A, 0x00, 0x01, 0x00, 0x00, 0x00, 0x51,       // RFTransmitter
+      0x01, 0x4A, 0x00, 0x18, 0x00,                                     // C1G2InventoryCommand
+      0x01, 0x4F, 0x00, 0x08, 0x03, 0xE9, 0x00, 0x00,                   // C1G2RFControl
+      0x01, 0x50, 0x00, 0x0B, 0x40, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00  // C1G2SingulationControl
+    ]);
+
+    connection.send(&[LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, request.message_id, payload)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let antennas = client.get_antenna_configurations().await.unwrap();
+  assert_eq!(antennas.len(), 1);
+
+  let antenna = &antennas[0];
+  let rf_transmitter = antenna.rf_transmitter.as_ref().unwrap();
+  assert_eq!((antenna.antenna_id, rf_transmitter.transmit_power_value, antenna.rf_receiver.as_ref().unwrap().receiver_sensitivity), (1, 81, 1));
+
+  let inventory_command = &antenna.c1g2_inventory_commands[0];
+  assert_eq!(inventory_command.c1g2_rf_control.as_ref().unwrap().mode_index, 1001);
+  assert_eq!(inventory_command.c1g2_singulation_control.as_ref().unwrap().session, 1);
+
+  let mut desired = client.config().reader_config.clone();
+  desired.hop_table_id = 1;
+  desired.channel_index = 0;
+  desired.tx_power_table_index = 90;
+  desired.rx_power_table_index = 1;
+
+  let desired_inventory = C1G2InventoryCommandConfig {
+    rf_control  : Some(C1G2RFControlConfig { mode_index: 1001, tari: 0 }),
+    singulation : Some(C1G2SingulationConfig::new(2, 32, 0)),
+    ..Default::default()
+  };
+
+  assert_eq!(antenna.differences(&desired, Some(&desired_inventory)), vec![
+    "reader_config.tx_power_table_index",
+    "rospec.inventory_command.singulation.session"
+  ]);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn reader_config_requests_select_the_data_antenna_and_ports() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    assert_eq!(request.payload, vec![0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]);
+    connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    assert_eq!(request.payload, vec![0x00, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00]);
+    connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    assert_eq!(request.payload, vec![0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x02]);
+    connection.send(&[status_response(LlrpMessageType::GetReaderConfigResponse, request.message_id)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  client.send_get_reader_config(ReaderConfigRequest::only(ReaderConfigData::Identification), |_| async {}).await.unwrap();
+
+  let antenna_3 = ReaderConfigRequest { antenna_id: 3, ..ReaderConfigRequest::only(ReaderConfigData::AntennaConfiguration) };
+  client.send_get_reader_config(antenna_3, |_| async {}).await.unwrap();
+
+  let gpo_2 = ReaderConfigRequest { gpo_port: 2, ..ReaderConfigRequest::only(ReaderConfigData::GPOWriteData) };
+  client.send_get_reader_config(gpo_2, |_| async {}).await.unwrap();
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn non_success_statuses_are_returned_as_reader_status_errors() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::EnableROSpec).await?;
+    let description = b"ROSpec 1 not found";
+    let mut payload = BytesMut::new();
+    payload.put_u16(LlrpParameterType::LLRPStatus.value());
+    payload.put_u16(8 + description.len() as u16);
+    payload.put_u16(100);
+    payload.put_u16(description.len() as u16);
+    payload.put_slice(description);
+    connection.send(&[LlrpMessage::new(LlrpMessageType::EnableROSpecResponse, request.message_id, payload.to_vec())]).await?;
+
+    let request = connection.expect(LlrpMessageType::EnableROSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::EnableROSpecResponse, request.message_id)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let error = client.send_enable_rospec_with_id(1).await.unwrap_err();
+  assert_eq!(error.code(), 5);
+  let LlrpError::ReaderStatus(status_error) = error else { panic!("expected a reader status error, got {}", error) };
+  assert_eq!(status_error.request, LlrpMessageType::EnableROSpec);
+  assert_eq!(status_error.status_code(), 100);
+  assert_eq!(status_error.status.error_description, "ROSpec 1 not found");
+
+  client.send_enable_rospec_with_id(1).await.unwrap();
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn tag_writes_go_ahead_when_there_is_no_access_spec_to_replace() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::DeleteAccessSpec).await?;
+    connection.send(&[failed_status_response(LlrpMessageType::DeleteAccessSpecResponse, request.message_id, 300)]).await?;
+
+    let request = connection.expect(LlrpMessageType::AddAccessSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::AddAccessSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::EnableAccessSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::EnableAccessSpecResponse, request.message_id)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  client.write_tag_memory(3, 0, &[0x1234], 0).await.unwrap();
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn tag_writes_past_the_end_of_the_bank_are_rejected_before_sending() {
+
+  let reader = ScriptedReader::start(|_connection| async move { Ok(()) }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  for (word_pointer, data) in [(0xFFFF, vec![1, 2]), (0, vec![0; 40000])] {
+    let error = client.write_tag_memory(3, word_pointer, &data, 0).await.unwrap_err();
+    assert_eq!(error.code(), 8);
+  }
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn refused_report_trigger_changes_restore_the_previous_rospec() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::StopROSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::StopROSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::DeleteROSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::DeleteROSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::AddROSpec).await?;
+    connection.send(&[failed_status_response(LlrpMessageType::AddROspecResponse, request.message_id, 301)]).await?;
+
+    for (request_type, response_type) in [
+      (LlrpMessageType::AddROSpec, LlrpMessageType::AddROspecResponse),
+      (LlrpMessageType::EnableROSpec, LlrpMessageType::EnableROSpecResponse),
+      (LlrpMessageType::StartROSpec, LlrpMessageType::StartROSpecResponse)
+    ] {
+      let request = connection.expect(request_type).await?;
+      connection.send(&[status_response(response_type, request.message_id)]).await?;
+    }
+
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let error = client.set_report_trigger_n(500).await.unwrap_err();
+  assert_eq!(error.code(), 5);
+  assert_eq!(client.config().rospec.ROReportTrigger_N, 1);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn refused_inventory_starts_roll_back_the_applied_steps_in_reverse() {
+
+  // The RF settings the reader reports before the client changes them.
+  let previous = ReaderConfig {
+    hop_table_id         : 3,
+    channel_index        : 7,
+    tx_power_table_index : 42,
+    rx_power_table_index : 12,
+    gpi_ports            : vec![],
+    gpo_outputs          : vec![],
+    keepalive_spec       : None
+  };
+
+  let reader = ScriptedReader::start(move |mut connection| async move {
+    let request = connection.expect(LlrpMessageType::GetReaderConfig).await?;
+    let mut payload = status_response(LlrpMessageType::GetReaderConfigResponse, 0).payload;
+    payload.extend_from_slice(&LlrpMessage::new_set_antenna_configuration(0, 1, &previous, false).payload[1..]);
+    connection.send(&[LlrpMessage::new(LlrpMessageType::GetReaderConfigResponse, request.message_id, payload)]).await?;
+
+    for (request_type, response_type) in [
+      (LlrpMessageType::SetReaderConfig, LlrpMessageType::SetReaderConfigResponse),
+      (LlrpMessageType::AddROSpec, LlrpMessageType::AddROspecResponse)
+    ] {
+      let request = connection.expect(request_type).await?;
+      connection.send(&[status_response(response_type, request.message_id)]).await?;
+    }
+
+    let request = connection.expect(LlrpMessageType::EnableROSpec).await?;
+    connection.send(&[failed_status_response(LlrpMessageType::EnableROSpecResponse, request.message_id, 100)]).await?;
+
+    // Rollback: the ROSpec is deleted first, then the captured configuration restored.
+    let request = connection.expect(LlrpMessageType::DeleteROSpec).await?;
+    connection.send(&[status_response(LlrpMessageType::DeleteROSpecResponse, request.message_id)]).await?;
+
+    let request = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    assert_eq!(request.payload, LlrpMessage::new_set_reader_config(request.message_id, &previous).payload);
+    connection.send(&[status_response(LlrpMessageType::SetReaderConfigResponse, request.message_id)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  let error = client.start_inventory().await.unwrap_err();
+  assert_eq!(error.code(), 5);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn tag_report_streams_yield_every_tag_until_the_connection_closes() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[
+      keepalive_ack(request.message_id),
+      ro_access_report(0xE200_0000_0000_0000_0000_0001),
+      ro_access_report(0xE200_0000_0000_0000_0000_0002)
+    ]).await?;
+    connection.send(&[ro_access_report(0xE200_0000_0000_0000_0000_0003)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let tags = client.tag_report_stream();
+
+  client.send_keep_alive().await.unwrap();
+  drop(client);
+
+  let epcs: Vec<u8> = tags.into_stream().map(|tag| tag.epc[11]).collect().await;
+  assert_eq!(epcs, vec![1, 2, 3]);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn decoded_reader_events_reach_event_subscribers() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+
+    let message = b"Antenna 2 VSWR too high";
+    let mut event = BytesMut::new();
+    event.put_u16(LlrpParameterType::ReaderEventNotificationData.value());
+    event.put_u16(4 + 7 + 6 + message.len() as u16);
+    event.put_u16(LlrpParameterType::AntennaEvent.value());
+    event.put_u16(7);
+    event.put_u8(0);
+    event.put_u16(2);
+    event.put_u16(LlrpParameterType::ReaderExceptionEvent.value());
+    event.put_u16(6 + message.len() as u16);
+    event.put_u16(message.len() as u16);
+    event.put_slice(message);
+
+    connection.send(&[
+      keepalive_ack(request.message_id),
+      LlrpMessage::new(LlrpMessageType::ReaderEventNotification, 0, event.to_vec())
+    ]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut events = client.subscribe_events();
+
+  client.send_keep_alive().await.unwrap();
+
+  let event = tokio::time::timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap();
+  let antenna_event = event.antenna_event.unwrap();
+  assert_eq!((antenna_event.event_type, antenna_event.antenna_id), (AntennaEventType::Disconnected, 2));
+  assert_eq!(event.reader_exception.unwrap().message, "Antenna 2 VSWR too high");
+  assert!(event.gpi_event.is_none() && !event.connection_close);
+
+  reader.finish().await;
+}
+
+#[tokio::test]
+async fn silent_readers_are_declared_dead_and_reported_to_connection_subscribers() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let probe = connection.expect(LlrpMessageType::Keepalive).await?;
+    assert_eq!(probe.message_id, 0);
+    connection.send(&[keepalive_ack(probe.message_id)]).await?;
+    // Stay connected but silent until the client gives up on the connection.
+    while connection.expect(LlrpMessageType::Keepalive).await.is_ok() {}
+    Ok(())
+  }).await;
+
+  let mut config = test_config(&reader.host, 1000);
+  config.liveness = Some(LivenessConfig { missed_keepalives: 2, keepalive_interval: Some(50) });
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+  let mut events = client.subscribe_connection_events();
+
+  let event = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+  match event.kind {
+    ConnectionEventKind::Disconnected { reason, .. } => assert!(reason.contains("No message from the reader")),
+    kind => panic!("Expected a disconnect, got {:?}", kind)
+  }
+
+  reader.finish().await;
+
+  let started = std::time::Instant::now();
+  assert!(client.send_keep_alive().await.unwrap_err().is_transient());
+  assert!(started.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn skipped_reports_do_not_end_the_wait_for_an_ro_access_report() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    // Give the client time to subscribe before the reports arrive.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    connection.send(&[ro_access_report(0xE200_0000_0000_0000_0000_0001), ro_access_report(0x3008_33b2_ddd9_0140_0000_0000)]).await
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut delivered = Vec::new();
+
+  client.await_ro_access_report_where(
+    |response_data| matches!(response_data, LlrpResponseData::TagReport(tags) if tags[0].epc[0] == 0x30),
+    |response_data| {
+      delivered.push(response_data);
+      async {}
+    }
+  ).await.unwrap();
+
+  reader.finish().await;
+
+  assert_eq!(delivered.len(), 1);
+}
+
+#[tokio::test]
+async fn shutdown_closes_the_connection_and_ends_subscriptions() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[ro_access_report(0x3008_33b2_ddd9_0140_0000_0000), keepalive_ack(request.message_id)]).await?;
+
+    let close = connection.expect(LlrpMessageType::CloseConnection).await?;
+    connection.send(&[status_response(LlrpMessageType::CloseConnectionResponse, close.message_id)]).await?;
+
+    let mut rest = Vec::new();
+    connection.stream.read_to_end(&mut rest).await?;
+    assert!(rest.is_empty());
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+  let mut tags = client.tag_report_stream();
+
+  client.send_keep_alive().await.unwrap();
+  client.shutdown().await.unwrap();
+  reader.finish().await;
+
+  assert!(tokio::time::timeout(Duration::from_secs(1), tags.recv()).await.unwrap().is_some());
+  assert!(tokio::time::timeout(Duration::from_secs(1), tags.recv()).await.unwrap().is_none());
+
+  match client.connection_history().last().map(|event| &event.kind) {
+    Some(ConnectionEventKind::Disconnected { reason, .. }) => assert_eq!(reason, "Closed by the client"),
+    kind => panic!("Expected a disconnect, got {:?}", kind)
+  }
+}
+
+#[tokio::test]
+async fn shutting_down_twice_only_closes_the_connection_once() {
+
+  let reader = ScriptedReader::start(|mut connection| async move {
+    let close = connection.expect(LlrpMessageType::CloseConnection).await?;
+    connection.send(&[status_response(LlrpMessageType::CloseConnectionResponse, close.message_id)]).await?;
+
+    let mut rest = Vec::new();
+    connection.stream.read_to_end(&mut rest).await?;
+    assert!(rest.is_empty());
+    Ok(())
+  }).await;
+
+  let mut client = connect(&reader.host, 1000).await;
+
+  client.shutdown().await.unwrap();
+  client.shutdown().await.unwrap();
+  reader.finish().await;
+
+  let disconnects = client.connection_history().into_iter()
+    .filter(|event| matches!(event.kind, ConnectionEventKind::Disconnected { .. }))
+    .count();
+  assert_eq!(disconnects, 1);
+}
+
+#[tokio::test]
+async fn reconnecting_records_the_end_of_the_previous_session() {
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let host = listener.local_addr().unwrap().to_string();
+
+  let reader = tokio::spawn(async move {
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+      let (mut stream, _) = listener.accept().await?;
+      stream.write_all(&connection_attempt_event().encode()).await?;
+      sessions.push(stream);
+    }
+    io::Result::Ok(sessions)
+  });
+
+  let mut client = connect(&host, 1000).await;
+  client.reconnect().await.unwrap();
+  let _sessions = reader.await.unwrap().unwrap();
+
+  let kinds: Vec<ConnectionEventKind> = client.connection_history().into_iter().map(|event| event.kind).collect();
+  let disconnect = kinds.iter().position(|kind| matches!(kind, ConnectionEventKind::Disconnected { reason, .. } if reason == "Reconnecting"));
+  let attempt = kinds.iter().position(|kind| matches!(kind, ConnectionEventKind::ReconnectAttempt { .. }));
+  assert!(disconnect.unwrap() < attempt.unwrap());
+}
+
+#[tokio::test]
+async fn failed_config_changes_reconnect_to_the_previous_host() {
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let host = listener.local_addr().unwrap().to_string();
+
+  let previous_reader = tokio::spawn(async move {
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+      let (mut stream, _) = listener.accept().await?;
+      stream.write_all(&connection_attempt_event().encode()).await?;
+      sessions.push(stream);
+    }
+    io::Result::Ok(sessions)
+  });
+
+  let new_reader = ScriptedReader::start(|mut connection| async move {
+    let request = connection.expect(LlrpMessageType::SetReaderConfig).await?;
+    connection.send(&[failed_status_response(LlrpMessageType::SetReaderConfigResponse, request.message_id, 101)]).await
+  }).await;
+
+  let mut client = connect(&host, 1000).await;
+
+  let mut config = test_config(&new_reader.host, 1000);
+  config.reader_config.tx_power_table_index = 2;
+  assert!(client.apply_config(config).await.is_err());
+  new_reader.finish().await;
+  let _sessions = previous_reader.await.unwrap().unwrap();
+
+  assert_eq!(client.config().host, host);
+  let last_connect = client.connection_history().into_iter().rev().find_map(|event| match event.kind {
+    ConnectionEventKind::Connected { host, .. } => Some(host),
+    _ => None
+  });
+  assert_eq!(last_connect, Some(host));
+}
+
+#[test]
+fn ai_spec_stop_triggers_without_an_encoding_fail_validation() {
+
+  let mut config = test_config("127.0.0.1:5084", 1000);
+  config.rospec.AISpecStopTriggerType = 1;
+  config.rospec.aispec_stop_duration_ms = Some(500);
+  assert!(config.validate().is_ok());
+
+  for trigger_type in [2, 3] {
+    config.rospec.AISpecStopTriggerType = trigger_type;
+    assert!(config.validate().is_err_and(|e| e.kind() == io::ErrorKind::InvalidInput), "AISpecStopTriggerType {}", trigger_type);
+  }
+}
+
+#[tokio::test]
+async fn readers_initiating_the_connection_are_accepted_in_listen_mode() {
+
+  let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+
+  let reader_address = address.clone();
+  let reader = tokio::spawn(async move {
+
+    let mut stream = loop {
+      match TcpStream::connect(&reader_address).await {
+        Ok(stream) => break stream,
+        Err(_) => tokio::time::sleep(Duration::from_millis(10)).await
+      }
+    };
+    stream.write_all(&connection_attempt_event().encode()).await?;
+
+    let mut connection = ScriptedConnection::new(stream);
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[keepalive_ack(request.message_id)]).await
+  });
+
+  let mut config = test_config("127.0.0.1:5084", 1000);
+  config.listen = Some(ListenConfig { address, accept_timeout: 5000 });
+  config.validate().unwrap();
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+
+  client.send_keep_alive().await.unwrap();
+  reader.await.unwrap().unwrap();
+
+  assert!(matches!(client.connection_history()[0].kind, ConnectionEventKind::Connected { .. }));
+}
+
+#[tokio::test]
+async fn connections_from_other_hosts_than_the_reader_are_refused_in_listen_mode() {
+
+  let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+
+  let reader_address = address.clone();
+  let reader = tokio::spawn(async move {
+
+    let mut stranger = loop {
+      match TcpStream::connect(&reader_address).await {
+        Ok(stream) => break stream,
+        Err(_) => tokio::time::sleep(Duration::from_millis(10)).await
+      }
+    };
+    assert_eq!(stranger.read(&mut [0; 1]).await?, 0);
+
+    let socket = tokio::net::TcpSocket::new_v4()?;
+    socket.bind("127.0.0.2:0".parse().unwrap())?;
+    let mut stream = socket.connect(reader_address.parse().unwrap()).await?;
+    stream.write_all(&connection_attempt_event().encode()).await?;
+
+    let mut connection = ScriptedConnection::new(stream);
+    let request = connection.expect(LlrpMessageType::Keepalive).await?;
+    connection.send(&[keepalive_ack(request.message_id)]).await
+  });
+
+  let mut config = test_config("127.0.0.2:5084", 1000);
+  config.listen = Some(ListenConfig { address, accept_timeout: 5000 });
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+
+  client.send_keep_alive().await.unwrap();
+  reader.await.unwrap().unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn tls_sessions_are_set_up_only_with_a_verified_reader_certificate() {
+
+  use std::path::PathBuf;
+  use tokio_rustls::rustls::crypto::ring;
+  use tokio_rustls::rustls::pki_types::pem::PemObject;
+  use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+  use tokio_rustls::rustls::ServerConfig;
+  use tokio_rustls::TlsAcceptor;
+  use crate::config::TlsConfig;
+
+  let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+
+  let certificates = CertificateDer::pem_file_iter(fixtures.join("reader.pem")).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+  let key = PrivateKeyDer::from_pem_file(fixtures.join("reader.key")).unwrap();
+  let server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+    .with_safe_default_protocol_versions().unwrap()
+    .with_no_client_auth()
+    .with_single_cert(certificates, key).unwrap();
+  let acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let host = listener.local_addr().unwrap().to_string();
+
+  let reader = tokio::spawn(async move {
+
+    // The first client expects another name and abandons the handshake.
+    let (stream, _) = listener.accept().await?;
+    assert!(acceptor.accept(stream).await.is_err());
+
+    let (stream, _) = listener.accept().await?;
+    let mut stream = acceptor.accept(stream).await?;
+    stream.write_all(&connection_attempt_event().encode()).await?;
+
+    let mut header = [0u8; LLRP_HEADER_LENGTH];
+    stream.read_exact(&mut header).await?;
+    let header = LlrpHeader::decode(&header)?;
+    assert_eq!(header.message_type_value, LlrpMessageType::Keepalive.value());
+    stream.write_all(&keepalive_ack(header.message_id).encode()).await?;
+    stream.flush().await
+  });
+
+  let tls_config = |server_name: Option<&str>| TlsConfig {
+    enabled     : true,
+    ca_path     : Some(fixtures.join("ca.pem")),
+    client_cert : None,
+    client_key  : None,
+    server_name : server_name.map(str::to_string)
+  };
+
+  let mut config = test_config(&host, 1000);
+  config.tls = Some(tls_config(Some("other.test")));
+  let error = LlrpClient::initialize_with_config(config).await.err().unwrap();
+  assert!(error.to_string().contains("TLS handshake"));
+
+  let mut config = test_config(&host, 1000);
+  config.tls = Some(tls_config(None));
+  let mut client = LlrpClient::initialize_with_config(config).await.unwrap();
+
+  client.send_keep_alive().await.unwrap();
+  reader.await.unwrap().unwrap();
+}
\ No newline at end of file
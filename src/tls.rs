@@ -0,0 +1,99 @@
+//! TLS sessions with readers, for the `tls` section of the configuration.
+
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::config::TlsConfig;
+
+/// Sets up a TLS session on `stream`, verifying the reader's certificate against
+/// `config.ca_path` and the name in `config.server_name`, or else `host`.
+pub async fn connect(
+  config : &TlsConfig,
+  host   : &str,
+  stream : TcpStream
+) -> io::Result<TlsStream<TcpStream>> {
+
+  let connector = TlsConnector::from(Arc::new(client_config(config)?));
+  let server_name = server_name(config, host)?;
+
+  connector.connect(server_name, stream).await
+    .map_err(|e| io::Error::new(e.kind(), format!("TLS handshake with {} failed: {}", host, e)))
+}
+
+fn client_config(
+  config: &TlsConfig
+) -> io::Result<ClientConfig> {
+
+  let ca_path = config.ca_path.as_deref()
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "tls.ca_path is not set"))?;
+
+  let mut roots = RootCertStore::empty();
+  for certificate in read_certificates(ca_path)? {
+    roots.add(certificate).map_err(|e| invalid_pem(ca_path, e))?;
+  }
+
+  let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+    .with_safe_default_protocol_versions()
+    .map_err(io::Error::other)?
+    .with_root_certificates(roots);
+
+  match (&config.client_cert, &config.client_key) {
+    (Some(cert_path), Some(key_path)) => {
+      let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| invalid_pem(key_path, e))?;
+      builder.with_client_auth_cert(read_certificates(cert_path)?, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("tls.client_key does not fit tls.client_cert: {}", e)))
+    }
+    _ => Ok(builder.with_no_client_auth())
+  }
+}
+
+fn read_certificates(
+  path: &Path
+) -> io::Result<Vec<CertificateDer<'static>>> {
+
+  let certificates = CertificateDer::pem_file_iter(path)
+    .map_err(|e| invalid_pem(path, e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| invalid_pem(path, e))?;
+
+  if certificates.is_empty() {
+    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} holds no PEM certificates", path.display())));
+  }
+
+  Ok(certificates)
+}
+
+/// The name in `config.server_name`, or the address of `host` without its port.
+fn server_name(
+  config : &TlsConfig,
+  host   : &str
+) -> io::Result<ServerName<'static>> {
+
+  let name = match &config.server_name {
+    Some(name) => name.as_str(),
+    None => host.rsplit_once(':').map_or(host, |(address, _)| address)
+  };
+  let name = name.trim_start_matches('[').trim_end_matches(']');
+
+  match name.parse::<IpAddr>() {
+    Ok(ip) => Ok(ServerName::IpAddress(ip.into())),
+    Err(_) => ServerName::try_from(name.to_string())
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not a valid TLS server name: {}", name, e)))
+  }
+}
+
+fn invalid_pem(
+  path  : &Path,
+  error : impl std::fmt::Display
+) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidInput, format!("Failed to read {}: {}", path.display(), error))
+}
\ No newline at end of file
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use std::time::Duration;
+use log::{info, warn};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::client::LlrpClient;
+use crate::config::ReportTuning;
+use crate::llrp::{LlrpResponse, LlrpResponseData};
+use crate::log_context;
+
+/// Adjusts the reader's `ROReportTrigger_N` to its observed tag rate, within the
+/// bounds of the configured `ReportTuning`.
+pub struct ReportTriggerTuner {
+  task: JoinHandle<()>
+}
+
+impl ReportTriggerTuner {
+
+  /// Starts tuning the client's ROSpec. Returns `None` when the configuration has
+  /// no `report_tuning` section.
+  ///
+  /// Must be called from within a Tokio runtime context.
+  pub async fn start(
+    client: Arc<Mutex<LlrpClient>>
+  ) -> Option<Self> {
+
+    let (reader_id, tuning, ro_report_rx) = {
+      let client = client.lock().await;
+      (client.config().reader_id(), client.config().report_tuning.clone()?, client.subscribe_ro_reports())
+    };
+
+    if tuning.min_n == 0 || tuning.min_n > tuning.max_n || tuning.evaluation_interval == 0 {
+      log_context::sync_scope(reader_id, || {
+        warn!("Ignoring report tuning with invalid bounds {}..{} or interval {} ms", tuning.min_n, tuning.max_n, tuning.evaluation_interval);
+      });
+      return None;
+    }
+
+    let task = tokio::spawn(log_context::scope(reader_id, run_tuner(client, tuning, ro_report_rx)));
+
+    Some(ReportTriggerTuner { task })
+  }
+
+  pub fn stop(
+    self
+  ) {
+    self.task.abort();
+  }
+}
+
+/// The N at which reports arrive every `target_latency_ms` for the given tag rate.
+fn target_report_n(
+  tags_per_sec : f64,
+  tuning       : &ReportTuning
+) -> u16 {
+  let report_n = (tags_per_sec * tuning.target_latency_ms as f64 / 1000.0).round();
+  report_n.clamp(tuning.min_n as f64, tuning.max_n as f64) as u16
+}
+
+/// Whether moving from `current_n` to `report_n` is a large enough relative change
+/// to re-apply the ROSpec.
+fn exceeds_hysteresis(
+  current_n : u16,
+  report_n  : u16,
+  tuning    : &ReportTuning
+) -> bool {
+  let change = (report_n as f64 - current_n as f64).abs() / current_n.max(1) as f64;
+  change >= tuning.hysteresis
+}
+
+async fn run_tuner(
+  client           : Arc<Mutex<LlrpClient>>,
+  tuning           : ReportTuning,
+  mut ro_report_rx : broadcast::Receiver<LlrpResponse>
+) {
+
+  info!(
+    "Starting report tuning: N in {}..{} for {} ms report latency",
+    tuning.min_n, tuning.max_n, tuning.target_latency_ms
+  );
+
+  let interval = Duration::from_millis(tuning.evaluation_interval);
+  let mut ticker = tokio::time::interval(interval);
+  ticker.tick().await;
+
+  let mut tag_count = 0usize;
+
+  loop {
+    tokio::select! {
+
+      report = ro_report_rx.recv() => match report {
+        Ok(report) => {
+          if let Ok(LlrpResponseData::TagReport(tag_reports)) = report.decode() {
+            tag_count += tag_reports.len();
+          }
+        }
+        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(broadcast::error::RecvError::Closed) => break
+      },
+
+      _ = ticker.tick() => {
+
+        let tags_per_sec = tag_count as f64 / interval.as_secs_f64();
+        tag_count = 0;
+
+        // No reads means no inventory is running; keep the current N for when it resumes.
+        if tags_per_sec == 0.0 {
+          continue;
+        }
+
+        let mut client = client.lock().await;
+
+        let current_n = client.config().rospec.ROReportTrigger_N;
+        let report_n = target_report_n(tags_per_sec, &tuning);
+
+        if !exceeds_hysteresis(current_n, report_n, &tuning) {
+          continue;
+        }
+
+        info!("Tag rate {:.1}/s, changing ROReportTrigger_N from {} to {}", tags_per_sec, current_n, report_n);
+
+        if let Err(e) = client.set_report_trigger_n(report_n).await {
+          warn!("Failed to change ROReportTrigger_N to {}: {}", report_n, e);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn tuning() -> ReportTuning {
+    ReportTuning {
+      min_n               : 5,
+      max_n               : 100,
+      target_latency_ms   : 500,
+      evaluation_interval : 10_000,
+      hysteresis          : 0.2
+    }
+  }
+
+  #[test]
+  fn report_n_follows_the_tag_rate_within_the_bounds() {
+
+    // Half a second of tags, rounded to the nearest whole tag.
+    assert_eq!(target_report_n(41.0, &tuning()), 21);
+    assert_eq!(target_report_n(40.8, &tuning()), 20);
+
+    assert_eq!(target_report_n(10.0, &tuning()), 5);
+    assert_eq!(target_report_n(1.0, &tuning()), 5);
+    assert_eq!(target_report_n(200.0, &tuning()), 100);
+    assert_eq!(target_report_n(5000.0, &tuning()), 100);
+  }
+
+  #[test]
+  fn small_changes_of_n_are_held_back_by_the_hysteresis() {
+
+    assert!(!exceeds_hysteresis(50, 50, &tuning()));
+    assert!(!exceeds_hysteresis(50, 59, &tuning()));
+    assert!(exceeds_hysteresis(50, 60, &tuning()));
+    assert!(exceeds_hysteresis(50, 40, &tuning()));
+    assert!(!exceeds_hysteresis(50, 41, &tuning()));
+
+    // An N of 0 counts as 1, so any change from it is applied.
+    assert!(exceeds_hysteresis(0, 5, &tuning()));
+  }
+}
\ No newline at end of file